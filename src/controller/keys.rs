@@ -0,0 +1,140 @@
+use crate::{
+    helper::keymap::{KeyBinding, Keymap, ReaderAction},
+    model::app_state::AppState,
+};
+use druid::{
+    widget::Controller, Env, Event, EventCtx, KeyEvent, LifeCycle, LifeCycleCtx, Selector, Widget,
+};
+
+// Give the keyboard focus back to the reader (e.g. after a search)
+pub const FOCUS_READER: Selector<()> = Selector::new("reader.focus");
+// Move the keyboard focus to the search box
+pub const FOCUS_SEARCH: Selector<()> = Selector::new("reader.focus-search");
+
+/**
+ * Key of a keyboard event, Cmd on macOS works as Ctrl
+ */
+fn key_binding(key: &KeyEvent) -> KeyBinding {
+    let mods = key.mods;
+    KeyBinding::new(
+        mods.ctrl() || mods.meta(),
+        mods.alt(),
+        mods.shift(),
+        key.key.to_string().as_str(),
+    )
+}
+
+/**
+ * ReaderKeyController
+ * Runs the reader actions bound to keys in the keymap.
+ * The reader takes the keyboard focus when it is opened or clicked,
+ * keys typed in a text box (which has the focus then) are left to it.
+ */
+pub struct ReaderKeyController {
+    keymap: Keymap,
+}
+
+impl ReaderKeyController {
+    pub fn new(keymap: Keymap) -> Self {
+        Self { keymap }
+    }
+
+    fn run(&self, action: ReaderAction, ctx: &mut EventCtx, data: &mut AppState) {
+        match action {
+            ReaderAction::NextPage => data.navigate_to_next_page(),
+            ReaderAction::PrevPage => data.navigate_to_prev_page(),
+            ReaderAction::FirstPage => data.navigate_to_first_page(),
+            ReaderAction::LastPage => data.navigate_to_last_page(),
+            ReaderAction::HistoryBack => data.go_back(),
+            ReaderAction::HistoryForward => data.go_forward(),
+            ReaderAction::Search => ctx.submit_command(FOCUS_SEARCH),
+            ReaderAction::IncreaseFont => data.increase_font_size(),
+            ReaderAction::DecreaseFont => data.decrease_font_size(),
+            ReaderAction::Back if data.is_note_visible() => data.close_note(),
+            ReaderAction::Back if data.is_lookup_visible() => data.close_lookup(),
+            ReaderAction::Back if data.is_annotation_visible() => data.close_annotation(),
+            ReaderAction::Back => data.close_book(),
+            ReaderAction::Help => data.toggle_help(),
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ReaderKeyController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::KeyDown(key) if ctx.is_focused() => {
+                let rtl = data.is_rtl_book();
+                if let Some(action) = self.keymap.book_action(&key_binding(key), rtl) {
+                    self.run(action, ctx, data);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            Event::Command(cmd) if cmd.is(FOCUS_READER) => {
+                ctx.request_focus();
+                ctx.set_handled();
+                return;
+            }
+            // A text box clicked after this takes the focus instead
+            Event::MouseDown(_) => ctx.request_focus(),
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.register_for_focus();
+            ctx.submit_command(FOCUS_READER.to(ctx.widget_id()));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+/**
+ * SearchController
+ * Wraps the search box of the reader: Enter searches the next match,
+ * Esc gives the focus back to the reader.
+ */
+pub struct SearchController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SearchController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(FOCUS_SEARCH) => {
+                ctx.request_focus();
+                ctx.set_handled();
+            }
+            Event::KeyDown(key) if ctx.has_focus() && key.key == druid::KbKey::Enter => {
+                data.search_book();
+                ctx.set_handled();
+            }
+            Event::KeyDown(key) if ctx.has_focus() && key.key == druid::KbKey::Escape => {
+                ctx.submit_command(FOCUS_READER);
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::model::app_state::AppState;
+use druid::{widget::Controller, Env, Event, EventCtx, Selector, Widget};
+
+// Library actions, the payload is the index of the book in the library
+pub const SET_COVER: Selector<usize> = Selector::new("library.set-cover");
+pub const GENERATE_COVER: Selector<usize> = Selector::new("library.generate-cover");
+pub const REMOVE_BOOK: Selector<usize> = Selector::new("library.remove-book");
+pub const DELETE_BOOK: Selector<usize> = Selector::new("library.delete-book");
+pub const OPEN_FOLDER: Selector<usize> = Selector::new("library.open-folder");
+pub const CONVERT_BOOK: Selector<usize> = Selector::new("library.convert-book");
+
+/**
+ * LibraryController
+ * Handles the actions on the books of the library.
+ * The rows of the list only have access to a copy of their book,
+ * so they submit a command that is handled here with the whole AppState.
+ */
+pub struct LibraryController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LibraryController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(SET_COVER) => {
+                data.set_cover_from_file(*cmd.get_unchecked(SET_COVER));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(GENERATE_COVER) => {
+                data.generate_cover(*cmd.get_unchecked(GENERATE_COVER));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(REMOVE_BOOK) => {
+                data.remove_book(*cmd.get_unchecked(REMOVE_BOOK));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(DELETE_BOOK) => {
+                data.delete_book(*cmd.get_unchecked(DELETE_BOOK));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(OPEN_FOLDER) => {
+                data.open_book_folder(*cmd.get_unchecked(OPEN_FOLDER));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(CONVERT_BOOK) => {
+                data.convert_book(*cmd.get_unchecked(CONVERT_BOOK));
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
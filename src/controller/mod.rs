@@ -1,3 +1,7 @@
+pub mod keys;
+pub mod library;
 pub mod navigator;
 pub mod parser;
+pub mod reading;
+pub mod speech;
 pub mod view;
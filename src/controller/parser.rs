@@ -1,221 +1,442 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashSet, rc::Rc};
 
 use druid::{
-    widget::{Flex, Label, ViewSwitcher},
-    Color, FontDescriptor, FontFamily, FontStyle, FontWeight, Widget, WidgetExt,
+    im::Vector,
+    kurbo::Rect,
+    lens,
+    text::{RichText, RichTextBuilder},
+    theme,
+    widget::{
+        Controller, CrossAxisAlignment, Flex, Label, LineBreaking, Painter, RawLabel, Scroll,
+        SizedBox, ViewSwitcher,
+    },
+    Color, Env, Event, EventCtx, FontStyle, FontWeight, Insets, RenderContext, Selector,
+    TextAlignment, TextLayout, Vec2, Widget, WidgetExt,
 };
 
-use html2text::{
-    from_read_rich,
-    render::text_renderer::{RichAnnotation, TaggedLine},
+use crate::{
+    helper::{
+        config::DEFAULT_FONT_SIZE,
+        dictionary::{sentence_at, word_at},
+        layout::{
+            layout_page, page_stylesheet, writing_mode, Block, Direction, RuleBlock, Span,
+            TableBlock, TextAlign, TextBlock, WritingMode, CELL_PADDING_EM,
+        },
+        notes::note_refs,
+        speech::find_sentence,
+    },
+    model::app_state::AppState,
+    view::vertical_text::VerticalText,
 };
 
-use crate::{helper::config::DEFAULT_FONT_SIZE, model::app_state::AppState};
+// Width of the markers of the list items (in em), they are drawn in the margin of the list
+const MARKER_WIDTH_EM: f64 = 2.0;
+const MARKER_GAP_EM: f64 = 0.5;
+// Width of the bars of the block quotes (in pixels)
+const QUOTE_BAR_WIDTH: f64 = 3.0;
+// Color of the sentence read aloud and of the highlighted sentences
+const SPOKEN_COLOR: Color = Color::rgb8(0x3a, 0x7b, 0xd5);
+const HIGHLIGHT_COLOR: Color = Color::rgb8(0xb0, 0x6a, 0x00);
+// Space the labels leave on the left and right of their text
+const LABEL_X_PADDING: f64 = 2.0;
+
+// Sent by a clicked link of the page, with its href
+pub const FOLLOW_LINK: Selector<String> = Selector::new("reader.follow-link");
+// Sent by a clicked note reference of the page, with its href
+pub const SHOW_NOTE: Selector<String> = Selector::new("reader.show-note");
+// Sent by a double clicked word of the page, with the sentence it is in
+pub const LOOKUP_WORD: Selector<(String, String)> = Selector::new("reader.lookup-word");
+// Sent by a sentence of the page double clicked with Shift, to highlight it
+pub const HIGHLIGHT_SENTENCE: Selector<String> = Selector::new("reader.highlight-sentence");
 
 /**
- * Parses a string (in html format) and returns a widget that can be used to display the text
- * The html text is parsed using the html2text crate.
- * Every element of the html is at the end converted to a Label widget with specific styling
- * based on the html tag.
+ * Sentences marked in the page: the one read aloud and the highlighted ones
  */
-pub fn parse(page: String) -> impl Widget<AppState> {
-    let view_switcher = ViewSwitcher::new(
-        |data: &AppState, _env| data.get_font_size(),
-        move |font_size_offset, _data, _env| {
-            let mut flex =
-                Flex::column().cross_axis_alignment(druid::widget::CrossAxisAlignment::Start);
-            let mut vect = Vec::<Vec<TaggedLine<Vec<RichAnnotation>>>>::new();
-            vect.push(from_read_rich(page.as_bytes(), 150));
-
-            let new_vector = vect.concat();
-            // Render
-            for (_i, line) in new_vector.iter().enumerate() {
-                let mut h: i32 = 0;
-                let mut flag: bool = false;
-                let mut line_str = String::from("");
-                let mut tag_vect = Vec::<RichAnnotation>::new();
-
-                //println!("Line: {:?}", line);
-
-                //If TaggedLine is not empty but does not have TaggedStrings => skip = true
-                //so that no useless lines are added
-                let skip: bool = line.iter().peekable().peek().is_some()
-                    && line.tagged_strings().peekable().peek().is_none();
-
-                if !skip {
-                    //Check elements in the vector of tagged string, each TaggedLine can contain multiple
-                    //TaggedString(s), this loop set the h flags, add text to the label and produce a vector of
-                    //RichAnnotation, after the loop we generate a Label with the overall text of the line
-                    //and with the given attributes, like font_size, font_style etc...
-                    for tagged_string in line.tagged_strings() {
-                        //If h has not been set yet, check if possible h label is being handled,
-                        //if a tag is already being handled (h > 0), just go ahead
-                        if h == 0 {
-                            (h, flag) = check_h(tagged_string.s.as_str());
-                        }
-
-                        //Each TaggedString can have multiple tags (uncommon), tag_vec makes a copy of the said vec
-                        let tags = tagged_string.clone().tag;
-
-                        //If not in a h specifier, add a label with given attributes, an h specifier with this library
-                        //is formatted as a TaggedString with no tag before the actual string that need styling
-                        //just checking h > 0 is not exhaustive, as we can have just normal string where h is 0
-                        if !flag {
-                            //Add the text to label
-                            line_str = [line_str, tagged_string.s.clone()].join("");
-
-                            //Else save all the flags in a vector
-                            for tag in tags.iter() {
-                                tag_vect.push(tag.clone());
-                            }
-                        }
-
-                        flag = false;
-                    }
-
-                    use RichAnnotation::*;
+struct Marks<'a> {
+    spoken: Option<&'a str>,
+    highlights: &'a [String],
+}
 
-                    //If no tag are present, just append a simple Label with normal text
-                    if tag_vect.is_empty() {
-                        let no_tag = no_tag(line_str.as_str(), h, *font_size_offset);
-                        flex.add_child(no_tag);
-                    }
+/**
+ * Parses a page (in xhtml format) with the stylesheets it links and returns a widget that displays it
+ * The page is laid out in blocks (see helper::layout), every paragraph is drawn
+ * as a rich text label with the margins, alignment and fonts given by the css
+ * (and the marker of its list item or the bars of its block quotes),
+ * every table as a grid of cells.
+ * Links to notes open the note over the page instead of following the link.
+ * Pages written vertically (writing-mode: vertical-rl) are drawn in columns instead.
+ * The sentence read aloud and the highlights of the book are marked,
+ * a double clicked word is looked up in the dictionaries.
+ */
+pub fn parse(page: String, stylesheets: Vec<String>) -> impl Widget<AppState> {
+    let notes = note_refs(page.as_str());
+    let stylesheet = page_stylesheet(page.as_str(), &stylesheets);
+    let blocks = layout_page(page.as_str(), &stylesheet);
+    let writing_mode = writing_mode(page.as_str(), &stylesheet);
 
-                    //Else add Label with correct style
-                    //TODO: add missing case
-                    //TODO: if more than one tag, this doesn't work, it's gonna add multiple child (even if rare)
-                    //TODO: lines with h should be centered
-                    for tag in tag_vect.iter() {
-                        match tag {
-                            Default => {}
-                            Link(link_value) => {
-                                //println!("link_value: {:?}", link_value);
-                                let link = link(
-                                    line_str.as_str(),
-                                    h,
-                                    *font_size_offset,
-                                    link_value.to_string(),
-                                );
-                                flex.add_child(link);
-                            }
-                            Image => (),
-                            Emphasis => {
-                                let emphasis = emphasis(line_str.as_str(), h, *font_size_offset);
-                                flex.add_child(emphasis);
-                            }
-                            Strong => (),
-                            Strikeout => (),
-                            Code => (),
-                            Preformat(_) => (),
-                        }
-                    }
-                }
+    ViewSwitcher::new(
+        |data: &AppState, _env| {
+            (
+                data.get_font_size(),
+                data.get_spoken_sentence(),
+                Vector::from(data.get_page_highlights()),
+            )
+        },
+        move |(font_size_offset, spoken, highlights), _data, _env| {
+            let font_size = DEFAULT_FONT_SIZE + font_size_offset;
+            if writing_mode == WritingMode::VerticalRl {
+                return Box::new(VerticalText::new(paragraphs(&blocks), font_size));
+            }
+            let highlights: Vec<String> = highlights.iter().cloned().collect();
+            let marks = Marks {
+                spoken: spoken.as_deref(),
+                highlights: &highlights,
+            };
+            let mut flex = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+            for block in blocks.iter() {
+                flex.add_child(block_widget(block, font_size, &notes, &marks));
             }
-
             Box::new(flex)
         },
-    );
-
-    view_switcher
+    )
 }
 
-pub fn no_tag(s: &str, h: i32, font_size_offset: f64) -> Label<AppState> {
-    if h > 0 {
-        return h_label(s, h, font_size_offset);
+fn block_widget(
+    block: &Block,
+    font_size: f64,
+    notes: &HashSet<String>,
+    marks: &Marks,
+) -> Box<dyn Widget<AppState>> {
+    match block {
+        Block::Text(text) => Box::new(text_block(text, font_size, notes, marks)),
+        Block::Table(table) => Box::new(table_block(table, font_size, notes, marks)),
+        Block::Rule(rule) => Box::new(rule_block(rule, font_size)),
     }
-    default(s, font_size_offset)
 }
 
-pub fn emphasis(s: &str, h: i32, font_size_offset: f64) -> Label<AppState> {
-    if h > 0 {
-        return h_label_emphasis(s, h, font_size_offset);
-    }
-    default_with_descriptor(
-        s,
-        FontDescriptor::new(FontFamily::SYSTEM_UI)
-            .with_style(FontStyle::Italic)
-            .with_size(DEFAULT_FONT_SIZE + font_size_offset),
-    )
+/**
+ * Text of the paragraphs of the blocks (and of the cells of their tables), for the vertical text
+ */
+fn paragraphs(blocks: &[Block]) -> Vec<Vec<Span>> {
+    blocks
+        .iter()
+        .flat_map(|block| match block {
+            Block::Text(text) => vec![text.spans.clone()],
+            Block::Table(table) => table
+                .rows
+                .iter()
+                .flatten()
+                .flat_map(|cell| paragraphs(&cell.blocks))
+                .collect(),
+            Block::Rule(_) => Vec::new(),
+        })
+        .collect()
 }
 
-pub fn link(s: &str, h: i32, font_size_offset: f64, link: String) -> impl Widget<AppState> {
-    let link_ref = Rc::new(link.clone());
-    if h > 0 {
-        return h_label_link(s, h, font_size_offset).on_click(move |_ctx, data, _env| {
-            data.navigate_to_page(link_ref.clone());
-        });
+/**
+ * A paragraph, lengths of the block are in em of the font size
+ * marks -> sentences marked if they are in the paragraph
+ */
+fn text_block(
+    block: &TextBlock,
+    font_size: f64,
+    notes: &HashSet<String>,
+    marks: &Marks,
+) -> impl Widget<AppState> {
+    let rtl = block.direction == Direction::Rtl;
+    let mut builder = RichTextBuilder::new();
+    // A right-to-left (or left-to-right) mark gives its direction to the paragraph,
+    // whatever its first letter is: the text engine aligns and reorders the words with it
+    let mut prefix = String::from(if rtl { "\u{200F}" } else { "\u{200E}" });
+    // The first line is indented with em spaces
+    let indent = block.text_indent.round().max(0.0) as usize;
+    prefix.push_str("\u{2003}".repeat(indent).as_str());
+    builder.push(prefix.as_str());
+    for span in block.spans.iter() {
+        let mut attributes = builder.push(span.text.as_str());
+        attributes.size(font_size * span.size);
+        if span.italic {
+            attributes.style(FontStyle::Italic);
+        }
+        if span.bold {
+            attributes.weight(FontWeight::BOLD);
+        }
+        if let Some(href) = &span.link {
+            let selector = if notes.contains(href) {
+                SHOW_NOTE
+            } else {
+                FOLLOW_LINK
+            };
+            attributes.text_color(Color::AQUA);
+            attributes.link(selector.with(href.clone()));
+        }
     }
-    default_with_color(s, Color::AQUA, font_size_offset).on_click(move |_ctx, data, _env| {
-        data.navigate_to_page(link_ref.clone());
-    })
-}
 
-pub fn default(s: &str, font_size_offset: f64) -> Label<AppState> {
-    Label::new(s).with_text_size(DEFAULT_FONT_SIZE + font_size_offset)
-}
+    // The sentences highlighted and the one read aloud, after the mark and the indent
+    let text: String = block.spans.iter().map(|span| span.text.as_str()).collect();
+    let offset = prefix.len();
+    let marked = marks
+        .highlights
+        .iter()
+        .map(|highlight| (highlight.as_str(), HIGHLIGHT_COLOR))
+        .chain(marks.spoken.map(|spoken| (spoken, SPOKEN_COLOR)));
+    for (sentence, color) in marked {
+        if let Some(range) = find_sentence(text.as_str(), sentence) {
+            builder
+                .add_attributes_for_range(range.start + offset..range.end + offset)
+                .text_color(color)
+                .underline(true);
+        }
+    }
 
-pub fn default_with_color(s: &str, color: Color, font_size_offset: f64) -> Label<AppState> {
-    Label::new(s)
-        .with_text_color(color)
-        .with_text_size(DEFAULT_FONT_SIZE + font_size_offset)
+    // Start and end follow the direction of the paragraph, left and right do not
+    let alignment = match block.text_align {
+        TextAlign::Start => TextAlignment::Start,
+        TextAlign::End => TextAlignment::End,
+        TextAlign::Left if rtl => TextAlignment::End,
+        TextAlign::Left => TextAlignment::Start,
+        TextAlign::Right if rtl => TextAlignment::Start,
+        TextAlign::Right => TextAlignment::End,
+        TextAlign::Center => TextAlignment::Center,
+        TextAlign::Justify => TextAlignment::Justified,
+    };
+    let rich_text = builder.build();
+    let words = WordController::new(rich_text.clone(), alignment, text, offset);
+    let text = RawLabel::new()
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .with_text_alignment(alignment)
+        .lens(lens::Constant(rich_text))
+        .controller(words)
+        .expand_width();
+
+    // The marker of a list item hangs in the margin, before the text
+    // (on the left of the text, or on its right in a right-to-left paragraph)
+    let (text, margin_left, margin_right): (Box<dyn Widget<AppState>>, f64, f64) =
+        match &block.marker {
+            Some(marker) => {
+                let marker = Label::new(marker.as_str())
+                    .with_text_size(font_size * block.spans.first().map_or(1.0, |s| s.size));
+                let row = Flex::row().cross_axis_alignment(CrossAxisAlignment::Start);
+                let gap = MARKER_GAP_EM * font_size;
+                if rtl {
+                    let marker_width = MARKER_WIDTH_EM.min(block.margin_right);
+                    let marker = marker
+                        .align_left()
+                        .padding(Insets::new(gap, 0.0, 0.0, 0.0))
+                        .fix_width(marker_width * font_size);
+                    let row = row.with_flex_child(text, 1.0).with_child(marker);
+                    (
+                        Box::new(row),
+                        block.margin_left,
+                        block.margin_right - marker_width,
+                    )
+                } else {
+                    let marker_width = MARKER_WIDTH_EM.min(block.margin_left);
+                    let marker = marker
+                        .align_right()
+                        .padding(Insets::new(0.0, 0.0, gap, 0.0))
+                        .fix_width(marker_width * font_size);
+                    let row = row.with_child(marker).with_flex_child(text, 1.0);
+                    (
+                        Box::new(row),
+                        block.margin_left - marker_width,
+                        block.margin_right,
+                    )
+                }
+            }
+            None => (Box::new(text), block.margin_left, block.margin_right),
+        };
+
+    let quote_bars = block.quote_bars.clone();
+    text.padding(Insets::new(
+        margin_left * font_size,
+        block.space_before * font_size,
+        margin_right * font_size,
+        0.0,
+    ))
+    .background(Painter::new(move |ctx, _data: &AppState, env| {
+        // Bars of the block quotes, over the whole height so that they join between paragraphs
+        let size = ctx.size();
+        let height = size.height;
+        for bar in quote_bars.iter() {
+            let x = if rtl {
+                size.width - bar * font_size - QUOTE_BAR_WIDTH
+            } else {
+                bar * font_size
+            };
+            ctx.fill(
+                Rect::new(x, 0.0, x + QUOTE_BAR_WIDTH, height),
+                &env.get(theme::BORDER_LIGHT),
+            );
+        }
+    }))
 }
 
-pub fn default_with_descriptor(s: &str, descr: FontDescriptor) -> Label<AppState> {
-    Label::new(s).with_font(descr)
+/**
+ * A table as a grid of cells with the widths of its columns,
+ * header cells are shaded and tables wider than the page scroll horizontally
+ */
+fn table_block(
+    table: &TableBlock,
+    font_size: f64,
+    notes: &HashSet<String>,
+    marks: &Marks,
+) -> impl Widget<AppState> {
+    let mut grid = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for row in table.rows.iter() {
+        let mut grid_row = Flex::row().cross_axis_alignment(CrossAxisAlignment::Start);
+        let mut column = 0;
+        for cell in row.iter() {
+            let width: f64 = table
+                .column_widths
+                .iter()
+                .skip(column)
+                .take(cell.colspan)
+                .sum();
+            column += cell.colspan;
+
+            let mut content = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+            for block in cell.blocks.iter() {
+                content.add_child(block_widget(block, font_size, notes, marks));
+            }
+            let content = content
+                .padding(CELL_PADDING_EM * font_size)
+                .fix_width(width * font_size);
+            if cell.header {
+                grid_row.add_child(content.background(Color::grey8(0xe8)));
+            } else {
+                grid_row.add_child(content);
+            }
+        }
+        grid.add_child(grid_row.border(Color::grey8(0xd4), 1.0));
+    }
+
+    Scroll::new(grid).horizontal().padding(Insets::new(
+        table.margin_left * font_size,
+        table.space_before * font_size,
+        0.0,
+        0.0,
+    ))
 }
 
-pub fn h_label(s: &str, h: i32, font_size_offset: f64) -> Label<AppState> {
-    Label::new(s).with_font(
-        FontDescriptor::new(FontFamily::SANS_SERIF)
-            .with_size(h_font_size(h) + font_size_offset)
-            .with_weight(FontWeight::BOLD),
-    )
+/**
+ * A horizontal rule, in the color of the borders of the theme
+ */
+fn rule_block(rule: &RuleBlock, font_size: f64) -> impl Widget<AppState> {
+    SizedBox::empty()
+        .expand_width()
+        .height(1.0)
+        .background(theme::BORDER_LIGHT)
+        .padding(Insets::new(
+            rule.margin_left * font_size,
+            rule.space_before * font_size,
+            rule.margin_right * font_size,
+            0.0,
+        ))
 }
 
-pub fn h_label_emphasis(s: &str, h: i32, font_size_offset: f64) -> Label<AppState> {
-    Label::new(s).with_font(
-        FontDescriptor::new(FontFamily::SYSTEM_UI)
-            .with_size(h_font_size(h) + font_size_offset)
-            .with_weight(FontWeight::BOLD)
-            .with_style(FontStyle::Italic),
-    )
+/**
+ * WordController
+ * Wraps the text of a paragraph: a double click looks up the word under the mouse,
+ * with Shift it highlights the sentence under the mouse.
+ * The text is laid out as the label lays it out (same width and alignment)
+ * to find the character clicked.
+ */
+struct WordController {
+    layout: TextLayout<RichText>,
+    // Text of the paragraph, after the direction mark and the indent (offset bytes)
+    text: String,
+    offset: usize,
 }
 
-pub fn h_label_link(s: &str, h: i32, font_size_offset: f64) -> Label<AppState> {
-    Label::new(s)
-        .with_text_color(Color::AQUA)
-        .with_text_size(h_font_size(h) + font_size_offset)
+impl WordController {
+    fn new(rich_text: RichText, alignment: TextAlignment, text: String, offset: usize) -> Self {
+        let mut layout = TextLayout::new();
+        layout.set_text(rich_text);
+        layout.set_text_alignment(alignment);
+        Self {
+            layout,
+            text,
+            offset,
+        }
+    }
 }
 
-pub fn check_h(s: &str) -> (i32, bool) {
-    match s {
-        "# " => (1, true),
-        "## " => (2, true),
-        "### " => (3, true),
-        "#### " => (4, true),
-        "##### " => (5, true),
-        "###### " => (6, true),
-        _ => (0, false), //This is basically useless, just to have a, exhaustive match
+impl<W: Widget<AppState>> Controller<AppState, W> for WordController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::MouseDown(mouse) = event {
+            if mouse.button.is_left() && mouse.count == 2 {
+                self.layout
+                    .set_wrap_width(ctx.size().width - 2.0 * LABEL_X_PADDING);
+                self.layout.rebuild_if_needed(ctx.text(), env);
+                let position = self
+                    .layout
+                    .text_position_for_point(mouse.pos - Vec2::new(LABEL_X_PADDING, 0.0));
+                let index = position.saturating_sub(self.offset);
+                if mouse.mods.shift() {
+                    let sentence = sentence_at(self.text.as_str(), index);
+                    if !sentence.is_empty() {
+                        ctx.submit_command(HIGHLIGHT_SENTENCE.with(sentence));
+                        ctx.set_handled();
+                        return;
+                    }
+                } else if let Some(word) = word_at(self.text.as_str(), index) {
+                    let context = sentence_at(self.text.as_str(), index);
+                    ctx.submit_command(LOOKUP_WORD.with((word, context)));
+                    ctx.set_handled();
+                    return;
+                }
+            }
+        }
+        child.event(ctx, event, data, env)
     }
 }
 
-pub fn h_font_size(h: i32) -> f64 {
-    // From Google
-    // h1 | 2em    | 32px
-    // h2 | 1.5em  | 24px
-    // h3 | 1.17em | 18.72px
-    // h4 | 1em    | 16px
-    // h5 | 0.83em | 13.28px
-    // h6 | 0.67em | 10.72px
-
-    let h_sizes: HashMap<i32, f64> = HashMap::from([
-        (1, 32_f64),
-        (2, 24_f64),
-        (3, 18.72_f64),
-        (4, 16_f64),
-        (5, 13.28_f64),
-        (6, 10.72_f64),
-    ]);
-
-    *h_sizes.get(&h).unwrap_or(&10.72_f64)
+/**
+ * LinkController
+ * Wraps the page and follows the links clicked in it, or shows the notes they refer to,
+ * looks up the words double clicked and highlights the sentences
+ */
+pub struct LinkController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LinkController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(FOLLOW_LINK) => {
+                let href = cmd.get_unchecked(FOLLOW_LINK);
+                data.navigate_to_page(Rc::new(href.clone()));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(SHOW_NOTE) => {
+                let href = cmd.get_unchecked(SHOW_NOTE);
+                data.show_note(Rc::new(href.clone()));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LOOKUP_WORD) => {
+                let (word, context) = cmd.get_unchecked(LOOKUP_WORD);
+                data.look_up_word(word.as_str(), context.as_str());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(HIGHLIGHT_SENTENCE) => {
+                let sentence = cmd.get_unchecked(HIGHLIGHT_SENTENCE);
+                data.highlight_sentence(sentence.as_str());
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
 }
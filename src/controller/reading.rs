@@ -0,0 +1,105 @@
+use crate::model::{app_state::AppState, ui_view::UiView};
+use druid::{
+    widget::{Axis, Controller, Scroll},
+    Env, Event, EventCtx, UpdateCtx, Widget,
+};
+
+/**
+ * ReadingController
+ * Wraps the reader and tracks the reading session of the open book
+ * (and loads its annotations when it is opened).
+ * Every page change happens while handling an event of the reader,
+ * so the session is updated after the event reached the children.
+ */
+pub struct ReadingController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ReadingController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::WindowCloseRequested = event {
+            data.end_reading_session();
+        }
+        child.event(ctx, event, data, env);
+        // The book may have been closed by the event
+        if data.nav_state.last() == Some(&UiView::BookRead) {
+            data.track_reading();
+            data.load_book_annotations();
+        }
+    }
+}
+
+/**
+ * ChapterScrollController
+ * Keeps the scroll of the chapter and the position saved in the book in sync:
+ * scrolling moves the position, seeking (or changing page) scrolls the chapter.
+ */
+#[derive(Default)]
+pub struct ChapterScrollController {
+    // Position to scroll to once the new content has been laid out
+    pending: Option<f64>,
+}
+
+impl ChapterScrollController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn max_offset<W: Widget<AppState>>(scroll: &Scroll<AppState, W>, height: f64) -> f64 {
+        (scroll.child_size().height - height).max(0.0)
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, Scroll<AppState, W>> for ChapterScrollController {
+    fn event(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::AnimFrame(_) = event {
+            if let Some(position) = self.pending.take() {
+                let max_offset = Self::max_offset(child, ctx.size().height);
+                child.scroll_to_on_axis(ctx, Axis::Vertical, position * max_offset);
+            }
+        }
+        let before = data.get_position();
+        child.event(ctx, event, data, env);
+
+        // Save the scroll, unless the event moved the reader somewhere else (e.g. a link)
+        let max_offset = Self::max_offset(child, ctx.size().height);
+        if self.pending.is_none() && max_offset > 0.0 && data.get_position() == before {
+            data.set_chapter_position(child.offset().y / max_offset);
+        }
+    }
+
+    fn update(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        child.update(ctx, old_data, data, env);
+
+        let position = data.get_chapter_position();
+        let max_offset = Self::max_offset(child, ctx.size().height);
+        let current = if max_offset > 0.0 {
+            child.offset().y / max_offset
+        } else {
+            0.0
+        };
+        if (position - current).abs() > 0.001 {
+            self.pending = Some(position);
+            ctx.request_anim_frame();
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use druid::{widget::Controller, Env, Event, EventCtx, Selector, Target, Widget};
+
+use crate::{
+    helper::speech::{SpeechEvent, SpeechListener},
+    model::app_state::AppState,
+};
+
+// Sent from the reading in the background, with the session of the reading
+pub const SPEECH_EVENT: Selector<(u64, SpeechEvent)> = Selector::new("reader.speech-event");
+
+/**
+ * Listener sending the events of a reading to the window, where SpeechController gets them
+ */
+pub fn speech_listener(ctx: &mut EventCtx) -> SpeechListener {
+    let sink = ctx.get_external_handle();
+    SpeechListener::new(move |session, event| {
+        if let Err(e) = sink.submit_command(SPEECH_EVENT, (session, event), Target::Auto) {
+            eprintln!("Error sending speech event: {}", e);
+        }
+    })
+}
+
+/**
+ * SpeechController
+ * Wraps the reader and follows the reading aloud: the sentence spoken is highlighted
+ * and the next chapter is read when the current one ends.
+ */
+pub struct SpeechController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SpeechController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(SPEECH_EVENT) => {
+                let (session, event) = cmd.get_unchecked(SPEECH_EVENT);
+                data.speech_event(*session, event);
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 pub const BOOK_READ: Selector<usize> = Selector::new("book-read");
 pub const BOOK_EDIT: Selector<usize> = Selector::new("book-edit");
+pub const METADATA_EDIT: Selector<usize> = Selector::new("metadata-edit");
 pub const POP_VIEW: Selector<()> = Selector::new("navigator.pop-view");
 
 // implements the view trait for your view type
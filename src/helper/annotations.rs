@@ -0,0 +1,366 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{artefacts::annotations_path, config::SAVED_PROGRESS_PATH, xml::XmlDocument};
+
+/*
+ * Annotations are the bookmarks and the highlights (sentences of the book, with a note
+ * written about them) of a book. They are saved with the progress of the book
+ * and can be exported to Markdown, JSON or a standalone HTML page.
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    Bookmark,
+    Highlight,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    // Page (in the spine) and position in the page (0.0 top, 1.0 bottom)
+    pub page: usize,
+    pub chapter_position: f64,
+    // Title of the chapter, from the table of contents or the first heading of the page
+    pub chapter: String,
+    // Sentence highlighted (empty for a bookmark) and note written about it
+    pub text: String,
+    pub note: String,
+    // Time the annotation was made (seconds since the epoch)
+    pub time: i64,
+}
+
+impl Annotation {
+    /**
+     * Position for the reader, e.g. "chapter 3, 42%"
+     */
+    pub fn position_label(&self) -> String {
+        format!(
+            "chapter {}, {:.0}%",
+            self.page,
+            self.chapter_position.clamp(0.0, 1.0) * 100.0
+        )
+    }
+}
+
+/**
+ * Annotations of a book, in the order of the book
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    pub title: String,
+    pub author: String,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Annotations {
+    /**
+     * Add an annotation in its place in the book, returns its index
+     */
+    pub fn add(&mut self, annotation: Annotation) -> usize {
+        let index = self
+            .annotations
+            .iter()
+            .position(|saved| {
+                (saved.page, saved.chapter_position)
+                    > (annotation.page, annotation.chapter_position)
+            })
+            .unwrap_or(self.annotations.len());
+        self.annotations.insert(index, annotation);
+        index
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.annotations.len() {
+            self.annotations.remove(index);
+        }
+    }
+
+    /**
+     * Highlight of a sentence of a page
+     */
+    pub fn find_highlight(&self, page: usize, text: &str) -> Option<usize> {
+        self.annotations.iter().position(|annotation| {
+            annotation.kind == AnnotationKind::Highlight
+                && annotation.page == page
+                && annotation.text == text
+        })
+    }
+
+    /**
+     * Sentences highlighted in a page
+     */
+    pub fn page_highlights(&self, page: usize) -> Vec<String> {
+        self.annotations
+            .iter()
+            .filter(|annotation| {
+                annotation.kind == AnnotationKind::Highlight && annotation.page == page
+            })
+            .map(|annotation| annotation.text.clone())
+            .collect()
+    }
+}
+
+pub fn load_annotations(title: &str, author: &str) -> Annotations {
+    let annotations = fs::read_to_string(annotations_path(title))
+        .ok()
+        .and_then(|json| serde_json::from_str::<Annotations>(json.as_str()).ok());
+    // The book may have been renamed since the annotations were saved
+    Annotations {
+        title: title.to_string(),
+        author: author.to_string(),
+        annotations: annotations
+            .map(|annotations| annotations.annotations)
+            .unwrap_or_default(),
+    }
+}
+
+pub fn save_annotations(annotations: &Annotations) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(SAVED_PROGRESS_PATH)?;
+    fs::write(
+        annotations_path(annotations.title.as_str()),
+        serde_json::to_string(annotations)?,
+    )?;
+    Ok(())
+}
+
+/**
+ * Title of the chapter of a page: the entry of the table of contents (label, path)
+ * pointing to the page, or else the first heading (or the title) of the page
+ */
+pub fn chapter_title(
+    toc: &[(String, PathBuf)],
+    page_path: Option<&Path>,
+    html: &str,
+) -> Option<String> {
+    if let Some(page_path) = page_path {
+        let entry = toc.iter().find(|(_, content)| {
+            let content = content.to_string_lossy();
+            let path = content.split('#').next().unwrap_or_default();
+            Path::new(path) == page_path
+        });
+        if let Some((label, _)) = entry {
+            let label = label.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if !label.is_empty() {
+                return Some(label);
+            }
+        }
+    }
+    let document = XmlDocument::parse_xhtml(html).ok()?;
+    ["h1", "h2", "h3", "title"].iter().find_map(|name| {
+        let text = document.root.find(name)?.text();
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+/**
+ * Formats the annotations are exported to
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Json,
+        ExportFormat::Html,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "HTML",
+        }
+    }
+
+    /**
+     * Format of a file from its extension, Markdown if it is not known
+     */
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        ExportFormat::ALL
+            .into_iter()
+            .find(|format| {
+                format.extension() == extension
+                    || (extension == "htm" && *format == ExportFormat::Html)
+            })
+            .unwrap_or(ExportFormat::Markdown)
+    }
+}
+
+/**
+ * Export the annotations of books (one book, or all the books of the library).
+ * The JSON of one book is an object, the one of several books an array.
+ */
+pub fn export_annotations(
+    books: &[Annotations],
+    format: ExportFormat,
+) -> Result<String, Box<dyn Error>> {
+    match format {
+        ExportFormat::Markdown => Ok(books
+            .iter()
+            .map(markdown)
+            .collect::<Vec<String>>()
+            .join("\n")),
+        ExportFormat::Json => match books {
+            [book] => Ok(serde_json::to_string_pretty(book)?),
+            books => Ok(serde_json::to_string_pretty(books)?),
+        },
+        ExportFormat::Html => Ok(html(books)),
+    }
+}
+
+/**
+ * Annotations grouped by page (the chapters of the book)
+ */
+fn chapters(annotations: &[Annotation]) -> Vec<&[Annotation]> {
+    let mut chapters = Vec::new();
+    let mut start = 0;
+    for index in 1..=annotations.len() {
+        if index == annotations.len() || annotations[index].page != annotations[start].page {
+            chapters.push(&annotations[start..index]);
+            start = index;
+        }
+    }
+    chapters
+}
+
+fn chapter_heading(annotation: &Annotation) -> String {
+    if annotation.chapter.is_empty() {
+        format!("Chapter {}", annotation.page)
+    } else {
+        annotation.chapter.clone()
+    }
+}
+
+fn markdown(book: &Annotations) -> String {
+    let mut markdown = format!("# {}\n\n", book.title);
+    if !book.author.is_empty() {
+        markdown.push_str(format!("*{}*\n\n", book.author).as_str());
+    }
+    for chapter in chapters(&book.annotations) {
+        markdown.push_str(format!("## {}\n\n", chapter_heading(&chapter[0])).as_str());
+        for annotation in chapter {
+            match annotation.kind {
+                AnnotationKind::Bookmark => markdown.push_str("### Bookmark\n\n"),
+                AnnotationKind::Highlight => {
+                    markdown.push_str("### Highlight\n\n");
+                    for line in annotation.text.lines() {
+                        markdown.push_str(format!("> {}\n", line).as_str());
+                    }
+                    markdown.push('\n');
+                }
+            }
+            if !annotation.note.trim().is_empty() {
+                markdown.push_str(format!("{}\n\n", annotation.note.trim()).as_str());
+            }
+            markdown.push_str(format!("*{}*\n\n", annotation.position_label()).as_str());
+        }
+    }
+    markdown
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body { font-family: Georgia, serif; max-width: 40em; margin: 2em auto; padding: 0 1em; color: #222; }
+h1 { margin-bottom: 0; }
+.author { color: #666; font-style: italic; margin-top: 0.2em; }
+blockquote { margin: 0.5em 0; padding-left: 1em; border-left: 3px solid #e0b000; }
+.note { white-space: pre-wrap; }
+.position { color: #888; font-size: 0.85em; }
+.annotation { margin-bottom: 1.5em; }";
+
+fn html(books: &[Annotations]) -> String {
+    let title = match books {
+        [book] => book.title.clone(),
+        _ => "Library notes".to_string(),
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n",
+        escape_html(title.as_str()),
+        HTML_STYLE
+    );
+    for book in books {
+        html.push_str("<section class=\"book\">\n");
+        html.push_str(format!("<h1>{}</h1>\n", escape_html(book.title.as_str())).as_str());
+        if !book.author.is_empty() {
+            html.push_str(
+                format!(
+                    "<p class=\"author\">{}</p>\n",
+                    escape_html(book.author.as_str())
+                )
+                .as_str(),
+            );
+        }
+        for chapter in chapters(&book.annotations) {
+            html.push_str(
+                format!(
+                    "<h2>{}</h2>\n",
+                    escape_html(chapter_heading(&chapter[0]).as_str())
+                )
+                .as_str(),
+            );
+            for annotation in chapter {
+                match annotation.kind {
+                    AnnotationKind::Bookmark => {
+                        html.push_str("<div class=\"annotation bookmark\">\n<h3>Bookmark</h3>\n")
+                    }
+                    AnnotationKind::Highlight => html.push_str(
+                        format!(
+                            "<div class=\"annotation highlight\">\n<blockquote>{}</blockquote>\n",
+                            escape_html(annotation.text.as_str())
+                        )
+                        .as_str(),
+                    ),
+                }
+                if !annotation.note.trim().is_empty() {
+                    html.push_str(
+                        format!(
+                            "<p class=\"note\">{}</p>\n",
+                            escape_html(annotation.note.trim())
+                        )
+                        .as_str(),
+                    );
+                }
+                html.push_str(
+                    format!(
+                        "<p class=\"position\">{}</p>\n</div>\n",
+                        escape_html(annotation.position_label().as_str())
+                    )
+                    .as_str(),
+                );
+            }
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    config::{AUDIO_PATH, COVERS_PATH, SAVED_PROGRESS_PATH},
+    thumbnail::invalidate_thumbnails,
+};
+
+/*
+ * Artefacts are the files kept for a book outside of its epub
+ * (progress, vocabulary, annotations, cover, cache, audio).
+ * They are named after the title of the book, so they must follow it
+ * when the book is renamed and be removed together with it.
+ */
+
+/**
+ * Name used for the files of a book
+ */
+pub fn book_slug(title: &str) -> String {
+    title.replace(' ', "-")
+}
+
+pub fn progress_path(title: &str) -> PathBuf {
+    Path::new(SAVED_PROGRESS_PATH).join(book_slug(title) + ".json")
+}
+
+pub fn stats_path(title: &str) -> PathBuf {
+    Path::new(SAVED_PROGRESS_PATH).join(book_slug(title) + ".stats.json")
+}
+
+/**
+ * Words looked up in a book
+ */
+pub fn vocabulary_path(title: &str) -> PathBuf {
+    Path::new(SAVED_PROGRESS_PATH).join(book_slug(title) + ".vocabulary.json")
+}
+
+/**
+ * Bookmarks and highlights of a book
+ */
+pub fn annotations_path(title: &str) -> PathBuf {
+    Path::new(SAVED_PROGRESS_PATH).join(book_slug(title) + ".annotations.json")
+}
+
+/**
+ * Folder of the chapters of a book read aloud into audio files
+ */
+pub fn audio_path(title: &str) -> PathBuf {
+    Path::new(AUDIO_PATH).join(book_slug(title))
+}
+
+/**
+ * Audio file of a chapter read aloud
+ * Example: ("The Prisoner of Zenda", 3) -> audio/The-Prisoner-of-Zenda/chapter-003.wav
+ */
+pub fn chapter_audio_path(title: &str, chapter: usize) -> PathBuf {
+    audio_path(title).join(format!("chapter-{:03}.wav", chapter))
+}
+
+/**
+ * Covers saved for a book (normally one, the extension follows the image format)
+ */
+pub fn cover_paths(title: &str) -> Vec<PathBuf> {
+    let slug = book_slug(title);
+    match fs::read_dir(COVERS_PATH) {
+        Ok(dir) => dir
+            .flatten()
+            .map(|file| file.path())
+            .filter(|path| path.file_stem().is_some_and(|stem| stem == slug.as_str()))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/**
+ * All the artefacts of a book that exist on disk
+ */
+pub fn artefact_paths(title: &str) -> Vec<PathBuf> {
+    let mut paths = vec![
+        progress_path(title),
+        stats_path(title),
+        vocabulary_path(title),
+        annotations_path(title),
+        audio_path(title),
+    ];
+    paths.append(&mut cover_paths(title));
+    paths.retain(|path| path.exists());
+    paths
+}
+
+/**
+ * Remove the artefacts of a book, cache_key is the key of its thumbnails
+ */
+pub fn remove_artefacts(title: &str, cache_key: &str) {
+    for path in artefact_paths(title) {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        if let Err(e) = removed {
+            eprintln!("Unable to remove {}: {}", path.display(), e);
+        }
+    }
+    invalidate_thumbnails(cache_key);
+}
+
+/**
+ * Move the artefacts of a book to its new title.
+ * Nothing is moved if the new title already has artefacts, they belong to another book
+ */
+pub fn rename_artefacts(old_title: &str, new_title: &str) -> io::Result<()> {
+    if book_slug(old_title) == book_slug(new_title) {
+        return Ok(());
+    }
+    if let Some(existing) = artefact_paths(new_title).first() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", existing.display()),
+        ));
+    }
+    let old_slug = book_slug(old_title);
+    let new_slug = book_slug(new_title);
+    for from in artefact_paths(old_title) {
+        // Keep what follows the title in the name (e.g. ".stats.json")
+        let name = from
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let suffix = name.strip_prefix(old_slug.as_str()).unwrap_or_default();
+        let to = from.with_file_name(new_slug.clone() + suffix);
+        if to.exists() {
+            eprintln!(
+                "Unable to move {}: {} already exists",
+                from.display(),
+                to.display()
+            );
+        } else if let Err(e) = fs::rename(&from, &to) {
+            eprintln!("Unable to move {}: {}", from.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Move a file into another folder, keeping its name.
+ * Returns the new path of the file.
+ */
+pub fn move_to_folder(path: &Path, folder: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+    let to = folder.join(name);
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+
+    // Renaming fails between different file systems, copy the file instead
+    if fs::rename(path, &to).is_err() {
+        fs::copy(path, &to)?;
+        fs::remove_file(path)?;
+    }
+    Ok(to)
+}
@@ -10,16 +10,57 @@ pub const PADDING_LG: f64 = 32.0;
 /* Font Sizes */
 pub const DEFAULT_FONT_SIZE: f64 = 8.0;
 pub const TITLE_SIZE: f64 = 24.0;
+pub const SUBTITLE_SIZE: f64 = 16.0;
 
 /* Text Styles */
 pub const TITLE: FontDescriptor = FontDescriptor::new(FontFamily::SANS_SERIF)
     .with_size(TITLE_SIZE)
     .with_weight(FontWeight::BOLD);
+pub const SUBTITLE: FontDescriptor = FontDescriptor::new(FontFamily::SANS_SERIF)
+    .with_size(SUBTITLE_SIZE)
+    .with_weight(FontWeight::BOLD);
 
 /* Data */
 pub const LIBRARY_PATH: &str = "./src/library";
 pub const SAVED_PROGRESS_PATH: &str = "./src/library/progress/";
 pub const COVERS_PATH: &str = "./src/library/covers/";
+pub const THUMBNAILS_PATH: &str = "./src/library/cache/thumbnails/";
+pub const AUDIO_PATH: &str = "./src/library/audio/";
+pub const SETTINGS_PATH: &str = "./src/library/settings.json";
+// StarDict dictionaries, in sub folders named with their language (e.g. dictionaries/en/)
+// when their .ifo file does not give it
+pub const DICTIONARIES_PATH: &str = "./src/library/dictionaries/";
+pub const LOOKUPS_PATH: &str = "./src/library/lookups.json";
+
+/* Reading statistics */
+// Words per minute used until the reading speed is known
+pub const DEFAULT_READING_SPEED: f64 = 250.0;
+// Pages left before this time are skimmed, not read
+pub const MIN_PAGE_SECONDS: i64 = 5;
+// Time on a page is capped at this, a book left open is not being read
+pub const IDLE_PAGE_SECONDS: i64 = 30 * 60;
+
+/* Progress */
+// Words in a location (Kindle-style locations, independent of the font size)
+pub const WORDS_PER_LOCATION: i64 = 25;
+
+/* Navigation history */
+// Positions kept for each book
+pub const HISTORY_LIMIT: usize = 100;
+
+/* Dictionary */
+// Words of the lookup history shown in the lookup panel
+pub const RECENT_LOOKUPS: usize = 10;
+
+/* Comics */
+// Zoom of the pages, from the whole page
+pub const MIN_ZOOM: f64 = 1.0;
+pub const MAX_ZOOM: f64 = 4.0;
+pub const ZOOM_STEP: f64 = 0.25;
+
+/* Generated covers */
+pub const COVER_WIDTH: usize = 400;
+pub const COVER_HEIGHT: usize = 600;
 
 /* Cover placeholder */
 pub const COVER_PLACEHOLDER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>
@@ -0,0 +1,703 @@
+use std::{
+    error::Error,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{
+    annotations::chapter_title,
+    cover::image_extension,
+    epub_writer::write_mimetype,
+    functions::resolve_relative_path,
+    opf::{image_media_type, PageProgression, CONTAINER_PATH},
+    source::{escape_xml, BookFormat, BookSource},
+    xml::{XmlDocument, XmlElement, XmlNode},
+};
+
+/*
+ * Conversion of a book of the library, whatever its format, into a clean EPUB3,
+ * a plain text file or a Markdown file.
+ * The EPUB is made again from the sections of the spine (kept at their paths, so their
+ * links still work) and the resources they use, with a new package document and a
+ * new navigation document. The mimetype entry is the first one, stored uncompressed.
+ * The text and the Markdown are the sections after the title page, with their headings.
+ */
+
+const PACKAGE_PATH: &str = "content.opf";
+const CONTAINER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n  <rootfiles>\n    <rootfile full-path=\"content.opf\" media-type=\"application/oebps-package+xml\"/>\n  </rootfiles>\n</container>\n";
+
+// Elements of an XHTML page that are blocks of text (the others are inside a line)
+const BLOCKS: [&str; 28] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+// Elements whose content is not text of the book
+const SKIPPED: [&str; 4] = ["head", "script", "style", "svg"];
+
+/**
+ * Formats a book can be converted into
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConvertFormat {
+    Epub,
+    Text,
+    Markdown,
+}
+
+impl ConvertFormat {
+    pub const ALL: [ConvertFormat; 3] = [
+        ConvertFormat::Epub,
+        ConvertFormat::Text,
+        ConvertFormat::Markdown,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConvertFormat::Epub => "epub",
+            ConvertFormat::Text => "txt",
+            ConvertFormat::Markdown => "md",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConvertFormat::Epub => "EPUB",
+            ConvertFormat::Text => "Plain text",
+            ConvertFormat::Markdown => "Markdown",
+        }
+    }
+
+    /**
+     * Format of a file from its extension, None if it is not known
+     */
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        ConvertFormat::ALL.into_iter().find(|format| {
+            format.extension() == extension
+                || (extension == "markdown" && *format == ConvertFormat::Markdown)
+        })
+    }
+}
+
+/**
+ * File and format of a conversion saved at path: a name without a known extension
+ * gets the EPUB one (book.pdf -> book.pdf.epub), so the name always matches the content
+ */
+pub fn converted_path(path: PathBuf) -> (PathBuf, ConvertFormat) {
+    match ConvertFormat::from_path(&path) {
+        Some(format) => (path, format),
+        None => {
+            let mut name = path.into_os_string();
+            name.push(".");
+            name.push(ConvertFormat::Epub.extension());
+            (PathBuf::from(name), ConvertFormat::Epub)
+        }
+    }
+}
+
+/**
+ * Convert a book into a format, returns the content of the converted file
+ */
+pub fn convert_book(
+    source: &mut dyn BookSource,
+    format: ConvertFormat,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if format != ConvertFormat::Epub && source.format() == BookFormat::Comic {
+        return Err("The pages of a comic book have no text to convert".into());
+    }
+    match format {
+        ConvertFormat::Epub => epub(source),
+        ConvertFormat::Text => Ok(text(source, false)?.into_bytes()),
+        ConvertFormat::Markdown => Ok(text(source, true)?.into_bytes()),
+    }
+}
+
+/**
+ * Sections of the spine of a book (path, XHTML), in reading order
+ */
+fn spine(source: &mut dyn BookSource) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let mut sections = Vec::new();
+    for index in 0..source.len() {
+        let path = source
+            .section_path(index)
+            .ok_or_else(|| format!("No section {}", index))?;
+        sections.push((path, source.section(index)?));
+    }
+    Ok(sections)
+}
+
+/* Plain text and Markdown */
+
+/**
+ * Text of the book (Markdown, with its headings and emphasis, if markdown):
+ * the title and the author, then the sections after the title page
+ */
+fn text(source: &mut dyn BookSource, markdown: bool) -> Result<String, Box<dyn Error>> {
+    let title = source.metadata("title").unwrap_or("Unknown".to_string());
+    let mut blocks = vec![if markdown {
+        format!("# {}", title)
+    } else {
+        title.to_uppercase()
+    }];
+    if let Some(author) = source.metadata("creator") {
+        blocks.push(author);
+    }
+    for (path, html) in spine(source)?.into_iter().skip(1) {
+        match XmlDocument::parse_xhtml(html.as_str()) {
+            Ok(document) => text_blocks(&document.root, markdown, &mut blocks),
+            Err(e) => eprintln!("Error converting {}: {}", path.display(), e),
+        }
+    }
+    Ok(blocks.join("\n\n") + "\n")
+}
+
+fn is_block(element: &XmlElement) -> bool {
+    BLOCKS.contains(&element.local_name())
+}
+
+/**
+ * Text inside a block: the whitespace of the XHTML is collapsed, the line breaks are kept
+ */
+fn inline_text(element: &XmlElement, markdown: bool) -> String {
+    let mut text = String::new();
+    inline(&element.children, markdown, &mut text);
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join(if markdown { "  \n" } else { "\n" })
+}
+
+fn inline(nodes: &[XmlNode], markdown: bool, text: &mut String) {
+    for node in nodes {
+        let element = match node {
+            XmlNode::Text(content) => {
+                text.push_str(content.replace('\n', " ").as_str());
+                continue;
+            }
+            XmlNode::Element(element) => element,
+            XmlNode::Other(_) => continue,
+        };
+        let mark = match element.local_name() {
+            "br" => {
+                text.push('\n');
+                continue;
+            }
+            name if SKIPPED.contains(&name) => continue,
+            "em" | "i" | "cite" if markdown => "*",
+            "strong" | "b" if markdown => "**",
+            "code" if markdown => "`",
+            _ => "",
+        };
+        let mut content = String::new();
+        inline(&element.children, markdown, &mut content);
+        if content.trim().is_empty() {
+            text.push_str(content.as_str());
+        } else {
+            // The marks go around the words, not the spaces
+            let start = content.len() - content.trim_start().len();
+            let end = content.trim_end().len();
+            text.push_str(&content[..start]);
+            text.push_str(mark);
+            text.push_str(&content[start..end]);
+            text.push_str(mark);
+            text.push_str(&content[end..]);
+        }
+    }
+}
+
+/**
+ * Blocks of text of an element (paragraphs, headings, items of lists...)
+ */
+fn text_blocks(element: &XmlElement, markdown: bool, blocks: &mut Vec<String>) {
+    let name = element.local_name();
+    if SKIPPED.contains(&name) {
+        return;
+    }
+    let level = name
+        .strip_prefix('h')
+        .and_then(|level| level.parse::<usize>().ok())
+        .filter(|level| (1..=6).contains(level));
+    if let Some(level) = level {
+        let heading = inline_text(element, false);
+        if heading.is_empty() {
+            return;
+        }
+        // The headings of the book are under its title
+        if markdown {
+            blocks.push(format!("{} {}", "#".repeat((level + 1).min(6)), heading));
+        } else {
+            blocks.push(heading);
+        }
+        return;
+    }
+    match name {
+        "hr" => blocks.push(if markdown { "---" } else { "* * *" }.to_string()),
+        "pre" => {
+            let code = element.text().trim_end().to_string();
+            if markdown {
+                blocks.push(format!("```\n{}\n```", code));
+            } else {
+                blocks.push(code);
+            }
+        }
+        "ul" | "ol" => {
+            let mut items = Vec::new();
+            for (index, item) in element.elements().enumerate() {
+                let marker = match name {
+                    "ol" => format!("{}. ", index + 1),
+                    _ => "- ".to_string(),
+                };
+                let mut item_blocks = Vec::new();
+                text_blocks(item, markdown, &mut item_blocks);
+                let item = item_blocks.join("\n").replace('\n', "\n   ");
+                if !item.is_empty() {
+                    items.push(format!("{}{}", marker, item));
+                }
+            }
+            if !items.is_empty() {
+                blocks.push(items.join("\n"));
+            }
+        }
+        "blockquote" => {
+            let mut quote = Vec::new();
+            children_blocks(element, markdown, &mut quote);
+            let prefix = if markdown { "> " } else { "    " };
+            for block in quote {
+                let lines: Vec<String> = block
+                    .lines()
+                    .map(|line| format!("{}{}", prefix, line))
+                    .collect();
+                blocks.push(lines.join("\n"));
+            }
+        }
+        "table" => {
+            let mut rows = Vec::new();
+            table_rows(element, markdown, &mut rows);
+            if !rows.is_empty() {
+                blocks.push(rows.join("\n"));
+            }
+        }
+        _ => children_blocks(element, markdown, blocks),
+    }
+}
+
+/**
+ * Blocks of the children of an element, the text between its child blocks is a block too
+ */
+fn children_blocks(element: &XmlElement, markdown: bool, blocks: &mut Vec<String>) {
+    let mut run: Vec<XmlNode> = Vec::new();
+    let flush = |run: &mut Vec<XmlNode>, blocks: &mut Vec<String>| {
+        let mut paragraph = XmlElement::new("p");
+        paragraph.children = std::mem::take(run);
+        let text = inline_text(&paragraph, markdown);
+        if !text.is_empty() {
+            blocks.push(text);
+        }
+    };
+    for child in element.children.iter() {
+        match child {
+            XmlNode::Element(child) if is_block(child) => {
+                flush(&mut run, blocks);
+                text_blocks(child, markdown, blocks);
+            }
+            child => run.push(child.clone()),
+        }
+    }
+    flush(&mut run, blocks);
+}
+
+fn table_rows(element: &XmlElement, markdown: bool, rows: &mut Vec<String>) {
+    for child in element.elements() {
+        match child.local_name() {
+            "tr" => {
+                let cells: Vec<String> = child
+                    .elements()
+                    .map(|cell| inline_text(cell, markdown).replace('\n', " "))
+                    .collect();
+                if markdown {
+                    rows.push(format!("| {} |", cells.join(" | ")));
+                } else {
+                    rows.push(cells.join("\t"));
+                }
+                // The first row of a Markdown table is its header
+                if markdown && rows.len() == 1 {
+                    rows.push(format!("|{}", " --- |".repeat(cells.len())));
+                }
+            }
+            _ => table_rows(child, markdown, rows),
+        }
+    }
+}
+
+/* EPUB */
+
+/**
+ * Media type of a file of an EPUB from its extension
+ */
+fn media_type(path: &Path) -> &'static str {
+    if let Some(media_type) = image_media_type(path) {
+        return media_type;
+    }
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "xhtml" | "html" | "htm" => "application/xhtml+xml",
+        "css" => "text/css",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/**
+ * Name of the archive entry of a file of the book
+ */
+fn entry_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/**
+ * Href of a file of the book, relative to the package document (at the root)
+ */
+fn href(path: &Path) -> String {
+    entry_name(path).replace(' ', "%20")
+}
+
+/**
+ * Whether an href is a file of the book (not a web page, a mail or a data URL)
+ */
+fn is_local(href: &str) -> bool {
+    !href.is_empty() && !href.starts_with('#') && !href.starts_with('/') && !href.contains(':')
+}
+
+/**
+ * Files a section uses: images, stylesheets, media
+ */
+fn linked_files(element: &XmlElement, hrefs: &mut Vec<String>) {
+    let keys: &[&str] = match element.local_name() {
+        "img" | "source" | "audio" | "video" | "track" => &["src", "poster"],
+        "image" => &["href", "xlink:href"],
+        "link"
+            if element
+                .attr("rel")
+                .is_some_and(|rel| rel.contains("stylesheet")) =>
+        {
+            &["href"]
+        }
+        _ => &[],
+    };
+    for key in keys {
+        if let Some(href) = element.attr(key) {
+            hrefs.push(href.to_string());
+        }
+    }
+    for child in element.elements() {
+        linked_files(child, hrefs);
+    }
+}
+
+/**
+ * Files a stylesheet uses: url(...) of the fonts and the images
+ */
+fn css_urls(css: &str) -> Vec<String> {
+    css.split("url(")
+        .skip(1)
+        .filter_map(|part| part.split_once(')'))
+        .map(|(url, _)| url.trim().trim_matches(['"', '\'']).to_string())
+        .collect()
+}
+
+/**
+ * Read the files used by the sections (and by their stylesheets) from the book
+ */
+fn linked_resources(
+    source: &mut dyn BookSource,
+    sections: &[(PathBuf, String)],
+) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut queue: Vec<(PathBuf, String)> = Vec::new();
+    for (path, html) in sections {
+        let mut hrefs = Vec::new();
+        if let Ok(document) = XmlDocument::parse_xhtml(html.as_str()) {
+            linked_files(&document.root, &mut hrefs);
+        }
+        queue.extend(hrefs.into_iter().map(|href| (path.clone(), href)));
+    }
+
+    let mut resources: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    while let Some((base, href)) = queue.pop() {
+        let href = href.split(['#', '?']).next().unwrap_or_default();
+        if !is_local(href) {
+            continue;
+        }
+        let path = resolve_relative_path(&base, href);
+        let known = resources.iter().any(|(saved, _)| *saved == path)
+            || sections.iter().any(|(section, _)| *section == path);
+        if known || path.as_os_str().is_empty() {
+            continue;
+        }
+        match source.resource(&path) {
+            Ok(bytes) => {
+                if media_type(&path) == "text/css" {
+                    let css = String::from_utf8_lossy(&bytes).to_string();
+                    queue.extend(
+                        css_urls(css.as_str())
+                            .into_iter()
+                            .map(|url| (path.clone(), url)),
+                    );
+                }
+                resources.push((path, bytes));
+            }
+            Err(e) => eprintln!("Missing resource {}: {}", path.display(), e),
+        }
+    }
+    resources.sort_by(|(a, _), (b, _)| a.cmp(b));
+    resources
+}
+
+/**
+ * Path for a new file of the book that is not one of its files yet
+ */
+fn free_path(name: &str, extension: &str, taken: &[&Path]) -> PathBuf {
+    let mut path = PathBuf::from(format!("{}.{}", name, extension));
+    let mut count = 1;
+    while taken.contains(&path.as_path()) {
+        path = PathBuf::from(format!("{}-{}.{}", name, count, extension));
+        count += 1;
+    }
+    path
+}
+
+/**
+ * Navigation document: the table of contents of the book, or the titles of its sections
+ */
+fn navigation(source: &dyn BookSource, sections: &[(PathBuf, String)], title: &str) -> String {
+    let mut toc = source.toc();
+    if toc.is_empty() {
+        toc = sections
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(index, (path, html))| {
+                let label = chapter_title(&[], Some(path), html.as_str())
+                    .unwrap_or_else(|| format!("Section {}", index));
+                (label, path.clone())
+            })
+            .collect();
+    }
+    let items: Vec<String> = toc
+        .iter()
+        .map(|(label, path)| {
+            format!(
+                "      <li><a href=\"{}\">{}</a></li>",
+                escape_xml(href(path).as_str()),
+                escape_xml(
+                    label
+                        .split_whitespace()
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                        .as_str()
+                )
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head>\n<title>{}</title>\n</head>\n<body>\n  <nav epub:type=\"toc\" id=\"toc\">\n    <h1>Contents</h1>\n    <ol>\n{}\n    </ol>\n  </nav>\n</body>\n</html>\n",
+        escape_xml(title),
+        items.join("\n")
+    )
+}
+
+/**
+ * Package document of the converted book
+ */
+fn package(
+    source: &mut dyn BookSource,
+    sections: &[(PathBuf, String)],
+    resources: &[(PathBuf, Vec<u8>)],
+    nav_path: &Path,
+    cover_path: Option<&Path>,
+) -> String {
+    let title = source.metadata("title").unwrap_or("Unknown".to_string());
+    let identifier = source.metadata("identifier").unwrap_or_else(|| {
+        let name: String = title
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("urn:ebook-reader:{}", name)
+    });
+    let language = source.metadata("language").unwrap_or("en".to_string());
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+
+    let mut metadata = vec![
+        format!(
+            "<dc:identifier id=\"book-id\">{}</dc:identifier>",
+            escape_xml(identifier.as_str())
+        ),
+        format!("<dc:title>{}</dc:title>", escape_xml(title.as_str())),
+    ];
+    if let Some(author) = source.metadata("creator") {
+        metadata.push(format!(
+            "<dc:creator>{}</dc:creator>",
+            escape_xml(author.as_str())
+        ));
+    }
+    metadata.push(format!(
+        "<dc:language>{}</dc:language>",
+        escape_xml(language.as_str())
+    ));
+    metadata.push(format!(
+        "<meta property=\"dcterms:modified\">{}</meta>",
+        modified
+    ));
+    if cover_path.is_some() {
+        metadata.push("<meta name=\"cover\" content=\"cover-image\"/>".to_string());
+    }
+
+    let item = |id: &str, path: &Path, properties: Option<&str>| {
+        let properties = properties
+            .map(|properties| format!(" properties=\"{}\"", properties))
+            .unwrap_or_default();
+        format!(
+            "<item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>",
+            id,
+            escape_xml(href(path).as_str()),
+            media_type(path),
+            properties
+        )
+    };
+    let mut manifest = vec![item("nav", nav_path, Some("nav"))];
+    for (index, (path, _)) in sections.iter().enumerate() {
+        manifest.push(item(format!("section-{}", index).as_str(), path, None));
+    }
+    for (index, (path, _)) in resources.iter().enumerate() {
+        let cover = (Some(path.as_path()) == cover_path).then_some("cover-image");
+        let id = match cover {
+            Some(_) => "cover-image".to_string(),
+            None => format!("resource-{}", index),
+        };
+        manifest.push(item(id.as_str(), path, cover));
+    }
+    let spine: Vec<String> = (0..sections.len())
+        .map(|index| format!("<itemref idref=\"section-{}\"/>", index))
+        .collect();
+    let direction = match source.page_progression() {
+        PageProgression::Rtl => " page-progression-direction=\"rtl\"",
+        PageProgression::Ltr => "",
+    };
+
+    let indent = |lines: Vec<String>| {
+        lines
+            .iter()
+            .map(|line| format!("    {}", line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{}\n  </metadata>\n  <manifest>\n{}\n  </manifest>\n  <spine{}>\n{}\n  </spine>\n</package>\n",
+        indent(metadata),
+        indent(manifest),
+        direction,
+        indent(spine)
+    )
+}
+
+/**
+ * EPUB3 of the book
+ */
+fn epub(source: &mut dyn BookSource) -> Result<Vec<u8>, Box<dyn Error>> {
+    let sections = spine(source)?;
+    let mut resources = linked_resources(source, &sections);
+
+    // The cover: one of the images of the book, or a new one
+    let cover_path = match source.cover() {
+        Some(cover) => match resources.iter().find(|(_, bytes)| *bytes == cover) {
+            Some((path, _)) => Some(path.clone()),
+            None => {
+                let taken: Vec<&Path> = sections
+                    .iter()
+                    .map(|(path, _)| path.as_path())
+                    .chain(resources.iter().map(|(path, _)| path.as_path()))
+                    .collect();
+                let path = free_path("cover", image_extension(&cover).unwrap_or("jpg"), &taken);
+                resources.push((path.clone(), cover));
+                Some(path)
+            }
+        },
+        None => None,
+    };
+    let taken: Vec<&Path> = sections
+        .iter()
+        .map(|(path, _)| path.as_path())
+        .chain(resources.iter().map(|(path, _)| path.as_path()))
+        .collect();
+    let nav_path = free_path("nav", "xhtml", &taken);
+
+    let title = source.metadata("title").unwrap_or("Unknown".to_string());
+    let nav = navigation(source, &sections, title.as_str());
+    let opf = package(
+        source,
+        &sections,
+        &resources,
+        &nav_path,
+        cover_path.as_deref(),
+    );
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    write_mimetype(&mut writer)?;
+
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut entries: Vec<(String, &[u8])> = vec![
+        (CONTAINER_PATH.to_string(), CONTAINER.as_bytes()),
+        (PACKAGE_PATH.to_string(), opf.as_bytes()),
+        (entry_name(&nav_path), nav.as_bytes()),
+    ];
+    for (path, content) in sections.iter() {
+        entries.push((entry_name(path), content.as_bytes()));
+    }
+    for (path, content) in resources.iter() {
+        entries.push((entry_name(path), content.as_slice()));
+    }
+    for (name, content) in entries {
+        writer.start_file(name, deflated)?;
+        writer.write_all(content)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
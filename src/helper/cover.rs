@@ -0,0 +1,197 @@
+use druid::{
+    kurbo::Rect,
+    piet::{
+        Device, FontFamily, FontWeight, ImageFormat, RenderContext, Text, TextAlignment,
+        TextLayout, TextLayoutBuilder,
+    },
+    Color,
+};
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use super::config::{COVERS_PATH, COVER_HEIGHT, COVER_WIDTH};
+
+/**
+ * Background colours for the generated covers
+ */
+const COVER_COLORS: [(u8, u8, u8); 8] = [
+    (0x2e, 0x4a, 0x62),
+    (0x7a, 0x2e, 0x3a),
+    (0x2f, 0x5d, 0x50),
+    (0x5b, 0x3f, 0x7a),
+    (0x8a, 0x5a, 0x1c),
+    (0x3b, 0x3b, 0x3b),
+    (0x1f, 0x5f, 0x8b),
+    (0x6b, 0x4e, 0x3d),
+];
+
+/**
+ * Get the file extension matching the format of the image bytes (png, jpg...)
+ */
+pub fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes) {
+        Ok(format) => format.extensions_str().first().copied(),
+        Err(_) => None,
+    }
+}
+
+/**
+ * Save the cover bytes in the covers folder, with the extension of the real image format.
+ * Covers of the same book saved with another extension are removed.
+ * Returns the path of the saved cover.
+ */
+pub fn save_cover_file(title: &str, bytes: &[u8]) -> Option<String> {
+    let extension = match image_extension(bytes) {
+        Some(extension) => extension,
+        None => {
+            eprintln!("Unknown cover format for {}", title);
+            return None;
+        }
+    };
+    let name = title.replace(' ', "-");
+    let cover_path = format!("{}{}.{}", COVERS_PATH, name, extension);
+
+    // Remove stale covers (e.g. a .png cover replaced by a .jpg one)
+    if let Ok(dir) = fs::read_dir(COVERS_PATH) {
+        for file in dir.flatten() {
+            let path = file.path();
+            let same_book = path.file_stem().is_some_and(|stem| stem == name.as_str());
+            if same_book && path != Path::new(cover_path.as_str()) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    // Rewriting the same cover would make its thumbnails look stale
+    if fs::read(cover_path.as_str()).is_ok_and(|saved| saved == bytes) {
+        return Some(cover_path);
+    }
+
+    match fs::write(cover_path.as_str(), bytes) {
+        Ok(_) => {
+            println!("Book cover path: {}", cover_path);
+            Some(cover_path)
+        }
+        Err(e) => {
+            eprintln!("Unable to save cover: {}", e);
+            None
+        }
+    }
+}
+
+/**
+ * Get the source of the first image (<img src> or svg <image href>) of an html page
+ */
+pub fn first_image_src(html: &str) -> Option<String> {
+    // Ascii lowercase keeps the byte offsets of the original string
+    let lower = html.to_ascii_lowercase();
+    let start = match (lower.find("<img"), lower.find("<image")) {
+        (Some(img), Some(image)) => img.min(image),
+        (img, image) => img.or(image)?,
+    };
+    let tag_end = start + lower[start..].find('>')?;
+    let tag = &html[start..tag_end];
+    let tag_lower = &lower[start..tag_end];
+
+    for attribute in ["src=", "xlink:href=", "href="] {
+        if let Some(position) = tag_lower.find(attribute) {
+            let value = &tag[position + attribute.len()..];
+            let quote = value.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                continue;
+            }
+            let end = value[1..].find(quote)?;
+            return Some(value[1..end + 1].to_string());
+        }
+    }
+    None
+}
+
+/**
+ * Generate a cover with the title and the author of the book on a coloured background.
+ * The colour depends on the title, so a book always gets the same cover.
+ * Returns the png bytes of the cover.
+ */
+pub fn generate_cover(title: &str, author: &str) -> Option<Vec<u8>> {
+    let mut hasher = DefaultHasher::new();
+    title.hash(&mut hasher);
+    let (r, g, b) = COVER_COLORS[(hasher.finish() % COVER_COLORS.len() as u64) as usize];
+
+    let width = COVER_WIDTH as f64;
+    let height = COVER_HEIGHT as f64;
+    let margin = width / 10.0;
+
+    let mut device = match Device::new() {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Unable to generate cover: {}", e);
+            return None;
+        }
+    };
+    let mut target = device.bitmap_target(COVER_WIDTH, COVER_HEIGHT, 1.0).ok()?;
+    {
+        let mut rc = target.render_context();
+        rc.fill(Rect::new(0.0, 0.0, width, height), &Color::rgb8(r, g, b));
+        // Light frame around the text
+        rc.stroke(
+            Rect::new(
+                margin / 2.0,
+                margin / 2.0,
+                width - margin / 2.0,
+                height - margin / 2.0,
+            ),
+            &Color::rgba8(0xff, 0xff, 0xff, 0x80),
+            2.0,
+        );
+
+        let title_layout = rc
+            .text()
+            .new_text_layout(title.to_string())
+            .font(FontFamily::SERIF, width / 10.0)
+            .default_attribute(FontWeight::BOLD)
+            .text_color(Color::WHITE)
+            .alignment(TextAlignment::Center)
+            .max_width(width - 2.0 * margin)
+            .build()
+            .ok()?;
+        let author_layout = rc
+            .text()
+            .new_text_layout(author.to_string())
+            .font(FontFamily::SANS_SERIF, width / 16.0)
+            .text_color(Color::rgb8(0xe0, 0xe0, 0xe0))
+            .alignment(TextAlignment::Center)
+            .max_width(width - 2.0 * margin)
+            .build()
+            .ok()?;
+
+        rc.draw_text(&title_layout, (margin, height / 4.0));
+        rc.draw_text(
+            &author_layout,
+            (margin, height - 2.0 * margin - author_layout.size().height),
+        );
+        if let Err(e) = rc.finish() {
+            eprintln!("Unable to generate cover: {}", e);
+            return None;
+        }
+    }
+
+    let image_buf = target.to_image_buf(ImageFormat::RgbaSeparate).ok()?;
+    let image = RgbaImage::from_raw(
+        image_buf.width() as u32,
+        image_buf.height() as u32,
+        image_buf.raw_pixels().to_vec(),
+    )?;
+    let mut bytes = Vec::new();
+    match DynamicImage::ImageRgba8(image).write_to(&mut bytes, ImageOutputFormat::Png) {
+        Ok(_) => Some(bytes),
+        Err(e) => {
+            eprintln!("Unable to encode cover: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use super::xml::XmlElement;
+
+/*
+ * Minimal CSS used to lay out the chapters: the rules of the stylesheets of a book
+ * with type, class and id selectors, combined with descendant (" ") and child (">") combinators.
+ * Rules with other selectors (pseudo-classes, attributes, siblings) and at-rules are skipped.
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub property: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/**
+ * Compounds of a selector from left to right,
+ * with the combinator linking each one to the previous one
+ */
+#[derive(Clone, Debug, PartialEq)]
+struct Selector {
+    parts: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    selector: Selector,
+    specificity: (usize, usize, usize),
+    declarations: Vec<Declaration>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stylesheet {
+    rules: Vec<Rule>,
+}
+
+impl Compound {
+    fn parse(text: &str) -> Option<Compound> {
+        let mut compound = Compound::default();
+        // Split before each "." and "#", e.g. "p.note#first" -> "p", ".note", "#first"
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            if (c == '.' || c == '#') && i > start {
+                parts.push(&text[start..i]);
+                start = i;
+            }
+        }
+        parts.push(&text[start..]);
+
+        for part in parts {
+            let valid_name = |name: &str| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            };
+            if let Some(class) = part.strip_prefix('.') {
+                if !valid_name(class) {
+                    return None;
+                }
+                compound.classes.push(class.to_string());
+            } else if let Some(id) = part.strip_prefix('#') {
+                if !valid_name(id) {
+                    return None;
+                }
+                compound.id = Some(id.to_string());
+            } else if part != "*" {
+                if !valid_name(part) {
+                    return None;
+                }
+                compound.tag = Some(part.to_lowercase());
+            }
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &XmlElement) -> bool {
+        if let Some(tag) = &self.tag {
+            if !element.local_name().eq_ignore_ascii_case(tag) {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if element.attr("id") != Some(id.as_str()) {
+                return false;
+            }
+        }
+        let classes: Vec<&str> = element
+            .attr("class")
+            .map(|class| class.split_whitespace().collect())
+            .unwrap_or_default();
+        self.classes
+            .iter()
+            .all(|class| classes.contains(&class.as_str()))
+    }
+}
+
+impl Selector {
+    fn parse(text: &str) -> Option<Selector> {
+        let mut parts = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        for token in text.replace('>', " > ").split_whitespace() {
+            if token == ">" {
+                if parts.is_empty() {
+                    return None;
+                }
+                combinator = Combinator::Child;
+                continue;
+            }
+            parts.push((combinator, Compound::parse(token)?));
+            combinator = Combinator::Descendant;
+        }
+        if parts.is_empty() || combinator == Combinator::Child {
+            return None;
+        }
+        Some(Selector { parts })
+    }
+
+    fn specificity(&self) -> (usize, usize, usize) {
+        self.parts
+            .iter()
+            .fold((0, 0, 0), |(ids, classes, tags), (_, compound)| {
+                (
+                    ids + compound.id.is_some() as usize,
+                    classes + compound.classes.len(),
+                    tags + compound.tag.is_some() as usize,
+                )
+            })
+    }
+
+    /**
+     * Whether the selector matches the element, ancestors go from the root to its parent
+     */
+    fn matches(&self, element: &XmlElement, ancestors: &[&XmlElement]) -> bool {
+        self.matches_part(self.parts.len() - 1, element, ancestors)
+    }
+
+    fn matches_part(&self, index: usize, element: &XmlElement, ancestors: &[&XmlElement]) -> bool {
+        let (combinator, compound) = &self.parts[index];
+        if !compound.matches(element) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        match combinator {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, ancestors)) => self.matches_part(index - 1, parent, ancestors),
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| self.matches_part(index - 1, ancestors[i], &ancestors[..i])),
+        }
+    }
+}
+
+/**
+ * Declarations of a block or of a style attribute ("margin: 0; font-style: italic")
+ */
+pub fn parse_declarations(text: &str) -> Vec<Declaration> {
+    text.split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let property = property.trim().to_lowercase();
+            let value = value.trim().trim_end_matches("!important").trim();
+            if property.is_empty() || value.is_empty() {
+                return None;
+            }
+            Some(Declaration {
+                property,
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn remove_comments(css: &str) -> String {
+    let mut text = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        text.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    text
+}
+
+/**
+ * Index after the block starting at the "{" at index start (nested blocks included)
+ */
+fn block_end(css: &str, start: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in css[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + i + 1;
+                }
+            }
+            _ => (),
+        }
+    }
+    css.len()
+}
+
+impl Stylesheet {
+    pub fn parse(css: &str) -> Stylesheet {
+        let css = remove_comments(css);
+        let mut rules = Vec::new();
+        let mut rest = css.as_str();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let open = rest.find('{');
+            // At-rules: "@import ...;", "@media ... { ... }", "@font-face { ... }"
+            if rest.starts_with('@') {
+                let semicolon = rest.find(';');
+                rest = match (open, semicolon) {
+                    (Some(open), Some(semicolon)) if semicolon < open => &rest[semicolon + 1..],
+                    (Some(open), _) => &rest[block_end(rest, open)..],
+                    (None, Some(semicolon)) => &rest[semicolon + 1..],
+                    (None, None) => "",
+                };
+                continue;
+            }
+            let open = match open {
+                Some(open) => open,
+                None => break,
+            };
+            let end = block_end(rest, open);
+            let body = rest[open + 1..end]
+                .strip_suffix('}')
+                .unwrap_or(&rest[open + 1..end]);
+            let declarations = parse_declarations(body);
+            for selector in rest[..open].split(',').filter_map(Selector::parse) {
+                rules.push(Rule {
+                    specificity: selector.specificity(),
+                    selector,
+                    declarations: declarations.clone(),
+                });
+            }
+            rest = &rest[end..];
+        }
+        Stylesheet { rules }
+    }
+
+    /**
+     * Add the rules of another stylesheet, they come after the rules of this one
+     */
+    pub fn extend(&mut self, other: Stylesheet) {
+        self.rules.extend(other.rules);
+    }
+
+    /**
+     * Declarations applying to the element, from the weakest to the strongest
+     * (by specificity, then by order), the style attribute comes last
+     */
+    pub fn declarations(
+        &self,
+        element: &XmlElement,
+        ancestors: &[&XmlElement],
+    ) -> Vec<Declaration> {
+        let mut rules: Vec<&Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.selector.matches(element, ancestors))
+            .collect();
+        // The sort is stable, rules with the same specificity stay in order
+        rules.sort_by_key(|rule| rule.specificity);
+
+        let mut declarations: Vec<Declaration> = rules
+            .into_iter()
+            .flat_map(|rule| rule.declarations.iter().cloned())
+            .collect();
+        if let Some(style) = element.attr("style") {
+            declarations.extend(parse_declarations(style));
+        }
+        declarations
+    }
+}
@@ -0,0 +1,645 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Debug,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use flate2::{read::GzDecoder, Decompress, FlushDecompress};
+use serde::{Deserialize, Serialize};
+
+use super::speech::{find_sentence, split_sentences};
+
+// Words kept in the lookup history
+pub const LOOKUP_HISTORY_LIMIT: usize = 200;
+
+// Flags of a gzip header, and the part of the file read to find the dictzip chunks
+// (the RA field can have up to 65535 bytes)
+const GZIP_HEADER_CRC: u8 = 2;
+const GZIP_EXTRA: u8 = 4;
+const GZIP_NAME: u8 = 8;
+const GZIP_COMMENT: u8 = 16;
+const MAX_GZIP_HEADER: usize = 12 + 65535 + 4096;
+
+// Lowercase word -> headword, offset and size of its entries in the .dict file
+type Index = HashMap<String, Vec<(String, u64, usize)>>;
+
+/**
+ * Definition of a word found in a dictionary
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    // Headword of the entry, as written in the dictionary
+    pub word: String,
+    pub text: String,
+    pub dictionary: String,
+}
+
+/**
+ * A dictionary the words of the books are looked up in, offline
+ */
+pub trait Dictionary: Debug + Send + Sync {
+    fn name(&self) -> &str;
+    // Language of the words (e.g. "en"), None if it is not known
+    fn language(&self) -> Option<&str>;
+    // Definitions of the word, the case is ignored
+    fn lookup(&self, word: &str) -> Vec<Definition>;
+}
+
+/**
+ * How the entries are read from the .dict file
+ */
+#[derive(Debug)]
+enum DictData {
+    // Not compressed: the bytes of an entry are read at its offset
+    Plain,
+    // Compressed with dictzip: only the chunks of an entry are decompressed
+    DictZip(DictZipChunks),
+    // Compressed with gzip only: the file is decompressed once, at the first lookup
+    Gzip(OnceLock<Result<Vec<u8>, String>>),
+}
+
+/**
+ * Chunks of a dictzip file, from the RA field of its gzip header: each chunk of
+ * chunk_length bytes of the dictionary is compressed on its own
+ */
+#[derive(Debug, PartialEq)]
+pub struct DictZipChunks {
+    pub chunk_length: usize,
+    // Offset in the file and compressed size of each chunk
+    pub chunks: Vec<(u64, usize)>,
+}
+
+/**
+ * A StarDict dictionary: the .ifo file describes it, the .idx file (or .idx.gz) gives
+ * the position of the entries in the .dict file (which can be compressed with dictzip,
+ * .dict.dz)
+ */
+#[derive(Debug)]
+pub struct StarDict {
+    name: String,
+    language: Option<String>,
+    index: Index,
+    dict_path: PathBuf,
+    dict_data: DictData,
+    // Types of the fields of the entries, when they are the same for all of them
+    same_type_sequence: Option<String>,
+}
+
+impl StarDict {
+    /**
+     * Open the dictionary of an .ifo file, the .idx and .dict files are next to it
+     */
+    pub fn open(ifo_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let ifo = fs::read_to_string(ifo_path)?;
+        let mut lines = ifo.lines();
+        if lines.next().map(|line| line.trim()) != Some("StarDict's dict ifo file") {
+            return Err(format!("{} is not a StarDict .ifo file", ifo_path.display()).into());
+        }
+        let options: HashMap<&str, &str> = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+
+        let offset_bits = match options.get("idxoffsetbits") {
+            Some(&"64") => 64,
+            _ => 32,
+        };
+        let idx = match ifo_path.with_extension("idx") {
+            path if path.exists() => fs::read(path)?,
+            _ => {
+                let mut idx = Vec::new();
+                GzDecoder::new(File::open(ifo_path.with_extension("idx.gz"))?)
+                    .read_to_end(&mut idx)?;
+                idx
+            }
+        };
+        let index = read_index(&idx, offset_bits)?;
+
+        let (dict_path, dict_data) = match ifo_path.with_extension("dict") {
+            path if path.exists() => (path, DictData::Plain),
+            _ => {
+                let path = ifo_path.with_extension("dict.dz");
+                if !path.exists() {
+                    return Err(format!("No .dict file for {}", ifo_path.display()).into());
+                }
+                let mut header = Vec::new();
+                File::open(&path)?
+                    .take(MAX_GZIP_HEADER as u64)
+                    .read_to_end(&mut header)?;
+                match dictzip_chunks(&header) {
+                    Some(chunks) => (path, DictData::DictZip(chunks)),
+                    None => (path, DictData::Gzip(OnceLock::new())),
+                }
+            }
+        };
+
+        let name = options
+            .get("bookname")
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| file_stem(ifo_path));
+        // The "lang" option (e.g. "en-it") is not in all the dictionaries, the folder
+        // of the dictionary (e.g. dictionaries/en/) gives the language otherwise
+        let language = options
+            .get("lang")
+            .and_then(|lang| lang.split(['-', '_', ' ']).next())
+            .map(|lang| lang.to_lowercase())
+            .filter(|lang| !lang.is_empty())
+            .or_else(|| folder_language(ifo_path));
+
+        Ok(Self {
+            name,
+            language,
+            index,
+            dict_path,
+            dict_data,
+            same_type_sequence: options
+                .get("sametypesequence")
+                .map(|types| types.to_string()),
+        })
+    }
+
+    /**
+     * Bytes of an entry of the .dict file
+     */
+    fn read_entry(&self, offset: u64, size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.dict_data {
+            DictData::Plain => {
+                let mut file = File::open(&self.dict_path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                let mut entry = vec![0; size];
+                file.read_exact(&mut entry)?;
+                Ok(entry)
+            }
+            DictData::DictZip(chunks) => read_dictzip(&self.dict_path, chunks, offset, size),
+            DictData::Gzip(data) => {
+                let data = data.get_or_init(|| {
+                    let mut data = Vec::new();
+                    File::open(&self.dict_path)
+                        .and_then(|file| GzDecoder::new(file).read_to_end(&mut data))
+                        .map(|_| data)
+                        .map_err(|e| e.to_string())
+                });
+                let data = data.as_ref().map_err(|e| e.clone())?;
+                let start = offset as usize;
+                Ok(data
+                    .get(start..start + size)
+                    .ok_or("Entry out of the .dict file")?
+                    .to_vec())
+            }
+        }
+    }
+
+    fn read_entries(
+        &self,
+        entries: &[(String, u64, usize)],
+    ) -> Result<Vec<Definition>, Box<dyn Error>> {
+        let mut definitions = Vec::new();
+        for (word, offset, size) in entries {
+            let entry = self
+                .read_entry(*offset, *size)
+                .map_err(|e| format!("Entry of \"{}\": {}", word, e))?;
+            let text = entry_text(&entry, self.same_type_sequence.as_deref());
+            if !text.is_empty() {
+                definitions.push(Definition {
+                    word: word.clone(),
+                    text,
+                    dictionary: self.name.clone(),
+                });
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+impl Dictionary for StarDict {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn lookup(&self, word: &str) -> Vec<Definition> {
+        let entries = match self.index.get(word.trim().to_lowercase().as_str()) {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+        match self.read_entries(entries) {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", self.dict_path.display(), e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/**
+ * Chunks of a dictzip file from the beginning of the file (its gzip header),
+ * None if it is a gzip file without the RA field of dictzip
+ */
+pub fn dictzip_chunks(header: &[u8]) -> Option<DictZipChunks> {
+    let flags = *header.get(3)?;
+    if header.get(..3)? != [0x1f, 0x8b, 8] || flags & GZIP_EXTRA == 0 {
+        return None;
+    }
+    let u16_at = |position: usize| {
+        header
+            .get(position..position + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let extra_length = u16_at(10)?;
+    let extra = header.get(12..12 + extra_length)?;
+
+    // The fields after the extra field: name and comment (ending with \0), header CRC
+    let mut position = 12 + extra_length;
+    for flag in [GZIP_NAME, GZIP_COMMENT] {
+        if flags & flag != 0 {
+            position += header.get(position..)?.iter().position(|b| *b == 0)? + 1;
+        }
+    }
+    if flags & GZIP_HEADER_CRC != 0 {
+        position += 2;
+    }
+
+    // Subfields of the extra field: id (2 bytes), length, data
+    let mut field = 0;
+    while field + 4 <= extra.len() {
+        let length = u16::from_le_bytes([extra[field + 2], extra[field + 3]]) as usize;
+        let data = extra.get(field + 4..field + 4 + length)?;
+        if &extra[field..field + 2] == b"RA" {
+            // Version, length of the chunks, number of chunks, compressed size of each
+            let numbers: Vec<usize> = data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+                .collect();
+            let (chunk_length, count) = (*numbers.get(1)?, *numbers.get(2)?);
+            let sizes = numbers.get(3..3 + count)?;
+            let mut offset = position as u64;
+            let mut chunks = Vec::new();
+            for size in sizes {
+                chunks.push((offset, *size));
+                offset += *size as u64;
+            }
+            return (numbers[0] == 1 && chunk_length > 0).then_some(DictZipChunks {
+                chunk_length,
+                chunks,
+            });
+        }
+        field += 4 + length;
+    }
+    None
+}
+
+/**
+ * Bytes of an entry of a dictzip file, from the chunks it is in
+ */
+fn read_dictzip(
+    path: &Path,
+    dictzip: &DictZipChunks,
+    offset: u64,
+    size: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+    let first = offset as usize / dictzip.chunk_length;
+    let last = (offset as usize + size - 1) / dictzip.chunk_length;
+    let chunks = dictzip
+        .chunks
+        .get(first..=last)
+        .ok_or("Entry out of the .dict file")?;
+
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    for (chunk_offset, chunk_size) in chunks {
+        let mut compressed = vec![0; *chunk_size];
+        file.seek(SeekFrom::Start(*chunk_offset))?;
+        file.read_exact(&mut compressed)?;
+        // Each chunk is raw deflate data, ended by a full flush
+        let mut chunk = Vec::with_capacity(dictzip.chunk_length);
+        Decompress::new(false).decompress_vec(&compressed, &mut chunk, FlushDecompress::Sync)?;
+        data.extend_from_slice(&chunk);
+    }
+    let start = offset as usize - first * dictzip.chunk_length;
+    Ok(data
+        .get(start..start + size)
+        .ok_or("Entry out of the .dict file")?
+        .to_vec())
+}
+
+/**
+ * Entries of an .idx file: the word (ending with \0), then the offset and the size
+ * of its entry in the .dict file (big endian, the offset on 32 or 64 bits)
+ */
+fn read_index(idx: &[u8], offset_bits: usize) -> Result<Index, Box<dyn Error>> {
+    let offset_len = offset_bits / 8;
+    let mut index = Index::new();
+    let mut position = 0;
+    while position < idx.len() {
+        let end = idx[position..]
+            .iter()
+            .position(|b| *b == 0)
+            .map(|end| position + end)
+            .ok_or("Word without end in the .idx file")?;
+        let word = String::from_utf8_lossy(&idx[position..end]).to_string();
+        let numbers = idx
+            .get(end + 1..end + 1 + offset_len + 4)
+            .ok_or("Truncated .idx file")?;
+        let offset = numbers[..offset_len]
+            .iter()
+            .fold(0u64, |n, b| (n << 8) | *b as u64);
+        let size = numbers[offset_len..]
+            .iter()
+            .fold(0usize, |n, b| (n << 8) | *b as usize);
+        index
+            .entry(word.to_lowercase())
+            .or_default()
+            .push((word, offset, size));
+        position = end + 1 + offset_len + 4;
+    }
+    Ok(index)
+}
+
+/**
+ * Text of an entry of the .dict file.
+ * Every field starts with its type, unless all the entries have the same types
+ * (sametypesequence), then the last field takes the rest of the entry.
+ * Fields of lowercase types are text ending with \0, those of uppercase types
+ * (sounds, pictures) start with their size and are skipped.
+ */
+fn entry_text(entry: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut texts = Vec::new();
+    let mut position = 0;
+    let types = same_type_sequence.map(|types| types.chars().collect::<Vec<char>>());
+    let mut field = 0;
+    while position < entry.len() {
+        let (field_type, last) = match &types {
+            Some(types) => match types.get(field) {
+                Some(field_type) => (*field_type, field + 1 == types.len()),
+                None => break,
+            },
+            None => {
+                let field_type = entry[position] as char;
+                position += 1;
+                (field_type, false)
+            }
+        };
+        field += 1;
+        let data = if field_type.is_ascii_uppercase() {
+            let (start, size) = if last {
+                (position, entry.len() - position)
+            } else {
+                let size = entry.get(position..position + 4).map_or(0, |size| {
+                    size.iter().fold(0usize, |n, b| (n << 8) | *b as usize)
+                });
+                (position + 4, size)
+            };
+            position = (start + size).min(entry.len());
+            None
+        } else {
+            let end = if last {
+                entry.len()
+            } else {
+                entry[position..]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .map_or(entry.len(), |end| position + end)
+            };
+            let data = &entry[position..end];
+            position = end + 1;
+            Some(data)
+        };
+        if let Some(data) = data {
+            let text = String::from_utf8_lossy(data);
+            let text = match field_type {
+                // Markup: html, pango, xdxf
+                'h' | 'g' | 'x' => strip_markup(text.as_ref()),
+                // Meaning, phonetics, yinbiao or kana, locale text
+                'm' | 't' | 'y' | 'l' | 'k' => text.trim().to_string(),
+                _ => String::new(),
+            };
+            if !text.is_empty() {
+                texts.push(text);
+            }
+        }
+    }
+    texts.join("\n")
+}
+
+/**
+ * Text of a field with markup, the line breaks of <br> and of the blocks are kept
+ */
+fn strip_markup(markup: &str) -> String {
+    let mut text = String::new();
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = rest[start + 1..end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if matches!(tag.as_str(), "br" | "p" | "div" | "li" | "tr") && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/**
+ * Language of the folder of a dictionary, if it is named with a language code (e.g. "en")
+ */
+fn folder_language(ifo_path: &Path) -> Option<String> {
+    let folder = ifo_path.parent()?.file_name()?.to_str()?;
+    let is_code =
+        (2..=3).contains(&folder.len()) && folder.chars().all(|c| c.is_ascii_alphabetic());
+    is_code.then(|| folder.to_lowercase())
+}
+
+/**
+ * Dictionaries of the folder and of its sub folders (one for every .ifo file),
+ * sorted by name
+ */
+pub fn load_dictionaries(folder: &Path) -> Vec<StarDict> {
+    let mut dictionaries = Vec::new();
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return dictionaries,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            dictionaries.extend(load_dictionaries(&path));
+        } else if path.extension().is_some_and(|extension| extension == "ifo") {
+            match StarDict::open(&path) {
+                Ok(dictionary) => dictionaries.push(dictionary),
+                Err(e) => eprintln!("Unable to open dictionary {}: {}", path.display(), e),
+            }
+        }
+    }
+    dictionaries.sort_by(|a, b| a.name.cmp(&b.name));
+    dictionaries
+}
+
+/**
+ * Dictionary to look the words of a book up in: the first one in the language of the book
+ * (e.g. "en" for "en-GB"), or the first one if none is in that language
+ */
+pub fn default_dictionary(
+    dictionaries: &[Arc<dyn Dictionary>],
+    language: Option<&str>,
+) -> Option<usize> {
+    let language = language
+        .and_then(|language| language.split(['-', '_']).next())
+        .map(|language| language.to_lowercase());
+    dictionaries
+        .iter()
+        .position(|dictionary| language.is_some() && dictionary.language() == language.as_deref())
+        .or_else(|| (!dictionaries.is_empty()).then_some(0))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '\'' || c == '’'
+}
+
+/**
+ * Word of the text at a (byte) position, without the hyphens and apostrophes around it
+ */
+pub fn word_at(text: &str, index: usize) -> Option<String> {
+    let index = (0..=index.min(text.len()))
+        .rev()
+        .find(|i| text.is_char_boundary(*i))?;
+    let start = text[..index]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(index, |(i, _)| i);
+    let end = text[index..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(text.len(), |(i, _)| index + i);
+    let word = text[start..end].trim_matches(|c: char| !c.is_alphanumeric());
+    (!word.is_empty()).then(|| word.to_string())
+}
+
+/**
+ * Sentence of the text at a (byte) position, the context of a word looked up
+ */
+pub fn sentence_at(text: &str, index: usize) -> String {
+    let mut from = 0;
+    for sentence in split_sentences(text) {
+        if let Some(range) = find_sentence(&text[from..], sentence.as_str()) {
+            if index < from + range.end {
+                return sentence;
+            }
+            from += range.end;
+        }
+    }
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/**
+ * Word looked up and its definitions, shown in the lookup panel of the reader
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lookup {
+    // Empty when the panel is opened without a word
+    pub word: String,
+    pub context: String,
+    // Names of the dictionaries and index of the one the word is looked up in
+    pub dictionaries: Vec<String>,
+    pub dictionary: Option<usize>,
+    pub definitions: Vec<Definition>,
+    // Last words looked up
+    pub history: Vec<String>,
+}
+
+/**
+ * A word looked up while reading
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LookupEntry {
+    pub word: String,
+    // Book read and sentence the word was in, empty if it was typed
+    pub book: String,
+    pub context: String,
+    // Time of the lookup (seconds since the epoch)
+    pub time: i64,
+}
+
+/**
+ * Words looked up, the last one first
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LookupHistory {
+    pub entries: Vec<LookupEntry>,
+}
+
+impl LookupHistory {
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<LookupHistory>(json.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /**
+     * Add a lookup, a word looked up again moves to the top
+     */
+    pub fn add(&mut self, entry: LookupEntry) {
+        self.entries
+            .retain(|saved| saved.word.to_lowercase() != entry.word.to_lowercase());
+        self.entries.insert(0, entry);
+        self.entries.truncate(LOOKUP_HISTORY_LIMIT);
+    }
+
+    /**
+     * Last words looked up
+     */
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        self.entries
+            .iter()
+            .take(count)
+            .map(|entry| entry.word.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{Read, Seek, Write},
+    path::Path,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/*
+ * Writing of EPUB files.
+ * A changed book is written again entry by entry from its archive: the entries that did
+ * not change are copied raw (without being decompressed again), in their order and with
+ * their compression, the changed ones are replaced in place and the new ones are added
+ * at the end. As the format requires, the mimetype entry is always the first one and is
+ * stored uncompressed, so that readers can recognize the file from its first bytes.
+ */
+
+pub const MIMETYPE_PATH: &str = "mimetype";
+pub const EPUB_MIMETYPE: &str = "application/epub+zip";
+
+/**
+ * Write the mimetype entry, it must be the first entry of the archive
+ */
+pub fn write_mimetype<W: Write + Seek>(writer: &mut ZipWriter<W>) -> Result<(), Box<dyn Error>> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    writer.start_file(MIMETYPE_PATH, options)?;
+    writer.write_all(EPUB_MIMETYPE.as_bytes())?;
+    Ok(())
+}
+
+/**
+ * Copy an EPUB archive into a writer, replacing the content of the given entries
+ * (path in the archive, content) and adding the ones that are not in the archive.
+ * Returns the writer once the archive is complete.
+ */
+pub fn rewrite_epub<R: Read + Seek, W: Write + Seek>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    replaced: &[(String, Vec<u8>)],
+) -> Result<W, Box<dyn Error>> {
+    let mut writer = ZipWriter::new(writer);
+    write_mimetype(&mut writer)?;
+
+    let mut written = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index)?;
+        let name = entry.name().to_string();
+        if name == MIMETYPE_PATH {
+            continue;
+        }
+        match replaced.iter().find(|(path, _)| *path == name) {
+            Some((_, content)) => {
+                let options = FileOptions::default()
+                    .compression_method(entry.compression())
+                    .last_modified_time(entry.last_modified());
+                drop(entry);
+                writer.start_file(name.as_str(), options)?;
+                writer.write_all(content)?;
+                written.push(name);
+            }
+            None => writer.raw_copy_file(entry)?,
+        }
+    }
+    for (path, content) in replaced.iter() {
+        if path != MIMETYPE_PATH && !written.contains(path) {
+            let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+            writer.start_file(path.as_str(), options)?;
+            writer.write_all(content)?;
+        }
+    }
+    Ok(writer.finish()?)
+}
+
+/**
+ * Write a copy of the EPUB file at epub_path into target, with the given entries replaced.
+ * Nothing is left at target if the copy fails.
+ */
+pub fn write_epub(
+    epub_path: &Path,
+    target: &Path,
+    replaced: &[(String, Vec<u8>)],
+) -> Result<(), Box<dyn Error>> {
+    let mut archive = ZipArchive::new(File::open(epub_path)?)?;
+    let written = File::create(target)
+        .map_err(|e| e.into())
+        .and_then(|file| rewrite_epub(&mut archive, file, replaced))
+        .and_then(|file| file.sync_all().map_err(|e| e.into()));
+    if written.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    written
+}
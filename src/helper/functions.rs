@@ -1,15 +1,17 @@
-use epub::doc::EpubDoc;
 use html2text::from_read;
-use native_dialog::FileDialog;
+use native_dialog::{FileDialog, MessageDialog, MessageType};
 use std::{
-    fs,
-    io::{Read, Write},
+    io::Read,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use crate::{
-    helper::config::{COVERS_PATH, LIBRARY_PATH},
+    helper::{
+        config::LIBRARY_PATH,
+        cover::{generate_cover, save_cover_file},
+        source::{open_source, BookFormat, BookSource},
+    },
     model::book::Book,
 };
 
@@ -19,7 +21,7 @@ use crate::{
 pub fn open_native_dialog() -> Option<PathBuf> {
     let path = FileDialog::new()
         .set_location("~/Desktop")
-        .add_filter("EPub", &["epub"])
+        .add_filter("Books", &BookFormat::all_extensions())
         .show_open_single_file();
 
     match path {
@@ -46,6 +48,81 @@ pub fn open_native_dialog_images() -> Option<PathBuf> {
     }
 }
 
+/**
+ * Open a file dialog to choose notes made on another reader
+ * (Kindle "My Clippings.txt" or KOReader "metadata.epub.lua")
+ */
+pub fn open_native_dialog_annotations() -> Option<PathBuf> {
+    let path = FileDialog::new()
+        .set_location("~/Desktop")
+        .add_filter("Kindle clippings", &["txt"])
+        .add_filter("KOReader notes", &["lua"])
+        .show_open_single_file();
+
+    match path {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error opening file dialog: {}", e);
+            None
+        }
+    }
+}
+
+pub fn open_native_dialog_folder() -> Option<PathBuf> {
+    let path = FileDialog::new()
+        .set_location("~/Desktop")
+        .show_open_single_dir();
+
+    match path {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error opening file dialog: {}", e);
+            None
+        }
+    }
+}
+
+/**
+ * Open a file dialog to choose where to save a file, name is the name proposed
+ * and filters the (description, extension) of the file types
+ */
+pub fn save_native_dialog(name: &str, filters: &[(&str, &str)]) -> Option<PathBuf> {
+    let mut dialog = FileDialog::new()
+        .set_location("~/Desktop")
+        .set_filename(name);
+    for (description, extension) in filters {
+        dialog = dialog.add_filter(description, std::slice::from_ref(extension));
+    }
+    let path = dialog.show_save_single_file();
+
+    match path {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error opening file dialog: {}", e);
+            None
+        }
+    }
+}
+
+/**
+ * Ask the user to confirm an action, returns false if the dialog cannot be shown
+ */
+pub fn confirm_dialog(title: &str, text: &str) -> bool {
+    let confirm = MessageDialog::new()
+        .set_type(MessageType::Warning)
+        .set_title(title)
+        .set_text(text)
+        .show_confirm();
+
+    match confirm {
+        Ok(confirm) => confirm,
+        Err(e) => {
+            eprintln!("Error opening message dialog: {}", e);
+            false
+        }
+    }
+}
+
 /**
  * Convert a path into a file and then into a byte array [u8] and return it.
  */
@@ -65,9 +142,45 @@ pub fn path_to_bytes(path: PathBuf) -> Option<Vec<u8>> {
 }
 
 /**
-* Converts an .epub file into the Book struct
+ * Resolve a relative href against the path of the document it appears in
+ * Example: ("OEBPS/text/ch01.xhtml", "../images/a.png") -> "OEBPS/images/a.png"
  */
-pub fn epub_to_book(path: PathBuf) -> Option<Book> {
+pub fn resolve_relative_path(base: &Path, href: &str) -> PathBuf {
+    let mut path = base.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    for component in href.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                path.pop();
+            }
+            component => path.push(component),
+        }
+    }
+    path
+}
+
+/**
+ * Words of each section of a book, the title page included
+ * (comic pages have no text, each one counts as a word so that the progress follows them)
+ */
+pub fn count_words(source: &mut dyn BookSource) -> Vec<i32> {
+    let mut word_count_chapters = Vec::<i32>::new();
+    for index in 0..source.len() {
+        if source.format() == BookFormat::Comic {
+            word_count_chapters.push(1);
+            continue;
+        }
+        let page_str = source.section(index);
+        let text = from_read(page_str.unwrap_or("".to_string()).as_bytes(), 1000);
+        word_count_chapters.push(text.split_whitespace().count() as i32);
+    }
+    word_count_chapters
+}
+
+/**
+* Converts a book file (EPUB, plain text, Markdown, HTML, FB2, CBZ or a folder of images) into the Book struct
+ */
+pub fn file_to_book(path: PathBuf) -> Option<Book> {
     let filename = path
         .file_name()
         .expect("Unable to read file")
@@ -76,70 +189,32 @@ pub fn epub_to_book(path: PathBuf) -> Option<Book> {
         .to_string();
     let file_path = format!("{}/{}", LIBRARY_PATH, filename);
 
-    let doc = EpubDoc::new(path);
-    let mut doc = match doc {
-        Ok(doc) => doc,
+    let source = open_source(&path);
+    let mut source = match source {
+        Ok(source) => source,
         Err(e) => {
             println!("Error: {}", e);
             return None;
         }
     };
 
-    let title = doc.mdata("title").unwrap_or("Unknown".to_string());
-    let author = doc.mdata("creator").unwrap_or("Unknown".to_string());
+    let title = source.metadata("title").unwrap_or("Unknown".to_string());
+    let author = source.metadata("creator").unwrap_or("Unknown".to_string());
 
-    // Cover
-    let cover_data = match doc.get_cover() {
-        Ok(cover_data) => cover_data,
-        Err(e) => {
-            println!("Error: {}", e);
-            return None;
-        }
-    };
-    let mut cover_path = String::from(COVERS_PATH);
-    cover_path.push_str(format!("{}.png", title.clone().replace(' ', "-")).as_str());
-    let path = Path::new(cover_path.as_str());
-    println!("Path: {:?}", path);
-    let f = fs::File::create(path);
-    let cover_path = match f {
-        Ok(mut file) => {
-            let resp = file.write_all(&cover_data);
-            match resp {
-                Ok(_) => {
-                    println!("Book cover path: {}", cover_path);
-                    Some(cover_path)
-                }
-                Err(_) => {
-                    eprintln!("Unable to fetch cover");
-                    None
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            None
-        }
-    };
+    // Cover: the one of the book, otherwise a generated one
+    // (books without a cover are still added)
+    let cover_data = source
+        .cover()
+        .or_else(|| generate_cover(title.as_str(), author.as_str()));
+    let cover_path = cover_data.and_then(|cover_data| save_cover_file(title.as_str(), &cover_data));
 
     // Count words in book
-    let mut word_count_chapters = Vec::<i32>::new();
-    for _i in 0..doc.get_num_pages() - 1 {
-        let page_str = doc.get_current_str();
-        let text = from_read(page_str.unwrap_or("".to_string()).as_bytes(), 1000);
-        match doc.go_next() {
-            Ok(_) => (),
-            Err(e) => {
-                println!("Error: {}", e);
-                break;
-            }
-        }
-        word_count_chapters.push(text.split_whitespace().count() as i32);
-    }
+    let word_count_chapters = count_words(source.as_mut());
     println!("Word count: {:?}", word_count_chapters);
 
     match cover_path {
         None => Some(Book::new(
-            doc,
+            source,
             title,
             author,
             String::new(),
@@ -147,7 +222,7 @@ pub fn epub_to_book(path: PathBuf) -> Option<Book> {
             Arc::new(word_count_chapters),
         )),
         Some(cover_path) => Some(Book::new(
-            doc,
+            source,
             title,
             author,
             cover_path,
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use super::config::HISTORY_LIMIT;
+
+/**
+ * A position in a book: page and position in the page (0.0 top, 1.0 bottom)
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub page: usize,
+    pub chapter_position: f64,
+}
+
+/**
+ * Navigation history of a book, like the one of a browser.
+ * The position left is recorded when a jump (link, search result) moves the reader,
+ * going back or forward moves between the recorded positions.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    back: Vec<Position>,
+    forward: Vec<Position>,
+}
+
+impl History {
+    /**
+     * Record the position left by a jump, the forward positions are dropped
+     */
+    pub fn record(&mut self, from: Position) {
+        if self.back.last() != Some(&from) {
+            self.back.push(from);
+        }
+        if self.back.len() > HISTORY_LIMIT {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    /**
+     * Position to go back to, current is kept to go forward again
+     */
+    pub fn back(&mut self, current: Position) -> Option<Position> {
+        let position = self.back.pop()?;
+        self.forward.push(current);
+        Some(position)
+    }
+
+    /**
+     * Position to go forward to, current is kept to go back again
+     */
+    pub fn forward(&mut self, current: Position) -> Option<Position> {
+        let position = self.forward.pop()?;
+        self.back.push(current);
+        Some(position)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+}
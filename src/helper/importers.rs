@@ -0,0 +1,578 @@
+use std::{error::Error, fs, path::Path};
+
+use chrono::NaiveDateTime;
+
+use super::annotations::AnnotationKind;
+
+/*
+ * Importers of the annotations made on other readers: the "My Clippings.txt" file
+ * of a Kindle and the "metadata.epub.lua" files KOReader keeps next to the books
+ * (in a ".sdr" folder). The annotations are matched to the books of the library
+ * by their identifier, title and author, then found in the text of the book.
+ */
+
+const KINDLE_SEPARATOR: &str = "==========";
+const KINDLE_DATE_FORMAT: &str = "%A, %B %d, %Y %I:%M:%S %p";
+const KOREADER_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/**
+ * Annotation read from another reader, not yet placed in a book of the library
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedAnnotation {
+    pub title: String,
+    pub author: String,
+    pub identifiers: Vec<String>,
+    pub kind: AnnotationKind,
+    // Passage highlighted (empty for a bookmark of a Kindle) and note written about it
+    pub text: String,
+    pub note: String,
+    pub chapter: String,
+    // Time the annotation was made (seconds since the epoch), 0 if unknown
+    pub time: i64,
+    // Page (in the spine) the reader placed the annotation in, if it is known
+    pub page_hint: Option<usize>,
+}
+
+/**
+ * Read the annotations of a file: Kindle clippings (.txt) or KOReader notes (.lua),
+ * a KOReader ".sdr" folder can be given for the notes it contains
+ */
+pub fn import_file(path: &Path) -> Result<Vec<ImportedAnnotation>, Box<dyn Error>> {
+    if path.is_dir() {
+        let metadata = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy())
+                    .is_some_and(|name| name.starts_with("metadata.") && name.ends_with(".lua"))
+            })
+            .ok_or("No KOReader notes in the folder")?;
+        return parse_koreader_metadata(fs::read_to_string(metadata)?.as_str());
+    }
+    let text = fs::read_to_string(path)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("lua") => parse_koreader_metadata(text.as_str()),
+        _ => Ok(parse_kindle_clippings(text.as_str())),
+    }
+}
+
+/**
+ * Title and author of a Kindle clipping: "Title (Author)"
+ */
+fn kindle_title(line: &str) -> (String, String) {
+    let line = line.trim();
+    if line.ends_with(')') {
+        if let Some(start) = line.rfind('(') {
+            let author = line[start + 1..line.len() - 1].trim();
+            let title = line[..start].trim();
+            if !title.is_empty() {
+                return (title.to_string(), author.to_string());
+            }
+        }
+    }
+    (line.to_string(), String::new())
+}
+
+/**
+ * Last number of a part of a Kindle clipping: "Location 170-172" -> 172
+ */
+fn kindle_location(part: &str) -> Option<u32> {
+    part.rsplit(|c: char| !c.is_ascii_digit())
+        .find(|number| !number.is_empty())?
+        .parse()
+        .ok()
+}
+
+/**
+ * Parse the "My Clippings.txt" file of a Kindle.
+ * Each clipping is a title line, a line with its kind, location and date, an empty line
+ * and its text. The notes are clippings of their own, they are given to the highlight
+ * they were written about (the one ending where the note is).
+ */
+pub fn parse_kindle_clippings(text: &str) -> Vec<ImportedAnnotation> {
+    let text = text.replace('\u{feff}', "").replace("\r\n", "\n");
+    let mut annotations: Vec<ImportedAnnotation> = Vec::new();
+    // Location where each highlight ends
+    let mut locations: Vec<Option<u32>> = Vec::new();
+    for clipping in text.split(KINDLE_SEPARATOR) {
+        let mut lines = clipping.trim_start_matches('\n').lines();
+        let (title, meta) = match (lines.next(), lines.next()) {
+            (Some(title), Some(meta)) if !title.trim().is_empty() => (title, meta),
+            _ => continue,
+        };
+        let (title, author) = kindle_title(title);
+        let content = lines.collect::<Vec<&str>>().join("\n").trim().to_string();
+
+        let parts: Vec<&str> = meta.trim_start_matches('-').split('|').collect();
+        let kind = parts[0].to_lowercase();
+        let location = parts
+            .iter()
+            .find(|part| part.to_lowercase().contains("location"))
+            .and_then(|part| kindle_location(part));
+        let time = parts
+            .iter()
+            .find_map(|part| part.trim().strip_prefix("Added on "))
+            .and_then(|date| NaiveDateTime::parse_from_str(date.trim(), KINDLE_DATE_FORMAT).ok())
+            .map(|date| date.and_utc().timestamp())
+            .unwrap_or(0);
+
+        let (kind, text, note) = if kind.contains("note") {
+            let highlight = annotations.iter().zip(locations.iter()).rposition(
+                |(annotation, highlight_location)| {
+                    annotation.title == title
+                        && annotation.kind == AnnotationKind::Highlight
+                        && location.is_some()
+                        && *highlight_location == location
+                },
+            );
+            if let Some(index) = highlight {
+                annotations[index].note = content;
+                continue;
+            }
+            // A note about no highlight is kept as a bookmark with its note
+            (AnnotationKind::Bookmark, String::new(), content)
+        } else if kind.contains("bookmark") || content.is_empty() {
+            (AnnotationKind::Bookmark, String::new(), String::new())
+        } else {
+            (AnnotationKind::Highlight, content, String::new())
+        };
+        annotations.push(ImportedAnnotation {
+            title,
+            author,
+            identifiers: Vec::new(),
+            kind,
+            text,
+            note,
+            chapter: String::new(),
+            time,
+            page_hint: None,
+        });
+        locations.push(location);
+    }
+    annotations
+}
+
+/**
+ * Value of a Lua file, KOReader saves its notes as a Lua table
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum LuaValue {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Table(Vec<(LuaValue, LuaValue)>),
+}
+
+impl LuaValue {
+    /**
+     * Value of a key of a table
+     */
+    pub fn get(&self, key: &str) -> Option<&LuaValue> {
+        match self {
+            LuaValue::Table(entries) => entries
+                .iter()
+                .find(|(entry, _)| matches!(entry, LuaValue::String(entry) if entry == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            LuaValue::String(string) => Some(string.as_str()),
+            _ => None,
+        }
+    }
+
+    /**
+     * Values of a table, in the order of their keys (numbers first)
+     */
+    pub fn values(&self) -> Vec<&LuaValue> {
+        match self {
+            LuaValue::Table(entries) => {
+                let mut entries: Vec<&(LuaValue, LuaValue)> = entries.iter().collect();
+                entries.sort_by(|(a, _), (b, _)| match (a, b) {
+                    (LuaValue::Number(a), LuaValue::Number(b)) => a.total_cmp(b),
+                    (LuaValue::Number(_), _) => std::cmp::Ordering::Less,
+                    (_, LuaValue::Number(_)) => std::cmp::Ordering::Greater,
+                    _ => std::cmp::Ordering::Equal,
+                });
+                entries.into_iter().map(|(_, value)| value).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/**
+ * Parser of the Lua tables written by KOReader (strings, numbers, booleans and tables)
+ */
+struct LuaParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl LuaParser<'_> {
+    fn skip_spaces(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                Some('-') => {
+                    let mut rest = self.chars.clone();
+                    rest.next();
+                    if rest.next() != Some('-') {
+                        return;
+                    }
+                    // Comment until the end of the line
+                    for c in self.chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            c => Err(format!("Expected '{}', found {:?}", expected, c)),
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.chars.peek() {
+            if !c.is_alphanumeric() && *c != '_' {
+                break;
+            }
+            name.push(*c);
+            self.chars.next();
+        }
+        name
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some(c) if c == quote => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    // Escaped line break
+                    Some('\n') => string.push('\n'),
+                    Some(c) if c.is_ascii_digit() => {
+                        let mut code = c.to_digit(10).unwrap_or(0);
+                        for _ in 0..2 {
+                            match self.chars.peek().and_then(|c| c.to_digit(10)) {
+                                Some(digit) => {
+                                    code = code * 10 + digit;
+                                    self.chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        string.push(char::from_u32(code).unwrap_or('?'));
+                    }
+                    Some(c) => string.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                },
+                Some(c) => string.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let mut number = String::new();
+        while let Some(c) = self.chars.peek() {
+            if !c.is_ascii_alphanumeric() && !matches!(c, '.' | '-' | '+') {
+                break;
+            }
+            number.push(*c);
+            self.chars.next();
+        }
+        number
+            .parse()
+            .map_err(|_| format!("Invalid number: {}", number))
+    }
+
+    fn table(&mut self) -> Result<LuaValue, String> {
+        let mut entries = Vec::new();
+        let mut next_index = 1.0;
+        loop {
+            self.skip_spaces();
+            match self.chars.peek() {
+                Some('}') => {
+                    self.chars.next();
+                    return Ok(LuaValue::Table(entries));
+                }
+                Some(',') | Some(';') => {
+                    self.chars.next();
+                }
+                Some('[') => {
+                    self.chars.next();
+                    let key = self.value()?;
+                    self.expect(']')?;
+                    self.expect('=')?;
+                    entries.push((key, self.value()?));
+                }
+                Some(c) if c.is_alphabetic() || *c == '_' => {
+                    let mut rest = self.chars.clone();
+                    let name: String = rest
+                        .by_ref()
+                        .take_while(|c| c.is_alphanumeric() || *c == '_')
+                        .collect();
+                    let key_value = rest.find(|c| !c.is_whitespace()) == Some('=')
+                        && !matches!(name.as_str(), "true" | "false" | "nil");
+                    if key_value {
+                        let name = self.name();
+                        self.expect('=')?;
+                        entries.push((LuaValue::String(name), self.value()?));
+                    } else {
+                        entries.push((LuaValue::Number(next_index), self.value()?));
+                        next_index += 1.0;
+                    }
+                }
+                Some(_) => {
+                    entries.push((LuaValue::Number(next_index), self.value()?));
+                    next_index += 1.0;
+                }
+                None => return Err("Unterminated table".to_string()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<LuaValue, String> {
+        self.skip_spaces();
+        match self.chars.peek().copied() {
+            Some('{') => {
+                self.chars.next();
+                self.table()
+            }
+            Some(quote) if quote == '"' || quote == '\'' => {
+                self.chars.next();
+                Ok(LuaValue::String(self.string(quote)?))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                Ok(LuaValue::Number(self.number()?))
+            }
+            Some(c) if c.is_alphabetic() => match self.name().as_str() {
+                "true" => Ok(LuaValue::Boolean(true)),
+                "false" => Ok(LuaValue::Boolean(false)),
+                "nil" => Ok(LuaValue::Nil),
+                name => Err(format!("Unexpected name: {}", name)),
+            },
+            c => Err(format!("Unexpected character: {:?}", c)),
+        }
+    }
+}
+
+/**
+ * Parse a Lua file made of "return <value>" (comments are ignored)
+ */
+pub fn parse_lua(text: &str) -> Result<LuaValue, String> {
+    let mut parser = LuaParser {
+        chars: text.chars().peekable(),
+    };
+    parser.skip_spaces();
+    let mut rest = parser.chars.clone();
+    if rest.by_ref().take(6).collect::<String>() == "return" {
+        parser.name();
+    }
+    parser.value()
+}
+
+/**
+ * Page of a KOReader position: "/body/DocFragment[3]/body/p[4]/text().0" is in
+ * the third file of the spine (index 2)
+ */
+fn koreader_page(position: &str) -> Option<usize> {
+    let start = position.find("DocFragment[")? + "DocFragment[".len();
+    let end = start + position[start..].find(']')?;
+    let fragment: usize = position[start..end].parse().ok()?;
+    fragment.checked_sub(1)
+}
+
+fn koreader_time(annotation: &LuaValue) -> i64 {
+    annotation
+        .get("datetime")
+        .and_then(|date| date.as_str())
+        .and_then(|date| NaiveDateTime::parse_from_str(date, KOREADER_DATE_FORMAT).ok())
+        .map(|date| date.and_utc().timestamp())
+        .unwrap_or(0)
+}
+
+fn koreader_string(table: &LuaValue, key: &str) -> String {
+    table
+        .get(key)
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/**
+ * Parse the notes KOReader keeps for a book ("metadata.epub.lua" in its ".sdr" folder).
+ * Recent versions keep them in "annotations" (a highlight has a start "pos0",
+ * a bookmark has not), older ones kept the highlights in "highlight", by page.
+ */
+pub fn parse_koreader_metadata(text: &str) -> Result<Vec<ImportedAnnotation>, Box<dyn Error>> {
+    let metadata = parse_lua(text)?;
+    let properties = metadata
+        .get("doc_props")
+        .or_else(|| metadata.get("stats"))
+        .cloned()
+        .unwrap_or(LuaValue::Nil);
+    let title = koreader_string(&properties, "title");
+    let author = koreader_string(&properties, "authors").replace('\n', ", ");
+    let identifiers: Vec<String> = koreader_string(&properties, "identifiers")
+        .lines()
+        .map(|identifier| identifier.trim().to_string())
+        .filter(|identifier| !identifier.is_empty())
+        .collect();
+
+    let imported = |kind, text: String, note: String, entry: &LuaValue| ImportedAnnotation {
+        title: title.clone(),
+        author: author.clone(),
+        identifiers: identifiers.clone(),
+        kind,
+        text,
+        note,
+        chapter: koreader_string(entry, "chapter"),
+        time: koreader_time(entry),
+        page_hint: ["pos0", "page"].iter().find_map(|key| {
+            entry
+                .get(key)
+                .and_then(|position| position.as_str())
+                .and_then(koreader_page)
+        }),
+    };
+
+    let mut annotations = Vec::new();
+    if let Some(entries) = metadata.get("annotations") {
+        for entry in entries.values() {
+            if entry.get("pos0").is_some() {
+                annotations.push(imported(
+                    AnnotationKind::Highlight,
+                    koreader_string(entry, "text"),
+                    koreader_string(entry, "note"),
+                    entry,
+                ));
+            } else {
+                // The text of a bookmark is made by KOReader ("in Chapter 2"), only its note is kept
+                annotations.push(imported(
+                    AnnotationKind::Bookmark,
+                    String::new(),
+                    koreader_string(entry, "note"),
+                    entry,
+                ));
+            }
+        }
+    } else if let Some(pages) = metadata.get("highlight") {
+        for page in pages.values() {
+            for entry in page.values() {
+                annotations.push(imported(
+                    AnnotationKind::Highlight,
+                    koreader_string(entry, "text"),
+                    String::new(),
+                    entry,
+                ));
+            }
+        }
+    }
+    Ok(annotations
+        .into_iter()
+        .filter(|annotation| {
+            annotation.kind == AnnotationKind::Bookmark || !annotation.text.is_empty()
+        })
+        .collect())
+}
+
+/**
+ * Identifier without its scheme, case and hyphens: "urn:uuid:AB-12" -> "ab12"
+ */
+fn normalize_identifier(identifier: &str) -> String {
+    let mut identifier = identifier.trim().to_lowercase();
+    for prefix in ["urn:", "uuid:", "isbn:", "isbn"] {
+        if let Some(rest) = identifier.strip_prefix(prefix) {
+            identifier = rest.trim().to_string();
+        }
+    }
+    identifier.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/**
+ * Words of a title or an author, in lowercase and without punctuation
+ */
+fn normalize_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+/**
+ * Book of the library (title, author, identifier) an annotation was made in.
+ * The identifier is looked at first, then the title (the title of one may only be
+ * the beginning of the other, e.g. without its subtitle) with at least one name
+ * of the author in common.
+ */
+pub fn match_book(books: &[(&str, &str, &str)], imported: &ImportedAnnotation) -> Option<usize> {
+    let identifiers: Vec<String> = imported
+        .identifiers
+        .iter()
+        .map(|identifier| normalize_identifier(identifier))
+        .filter(|identifier| !identifier.is_empty())
+        .collect();
+    let by_identifier = books.iter().position(|(_, _, identifier)| {
+        let identifier = normalize_identifier(identifier);
+        !identifier.is_empty() && identifiers.contains(&identifier)
+    });
+    if by_identifier.is_some() {
+        return by_identifier;
+    }
+
+    let title = normalize_words(imported.title.as_str());
+    let author = normalize_words(imported.author.as_str());
+    if title.is_empty() {
+        return None;
+    }
+    let scores: Vec<usize> = books
+        .iter()
+        .map(|(book_title, book_author, _)| {
+            let book_title = normalize_words(book_title);
+            let book_author = normalize_words(book_author);
+            let same_author = author.is_empty()
+                || book_author.is_empty()
+                || author
+                    .iter()
+                    .any(|name| name.chars().count() > 1 && book_author.contains(name));
+            if !same_author || book_title.is_empty() {
+                0
+            } else if book_title == title {
+                2
+            } else if book_title.starts_with(&title) || title.starts_with(&book_title) {
+                1
+            } else {
+                0
+            }
+        })
+        .collect();
+    let best = scores.iter().copied().max().filter(|best| *best > 0)?;
+    // Two books matching as well: the annotation cannot be placed
+    if scores.iter().filter(|score| **score == best).count() > 1 {
+        return None;
+    }
+    scores.iter().position(|score| *score == best)
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+/**
+ * Actions of the reader that can be bound to keys
+ */
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ReaderAction {
+    NextPage,
+    PrevPage,
+    FirstPage,
+    LastPage,
+    HistoryBack,
+    HistoryForward,
+    Search,
+    IncreaseFont,
+    DecreaseFont,
+    Back,
+    Help,
+}
+
+impl ReaderAction {
+    pub const ALL: [ReaderAction; 11] = [
+        ReaderAction::NextPage,
+        ReaderAction::PrevPage,
+        ReaderAction::FirstPage,
+        ReaderAction::LastPage,
+        ReaderAction::HistoryBack,
+        ReaderAction::HistoryForward,
+        ReaderAction::Search,
+        ReaderAction::IncreaseFont,
+        ReaderAction::DecreaseFont,
+        ReaderAction::Back,
+        ReaderAction::Help,
+    ];
+
+    /**
+     * Name of the action in the settings file
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReaderAction::NextPage => "next_page",
+            ReaderAction::PrevPage => "prev_page",
+            ReaderAction::FirstPage => "first_page",
+            ReaderAction::LastPage => "last_page",
+            ReaderAction::HistoryBack => "history_back",
+            ReaderAction::HistoryForward => "history_forward",
+            ReaderAction::Search => "search",
+            ReaderAction::IncreaseFont => "increase_font",
+            ReaderAction::DecreaseFont => "decrease_font",
+            ReaderAction::Back => "back",
+            ReaderAction::Help => "help",
+        }
+    }
+
+    /**
+     * Description of the action in the help overlay
+     */
+    pub fn description(&self) -> &'static str {
+        match self {
+            ReaderAction::NextPage => "Next page",
+            ReaderAction::PrevPage => "Previous page",
+            ReaderAction::FirstPage => "First page",
+            ReaderAction::LastPage => "Last page",
+            ReaderAction::HistoryBack => "Back to the position before a link",
+            ReaderAction::HistoryForward => "Forward to the position of the link",
+            ReaderAction::Search => "Search in the book",
+            ReaderAction::IncreaseFont => "Increase font size",
+            ReaderAction::DecreaseFont => "Decrease font size",
+            ReaderAction::Back => "Close the note, or back to the library",
+            ReaderAction::Help => "Show or hide this help",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ReaderAction> {
+        ReaderAction::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            ReaderAction::NextPage => &["ArrowRight", "ArrowDown", "PageDown", "Space"],
+            ReaderAction::PrevPage => &["ArrowLeft", "ArrowUp", "PageUp", "Shift+Space"],
+            ReaderAction::FirstPage => &["Home"],
+            ReaderAction::LastPage => &["End"],
+            ReaderAction::HistoryBack => &["Alt+ArrowLeft", "BrowserBack", "Backspace"],
+            ReaderAction::HistoryForward => &["Alt+ArrowRight", "BrowserForward"],
+            ReaderAction::Search => &["Ctrl+F"],
+            ReaderAction::IncreaseFont => &["Ctrl++", "Ctrl+="],
+            ReaderAction::DecreaseFont => &["Ctrl+-"],
+            ReaderAction::Back => &["Escape"],
+            ReaderAction::Help => &["F1", "?"],
+        }
+    }
+}
+
+/**
+ * A key with its modifiers, written as "Ctrl+Shift+F".
+ * Keys are named as in the browsers ("ArrowRight", "PageDown", "Home", ...),
+ * "Space" is the space bar and characters are case insensitive.
+ */
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: String,
+}
+
+impl KeyBinding {
+    pub fn new(ctrl: bool, alt: bool, shift: bool, key: &str) -> Self {
+        let key = normalize_key(key);
+        // Symbols like "?" or "+" need shift on some layouts, it is not part of the binding
+        let symbol = key.chars().count() == 1 && !key.chars().any(char::is_alphanumeric);
+        Self {
+            ctrl,
+            alt,
+            shift: shift && !symbol,
+            key,
+        }
+    }
+
+    pub fn parse(binding: &str) -> Option<KeyBinding> {
+        let binding = binding.trim();
+        // The last part is the key, it can be "+" itself (e.g. "Ctrl++")
+        let (modifiers, key) = match binding.strip_suffix('+') {
+            Some(modifiers) if modifiers.is_empty() || modifiers.ends_with('+') => (modifiers, "+"),
+            _ => match binding.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", binding),
+            },
+        };
+        if key.is_empty() {
+            return None;
+        }
+
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => ctrl = true,
+                "alt" | "option" => alt = true,
+                "shift" => shift = true,
+                _ => return None,
+            }
+        }
+        Some(KeyBinding::new(ctrl, alt, shift, key))
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.key.chars().count() == 1 {
+            write!(f, "{}", self.key.to_uppercase())
+        } else {
+            write!(f, "{}", self.key)
+        }
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    match key {
+        " " => "Space".to_string(),
+        "Esc" => "Escape".to_string(),
+        key if key.chars().count() == 1 => key.to_lowercase(),
+        key => key.to_string(),
+    }
+}
+
+/**
+ * Bindings of the reader actions
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, ReaderAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = ReaderAction::ALL
+            .into_iter()
+            .flat_map(|action| {
+                action
+                    .default_keys()
+                    .iter()
+                    .filter_map(move |key| KeyBinding::parse(key).map(|key| (key, action)))
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /**
+     * Default keymap with the bindings of the settings file
+     * (action name -> keys), the keys of an action replace its default ones.
+     * The actions are taken in their order, not in the one of the map: a key given to
+     * two actions stays with the first one, the other one is rejected
+     */
+    pub fn with_overrides(overrides: &HashMap<String, Vec<String>>) -> Self {
+        let mut names: Vec<&String> = overrides.keys().collect();
+        names.sort();
+        for name in names {
+            if ReaderAction::from_name(name).is_none() {
+                eprintln!("Unknown action in keymap: {}", name);
+            }
+        }
+
+        let mut keymap = Keymap::default();
+        let mut overridden: Vec<(KeyBinding, ReaderAction)> = Vec::new();
+        for action in ReaderAction::ALL {
+            let keys = match overrides.get(action.name()) {
+                Some(keys) => keys,
+                None => continue,
+            };
+            keymap.bindings.retain(|(_, bound)| *bound != action);
+            for key in keys {
+                let key = match KeyBinding::parse(key) {
+                    Some(key) => key,
+                    None => {
+                        eprintln!("Invalid key in keymap: {}", key);
+                        continue;
+                    }
+                };
+                match overridden.iter().find(|(bound, _)| *bound == key) {
+                    Some((_, first)) => eprintln!(
+                        "Key {} given to {} and {} in keymap, kept for {}",
+                        key,
+                        first.name(),
+                        action.name(),
+                        first.name()
+                    ),
+                    None => {
+                        overridden.push((key.clone(), action));
+                        keymap.bind(key, action);
+                    }
+                }
+            }
+        }
+        keymap
+    }
+
+    /**
+     * Bind a key to an action, replacing its previous action
+     */
+    pub fn bind(&mut self, key: KeyBinding, action: ReaderAction) {
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self.bindings.push((key, action));
+    }
+
+    pub fn action(&self, key: &KeyBinding) -> Option<ReaderAction> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound == key)
+            .map(|(_, action)| *action)
+    }
+
+    /**
+     * Action of a key in a book: in the books read from right to left
+     * the arrows turn the pages the other way (the next page is on the left)
+     */
+    pub fn book_action(&self, key: &KeyBinding, rtl: bool) -> Option<ReaderAction> {
+        let action = self.action(key)?;
+        let arrow = matches!(key.key.as_str(), "ArrowLeft" | "ArrowRight");
+        Some(match action {
+            ReaderAction::NextPage if rtl && arrow => ReaderAction::PrevPage,
+            ReaderAction::PrevPage if rtl && arrow => ReaderAction::NextPage,
+            action => action,
+        })
+    }
+
+    pub fn keys(&self, action: ReaderAction) -> Vec<&KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /**
+     * Lines of the help overlay: description of each action and its keys
+     */
+    pub fn help(&self) -> Vec<(String, String)> {
+        ReaderAction::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = self
+                    .keys(action)
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                (action.description().to_string(), keys)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,1071 @@
+use html2text::from_read;
+
+use super::{
+    css::{Declaration, Stylesheet},
+    xml::{XmlDocument, XmlElement, XmlNode},
+};
+
+/*
+ * Layout of a chapter: the XHTML is parsed into a tree, styled with the stylesheets of the book
+ * (on top of the default styles of the browsers) and flattened into blocks of styled text.
+ * Lengths are in em of the base font size of the reader, so that the blocks can be drawn
+ * at any font size.
+ * Every paragraph has a base direction (from the dir attribute, the direction property or
+ * its first strong character), the words in other scripts are reordered by the text engine.
+ */
+
+// Default styles of the elements, as in the browsers
+pub const USER_AGENT_CSS: &str = "
+html, body, div, p, section, article, aside, nav, header, footer, main, figure, figcaption,
+blockquote, address, pre, h1, h2, h3, h4, h5, h6, ul, ol, dl, dt, dd, hr, center, caption
+    { display: block }
+li { display: list-item }
+table { display: table; margin: 1em 0 }
+head, title, style, script, link, meta { display: none }
+p { margin: 1em 0 }
+h1 { font-size: 2em; margin: 0.67em 0; font-weight: bold }
+h2 { font-size: 1.5em; margin: 0.83em 0; font-weight: bold }
+h3 { font-size: 1.17em; margin: 1em 0; font-weight: bold }
+h4 { margin: 1.33em 0; font-weight: bold }
+h5 { font-size: 0.83em; margin: 1.67em 0; font-weight: bold }
+h6 { font-size: 0.67em; margin: 2.33em 0; font-weight: bold }
+blockquote, figure { margin: 1em 2.5em }
+ul, ol, dl { margin: 1em 0 }
+ul, ol { padding-inline-start: 2.5em }
+ul { list-style-type: disc }
+ol { list-style-type: decimal }
+ul ul, ol ul { list-style-type: circle }
+ul ul ul, ol ol ul, ol ul ul, ul ol ul { list-style-type: square }
+li ul, li ol { margin: 0 }
+hr { margin: 0.5em 0 }
+dd { margin-inline-start: 2.5em }
+pre { white-space: pre; margin: 1em 0 }
+center, caption, th { text-align: center }
+em, i, cite, var, dfn, address { font-style: italic }
+strong, b, th, dt { font-weight: bold }
+small { font-size: 0.83em }
+";
+
+// Width of the page in em, for the lengths in percent and the width of the tables
+const PAGE_WIDTH_EM: f64 = 30.0;
+// Average width of a character in em, to measure the text of the cells
+const CHAR_WIDTH_EM: f64 = 0.5;
+// Padding of the cells of the tables on each side
+pub const CELL_PADDING_EM: f64 = 0.4;
+// Narrowest column of a table
+const MIN_COLUMN_EM: f64 = 2.0;
+// Space between the bar of a block quote and its text
+const QUOTE_BAR_GAP_EM: f64 = 1.0;
+
+/**
+ * Alignment of a paragraph, start and end depend on its direction, left and right do not
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TextAlign {
+    #[default]
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+/**
+ * Base direction of a paragraph: Ltr (latin, cyrillic, cjk...) or Rtl (hebrew, arabic...)
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/**
+ * Writing mode of a page: HorizontalTb -> lines from top to bottom,
+ * VerticalRl -> vertical lines (columns) from right to left, as in japanese books
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WritingMode {
+    #[default]
+    HorizontalTb,
+    VerticalRl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Display {
+    Block,
+    Inline,
+    ListItem,
+    Table,
+    None,
+}
+
+/**
+ * Text with the same style in a block
+ * size -> font size in em, link -> href of the link around the text
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub italic: bool,
+    pub bold: bool,
+    pub size: f64,
+    pub link: Option<String>,
+}
+
+/**
+ * A paragraph of text: space before it, margins, alignment and indent of its first line
+ * marker -> bullet or number of a list item, drawn in the margin on the start side
+ * quote_bars -> positions of the bars of the block quotes around the paragraph, from the start side
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextBlock {
+    pub spans: Vec<Span>,
+    pub space_before: f64,
+    pub margin_left: f64,
+    pub margin_right: f64,
+    pub text_align: TextAlign,
+    pub text_indent: f64,
+    pub marker: Option<String>,
+    pub quote_bars: Vec<f64>,
+    pub direction: Direction,
+}
+
+/**
+ * A cell of a table, laid out in blocks like a page
+ * colspan -> columns taken by the cell
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableCell {
+    pub blocks: Vec<Block>,
+    pub header: bool,
+    pub colspan: usize,
+}
+
+/**
+ * A table: rows of cells and the width of each column (in em, padding included)
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableBlock {
+    pub rows: Vec<Vec<TableCell>>,
+    pub column_widths: Vec<f64>,
+    pub space_before: f64,
+    pub margin_left: f64,
+}
+
+/**
+ * A horizontal rule (<hr>)
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleBlock {
+    pub space_before: f64,
+    pub margin_left: f64,
+    pub margin_right: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Text(TextBlock),
+    Table(TableBlock),
+    Rule(RuleBlock),
+}
+
+/**
+ * Computed style of an element
+ */
+#[derive(Clone, Debug, PartialEq)]
+struct Style {
+    display: Display,
+    // Box of the element (not inherited): top, right, bottom, left
+    margin: [f64; 4],
+    padding_left: f64,
+    // Margin and padding on the start side: left or right, depending on the direction
+    margin_start: f64,
+    padding_start: f64,
+    // Inherited
+    italic: bool,
+    bold: bool,
+    size: f64,
+    text_align: TextAlign,
+    text_indent: f64,
+    preserve_whitespace: bool,
+    list_style_type: String,
+    link: Option<String>,
+    // None -> from the text of the element (dir="auto" or no direction given)
+    direction: Option<Direction>,
+    writing_mode: WritingMode,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            display: Display::Block,
+            margin: [0.0; 4],
+            padding_left: 0.0,
+            margin_start: 0.0,
+            padding_start: 0.0,
+            italic: false,
+            bold: false,
+            size: 1.0,
+            text_align: TextAlign::Start,
+            text_indent: 0.0,
+            preserve_whitespace: false,
+            list_style_type: String::from("disc"),
+            link: None,
+            direction: None,
+            writing_mode: WritingMode::HorizontalTb,
+        }
+    }
+}
+
+impl Style {
+    /**
+     * Style of a child: the inherited properties of the parent and the defaults of the others
+     */
+    fn inherit(&self) -> Style {
+        Style {
+            display: Display::Inline,
+            margin: [0.0; 4],
+            padding_left: 0.0,
+            margin_start: 0.0,
+            padding_start: 0.0,
+            link: self.link.clone(),
+            ..self.clone()
+        }
+    }
+
+    fn apply(&mut self, declaration: &Declaration, parent_size: f64) {
+        let value = declaration.value.to_lowercase();
+        let value = value.as_str();
+        match declaration.property.as_str() {
+            "display" => {
+                self.display = match value {
+                    "none" => Display::None,
+                    "inline" | "inline-block" => Display::Inline,
+                    "list-item" => Display::ListItem,
+                    "table" => Display::Table,
+                    _ => Display::Block,
+                }
+            }
+            "font-style" => self.italic = value == "italic" || value == "oblique",
+            "font-weight" => {
+                self.bold = match value {
+                    "bold" | "bolder" => true,
+                    "normal" | "lighter" => false,
+                    weight => weight.parse::<u32>().map_or(self.bold, |w| w >= 600),
+                }
+            }
+            "font-size" => {
+                if let Some(size) = font_size(value, parent_size) {
+                    self.size = size;
+                }
+            }
+            "text-align" => {
+                self.text_align = match value {
+                    "center" => TextAlign::Center,
+                    "end" => TextAlign::End,
+                    "left" => TextAlign::Left,
+                    "right" => TextAlign::Right,
+                    "justify" => TextAlign::Justify,
+                    _ => TextAlign::Start,
+                }
+            }
+            "text-indent" => {
+                if let Some(indent) = length(value, self.size) {
+                    self.text_indent = indent;
+                }
+            }
+            "white-space" => self.preserve_whitespace = value.starts_with("pre"),
+            "list-style-type" => self.list_style_type = value.to_string(),
+            "list-style" => {
+                // Shorthand with the position and the image too, e.g. "square inside"
+                let list_style_type = value
+                    .split_whitespace()
+                    .find(|v| !matches!(*v, "inside" | "outside") && !v.starts_with("url("));
+                if let Some(list_style_type) = list_style_type {
+                    self.list_style_type = list_style_type.to_string();
+                }
+            }
+            "margin" => {
+                let lengths: Vec<f64> = value
+                    .split_whitespace()
+                    .filter_map(|v| length(v, self.size))
+                    .collect();
+                // As in css: "all", "vertical horizontal", "top horizontal bottom", "top right bottom left"
+                self.margin = match lengths[..] {
+                    [all] => [all; 4],
+                    [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+                    [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+                    [top, right, bottom, left] => [top, right, bottom, left],
+                    _ => self.margin,
+                };
+            }
+            "margin-top" | "margin-right" | "margin-bottom" | "margin-left" => {
+                let side = ["margin-top", "margin-right", "margin-bottom", "margin-left"]
+                    .iter()
+                    .position(|side| *side == declaration.property)
+                    .unwrap_or(0);
+                if let Some(length) = length(value, self.size) {
+                    self.margin[side] = length;
+                }
+            }
+            "padding-left" => {
+                if let Some(length) = length(value, self.size) {
+                    self.padding_left = length;
+                }
+            }
+            "margin-inline-start" | "padding-inline-start" => {
+                if let Some(length) = length(value, self.size) {
+                    if declaration.property == "margin-inline-start" {
+                        self.margin_start = length;
+                    } else {
+                        self.padding_start = length;
+                    }
+                }
+            }
+            "direction" => match value {
+                "rtl" => self.direction = Some(Direction::Rtl),
+                "ltr" => self.direction = Some(Direction::Ltr),
+                _ => (),
+            },
+            "writing-mode" | "-epub-writing-mode" | "-webkit-writing-mode" => {
+                self.writing_mode = match value {
+                    "vertical-rl" | "tb-rl" | "tb" => WritingMode::VerticalRl,
+                    _ => WritingMode::HorizontalTb,
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn number(value: &str, unit: &str) -> Option<f64> {
+    value.strip_suffix(unit)?.trim().parse::<f64>().ok()
+}
+
+/**
+ * Length in em of the base font size, size is the font size of the element
+ * (px are taken as 1/16 em, pt as 1/12 em)
+ */
+fn length(value: &str, size: f64) -> Option<f64> {
+    if value == "0" || value == "auto" {
+        return Some(0.0);
+    }
+    if let Some(rem) = number(value, "rem") {
+        return Some(rem);
+    }
+    if let Some(em) = number(value, "em") {
+        return Some(em * size);
+    }
+    if let Some(percent) = number(value, "%") {
+        return Some(percent / 100.0 * PAGE_WIDTH_EM);
+    }
+    if let Some(px) = number(value, "px") {
+        return Some(px / 16.0);
+    }
+    number(value, "pt").map(|pt| pt / 12.0)
+}
+
+/**
+ * Font size in em of the base font size, from the size of the parent
+ */
+fn font_size(value: &str, parent_size: f64) -> Option<f64> {
+    let keyword = match value {
+        "xx-small" => Some(0.6),
+        "x-small" => Some(0.75),
+        "small" => Some(0.89),
+        "medium" => Some(1.0),
+        "large" => Some(1.2),
+        "x-large" => Some(1.5),
+        "xx-large" => Some(2.0),
+        "smaller" => Some(parent_size / 1.2),
+        "larger" => Some(parent_size * 1.2),
+        _ => None,
+    };
+    if keyword.is_some() {
+        return keyword;
+    }
+    if let Some(percent) = number(value, "%") {
+        return Some(parent_size * percent / 100.0);
+    }
+    // Relative to the parent and not to the element itself
+    length(value, parent_size)
+}
+
+/**
+ * Hrefs of the stylesheets linked by a page
+ */
+pub fn stylesheet_hrefs(html: &str) -> Vec<String> {
+    let document = match XmlDocument::parse_xhtml(html) {
+        Ok(document) => document,
+        Err(_) => return Vec::new(),
+    };
+    let head = match document.root.find("head") {
+        Some(head) => head,
+        None => return Vec::new(),
+    };
+    head.elements()
+        .filter(|element| element.local_name() == "link")
+        .filter(|link| {
+            link.attr("rel")
+                .is_some_and(|rel| rel.to_lowercase().contains("stylesheet"))
+        })
+        .filter_map(|link| link.attr("href").map(|href| href.to_string()))
+        .collect()
+}
+
+/**
+ * Styles of a page: the default ones, the linked stylesheets (in order) and the <style> elements
+ */
+pub fn page_stylesheet(html: &str, linked: &[String]) -> Stylesheet {
+    let mut stylesheet = Stylesheet::parse(USER_AGENT_CSS);
+    for css in linked {
+        stylesheet.extend(Stylesheet::parse(css));
+    }
+    if let Ok(document) = XmlDocument::parse_xhtml(html) {
+        if let Some(head) = document.root.find("head") {
+            for style in head.elements().filter(|e| e.local_name() == "style") {
+                stylesheet.extend(Stylesheet::parse(style.text().as_str()));
+            }
+        }
+    }
+    stylesheet
+}
+
+/**
+ * Blocks of a page. A page that is not valid XHTML is shown as plain text.
+ */
+pub fn layout_page(html: &str, stylesheet: &Stylesheet) -> Vec<Block> {
+    let document = match XmlDocument::parse_xhtml(html) {
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("Error parsing page, showing it as plain text: {}", e);
+            return plain_text_blocks(html);
+        }
+    };
+
+    let mut builder = LayoutBuilder::new(stylesheet);
+    let root = &document.root;
+    let body = root.find("body").unwrap_or(root);
+    // The styles of html and body still apply to the text
+    let mut ancestors = Vec::new();
+    let mut style = Style::default();
+    if !std::ptr::eq(body, root) {
+        style = builder.compute_style(root, &[], &style);
+        ancestors.push(root);
+    }
+    builder.element(body, &mut ancestors, &style, &Container::default());
+    builder.finish(&Container::default())
+}
+
+/**
+ * Writing mode of a page, given by the styles of its html or body element
+ */
+pub fn writing_mode(html: &str, stylesheet: &Stylesheet) -> WritingMode {
+    let document = match XmlDocument::parse_xhtml(html) {
+        Ok(document) => document,
+        Err(_) => return WritingMode::HorizontalTb,
+    };
+    let builder = LayoutBuilder::new(stylesheet);
+    let root = &document.root;
+    let mut style = builder.compute_style(root, &[], &Style::default());
+    if let Some(body) = root.find("body") {
+        style = builder.compute_style(body, &[root], &style);
+    }
+    style.writing_mode
+}
+
+/**
+ * Direction of the first strong character of a text (a letter), None if it has none
+ * Example: "1. שלום" -> Rtl, "« Hello »" -> Ltr, "42" -> None
+ */
+pub fn base_direction(text: &str) -> Option<Direction> {
+    text.chars().find_map(|c| {
+        if is_rtl_char(c) {
+            Some(Direction::Rtl)
+        } else if c.is_alphabetic() {
+            Some(Direction::Ltr)
+        } else {
+            None
+        }
+    })
+}
+
+/**
+ * Characters of the scripts written from right to left:
+ * hebrew, arabic, syriac, thaana, nko and their presentation forms
+ */
+fn is_rtl_char(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+        | '\u{10800}'..='\u{10FFF}'
+        | '\u{1E800}'..='\u{1EFFF}'
+    ) && c.is_alphabetic()
+}
+
+fn plain_text_blocks(html: &str) -> Vec<Block> {
+    from_read(html.as_bytes(), usize::MAX)
+        .split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            Block::Text(TextBlock {
+                space_before: 1.0,
+                margin_left: 0.0,
+                margin_right: 0.0,
+                text_align: TextAlign::Start,
+                text_indent: 0.0,
+                marker: None,
+                quote_bars: Vec::new(),
+                direction: base_direction(paragraph.as_str()).unwrap_or_default(),
+                spans: vec![Span {
+                    text: paragraph,
+                    italic: false,
+                    bold: false,
+                    size: 1.0,
+                    link: None,
+                }],
+            })
+        })
+        .collect()
+}
+
+/**
+ * Block the text is laid out in: margins from the blocks around, alignment, indent and direction
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Container {
+    margin_left: f64,
+    margin_right: f64,
+    text_align: TextAlign,
+    text_indent: f64,
+    quote_bars: Vec<f64>,
+    direction: Direction,
+}
+
+struct LayoutBuilder<'a> {
+    stylesheet: &'a Stylesheet,
+    blocks: Vec<Block>,
+    // Text of the block being laid out
+    spans: Vec<Span>,
+    // Vertical margins collapse: the largest one between two blocks is kept
+    space: f64,
+    // Next number and step (-1 for reversed lists) of the lists being laid out
+    counters: Vec<(i64, i64)>,
+    // Marker of the list item being laid out, for its first paragraph
+    marker: Option<String>,
+}
+
+impl<'a> LayoutBuilder<'a> {
+    fn new(stylesheet: &'a Stylesheet) -> Self {
+        Self {
+            stylesheet,
+            blocks: Vec::new(),
+            spans: Vec::new(),
+            space: 0.0,
+            counters: Vec::new(),
+            marker: None,
+        }
+    }
+
+    fn compute_style(
+        &self,
+        element: &XmlElement,
+        ancestors: &[&XmlElement],
+        parent: &Style,
+    ) -> Style {
+        let mut style = parent.inherit();
+        // The dir attribute comes before the styles, the direction property wins over it
+        if let Some(dir) = element.attr("dir") {
+            style.direction = match dir.trim().to_lowercase().as_str() {
+                "rtl" => Some(Direction::Rtl),
+                "ltr" => Some(Direction::Ltr),
+                _ => None,
+            };
+        }
+        // The font size first, the lengths in em depend on it
+        let declarations = self.stylesheet.declarations(element, ancestors);
+        let (sizes, others): (Vec<&Declaration>, Vec<&Declaration>) = declarations
+            .iter()
+            .partition(|declaration| declaration.property == "font-size");
+        for declaration in sizes.into_iter().chain(others) {
+            style.apply(declaration, parent.size);
+        }
+        if element.local_name() == "a" {
+            if let Some(href) = element.attr("href") {
+                style.link = Some(href.to_string());
+            }
+        }
+        style
+    }
+
+    fn element<'e>(
+        &mut self,
+        element: &'e XmlElement,
+        ancestors: &mut Vec<&'e XmlElement>,
+        parent: &Style,
+        container: &Container,
+    ) {
+        let style = self.compute_style(element, ancestors, parent);
+        match style.display {
+            Display::None => (),
+            Display::Inline => {
+                if element.local_name() == "br" {
+                    self.push_text("\n", &style, true);
+                }
+                self.children(element, ancestors, &style, container);
+            }
+            Display::Block | Display::ListItem => {
+                self.flush(container);
+                let [top, right, bottom, left] = style.margin;
+                self.space = self.space.max(top);
+                if element.local_name() == "hr" {
+                    self.blocks.push(Block::Rule(RuleBlock {
+                        space_before: self.space,
+                        margin_left: container.margin_left + left,
+                        margin_right: container.margin_right + right,
+                    }));
+                    self.space = bottom;
+                    return;
+                }
+
+                let direction = element_direction(element, &style, container.direction);
+                let start = style.margin_start + style.padding_start;
+                let (start_left, start_right) = match direction {
+                    Direction::Ltr => (start, 0.0),
+                    Direction::Rtl => (0.0, start),
+                };
+                let mut inner = Container {
+                    margin_left: container.margin_left + left + style.padding_left + start_left,
+                    margin_right: container.margin_right + right + start_right,
+                    text_align: style.text_align,
+                    text_indent: style.text_indent,
+                    quote_bars: container.quote_bars.clone(),
+                    direction,
+                };
+                if element.local_name() == "blockquote" {
+                    // The bar is on the start side of the quote
+                    let bar = match direction {
+                        Direction::Ltr => {
+                            (inner.margin_left - QUOTE_BAR_GAP_EM).max(container.margin_left)
+                        }
+                        Direction::Rtl => {
+                            (inner.margin_right - QUOTE_BAR_GAP_EM).max(container.margin_right)
+                        }
+                    };
+                    inner.quote_bars.push(bar);
+                }
+                if style.display == Display::ListItem {
+                    self.marker = self.next_marker(element, &style);
+                }
+                let list = matches!(element.local_name(), "ol" | "ul");
+                if list {
+                    self.counters.push(list_counter(element));
+                }
+
+                self.children(element, ancestors, &style, &inner);
+                self.flush(&inner);
+                if list {
+                    self.counters.pop();
+                }
+                // A list item without text has no paragraph to take its marker
+                self.marker = None;
+                self.space = self.space.max(bottom);
+            }
+            Display::Table => {
+                self.flush(container);
+                let [top, _, bottom, left] = style.margin;
+                self.space = self.space.max(top);
+                let mut rows = Vec::new();
+                ancestors.push(element);
+                self.table_rows(element, ancestors, &style, container, false, &mut rows);
+                ancestors.pop();
+                if !rows.is_empty() {
+                    let available = PAGE_WIDTH_EM - container.margin_left - left;
+                    self.blocks.push(Block::Table(TableBlock {
+                        column_widths: table_column_widths(&rows, available),
+                        rows,
+                        space_before: self.space,
+                        margin_left: container.margin_left + left,
+                    }));
+                    self.space = 0.0;
+                }
+                self.space = self.space.max(bottom);
+            }
+        }
+    }
+
+    /**
+     * Rows of a table (or of its head, body and foot), the caption is laid out before it
+     */
+    fn table_rows<'e>(
+        &mut self,
+        element: &'e XmlElement,
+        ancestors: &mut Vec<&'e XmlElement>,
+        style: &Style,
+        container: &Container,
+        header: bool,
+        rows: &mut Vec<Vec<TableCell>>,
+    ) {
+        for child in element.elements() {
+            match child.local_name() {
+                "caption" => self.element(child, ancestors, style, container),
+                "thead" | "tbody" | "tfoot" | "tr" => {
+                    let child_style = self.compute_style(child, ancestors, style);
+                    if child_style.display == Display::None {
+                        continue;
+                    }
+                    let header = header || child.local_name() == "thead";
+                    ancestors.push(child);
+                    if child.local_name() == "tr" {
+                        let cells = child
+                            .elements()
+                            .filter(|cell| matches!(cell.local_name(), "td" | "th"))
+                            .map(|cell| self.table_cell(cell, ancestors, &child_style, header))
+                            .collect::<Vec<TableCell>>();
+                        if !cells.is_empty() {
+                            rows.push(cells);
+                        }
+                    } else {
+                        self.table_rows(child, ancestors, &child_style, container, header, rows);
+                    }
+                    ancestors.pop();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn table_cell<'e>(
+        &self,
+        cell: &'e XmlElement,
+        ancestors: &mut Vec<&'e XmlElement>,
+        row_style: &Style,
+        header: bool,
+    ) -> TableCell {
+        let style = self.compute_style(cell, ancestors, row_style);
+        let container = Container {
+            text_align: style.text_align,
+            text_indent: style.text_indent,
+            direction: element_direction(cell, &style, Direction::Ltr),
+            ..Default::default()
+        };
+        let mut builder = LayoutBuilder::new(self.stylesheet);
+        builder.children(cell, ancestors, &style, &container);
+        let mut blocks = builder.finish(&container);
+        // No space above the first paragraph of a cell
+        match blocks.first_mut() {
+            Some(Block::Text(text)) => text.space_before = 0.0,
+            Some(Block::Table(table)) => table.space_before = 0.0,
+            Some(Block::Rule(rule)) => rule.space_before = 0.0,
+            None => (),
+        }
+        TableCell {
+            blocks,
+            header: header || cell.local_name() == "th",
+            colspan: cell
+                .attr("colspan")
+                .and_then(|colspan| colspan.trim().parse::<usize>().ok())
+                .unwrap_or(1)
+                .max(1),
+        }
+    }
+
+    fn children<'e>(
+        &mut self,
+        element: &'e XmlElement,
+        ancestors: &mut Vec<&'e XmlElement>,
+        style: &Style,
+        container: &Container,
+    ) {
+        ancestors.push(element);
+        for child in element.children.iter() {
+            match child {
+                XmlNode::Element(child) => self.element(child, ancestors, style, container),
+                XmlNode::Text(text) => self.push_text(text, style, style.preserve_whitespace),
+                XmlNode::Other(_) => (),
+            }
+        }
+        ancestors.pop();
+    }
+
+    fn push_text(&mut self, text: &str, style: &Style, preserve: bool) {
+        let mut text = if preserve {
+            text.to_string()
+        } else {
+            // Only the spaces of html collapse, not the non-breaking ones
+            let mut collapsed = text
+                .split(|c: char| c.is_ascii_whitespace())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" ");
+            if text.starts_with(|c: char| c.is_ascii_whitespace()) && !collapsed.is_empty() {
+                collapsed.insert(0, ' ');
+            }
+            if text.ends_with(|c: char| c.is_ascii_whitespace()) {
+                collapsed.push(' ');
+            }
+            collapsed
+        };
+        // No space at the start of a line, nor two spaces in a row
+        let line_start = self
+            .spans
+            .last()
+            .is_none_or(|span| span.text.ends_with([' ', '\n']));
+        if line_start && !preserve {
+            text = text.trim_start_matches([' ', '\n']).to_string();
+        }
+        if text.is_empty() {
+            return;
+        }
+
+        match self.spans.last_mut() {
+            Some(span)
+                if span.italic == style.italic
+                    && span.bold == style.bold
+                    && span.size == style.size
+                    && span.link == style.link =>
+            {
+                span.text.push_str(text.as_str())
+            }
+            _ => self.spans.push(Span {
+                text,
+                italic: style.italic,
+                bold: style.bold,
+                size: style.size,
+                link: style.link.clone(),
+            }),
+        }
+    }
+
+    /**
+     * Marker of a list item, its number comes from the list (or from its value attribute)
+     */
+    fn next_marker(&mut self, item: &XmlElement, style: &Style) -> Option<String> {
+        let (next, step) = self.counters.last().copied().unwrap_or((1, 1));
+        let number = item
+            .attr("value")
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .unwrap_or(next);
+        if let Some(counter) = self.counters.last_mut() {
+            *counter = (number + step, step);
+        }
+        list_marker(style.list_style_type.as_str(), number)
+    }
+
+    /**
+     * End the block being laid out, it is dropped if it has no text
+     */
+    fn flush(&mut self, container: &Container) {
+        let mut spans = std::mem::take(&mut self.spans);
+        if let Some(span) = spans.last_mut() {
+            let trimmed = span.text.trim_end_matches([' ', '\n']).len();
+            span.text.truncate(trimmed);
+        }
+        spans.retain(|span| !span.text.is_empty());
+        if spans.iter().all(|span| span.text.trim().is_empty()) {
+            return;
+        }
+        self.blocks.push(Block::Text(TextBlock {
+            spans,
+            space_before: self.space,
+            margin_left: container.margin_left,
+            margin_right: container.margin_right,
+            text_align: container.text_align,
+            text_indent: container.text_indent,
+            marker: self.marker.take(),
+            quote_bars: container.quote_bars.clone(),
+            direction: container.direction,
+        }));
+        self.space = 0.0;
+    }
+
+    fn finish(mut self, container: &Container) -> Vec<Block> {
+        self.flush(container);
+        self.blocks
+    }
+}
+
+/**
+ * Direction of a block element: the one given by its styles,
+ * otherwise the one of its first strong character, otherwise the one of its parent
+ */
+fn element_direction(element: &XmlElement, style: &Style, parent: Direction) -> Direction {
+    style
+        .direction
+        .or_else(|| base_direction(element.text().as_str()))
+        .unwrap_or(parent)
+}
+
+/**
+ * Narrowest and widest width (in em) of blocks: the longest word and the longest paragraph
+ */
+fn blocks_width(blocks: &[Block]) -> (f64, f64) {
+    blocks.iter().fold((0.0, 0.0), |(min, max), block| {
+        let (block_min, block_max) = match block {
+            Block::Text(text) => {
+                let margins = text.margin_left + text.margin_right;
+                let (words, line) =
+                    text.spans
+                        .iter()
+                        .fold((0.0_f64, 0.0), |(words, line), span| {
+                            let char_width = CHAR_WIDTH_EM * span.size;
+                            let longest_word = span
+                                .text
+                                .split_whitespace()
+                                .map(|word| word.chars().count())
+                                .max()
+                                .unwrap_or(0);
+                            (
+                                words.max(longest_word as f64 * char_width),
+                                line + span.text.chars().count() as f64 * char_width,
+                            )
+                        });
+                (words + margins, line + margins)
+            }
+            Block::Table(table) => {
+                let width = table.margin_left + table.column_widths.iter().sum::<f64>();
+                (width, width)
+            }
+            Block::Rule(_) => (0.0, 0.0),
+        };
+        (min.max(block_min), max.max(block_max))
+    })
+}
+
+fn table_column_widths(rows: &[Vec<TableCell>], available: f64) -> Vec<f64> {
+    let columns = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.colspan).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    let mut min = vec![MIN_COLUMN_EM; columns];
+    let mut max = vec![MIN_COLUMN_EM; columns];
+    for row in rows {
+        let mut column = 0;
+        for cell in row {
+            // Cells over several columns do not change the widths
+            if cell.colspan == 1 {
+                let (cell_min, cell_max) = blocks_width(&cell.blocks);
+                min[column] = min[column].max(cell_min + 2.0 * CELL_PADDING_EM);
+                max[column] = max[column].max(cell_max + 2.0 * CELL_PADDING_EM);
+            }
+            column += cell.colspan;
+        }
+    }
+    column_widths(&min, &max, available)
+}
+
+/**
+ * Width of the columns of a table, from the narrowest and widest width of each column,
+ * as the automatic layout of the browsers: the widest widths if they fit in the available width,
+ * the narrowest ones if not even those fit (the table scrolls), or in between.
+ */
+pub fn column_widths(min: &[f64], max: &[f64], available: f64) -> Vec<f64> {
+    let total_min: f64 = min.iter().sum();
+    let total_max: f64 = max.iter().sum();
+    if total_max <= available {
+        return max.to_vec();
+    }
+    if total_min >= available {
+        return min.to_vec();
+    }
+    // Each column gets a share of the space left in proportion to how much it can grow
+    let ratio = (available - total_min) / (total_max - total_min);
+    min.iter()
+        .zip(max)
+        .map(|(min, max)| min + (max - min) * ratio)
+        .collect()
+}
+
+/**
+ * First number and step of a list: ol can start at another number or count down (reversed)
+ */
+fn list_counter(list: &XmlElement) -> (i64, i64) {
+    let reversed = list.local_name() == "ol" && list.attr("reversed").is_some();
+    let start = list
+        .attr("start")
+        .and_then(|start| start.trim().parse::<i64>().ok());
+    match (reversed, start) {
+        (false, start) => (start.unwrap_or(1), 1),
+        (true, Some(start)) => (start, -1),
+        (true, None) => {
+            let items = list.elements().filter(|e| e.local_name() == "li").count();
+            (items as i64, -1)
+        }
+    }
+}
+
+/**
+ * Marker of the list item with the given number, for a list-style-type of css
+ */
+pub fn list_marker(list_style_type: &str, number: i64) -> Option<String> {
+    let marker = match list_style_type {
+        "none" => return None,
+        "circle" => String::from("◦"),
+        "square" => String::from("▪"),
+        "decimal" => format!("{}.", number),
+        "decimal-leading-zero" => format!("{:02}.", number),
+        "lower-alpha" | "lower-latin" => format!("{}.", alphabetic(number)),
+        "upper-alpha" | "upper-latin" => format!("{}.", alphabetic(number).to_uppercase()),
+        "lower-roman" => format!("{}.", roman(number).to_lowercase()),
+        "upper-roman" => format!("{}.", roman(number)),
+        _ => String::from("•"),
+    };
+    Some(marker)
+}
+
+/**
+ * 1 -> a, 26 -> z, 27 -> aa
+ */
+fn alphabetic(number: i64) -> String {
+    if number <= 0 {
+        return number.to_string();
+    }
+    let mut letters = Vec::new();
+    let mut number = number;
+    while number > 0 {
+        number -= 1;
+        letters.push((b'a' + (number % 26) as u8) as char);
+        number /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn roman(number: i64) -> String {
+    if !(1..4000).contains(&number) {
+        return number.to_string();
+    }
+    let numerals = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut number = number;
+    let mut text = String::new();
+    for (value, numeral) in numerals {
+        while number >= value {
+            text.push_str(numeral);
+            number -= value;
+        }
+    }
+    text
+}
@@ -0,0 +1,174 @@
+use std::{
+    error::Error,
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use super::functions::resolve_relative_path;
+
+// Schemes of the links opened outside of the reader
+const EXTERNAL_SCHEMES: [&str; 5] = ["http:", "https:", "mailto:", "ftp:", "tel:"];
+
+/**
+ * Where a link of a page leads
+ * Internal -> a document of the book (full path in the epub) and the anchor in it
+ * External -> a url to open in the browser (or the mail client, ...)
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTarget {
+    Internal {
+        path: PathBuf,
+        fragment: Option<String>,
+    },
+    External(String),
+}
+
+/**
+ * Resolve the href of a link found in the document at current_doc (e.g. OEBPS/chapter_001.xhtml).
+ * "#note" stays in the current document, "../text/ch2.xhtml#p3" is relative to its folder.
+ */
+pub fn resolve_link(current_doc: &Path, href: &str) -> LinkTarget {
+    let href = href.trim();
+    let lowercase = href.to_lowercase();
+    if EXTERNAL_SCHEMES
+        .iter()
+        .any(|scheme| lowercase.starts_with(scheme))
+    {
+        return LinkTarget::External(href.to_string());
+    }
+
+    let (path, fragment) = match href.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (href, None),
+    };
+    let fragment = fragment
+        .filter(|fragment| !fragment.is_empty())
+        .map(percent_decode);
+    let path = if path.is_empty() {
+        current_doc.to_path_buf()
+    } else {
+        resolve_relative_path(current_doc, percent_decode(path).as_str())
+    };
+    LinkTarget::Internal { path, fragment }
+}
+
+/**
+ * Decode the %XX escapes of an href (e.g. "chapter%201.xhtml")
+ */
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/**
+ * Position (0.0 top, 1.0 bottom) of the element with the given id in the page,
+ * estimated from the text before it. None if there is no such element.
+ */
+pub fn fragment_position(html: &str, fragment: &str) -> Option<f64> {
+    let index = ["id", "name"]
+        .iter()
+        .flat_map(|attribute| {
+            [
+                format!("{}=\"{}\"", attribute, fragment),
+                format!("{}='{}'", attribute, fragment),
+            ]
+        })
+        .filter_map(|pattern| find_attribute(html, pattern.as_str()))
+        .min()?;
+    // Start of the tag holding the attribute
+    let start = html[..index].rfind('<').unwrap_or(index);
+
+    let body = html.find("<body").unwrap_or(0);
+    let total = text_length(&html[body..]);
+    if total == 0 || start < body {
+        return Some(0.0);
+    }
+    Some((text_length(&html[body..start]) as f64 / total as f64).clamp(0.0, 1.0))
+}
+
+/**
+ * Index of an attribute in the html, it must not be part of a longer one (e.g. data-id)
+ */
+fn find_attribute(html: &str, pattern: &str) -> Option<usize> {
+    html.match_indices(pattern)
+        .map(|(index, _)| index)
+        .find(|index| {
+            html[..*index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_whitespace)
+        })
+}
+
+/**
+ * Characters of text (not tags nor spaces) in the html
+ */
+fn text_length(html: &str) -> usize {
+    let mut in_tag = false;
+    html.chars()
+        .filter(|c| match c {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' => {
+                in_tag = false;
+                false
+            }
+            c => !in_tag && !c.is_whitespace(),
+        })
+        .count()
+}
+
+/**
+ * Opens the external links, the system one can be replaced (e.g. in the tests)
+ */
+pub trait LinkOpener: Debug {
+    fn open(&self, url: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/**
+ * Opens the links with the default application of the system
+ */
+#[derive(Debug, Default)]
+pub struct SystemOpener;
+
+impl LinkOpener for SystemOpener {
+    fn open(&self, url: &str) -> Result<(), Box<dyn Error>> {
+        opener::open(url)?;
+        Ok(())
+    }
+}
+
+/**
+ * Open an external link if the user confirms it, returns whether it was opened
+ */
+pub fn open_external(
+    url: &str,
+    opener: &dyn LinkOpener,
+    confirm: impl FnOnce(&str) -> bool,
+) -> Result<bool, Box<dyn Error>> {
+    if !confirm(url) {
+        return Ok(false);
+    }
+    opener.open(url)?;
+    Ok(true)
+}
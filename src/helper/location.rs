@@ -0,0 +1,71 @@
+use super::{config::WORDS_PER_LOCATION, history::Position};
+
+/*
+ * Progress in the book, from the words of each chapter (counts[i] -> words of page i)
+ * and the position in the current chapter (0.0 top, 1.0 bottom).
+ * Unlike the page number it grows evenly between short and long chapters.
+ */
+
+fn total_words(counts: &[i32]) -> i64 {
+    counts.iter().map(|words| *words as i64).sum()
+}
+
+/**
+ * Words before the position: the previous pages and the part of the current one already read
+ */
+pub fn words_read(counts: &[i32], page: usize, position: f64) -> i64 {
+    let before = total_words(&counts[..page.min(counts.len())]);
+    let current = counts.get(page).map_or(0, |words| *words as i64);
+    before + (current as f64 * position.clamp(0.0, 1.0)).round() as i64
+}
+
+/**
+ * Fraction of the book read (0.0 - 1.0)
+ */
+pub fn progress(counts: &[i32], page: usize, position: f64) -> f64 {
+    let total = total_words(counts);
+    if total == 0 {
+        return 0.0;
+    }
+    (words_read(counts, page, position) as f64 / total as f64).clamp(0.0, 1.0)
+}
+
+/**
+ * Current location and number of locations of the book (a location is a fixed amount of words)
+ */
+pub fn location(counts: &[i32], page: usize, position: f64) -> (i64, i64) {
+    let total = (total_words(counts) + WORDS_PER_LOCATION - 1) / WORDS_PER_LOCATION;
+    let current = words_read(counts, page, position) / WORDS_PER_LOCATION + 1;
+    (current.min(total.max(1)), total.max(1))
+}
+
+/**
+ * Page and position in the page at a fraction of the book
+ */
+pub fn seek(counts: &[i32], progress: f64) -> (usize, f64) {
+    let target = (progress.clamp(0.0, 1.0) * total_words(counts) as f64).round() as i64;
+    let mut before = 0;
+    for (page, words) in counts.iter().enumerate() {
+        let words = *words as i64;
+        if words > 0 && before + words > target {
+            return (page, (target - before) as f64 / words as f64);
+        }
+        before += words;
+    }
+    (counts.len().saturating_sub(1), 1.0)
+}
+
+/**
+ * Position saved with the progress of a book: the page and the position in it,
+ * or only the page for the progress saved by older versions
+ */
+pub fn parse_saved_position(json: &str) -> Option<Position> {
+    serde_json::from_str::<Position>(json).ok().or_else(|| {
+        serde_json::from_str::<usize>(json)
+            .ok()
+            .map(|page| Position {
+                page,
+                chapter_position: 0.0,
+            })
+    })
+}
@@ -1,2 +1,26 @@
+pub mod annotations;
+pub mod artefacts;
 pub mod config;
+pub mod convert;
+pub mod cover;
+pub mod css;
+pub mod dictionary;
+pub mod epub_writer;
 pub mod functions;
+pub mod history;
+pub mod importers;
+pub mod keymap;
+pub mod layout;
+pub mod links;
+pub mod location;
+pub mod notes;
+pub mod opf;
+pub mod search;
+pub mod settings;
+pub mod source;
+pub mod speech;
+pub mod stats;
+pub mod thumbnail;
+pub mod viewer;
+pub mod vocabulary;
+pub mod xml;
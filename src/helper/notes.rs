@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use super::xml::{XmlDocument, XmlElement};
+
+// Last word of the ids of the notes in books without epub:type ("#fn1", "#footnote-3", "#ch2_note12")
+const NOTE_IDS: [&str; 6] = ["fn", "footnote", "note", "endnote", "en", "ftn"];
+// Elements that are part of a line, a note anchored on them is the block around
+const INLINE_ELEMENTS: [&str; 10] = [
+    "a", "span", "sup", "sub", "em", "i", "b", "strong", "small", "abbr",
+];
+
+/**
+ * A note opened from a note reference of the page
+ * href -> the link of the reference, to go to the note itself
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub href: String,
+    pub text: String,
+}
+
+fn parse_xhtml(html: &str) -> Option<XmlDocument> {
+    match XmlDocument::parse_xhtml(html) {
+        Ok(document) => Some(document),
+        Err(e) => {
+            eprintln!("Error parsing page for notes: {}", e);
+            None
+        }
+    }
+}
+
+fn has_token(element: &XmlElement, attribute: &str, token: &str) -> bool {
+    element
+        .attr(attribute)
+        .is_some_and(|value| value.split_whitespace().any(|t| t == token))
+}
+
+/**
+ * Whether the id of a fragment looks like the one of a note (word followed by a number)
+ */
+pub fn is_note_id(fragment: &str) -> bool {
+    let separator = |c: char| matches!(c, '-' | '_' | ':' | '.');
+    let id = fragment.to_lowercase();
+    let word = id.trim_end_matches(|c: char| c.is_ascii_digit() || separator(c));
+    if word.len() == id.len() || !id[word.len()..].chars().any(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let word = word.rsplit(separator).next().unwrap_or(word);
+    NOTE_IDS.contains(&word)
+}
+
+/**
+ * Hrefs of the links of a page that refer to a note:
+ * marked with epub:type="noteref" (or role="doc-noteref"),
+ * or pointing to an id like "fn1" or written as a superscript
+ */
+pub fn note_refs(html: &str) -> HashSet<String> {
+    let mut refs = HashSet::new();
+    if let Some(document) = parse_xhtml(html) {
+        collect_note_refs(&document.root, false, &mut refs);
+    }
+    refs
+}
+
+fn collect_note_refs(element: &XmlElement, in_sup: bool, refs: &mut HashSet<String>) {
+    let in_sup = in_sup || element.local_name() == "sup";
+    if element.local_name() == "a" {
+        if let Some(href) = element.attr("href") {
+            let fragment = href.split_once('#').map(|(_, fragment)| fragment);
+            let semantic = has_token(element, "epub:type", "noteref")
+                || has_token(element, "role", "doc-noteref");
+            let looks_like_note = fragment.is_some_and(|fragment| {
+                is_note_id(fragment) || in_sup || element.find("sup").is_some()
+            });
+            if semantic || looks_like_note {
+                refs.insert(href.to_string());
+            }
+        }
+    }
+    for child in element.elements() {
+        collect_note_refs(child, in_sup, refs);
+    }
+}
+
+/**
+ * Text of the note with the given id in a document.
+ * An id on an inline element (e.g. the number linking back) gives the paragraph around it.
+ */
+pub fn note_text(html: &str, fragment: &str) -> Option<String> {
+    let document = parse_xhtml(html)?;
+    let mut path = Vec::new();
+    if !find_path(&document.root, fragment, &mut path) {
+        return None;
+    }
+    let note = path
+        .iter()
+        .rev()
+        .find(|element| !INLINE_ELEMENTS.contains(&element.local_name()))?;
+    let text = note
+        .text()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/**
+ * Elements from the root to the one with the id (or the anchor with the name)
+ */
+fn find_path<'a>(element: &'a XmlElement, fragment: &str, path: &mut Vec<&'a XmlElement>) -> bool {
+    path.push(element);
+    let anchor = element.local_name() == "a" && element.attr("name") == Some(fragment);
+    if element.attr("id") == Some(fragment) || anchor {
+        return true;
+    }
+    if element
+        .elements()
+        .any(|child| find_path(child, fragment, path))
+    {
+        return true;
+    }
+    path.pop();
+    false
+}
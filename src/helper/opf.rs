@@ -0,0 +1,567 @@
+use druid::Data;
+use std::{
+    cell::Cell,
+    error::Error,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+use super::{
+    epub_writer::write_epub,
+    xml::{XmlDocument, XmlElement, XmlNode},
+};
+
+pub const CONTAINER_PATH: &str = "META-INF/container.xml";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/**
+ * Editable metadata of a book, as stored in the package document (OPF)
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookMetadata {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub series_index: Option<String>,
+    pub tags: Vec<String>,
+    pub description: String,
+    pub language: String,
+}
+
+/**
+ * Where the edited book is written
+ * InPlace -> the original file is replaced (atomically)
+ * Copy -> a new "-copy.epub" file is created next to the original one
+ * (the library marks its title if it is the one of another book)
+ */
+#[derive(Clone, Copy, Data, Debug, PartialEq)]
+pub enum SaveMode {
+    InPlace,
+    Copy,
+}
+
+/**
+ * Direction the pages of a book are turned, from the page-progression-direction of the spine
+ * Ltr -> the next page is on the right, Rtl -> the next page is on the left (arabic, hebrew, manga)
+ */
+#[derive(Clone, Copy, Data, Debug, Default, PartialEq)]
+pub enum PageProgression {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/**
+ * Get the path of the package document from the content of META-INF/container.xml
+ */
+pub fn find_opf_path(container: &str) -> Option<String> {
+    let container = match XmlDocument::parse(container) {
+        Ok(container) => container,
+        Err(e) => {
+            eprintln!("Error parsing container.xml: {}", e);
+            return None;
+        }
+    };
+    container
+        .root
+        .find("rootfile")
+        .and_then(|rootfile| rootfile.attr("full-path"))
+        .map(|path| path.to_string())
+}
+
+/**
+ * Resolve an href found in the package document into a path inside the archive
+ * Example: ("OEBPS/content.opf", "images/cover.png") -> "OEBPS/images/cover.png"
+ */
+pub fn resolve_href(opf_path: &str, href: &str) -> String {
+    match opf_path.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, href),
+        None => href.to_string(),
+    }
+}
+
+/**
+ * Prefixes of the Dublin Core elements in the package document, in the order they are
+ * declared, with "dc" added at the end if it is not one of them: the first one is the
+ * declared prefix (usually "dc"), or "dc" when the book declares none
+ */
+fn dc_prefixes(opf: &XmlDocument) -> Vec<String> {
+    let mut prefixes = Vec::new();
+    for element in [Some(&opf.root), opf.root.child("metadata")]
+        .into_iter()
+        .flatten()
+    {
+        for (key, value) in element.attributes.iter() {
+            if let Some(prefix) = key.strip_prefix("xmlns:") {
+                if value == DC_NAMESPACE && !prefixes.iter().any(|p| p == prefix) {
+                    prefixes.push(prefix.to_string());
+                }
+            }
+        }
+    }
+    if !prefixes.iter().any(|prefix| prefix == "dc") {
+        prefixes.push(String::from("dc"));
+    }
+    prefixes
+}
+
+/**
+ * Name of a Dublin Core element without its prefix ("dc:title" -> "title"),
+ * None if the element is not a Dublin Core element
+ */
+fn dc_name<'a>(element: &'a XmlElement, prefixes: &[String]) -> Option<&'a str> {
+    match element.name.split_once(':') {
+        Some((prefix, name)) => {
+            let declared = element.attr(format!("xmlns:{}", prefix).as_str()) == Some(DC_NAMESPACE);
+            (declared || prefixes.iter().any(|p| p == prefix)).then_some(name)
+        }
+        None => (element.attr("xmlns") == Some(DC_NAMESPACE)).then_some(element.name.as_str()),
+    }
+}
+
+/**
+ * Read the editable metadata from the package document
+ */
+pub fn read_metadata(opf: &XmlDocument) -> BookMetadata {
+    let mut metadata = BookMetadata::default();
+    let prefixes = dc_prefixes(opf);
+    let metadata_element = match opf.root.child("metadata") {
+        Some(element) => element,
+        None => return metadata,
+    };
+
+    for element in metadata_element.elements() {
+        let text = element.text().trim().to_string();
+        match (dc_name(element, &prefixes), element.local_name()) {
+            (Some("title"), _) if metadata.title.is_empty() => metadata.title = text,
+            (Some("creator"), _) => metadata.authors.push(text),
+            (Some("subject"), _) => metadata.tags.push(text),
+            (Some("description"), _) => metadata.description = text,
+            (Some("language"), _) if metadata.language.is_empty() => metadata.language = text,
+            (None, "meta") => match (element.attr("name"), element.attr("property")) {
+                (Some("calibre:series"), _) if metadata.series.is_none() => {
+                    metadata.series = element.attr("content").map(|s| s.to_string())
+                }
+                (Some("calibre:series_index"), _) if metadata.series_index.is_none() => {
+                    metadata.series_index = element.attr("content").map(|s| s.to_string())
+                }
+                // EPUB3 collections take precedence over the calibre metadata
+                (_, Some("belongs-to-collection")) => {
+                    let refines = element.attr("id").map(|id| format!("#{}", id));
+                    let is_series = refines.as_ref().is_none_or(|refines| {
+                        refined_property(metadata_element, refines, "collection-type")
+                            .is_none_or(|kind| kind == "series")
+                    });
+                    if is_series {
+                        metadata.series = Some(text);
+                        if let Some(refines) = refines {
+                            if let Some(position) =
+                                refined_property(metadata_element, &refines, "group-position")
+                            {
+                                metadata.series_index = Some(position);
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    metadata
+}
+
+/**
+ * Read the page progression direction of the spine ("default" reads left to right)
+ */
+pub fn read_page_progression(opf: &XmlDocument) -> PageProgression {
+    let direction = opf
+        .root
+        .child("spine")
+        .and_then(|spine| spine.attr("page-progression-direction"));
+    match direction {
+        Some(direction) if direction.trim().eq_ignore_ascii_case("rtl") => PageProgression::Rtl,
+        _ => PageProgression::Ltr,
+    }
+}
+
+/**
+ * Get the value of a <meta refines="#id" property="..."> element
+ */
+fn refined_property(metadata: &XmlElement, refines: &str, property: &str) -> Option<String> {
+    metadata
+        .elements()
+        .find(|e| e.attr("refines") == Some(refines) && e.attr("property") == Some(property))
+        .map(|e| e.text().trim().to_string())
+}
+
+/**
+ * Remove the <meta refines="#id"> elements of the given ids
+ */
+fn remove_refines(metadata: &mut XmlElement, ids: &[String]) {
+    let refines: Vec<String> = ids.iter().map(|id| format!("#{}", id)).collect();
+    metadata.retain_elements(|e| match e.attr("refines") {
+        Some(r) => !refines.iter().any(|refines| refines == r),
+        None => true,
+    });
+}
+
+/**
+ * Set the text of the first Dublin Core element with the given name, creating it
+ * (with the first prefix) if missing
+ */
+fn set_single(metadata: &mut XmlElement, prefixes: &[String], name: &str, value: &str) {
+    if let Some(element) = metadata
+        .elements_mut()
+        .find(|e| dc_name(e, prefixes) == Some(name))
+    {
+        element.set_text(value);
+        return;
+    }
+    let name = format!("{}:{}", prefixes[0], name);
+    metadata.push(XmlElement::new(name.as_str()).with_text(value));
+}
+
+/**
+ * Write the metadata into the package document.
+ * Elements that are not edited here (identifiers, dates, rights...) are left untouched.
+ */
+pub fn apply_metadata(opf: &mut XmlDocument, metadata: &BookMetadata) {
+    let is_epub3 = opf
+        .root
+        .attr("version")
+        .is_some_and(|version| version.starts_with('3'));
+    let prefixes = dc_prefixes(opf);
+    let is_dc = |element: &XmlElement, name: &str| dc_name(element, &prefixes) == Some(name);
+    if opf.root.child("metadata").is_none() {
+        opf.root
+            .children
+            .insert(0, XmlNode::Element(XmlElement::new("metadata")));
+    }
+    let metadata_element = opf.root.child_mut("metadata").unwrap(); // Unwrap is safe because it was just created if missing
+
+    let dc = |name: &str| format!("{}:{}", prefixes[0], name);
+
+    // Title and language
+    set_single(
+        metadata_element,
+        &prefixes,
+        "title",
+        metadata.title.as_str(),
+    );
+    if !metadata.language.is_empty() {
+        set_single(
+            metadata_element,
+            &prefixes,
+            "language",
+            metadata.language.as_str(),
+        );
+    }
+
+    // Authors: existing creators are updated in order, so that their role is kept,
+    // the ones after the last author are removed
+    let mut removed_ids = Vec::new();
+    let mut index = 0;
+    for element in metadata_element.elements_mut() {
+        if !is_dc(element, "creator") {
+            continue;
+        }
+        match metadata.authors.get(index) {
+            Some(author) => {
+                if element.text().trim() != author {
+                    element.set_text(author);
+                    // The sort name is stale when the name changes
+                    element.attributes.retain(|(k, _)| k != "opf:file-as");
+                }
+            }
+            None => {
+                if let Some(id) = element.attr("id") {
+                    removed_ids.push(id.to_string());
+                }
+            }
+        }
+        index += 1;
+    }
+    let creators = Cell::new(0);
+    metadata_element.retain_elements(|e| {
+        if !is_dc(e, "creator") {
+            return true;
+        }
+        creators.set(creators.get() + 1);
+        creators.get() <= metadata.authors.len()
+    });
+    for author in metadata.authors.iter().skip(index) {
+        metadata_element.push(XmlElement::new(dc("creator").as_str()).with_text(author));
+    }
+
+    // Tags
+    metadata_element.retain_elements(|e| !is_dc(e, "subject"));
+    for tag in metadata.tags.iter() {
+        metadata_element.push(XmlElement::new(dc("subject").as_str()).with_text(tag));
+    }
+
+    // Description
+    if metadata.description.is_empty() {
+        metadata_element.retain_elements(|e| !is_dc(e, "description"));
+    } else {
+        set_single(
+            metadata_element,
+            &prefixes,
+            "description",
+            metadata.description.as_str(),
+        );
+    }
+
+    // Series
+    for element in metadata_element.elements() {
+        if element.attr("property") == Some("belongs-to-collection") {
+            if let Some(id) = element.attr("id") {
+                removed_ids.push(id.to_string());
+            }
+        }
+    }
+    metadata_element.retain_elements(|e| {
+        e.attr("property") != Some("belongs-to-collection")
+            && e.attr("name") != Some("calibre:series")
+            && e.attr("name") != Some("calibre:series_index")
+    });
+    remove_refines(metadata_element, &removed_ids);
+    if let Some(series) = &metadata.series {
+        metadata_element.push(
+            XmlElement::new("meta")
+                .with_attr("name", "calibre:series")
+                .with_attr("content", series),
+        );
+        if let Some(index) = &metadata.series_index {
+            metadata_element.push(
+                XmlElement::new("meta")
+                    .with_attr("name", "calibre:series_index")
+                    .with_attr("content", index),
+            );
+        }
+        if is_epub3 {
+            metadata_element.push(
+                XmlElement::new("meta")
+                    .with_attr("property", "belongs-to-collection")
+                    .with_attr("id", "series")
+                    .with_text(series),
+            );
+            metadata_element.push(
+                XmlElement::new("meta")
+                    .with_attr("refines", "#series")
+                    .with_attr("property", "collection-type")
+                    .with_text("series"),
+            );
+            if let Some(index) = &metadata.series_index {
+                metadata_element.push(
+                    XmlElement::new("meta")
+                        .with_attr("refines", "#series")
+                        .with_attr("property", "group-position")
+                        .with_text(index),
+                );
+            }
+        }
+    }
+}
+
+/**
+ * Get the media type of an image from its extension
+ */
+pub fn image_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/**
+ * Register the image at href (relative to the package document) as the cover of the book.
+ * The manifest item gets properties="cover-image" (EPUB3) and a <meta name="cover"> is
+ * added for EPUB2 readers. Returns the id of the manifest item.
+ */
+pub fn set_cover_image(opf: &mut XmlDocument, href: &str, media_type: &str) -> String {
+    let id = String::from("cover-image");
+    if opf.root.child("manifest").is_none() {
+        opf.root.push(XmlElement::new("manifest"));
+    }
+    let manifest = opf.root.child_mut("manifest").unwrap(); // Unwrap is safe because it was just created if missing
+
+    // Only one item can be the cover image
+    for item in manifest.elements_mut() {
+        if let Some(properties) = item.attr("properties") {
+            let properties: Vec<&str> = properties
+                .split_whitespace()
+                .filter(|p| *p != "cover-image")
+                .collect();
+            let properties = properties.join(" ");
+            if properties.is_empty() {
+                item.attributes.retain(|(k, _)| k != "properties");
+            } else {
+                item.set_attr("properties", properties.as_str());
+            }
+        }
+    }
+    manifest.retain_elements(|item| {
+        item.attr("id") != Some(id.as_str()) && item.attr("href") != Some(href)
+    });
+    manifest.push(
+        XmlElement::new("item")
+            .with_attr("id", id.as_str())
+            .with_attr("href", href)
+            .with_attr("media-type", media_type)
+            .with_attr("properties", "cover-image"),
+    );
+
+    if let Some(metadata) = opf.root.child_mut("metadata") {
+        metadata.retain_elements(|e| !(e.name == "meta" && e.attr("name") == Some("cover")));
+        metadata.push(
+            XmlElement::new("meta")
+                .with_attr("name", "cover")
+                .with_attr("content", id.as_str()),
+        );
+    }
+
+    id
+}
+
+/**
+ * Read an entry of the archive into a string
+ */
+pub fn read_entry_string(
+    archive: &mut ZipArchive<File>,
+    name: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut entry = archive.by_name(name)?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/**
+ * Read the editable metadata of an epub file
+ */
+pub fn load_metadata(epub_path: &Path) -> Result<BookMetadata, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(File::open(epub_path)?)?;
+    let container = read_entry_string(&mut archive, CONTAINER_PATH)?;
+    let opf_path = find_opf_path(container.as_str()).ok_or("No package document found")?;
+    let opf = XmlDocument::parse(read_entry_string(&mut archive, opf_path.as_str())?.as_str())?;
+    Ok(read_metadata(&opf))
+}
+
+/**
+ * Get the path of a new copy of an edited book ("-copy.epub", then "-copy-2.epub"...),
+ * the earlier copies are kept
+ */
+pub fn copy_path(epub_path: &Path) -> PathBuf {
+    let stem = epub_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut path = epub_path.with_file_name(format!("{}-copy.epub", stem));
+    let mut number = 2;
+    while path.exists() {
+        path = epub_path.with_file_name(format!("{}-copy-{}.epub", stem, number));
+        number += 1;
+    }
+    path
+}
+
+/**
+ * Write the metadata (and optionally a new cover) into the epub file.
+ * Returns the path of the saved book.
+ */
+pub fn save_metadata(
+    epub_path: &Path,
+    metadata: &BookMetadata,
+    cover: Option<PathBuf>,
+    mode: SaveMode,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if metadata.title.trim().is_empty() {
+        return Err("The title cannot be empty".into());
+    }
+
+    update_package(epub_path, mode, |opf, opf_path| {
+        apply_metadata(opf, metadata);
+        match cover {
+            Some(cover) => Ok(vec![add_cover(opf, opf_path, cover.as_path())?]),
+            None => Ok(Vec::new()),
+        }
+    })
+}
+
+/**
+ * Replace the cover of the epub file with the given image (in place)
+ */
+pub fn save_cover(epub_path: &Path, cover: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    update_package(epub_path, SaveMode::InPlace, |opf, opf_path| {
+        Ok(vec![add_cover(opf, opf_path, cover)?])
+    })
+}
+
+/**
+ * Register the image as cover in the package document and
+ * return the archive entry to write for it
+ */
+fn add_cover(
+    opf: &mut XmlDocument,
+    opf_path: &str,
+    cover: &Path,
+) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+    let media_type = image_media_type(cover).ok_or("Unsupported cover image format")?;
+    let extension = cover
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let href = format!("cover.{}", extension);
+    set_cover_image(opf, href.as_str(), media_type);
+    Ok((resolve_href(opf_path, href.as_str()), fs::read(cover)?))
+}
+
+/**
+ * Edit the package document of an epub file.
+ * The edit closure gets the parsed package document and its path in the archive,
+ * and returns the other entries to replace (or add).
+ * The archive is rebuilt entry by entry in a temporary folder next to the target, removed
+ * whatever happens, then renamed into place: the original file is never left half written
+ * and two saves never share their files.
+ */
+fn update_package<F>(epub_path: &Path, mode: SaveMode, edit: F) -> Result<PathBuf, Box<dyn Error>>
+where
+    F: FnOnce(&mut XmlDocument, &str) -> Result<Vec<(String, Vec<u8>)>, Box<dyn Error>>,
+{
+    let mut archive = ZipArchive::new(File::open(epub_path)?)?;
+    let container = read_entry_string(&mut archive, CONTAINER_PATH)?;
+    let opf_path = find_opf_path(container.as_str()).ok_or("No package document found")?;
+    let mut opf = XmlDocument::parse(read_entry_string(&mut archive, opf_path.as_str())?.as_str())?;
+
+    // Entries to replace (or add) in the new archive
+    let mut replaced = edit(&mut opf, opf_path.as_str())?;
+    replaced.push((opf_path, opf.to_string()?.into_bytes()));
+
+    let target = match mode {
+        SaveMode::InPlace => epub_path.to_path_buf(),
+        SaveMode::Copy => copy_path(epub_path),
+    };
+    drop(archive);
+    let folder = match target.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    // Hidden and in the folder of the library, the library scan skips it
+    let tmp_dir = tempfile::Builder::new()
+        .prefix(".edit-")
+        .tempdir_in(&folder)?;
+    let tmp_path = tmp_dir.path().join("book.epub");
+    write_epub(epub_path, &tmp_path, &replaced)?;
+    fs::rename(&tmp_path, &target)?;
+    Ok(target)
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+// Share of the words of a passage that must be found together in a page
+pub const FUZZY_THRESHOLD: f64 = 0.8;
+
+/**
+ * Passage found in a page
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassageMatch {
+    pub page: usize,
+    // Position of the passage in the text of the page (0.0 start, 1.0 end)
+    pub position: f64,
+    // Share of the words of the passage found, 1.0 if it is in the page as it is
+    pub score: f64,
+}
+
+/**
+ * Words of a text, in lowercase and without punctuation
+ */
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .map(|word| word.to_string())
+        .collect()
+}
+
+/**
+ * Find a passage in the pages (page index, text of the page).
+ * The case, the punctuation and the spaces are ignored; if the passage is not in a page
+ * as it is (e.g. text from OCR or from another edition), the page with the most words
+ * of the passage close together is taken, if it has enough of them (FUZZY_THRESHOLD).
+ * The hint is the page to look at first.
+ */
+pub fn find_passage(
+    pages: &[(usize, String)],
+    passage: &str,
+    hint: Option<usize>,
+) -> Option<PassageMatch> {
+    let passage = words(passage);
+    if passage.is_empty() {
+        return None;
+    }
+    let mut pages: Vec<&(usize, String)> = pages.iter().collect();
+    if let Some(hint) = hint {
+        pages.sort_by_key(|(page, _)| *page != hint);
+    }
+
+    let joined = format!(" {} ", passage.join(" "));
+    let mut best: Option<PassageMatch> = None;
+    for (page, text) in pages {
+        let text = words(text);
+        if text.is_empty() {
+            continue;
+        }
+
+        // The passage as it is
+        let page_text = format!(" {} ", text.join(" "));
+        if let Some(start) = page_text.find(joined.as_str()) {
+            let word = page_text[..start].matches(' ').count();
+            return Some(PassageMatch {
+                page: *page,
+                position: word as f64 / text.len() as f64,
+                score: 1.0,
+            });
+        }
+
+        // Window of the length of the passage with the most of its words
+        let mut wanted: HashMap<&str, usize> = HashMap::new();
+        for word in passage.iter() {
+            *wanted.entry(word.as_str()).or_default() += 1;
+        }
+        let mut window: HashMap<&str, usize> = HashMap::new();
+        let mut found = 0;
+        let (mut best_found, mut best_start) = (0, 0);
+        for (index, word) in text.iter().enumerate() {
+            let count = window.entry(word.as_str()).or_default();
+            if *count < wanted.get(word.as_str()).copied().unwrap_or(0) {
+                found += 1;
+            }
+            *count += 1;
+            if index >= passage.len() {
+                let removed = text[index - passage.len()].as_str();
+                let count = window.entry(removed).or_default();
+                *count -= 1;
+                if *count < wanted.get(removed).copied().unwrap_or(0) {
+                    found -= 1;
+                }
+            }
+            if found > best_found {
+                best_found = found;
+                best_start = (index + 1).saturating_sub(passage.len());
+            }
+        }
+        let score = best_found as f64 / passage.len() as f64;
+        if score >= FUZZY_THRESHOLD && best.is_none_or(|best| score > best.score) {
+            best = Some(PassageMatch {
+                page: *page,
+                position: best_start as f64 / text.len() as f64,
+                score,
+            });
+        }
+    }
+    best
+}
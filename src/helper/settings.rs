@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs};
+
+use super::config::SETTINGS_PATH;
+
+/**
+ * User settings, read from the settings file (json).
+ * Every field is optional in the file, missing ones keep their default.
+ * keymap -> action name (e.g. "next_page") and its keys (e.g. ["ArrowRight", "L"])
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub keymap: HashMap<String, Vec<String>>,
+}
+
+impl Settings {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/**
+ * Load the settings file, the defaults are used if it does not exist or is invalid
+ */
+pub fn load_settings() -> Settings {
+    let json = match fs::read_to_string(SETTINGS_PATH) {
+        Ok(json) => json,
+        Err(_) => return Settings::default(),
+    };
+    match Settings::from_json(json.as_str()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Invalid settings file: {}", e);
+            Settings::default()
+        }
+    }
+}
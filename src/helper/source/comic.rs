@@ -0,0 +1,328 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fs::{self, File},
+    io::Read,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+use zip::ZipArchive;
+
+use super::{escape_xml, title_from_path, xhtml_page, BookFormat, BookSource};
+use crate::helper::{
+    opf::PageProgression,
+    xml::{XmlDocument, XmlElement},
+};
+
+/*
+ * Comic books: a CBZ archive (a zip of images) or a folder of images.
+ * Each image is a page, in the order of their names ("page2" before "page10").
+ * The first image is the cover, as the first section of an EPUB.
+ * The ComicInfo.xml file of the archive, if any, gives the metadata and tells
+ * whether the pages are read from right to left (manga).
+ */
+
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+const COMIC_INFO: &str = "ComicInfo.xml";
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+/**
+ * Compare names with their numbers as numbers: "page2.jpg" < "page10.jpg"
+ */
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_digits(&mut a), take_digits(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/**
+ * What the ComicInfo.xml file of a comic tells about it
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComicInfo {
+    // By their Dublin Core names ("title", "creator"...)
+    pub metadata: Vec<(String, String)>,
+    pub page_progression: PageProgression,
+}
+
+/**
+ * Read a ComicInfo.xml file
+ */
+pub fn parse_comic_info(xml: &str) -> Result<ComicInfo, Box<dyn Error>> {
+    let document = XmlDocument::parse(xml)?;
+    let root = &document.root;
+    let text = |name: &str| {
+        root.child(name)
+            .map(XmlElement::text)
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let mut metadata = Vec::new();
+    // "Series #3: Title" when the comic is part of a series
+    let title = match (text("Series"), text("Number"), text("Title")) {
+        (Some(series), Some(number), Some(title)) => {
+            Some(format!("{} #{}: {}", series, number, title))
+        }
+        (Some(series), Some(number), None) => Some(format!("{} #{}", series, number)),
+        (Some(series), None, title) => Some(title.unwrap_or(series)),
+        (None, _, title) => title,
+    };
+    if let Some(title) = title {
+        metadata.push(("title".to_string(), title));
+    }
+    if let Some(writer) = text("Writer").or_else(|| text("Penciller")) {
+        metadata.push(("creator".to_string(), writer));
+    }
+    if let Some(language) = text("LanguageISO") {
+        metadata.push(("language".to_string(), language));
+    }
+    if let Some(identifier) = text("GTIN") {
+        metadata.push(("identifier".to_string(), identifier));
+    }
+    let page_progression = match text("Manga").as_deref() {
+        Some("YesAndRightToLeft") => PageProgression::Rtl,
+        _ => PageProgression::Ltr,
+    };
+    Ok(ComicInfo {
+        metadata,
+        page_progression,
+    })
+}
+
+/**
+ * Copy the images of a folder (and its ComicInfo.xml) into a new folder
+ */
+pub fn copy_comic_folder(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    let files: Vec<PathBuf> = fs::read_dir(from)?
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| path.is_file())
+        .filter(|path| is_image(path) || path.file_name().is_some_and(|name| name == COMIC_INFO))
+        .collect();
+    if !files.iter().any(|path| is_image(path)) {
+        return Err(format!("No images in {}", from.display()).into());
+    }
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()).into());
+    }
+    fs::create_dir_all(to)?;
+    for file in files {
+        if let Some(name) = file.file_name() {
+            fs::copy(&file, to.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Where the images of a comic are read from
+ */
+enum Pages {
+    Archive(ZipArchive<File>),
+    Folder(PathBuf),
+}
+
+pub struct ComicSource {
+    pages: Pages,
+    // Paths of the images, in the archive or the folder, in reading order
+    images: Vec<PathBuf>,
+    metadata: Vec<(String, String)>,
+    page_progression: PageProgression,
+}
+
+impl ComicSource {
+    /**
+     * Open a CBZ archive
+     */
+    pub fn open_cbz(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let images: Vec<PathBuf> = archive
+            .file_names()
+            .map(PathBuf::from)
+            .filter(|name| is_image(name))
+            // Not the files macOS adds to the archives
+            .filter(|name| !name.starts_with("__MACOSX"))
+            .collect();
+        let comic_info = archive
+            .file_names()
+            .find(|name| {
+                Path::new(name)
+                    .file_name()
+                    .is_some_and(|file| file == COMIC_INFO)
+            })
+            .map(str::to_string);
+        let comic_info = comic_info.and_then(|name| {
+            let mut xml = String::new();
+            archive
+                .by_name(name.as_str())
+                .ok()?
+                .read_to_string(&mut xml)
+                .ok()?;
+            Some(xml)
+        });
+        Self::new(Pages::Archive(archive), images, comic_info, path)
+    }
+
+    /**
+     * Open a folder of images
+     */
+    pub fn open_folder(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let images: Vec<PathBuf> = fs::read_dir(path)?
+            .flatten()
+            .map(|file| PathBuf::from(file.file_name()))
+            .filter(|name| is_image(name))
+            .collect();
+        let comic_info = fs::read_to_string(path.join(COMIC_INFO)).ok();
+        Self::new(Pages::Folder(path.to_path_buf()), images, comic_info, path)
+    }
+
+    fn new(
+        pages: Pages,
+        mut images: Vec<PathBuf>,
+        comic_info: Option<String>,
+        path: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
+        if images.is_empty() {
+            return Err(format!("No images in {}", path.display()).into());
+        }
+        images.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+
+        let mut comic_info = match comic_info.map(|xml| parse_comic_info(xml.as_str())) {
+            Some(Ok(comic_info)) => comic_info,
+            Some(Err(e)) => {
+                eprintln!("Invalid {}: {}", COMIC_INFO, e);
+                ComicInfo::default()
+            }
+            None => ComicInfo::default(),
+        };
+        if !comic_info.metadata.iter().any(|(key, _)| key == "title") {
+            let title = title_from_path(path);
+            comic_info.metadata.push(("title".to_string(), title));
+        }
+        Ok(Self {
+            pages,
+            images,
+            metadata: comic_info.metadata,
+            page_progression: comic_info.page_progression,
+        })
+    }
+}
+
+impl BookSource for ComicSource {
+    fn format(&self) -> BookFormat {
+        BookFormat::Comic
+    }
+
+    fn metadata(&self, name: &str) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    fn section_path(&self, index: usize) -> Option<PathBuf> {
+        (index < self.images.len()).then(|| PathBuf::from(format!("page-{:03}.xhtml", index)))
+    }
+
+    /**
+     * Page showing the image (the reader shows the image itself, with page_image)
+     */
+    fn section(&mut self, index: usize) -> Result<String, Box<dyn Error>> {
+        let image = self
+            .images
+            .get(index)
+            .ok_or_else(|| format!("No page {}", index))?;
+        let src = escape_xml(image.to_string_lossy().as_ref());
+        let body = format!("<img src=\"{}\" alt=\"\"/>", src);
+        Ok(xhtml_page(
+            format!("Page {}", index).as_str(),
+            "",
+            body.as_str(),
+        ))
+    }
+
+    fn resource(&mut self, path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+        // Only the images of the comic
+        if !self.images.iter().any(|image| image == path) {
+            return Err(format!("No resource {}", path.display()).into());
+        }
+        match &mut self.pages {
+            Pages::Archive(archive) => {
+                let mut file = archive.by_name(path.to_string_lossy().as_ref())?;
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            Pages::Folder(folder) => Ok(fs::read(folder.join(path))?),
+        }
+    }
+
+    fn toc(&self) -> Vec<(String, PathBuf)> {
+        Vec::new()
+    }
+
+    fn cover(&mut self) -> Option<Vec<u8>> {
+        self.page_image(0)
+    }
+
+    fn page_progression(&mut self) -> PageProgression {
+        self.page_progression
+    }
+
+    fn page_image(&mut self, index: usize) -> Option<Vec<u8>> {
+        let image = self.images.get(index)?.clone();
+        match self.resource(&image) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                eprintln!("Error reading page {}: {}", image.display(), e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use epub::doc::EpubDoc;
+
+use super::{BookFormat, BookSource};
+use crate::helper::{
+    cover::first_image_src,
+    functions::resolve_relative_path,
+    opf::{read_page_progression, PageProgression},
+    xml::XmlDocument,
+};
+
+/**
+ * EPUB book, read with the epub crate
+ */
+pub struct EpubSource {
+    doc: EpubDoc<BufReader<File>>,
+}
+
+impl EpubSource {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        // The errors of the epub crate are kept as their message
+        let doc = EpubDoc::new(path).map_err(|e| e.to_string())?;
+        Ok(Self { doc })
+    }
+
+    /**
+     * Get the first image of the first page of the book (usually the cover page)
+     */
+    fn first_page_image(&mut self) -> Option<Vec<u8>> {
+        let base = self.section_path(0)?;
+        let html = self.section(0).ok()?;
+        let src = first_image_src(html.as_str())?;
+        self.doc
+            .get_resource_by_path(resolve_relative_path(&base, src.as_str()))
+            .ok()
+    }
+}
+
+impl BookSource for EpubSource {
+    fn format(&self) -> BookFormat {
+        BookFormat::Epub
+    }
+
+    fn metadata(&self, name: &str) -> Option<String> {
+        self.doc.mdata(name)
+    }
+
+    fn len(&self) -> usize {
+        self.doc.spine.len()
+    }
+
+    fn section_path(&self, index: usize) -> Option<PathBuf> {
+        let id = self.doc.spine.get(index)?;
+        self.doc.resources.get(id).map(|(path, _)| path.clone())
+    }
+
+    fn section(&mut self, index: usize) -> Result<String, Box<dyn Error>> {
+        self.doc
+            .set_current_page(index)
+            .map_err(|e| e.to_string())?;
+        Ok(self.doc.get_current_str().map_err(|e| e.to_string())?)
+    }
+
+    fn resource(&mut self, path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self
+            .doc
+            .get_resource_by_path(path)
+            .map_err(|e| e.to_string())?)
+    }
+
+    fn resource_str(&mut self, path: &Path) -> Result<String, Box<dyn Error>> {
+        Ok(self
+            .doc
+            .get_resource_str_by_path(path)
+            .map_err(|e| e.to_string())?)
+    }
+
+    /**
+     * doc.resources
+     * It’s a HashMap<a: String, (b: PathBuf, c: String)> where ‘a’ is the resource id, ‘b’ is the resource full path and ‘c’ is the resource mimetype
+     * Get the resource id of the resource at the path (or, for links badly resolved, ending with it)
+     * and the chapter of the spine it is
+     */
+    fn section_index(&self, path: &Path) -> Option<usize> {
+        let resource_id = self
+            .doc
+            .resources
+            .iter()
+            .find(|(_, (resource, _))| resource == path)
+            .or_else(|| {
+                self.doc
+                    .resources
+                    .iter()
+                    .find(|(_, (resource, _))| resource.ends_with(path))
+            })
+            .map(|(id, _)| id.clone());
+        resource_id.and_then(|id| self.doc.resource_id_to_chapter(&id))
+    }
+
+    fn toc(&self) -> Vec<(String, PathBuf)> {
+        self.doc
+            .toc
+            .iter()
+            .map(|point| (point.label.clone(), point.content.clone()))
+            .collect()
+    }
+
+    /**
+     * The cover declared in the package, otherwise the first image of the first page
+     */
+    fn cover(&mut self) -> Option<Vec<u8>> {
+        match self.doc.get_cover() {
+            Ok(cover) => Some(cover),
+            Err(e) => {
+                println!("No cover declared: {}", e);
+                self.first_page_image()
+            }
+        }
+    }
+
+    /**
+     * Page progression direction of the book, read from its package document
+     */
+    fn page_progression(&mut self) -> PageProgression {
+        let opf_path = self.doc.root_file.clone();
+        let opf = match self.doc.get_resource_str_by_path(&opf_path) {
+            Ok(opf) => opf,
+            Err(e) => {
+                eprintln!("Error reading package document: {}", e);
+                return PageProgression::Ltr;
+            }
+        };
+        match XmlDocument::parse(opf.as_str()) {
+            Ok(opf) => read_page_progression(&opf),
+            Err(e) => {
+                eprintln!("Error parsing package document: {}", e);
+                PageProgression::Ltr
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::{error::Error, fs, path::Path, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::{title_from_path, xhtml_page, BookFormat, BookSource, MemorySource, Section};
+use crate::helper::xml::{XmlDocument, XmlElement, XmlNode};
+
+/*
+ * FictionBook (FB2) books: a single XML file with the description of the book,
+ * its bodies (the text, then the notes) made of sections, and its images in base64.
+ * Each top level section of the text is a chapter, the notes are a page of their own
+ * so that the links to them open as notes.
+ */
+
+const NOTES_PATH: &str = "notes.xhtml";
+const IMAGES_FOLDER: &str = "images";
+
+/**
+ * Target of an FB2 link ("l:href" or "xlink:href", whatever the prefix)
+ */
+fn href(element: &XmlElement) -> Option<&str> {
+    element
+        .attributes
+        .iter()
+        .find(|(key, _)| key == "href" || key.ends_with(":href"))
+        .map(|(_, value)| value.as_str())
+}
+
+/**
+ * Ids of the elements of a body (the notes links are to these ids)
+ */
+fn ids(element: &XmlElement, ids: &mut Vec<String>) {
+    if let Some(id) = element.attr("id") {
+        ids.push(id.to_string());
+    }
+    for child in element.elements() {
+        self::ids(child, ids);
+    }
+}
+
+/**
+ * XHTML of an FB2 element, depth is the level of the section it is in (for its title)
+ */
+fn to_xhtml(element: &XmlElement, depth: usize, notes: &[String]) -> XmlElement {
+    let name = match element.local_name() {
+        "title" => match depth {
+            0 | 1 => "h2",
+            2 => "h3",
+            _ => "h4",
+        },
+        "subtitle" => "h4",
+        "p" | "v" | "text-author" => "p",
+        "emphasis" => "em",
+        "strong" => "strong",
+        "strikethrough" => "del",
+        "sub" => "sub",
+        "sup" => "sup",
+        "code" => "code",
+        "empty-line" => "br",
+        "epigraph" | "cite" => "blockquote",
+        "image" => "img",
+        "a" => "a",
+        "table" => "table",
+        "tr" => "tr",
+        "td" => "td",
+        "th" => "th",
+        "section" | "poem" | "stanza" | "annotation" | "body" => "div",
+        _ => "span",
+    };
+    let mut xhtml = XmlElement::new(name);
+    if let Some(id) = element.attr("id") {
+        xhtml.set_attr("id", id);
+    }
+    match element.local_name() {
+        "v" => xhtml.set_attr("class", "verse"),
+        "text-author" => xhtml.set_attr("class", "author"),
+        "poem" => xhtml.set_attr("class", "poem"),
+        _ => (),
+    }
+    match (element.local_name(), href(element)) {
+        ("image", Some(href)) => {
+            let id = href.trim_start_matches('#');
+            xhtml.set_attr("src", format!("{}/{}", IMAGES_FOLDER, id).as_str());
+            xhtml.set_attr("alt", element.attr("alt").unwrap_or_default());
+        }
+        ("a", Some(href)) => {
+            let id = href.trim_start_matches('#');
+            // The notes are in a page of their own
+            if href.starts_with('#') && notes.iter().any(|note| note == id) {
+                xhtml.set_attr("href", format!("{}#{}", NOTES_PATH, id).as_str());
+            } else {
+                xhtml.set_attr("href", href);
+            }
+        }
+        _ => (),
+    }
+    // The paragraphs of a title are its lines
+    if element.local_name() == "title" {
+        let lines: Vec<String> = element
+            .elements()
+            .filter_map(|line| text_of(Some(line)))
+            .collect();
+        return xhtml.with_text(lines.join(" ").as_str());
+    }
+    let depth = if element.local_name() == "section" {
+        depth + 1
+    } else {
+        depth
+    };
+    for child in element.children.iter() {
+        match child {
+            XmlNode::Element(child) => xhtml
+                .children
+                .push(XmlNode::Element(to_xhtml(child, depth, notes))),
+            XmlNode::Text(text) => xhtml.children.push(XmlNode::Text(text.clone())),
+            XmlNode::Other(_) => (),
+        }
+    }
+    xhtml
+}
+
+/**
+ * Serialized children of an XHTML element
+ */
+fn inner_xhtml(element: XmlElement) -> Result<String, Box<dyn Error>> {
+    let mut html = String::new();
+    for child in element.children {
+        match child {
+            XmlNode::Element(child) => html.push_str(
+                XmlDocument {
+                    prolog: Vec::new(),
+                    root: child,
+                }
+                .to_string()?
+                .as_str(),
+            ),
+            XmlNode::Text(text) => html.push_str(super::escape_xml(text.as_str()).as_str()),
+            XmlNode::Other(_) => (),
+        }
+    }
+    Ok(html)
+}
+
+fn text_of(element: Option<&XmlElement>) -> Option<String> {
+    let text = element?.text();
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/**
+ * Name of an FB2 author: first, middle and last names, or nickname
+ */
+fn author_name(author: &XmlElement) -> String {
+    let names: Vec<String> = ["first-name", "middle-name", "last-name"]
+        .iter()
+        .filter_map(|name| text_of(author.child(name)))
+        .collect();
+    if names.is_empty() {
+        text_of(author.child("nickname")).unwrap_or_default()
+    } else {
+        names.join(" ")
+    }
+}
+
+/**
+ * Convert an FB2 document into a book
+ */
+pub fn parse_fb2(xml: &str, fallback_title: &str) -> Result<MemorySource, Box<dyn Error>> {
+    let document = XmlDocument::parse_xhtml(xml)?;
+    let root = &document.root;
+    if root.local_name() != "FictionBook" {
+        return Err("Not a FictionBook document".into());
+    }
+    let description = root.child("description");
+    let title_info = description.and_then(|description| description.child("title-info"));
+    let title = text_of(title_info.and_then(|info| info.child("book-title")))
+        .unwrap_or_else(|| fallback_title.to_string());
+    let author = title_info
+        .map(|info| {
+            info.elements()
+                .filter(|element| element.local_name() == "author")
+                .map(author_name)
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<String>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+
+    let bodies: Vec<&XmlElement> = root
+        .elements()
+        .filter(|element| element.local_name() == "body")
+        .collect();
+    let (text, notes): (Vec<&XmlElement>, Vec<&XmlElement>) = bodies
+        .into_iter()
+        .partition(|body| !matches!(body.attr("name"), Some("notes") | Some("comments")));
+    let mut note_ids = Vec::new();
+    for body in notes.iter() {
+        ids(body, &mut note_ids);
+    }
+
+    let mut chapters = Vec::new();
+    for body in text.iter() {
+        let sections: Vec<&XmlElement> = body
+            .elements()
+            .filter(|element| element.local_name() == "section")
+            .collect();
+        if sections.is_empty() {
+            chapters.push((title.clone(), inner_xhtml(to_xhtml(body, 0, &note_ids))?));
+            continue;
+        }
+        for section in sections {
+            let name = text_of(section.child("title"))
+                .unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1));
+            chapters.push((name, inner_xhtml(to_xhtml(section, 0, &note_ids))?));
+        }
+    }
+    let mut source = MemorySource::new(
+        BookFormat::Fb2,
+        title.as_str(),
+        author.as_str(),
+        "",
+        chapters,
+    );
+
+    if !notes.is_empty() {
+        let mut html = String::new();
+        for body in notes {
+            html.push_str(inner_xhtml(to_xhtml(body, 0, &note_ids))?.as_str());
+        }
+        source.sections.push(Section {
+            path: PathBuf::from(NOTES_PATH),
+            title: "Notes".to_string(),
+            html: xhtml_page("Notes", "", html.as_str()),
+        });
+    }
+
+    for (key, path) in [
+        ("identifier", "document-info/id"),
+        ("identifier", "publish-info/isbn"),
+        ("language", "title-info/lang"),
+    ] {
+        let (info, name) = path.split_once('/').unwrap_or_default();
+        let value = text_of(
+            description
+                .and_then(|d| d.child(info))
+                .and_then(|i| i.child(name)),
+        );
+        if let Some(value) = value {
+            if source.metadata(key).is_none() {
+                source.set_metadata(key, value.as_str());
+            }
+        }
+    }
+
+    // Images, in base64
+    for binary in root
+        .elements()
+        .filter(|element| element.local_name() == "binary")
+    {
+        let id = match binary.attr("id") {
+            Some(id) => id,
+            None => continue,
+        };
+        let data: String = binary.text().split_whitespace().collect();
+        match STANDARD.decode(data.as_bytes()) {
+            Ok(bytes) => source
+                .resources
+                .push((Path::new(IMAGES_FOLDER).join(id), bytes)),
+            Err(e) => eprintln!("Error decoding image {}: {}", id, e),
+        }
+    }
+    let cover = title_info
+        .and_then(|info| info.child("coverpage"))
+        .and_then(|coverpage| coverpage.child("image"))
+        .and_then(href)
+        .map(|href| Path::new(IMAGES_FOLDER).join(href.trim_start_matches('#')));
+    source.cover = cover.and_then(|cover| {
+        source
+            .resources
+            .iter()
+            .find(|(path, _)| *path == cover)
+            .map(|(_, bytes)| bytes.clone())
+    });
+    Ok(source)
+}
+
+/**
+ * Read a FictionBook (FB2) book
+ */
+pub fn open_fb2(path: &Path) -> Result<MemorySource, Box<dyn Error>> {
+    let xml = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    parse_fb2(xml.as_str(), title_from_path(path).as_str())
+}
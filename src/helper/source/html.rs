@@ -0,0 +1,193 @@
+use std::{error::Error, fs, path::Path};
+
+use super::{title_from_path, BookFormat, MemorySource};
+use crate::helper::cover::first_image_src;
+
+/*
+ * Single file HTML books: the page is cut into chapters at its headings.
+ * Markdown books are converted to HTML and cut the same way.
+ */
+
+/**
+ * Text of a piece of html, without its tags and with its spaces collapsed
+ */
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/**
+ * Content of the first element with a name: ("<title>Book</title>", "title") -> "Book"
+ */
+fn element_content<'a>(html: &'a str, lower: &str, name: &str) -> Option<&'a str> {
+    let start = find_tag(lower, name, 0)?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = lower[start..]
+        .find(format!("</{}", name).as_str())
+        .map_or(html.len(), |end| start + end);
+    Some(&html[start..end])
+}
+
+/**
+ * Position of the next opening tag with a name, from a position of the (lowercase) html
+ */
+fn find_tag(lower: &str, name: &str, from: usize) -> Option<usize> {
+    let tag = format!("<{}", name);
+    let mut from = from;
+    while let Some(position) = lower[from..].find(tag.as_str()) {
+        let position = from + position;
+        // "<h1>" or "<h1 class=...>", not "<h10>" or "<header>"
+        match lower[position + tag.len()..].chars().next() {
+            Some(c) if c == '>' || c == '/' || c.is_whitespace() => return Some(position),
+            _ => from = position + tag.len(),
+        }
+    }
+    None
+}
+
+fn find_tags(lower: &str, name: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut from = 0;
+    while let Some(position) = find_tag(lower, name, from) {
+        positions.push(position);
+        from = position + 1;
+    }
+    positions
+}
+
+/**
+ * Value of a <meta name="..." content="..."> of the html
+ */
+fn meta_content(html: &str, lower: &str, name: &str) -> Option<String> {
+    find_tags(lower, "meta").into_iter().find_map(|start| {
+        let end = start + lower[start..].find('>')?;
+        let tag = &lower[start..end];
+        let quoted = |attribute: &str| -> Option<(usize, usize)> {
+            let position = tag.find(format!("{}=", attribute).as_str())? + attribute.len() + 1;
+            let quote = tag[position..].chars().next()?;
+            let value_end = position + 1 + tag[position + 1..].find(quote)?;
+            Some((position + 1, value_end))
+        };
+        let (name_start, name_end) = quoted("name")?;
+        if &tag[name_start..name_end] != name {
+            return None;
+        }
+        let (content_start, content_end) = quoted("content")?;
+        Some(strip_tags(
+            &html[start + content_start..start + content_end],
+        ))
+    })
+}
+
+/**
+ * Cut the body of an html page into chapters (title, html) at its headings:
+ * at the second level headings if there is only one first level heading (the title
+ * of the book, which is returned), otherwise at the first level headings, if any.
+ * The text before the first heading is a chapter named after the book.
+ */
+pub fn split_sections(body: &str, book_title: &str) -> (Option<String>, Vec<(String, String)>) {
+    // Ascii lowercase keeps the byte offsets of the original string
+    let lower = body.to_ascii_lowercase();
+    let h1 = find_tags(&lower, "h1");
+    let h2 = find_tags(&lower, "h2");
+    let heading_text = |start: usize, name: &str| -> String {
+        element_content(&body[start..], &lower[start..], name)
+            .map(strip_tags)
+            .unwrap_or_default()
+    };
+
+    let (title, level, starts) = if h1.len() == 1 && !h2.is_empty() {
+        (Some(heading_text(h1[0], "h1")), "h2", h2)
+    } else if !h1.is_empty() {
+        (None, "h1", h1)
+    } else {
+        (None, "h2", h2)
+    };
+    let title = title.filter(|title| !title.is_empty());
+
+    let mut chapters = Vec::new();
+    let first = starts.first().copied().unwrap_or(body.len());
+    let before = &body[..first];
+    // Without the title of the book
+    let mut before_text = strip_tags(before);
+    if let Some(title) = &title {
+        before_text = before_text.replacen(title.as_str(), "", 1);
+    }
+    if !before_text.trim().is_empty() {
+        let name = title.clone().unwrap_or_else(|| book_title.to_string());
+        chapters.push((name, before.trim().to_string()));
+    }
+    for (index, start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).copied().unwrap_or(body.len());
+        let mut name = heading_text(*start, level);
+        if name.is_empty() {
+            name = format!("Chapter {}", index + 1);
+        }
+        chapters.push((name, body[*start..end].trim().to_string()));
+    }
+    (title, chapters)
+}
+
+/**
+ * Read a single file HTML book, its stylesheets and images are read next to it
+ */
+pub fn open_html(path: &Path) -> Result<MemorySource, Box<dyn Error>> {
+    let html = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let lower = html.to_ascii_lowercase();
+
+    let title = element_content(&html, &lower, "title")
+        .map(strip_tags)
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| title_from_path(path));
+    let author = meta_content(&html, &lower, "author").unwrap_or_default();
+    // The head without its title, for the stylesheets
+    let head = match element_content(&html, &lower, "head") {
+        Some(head) => {
+            let head_lower = head.to_ascii_lowercase();
+            match (
+                find_tag(&head_lower, "title", 0),
+                head_lower.find("</title>"),
+            ) {
+                (Some(start), Some(end)) if start < end => {
+                    format!("{}{}", &head[..start], &head[end + "</title>".len()..])
+                }
+                _ => head.to_string(),
+            }
+        }
+        None => String::new(),
+    };
+    let body = element_content(&html, &lower, "body").unwrap_or(html.as_str());
+
+    let (_, chapters) = split_sections(body, title.as_str());
+    let mut source = MemorySource::new(
+        BookFormat::Html,
+        title.as_str(),
+        author.as_str(),
+        head.trim(),
+        chapters,
+    );
+    source.folder = path.parent().map(|folder| folder.to_path_buf());
+    source.cover = first_image_src(body)
+        .and_then(|src| source.folder.as_ref().map(|folder| folder.join(src)))
+        .and_then(|cover| fs::read(cover).ok());
+    Ok(source)
+}
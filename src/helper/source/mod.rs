@@ -0,0 +1,368 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::opf::PageProgression;
+
+pub mod comic;
+pub mod epub;
+pub mod fb2;
+pub mod html;
+pub mod pdf;
+pub mod text;
+
+/*
+ * A book source gives the reader what it needs from a book file, whatever its format:
+ * its metadata, its sections in reading order (the spine, as XHTML pages), the resources
+ * the sections link to (stylesheets, images, notes) and its cover.
+ * EPUB books are read as they are, the other formats are converted into sections when
+ * they are opened. As in an EPUB, the first section is the cover (or title) page and
+ * the reader starts at the second one. The pages of a comic are images, shown as they are,
+ * the sections of a PDF are its pages, with their text.
+ */
+
+/**
+ * Formats of the books of the library
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BookFormat {
+    Epub,
+    Text,
+    Markdown,
+    Html,
+    Fb2,
+    Comic,
+    Pdf,
+}
+
+impl BookFormat {
+    pub const ALL: [BookFormat; 7] = [
+        BookFormat::Epub,
+        BookFormat::Text,
+        BookFormat::Markdown,
+        BookFormat::Html,
+        BookFormat::Fb2,
+        BookFormat::Comic,
+        BookFormat::Pdf,
+    ];
+
+    /**
+     * Extensions of the files of the format, the first one is the usual one
+     */
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            BookFormat::Epub => &["epub"],
+            BookFormat::Text => &["txt"],
+            BookFormat::Markdown => &["md", "markdown"],
+            BookFormat::Html => &["html", "htm", "xhtml"],
+            BookFormat::Fb2 => &["fb2"],
+            BookFormat::Comic => &["cbz"],
+            BookFormat::Pdf => &["pdf"],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BookFormat::Epub => "EPUB",
+            BookFormat::Text => "Plain text",
+            BookFormat::Markdown => "Markdown",
+            BookFormat::Html => "HTML",
+            BookFormat::Fb2 => "FictionBook",
+            BookFormat::Comic => "Comic book",
+            BookFormat::Pdf => "PDF",
+        }
+    }
+
+    /**
+     * Format of a file from its extension, None if it is not a book
+     */
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        BookFormat::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    /**
+     * Extensions of all the formats (for the open dialog)
+     */
+    pub fn all_extensions() -> Vec<&'static str> {
+        BookFormat::ALL
+            .iter()
+            .flat_map(|format| format.extensions().iter().copied())
+            .collect()
+    }
+}
+
+pub trait BookSource: Send {
+    fn format(&self) -> BookFormat;
+
+    /**
+     * Metadata by its Dublin Core name ("title", "creator", "identifier", "language")
+     */
+    fn metadata(&self, name: &str) -> Option<String>;
+
+    /**
+     * Number of sections (the spine)
+     */
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * Path of a section, the links and resources of the section are relative to it
+     */
+    fn section_path(&self, index: usize) -> Option<PathBuf>;
+
+    /**
+     * XHTML of a section
+     */
+    fn section(&mut self, index: usize) -> Result<String, Box<dyn Error>>;
+
+    /**
+     * Resource at a path of the book (stylesheet, image, page of notes...)
+     */
+    fn resource(&mut self, path: &Path) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    fn resource_str(&mut self, path: &Path) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(self.resource(path)?)?)
+    }
+
+    /**
+     * Section at a path (or, for links badly resolved, ending with it)
+     */
+    fn section_index(&self, path: &Path) -> Option<usize> {
+        let paths: Vec<PathBuf> = (0..self.len())
+            .filter_map(|index| self.section_path(index))
+            .collect();
+        paths
+            .iter()
+            .position(|section| section == path)
+            .or_else(|| paths.iter().position(|section| section.ends_with(path)))
+    }
+
+    /**
+     * Table of contents (label, path of the section with an optional fragment)
+     */
+    fn toc(&self) -> Vec<(String, PathBuf)>;
+
+    /**
+     * Image of the cover, if the book has one
+     */
+    fn cover(&mut self) -> Option<Vec<u8>>;
+
+    fn page_progression(&mut self) -> PageProgression {
+        PageProgression::Ltr
+    }
+
+    /**
+     * Image of a section that is only an image (the pages of a comic), None otherwise
+     */
+    fn page_image(&mut self, _index: usize) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+pub type SharedSource = Arc<Mutex<Box<dyn BookSource>>>;
+
+/**
+ * Open a book file with the source of its format (a folder is a comic of its images)
+ */
+pub fn open_source(path: &Path) -> Result<Box<dyn BookSource>, Box<dyn Error>> {
+    if path.is_dir() {
+        return Ok(Box::new(comic::ComicSource::open_folder(path)?));
+    }
+    let format = BookFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported book format: {}", path.display()))?;
+    match format {
+        BookFormat::Epub => Ok(Box::new(epub::EpubSource::open(path)?)),
+        BookFormat::Text => Ok(Box::new(text::open_text(path)?)),
+        BookFormat::Markdown => Ok(Box::new(text::open_markdown(path)?)),
+        BookFormat::Html => Ok(Box::new(html::open_html(path)?)),
+        BookFormat::Fb2 => Ok(Box::new(fb2::open_fb2(path)?)),
+        BookFormat::Comic => Ok(Box::new(comic::ComicSource::open_cbz(path)?)),
+        BookFormat::Pdf => Ok(Box::new(pdf::open_pdf(path)?)),
+    }
+}
+
+/**
+ * Section of a book converted when it is opened
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    pub path: PathBuf,
+    pub title: String,
+    pub html: String,
+}
+
+/**
+ * Book converted from a format other than EPUB, kept in memory
+ */
+#[derive(Clone, Debug)]
+pub struct MemorySource {
+    pub format: BookFormat,
+    pub metadata: Vec<(String, String)>,
+    // The first section is the title page
+    pub sections: Vec<Section>,
+    pub resources: Vec<(PathBuf, Vec<u8>)>,
+    pub cover: Option<Vec<u8>>,
+    // Table of contents, when it is not the titles of the sections
+    pub toc: Vec<(String, PathBuf)>,
+    // Folder of the book file, the resources it links to are read from there
+    pub folder: Option<PathBuf>,
+}
+
+impl MemorySource {
+    /**
+     * Source made of a title page and the chapters (title, XHTML body) of a book,
+     * head is added to the head of the chapters (e.g. the stylesheets of an HTML book)
+     */
+    pub fn new(
+        format: BookFormat,
+        title: &str,
+        author: &str,
+        head: &str,
+        chapters: Vec<(String, String)>,
+    ) -> Self {
+        let mut sections = vec![Section {
+            path: PathBuf::from("title.xhtml"),
+            title: title.to_string(),
+            html: title_page(title, author),
+        }];
+        for (index, (chapter, body)) in chapters.into_iter().enumerate() {
+            sections.push(Section {
+                path: PathBuf::from(format!("chapter-{:03}.xhtml", index + 1)),
+                html: xhtml_page(chapter.as_str(), head, body.as_str()),
+                title: chapter,
+            });
+        }
+        let mut metadata = vec![("title".to_string(), title.to_string())];
+        if !author.is_empty() {
+            metadata.push(("creator".to_string(), author.to_string()));
+        }
+        Self {
+            format,
+            metadata,
+            sections,
+            resources: Vec::new(),
+            cover: None,
+            toc: Vec::new(),
+            folder: None,
+        }
+    }
+
+    pub fn set_metadata(&mut self, name: &str, value: &str) {
+        match self.metadata.iter_mut().find(|(key, _)| key == name) {
+            Some((_, saved)) => *saved = value.to_string(),
+            None => self.metadata.push((name.to_string(), value.to_string())),
+        }
+    }
+}
+
+impl BookSource for MemorySource {
+    fn format(&self) -> BookFormat {
+        self.format
+    }
+
+    fn metadata(&self, name: &str) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section_path(&self, index: usize) -> Option<PathBuf> {
+        self.sections.get(index).map(|section| section.path.clone())
+    }
+
+    fn section(&mut self, index: usize) -> Result<String, Box<dyn Error>> {
+        match self.sections.get(index) {
+            Some(section) => Ok(section.html.clone()),
+            None => Err(format!("No section {}", index).into()),
+        }
+    }
+
+    fn resource(&mut self, path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+        if let Some(section) = self.sections.iter().find(|section| section.path == path) {
+            return Ok(section.html.clone().into_bytes());
+        }
+        if let Some((_, resource)) = self.resources.iter().find(|(saved, _)| saved == path) {
+            return Ok(resource.clone());
+        }
+        match &self.folder {
+            // Only the files next to the book (the path is relative, without "..")
+            Some(folder)
+                if path.is_relative()
+                    && !path
+                        .components()
+                        .any(|component| component == std::path::Component::ParentDir) =>
+            {
+                Ok(fs::read(folder.join(path))?)
+            }
+            _ => Err(format!("No resource {}", path.display()).into()),
+        }
+    }
+
+    fn toc(&self) -> Vec<(String, PathBuf)> {
+        if !self.toc.is_empty() {
+            return self.toc.clone();
+        }
+        self.sections
+            .iter()
+            .skip(1)
+            .filter(|section| !section.title.is_empty())
+            .map(|section| (section.title.clone(), section.path.clone()))
+            .collect()
+    }
+
+    fn cover(&mut self) -> Option<Vec<u8>> {
+        self.cover.clone()
+    }
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/**
+ * XHTML page of a section, head is added as it is to the head of the page
+ */
+pub fn xhtml_page(title: &str, head: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">\n<head>\n<title>{}</title>\n{}</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_xml(title),
+        head,
+        body
+    )
+}
+
+fn title_page(title: &str, author: &str) -> String {
+    let mut body = format!("<h1>{}</h1>", escape_xml(title));
+    if !author.is_empty() {
+        body.push_str(format!("\n<p>{}</p>", escape_xml(author)).as_str());
+    }
+    xhtml_page(title, "", body.as_str())
+}
+
+/**
+ * Title of a book from the name of its file: "the_old-man.txt" -> "the old man"
+ */
+pub fn title_from_path(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+        .map(|title| title.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
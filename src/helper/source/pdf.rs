@@ -0,0 +1,479 @@
+use std::{
+    error::Error,
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    path::PathBuf,
+};
+
+use pdf_extract::{output_doc_page, Document, Object, PlainTextOutput};
+
+use super::{
+    escape_xml, text::is_heading, title_from_path, xhtml_page, BookFormat, MemorySource, Section,
+};
+
+/*
+ * PDF books: the text of each page is extracted (with pdf-extract, in Rust) and its
+ * paragraphs and headings are found again from the layout of the lines. The pages keep
+ * their numbers: page 12 of the PDF is the section 12 of the book (the section 0 is
+ * the title page), so the reader, the search and the progress work as with an EPUB.
+ * A page whose text cannot be extracted keeps its section, with a notice instead of its text.
+ */
+
+// Headings of the sections of a paper, alone on their line
+const PAPER_HEADINGS: [&str; 12] = [
+    "abstract",
+    "introduction",
+    "background",
+    "methods",
+    "results",
+    "discussion",
+    "conclusion",
+    "conclusions",
+    "acknowledgements",
+    "acknowledgments",
+    "references",
+    "bibliography",
+];
+const MAX_HEADING_LENGTH: usize = 80;
+
+/**
+ * Block of text of a page
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    // Level (1 for "2 Methods", 2 for "2.1 Data"...) and text
+    Heading(usize, String),
+    Paragraph(String),
+}
+
+// Roman numerals from the largest, with the subtractive pairs
+const ROMAN_NUMERALS: [(&str, usize); 13] = [
+    ("m", 1000),
+    ("cm", 900),
+    ("d", 500),
+    ("cd", 400),
+    ("c", 100),
+    ("xc", 90),
+    ("l", 50),
+    ("xl", 40),
+    ("x", 10),
+    ("ix", 9),
+    ("v", 5),
+    ("iv", 4),
+    ("i", 1),
+];
+
+/**
+ * Value of a lowercase roman numeral written the usual way: "xiv" -> 14,
+ * None for a word that only has their letters ("ill", "civic", "iiii")
+ */
+fn roman_value(numeral: &str) -> Option<usize> {
+    let mut rest = numeral;
+    let mut value = 0;
+    for (letters, number) in ROMAN_NUMERALS {
+        while let Some(next) = rest.strip_prefix(letters) {
+            value += number;
+            rest = next;
+        }
+    }
+    if numeral.is_empty() || !rest.is_empty() {
+        return None;
+    }
+    // The numeral must be the one the value is written as
+    let mut written = String::new();
+    let mut left = value;
+    for (letters, number) in ROMAN_NUMERALS {
+        while left >= number {
+            written.push_str(letters);
+            left -= number;
+        }
+    }
+    (written == numeral).then_some(value)
+}
+
+/**
+ * Number of a page printed on it: "12", "- 12 -", "Page 12", "12 of 30", "xiv",
+ * with true for a roman numeral (the pages before the text)
+ */
+fn page_number(line: &str) -> Option<(usize, bool)> {
+    let line = line
+        .trim()
+        .trim_matches(|c: char| c == '-' || c == '–' || c.is_whitespace());
+    let line = line
+        .strip_prefix("Page ")
+        .or_else(|| line.strip_prefix("page "))
+        .unwrap_or(line);
+    let number = line.split(" of ").next().unwrap_or_default().trim();
+    if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        number.parse().ok().map(|number| (number, false))
+    } else {
+        roman_value(number).map(|number| (number, true))
+    }
+}
+
+/**
+ * Page numbers that may be printed in the first and the last paragraph of a page
+ */
+fn margin_numbers(page: &[Vec<&str>]) -> Vec<(usize, bool)> {
+    page.first()
+        .into_iter()
+        .chain(page.last())
+        .flatten()
+        .filter_map(|line| page_number(line))
+        .collect()
+}
+
+/**
+ * A number is the page number when another page has a number of the same kind
+ * as far from its own index ("11" on the page 10 and "14" on the page 13)
+ */
+fn in_page_sequence(numbers: &[Vec<(usize, bool)>], index: usize, number: (usize, bool)) -> bool {
+    let offset = number.0 as i64 - index as i64;
+    numbers.iter().enumerate().any(|(other, page)| {
+        other != index
+            && page
+                .iter()
+                .any(|(value, roman)| *roman == number.1 && *value as i64 - other as i64 == offset)
+    })
+}
+
+/**
+ * Level of a numbered section heading: "2 Methods" -> 1, "2.1. Data" -> 2
+ */
+fn numbered_heading_level(line: &str) -> Option<usize> {
+    let (number, text) = line.split_once(' ')?;
+    let number = number.trim_end_matches('.');
+    let parts: Vec<&str> = number.split('.').collect();
+    let numbered = !number.is_empty()
+        && parts.len() <= 4
+        && parts.iter().all(|part| {
+            !part.is_empty() && part.len() <= 2 && part.chars().all(|c| c.is_ascii_digit())
+        });
+    let text = text.trim();
+    let titled = text.chars().next().is_some_and(char::is_uppercase)
+        && !text.ends_with(['.', ',', ';', ':'])
+        // Not a sentence starting with a number ("2 Apples were on the table and...")
+        && text.split_whitespace().count() <= 10;
+    (numbered && titled).then_some(parts.len())
+}
+
+/**
+ * Level of the heading a paragraph is, None if it is text
+ */
+fn heading_level(paragraph: &str) -> Option<usize> {
+    let line = paragraph.trim();
+    if line.is_empty() || line.contains('\n') || line.chars().count() > MAX_HEADING_LENGTH {
+        return None;
+    }
+    if let Some(level) = numbered_heading_level(line) {
+        return Some(level);
+    }
+    let word = line
+        .trim_end_matches(['.', ':'])
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ' ')
+        .to_lowercase();
+    if PAPER_HEADINGS.contains(&word.as_str()) || is_heading(line) {
+        return Some(1);
+    }
+    None
+}
+
+/**
+ * Lines of a paragraph joined, with the words cut at the end of a line ("para-", "graph")
+ * joined again
+ */
+fn join_lines(lines: &[&str]) -> String {
+    let mut text = String::new();
+    for line in lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+    {
+        let cut = text.ends_with('-')
+            && text.chars().rev().nth(1).is_some_and(|c| c.is_alphabetic())
+            && line.chars().next().is_some_and(|c| c.is_lowercase());
+        if cut {
+            text.pop();
+        } else if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line);
+    }
+    text
+}
+
+/**
+ * Paragraphs of the text of a page (separated by empty lines), as their lines
+ */
+fn page_paragraphs(text: &str) -> Vec<Vec<&str>> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+    lines
+        .split(|line| line.trim().is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.to_vec())
+        .collect()
+}
+
+/**
+ * Running header or footer: the line without its numbers ("Journal of Tests, 12")
+ */
+fn running_key(line: &str) -> String {
+    line.chars()
+        .filter(|c| !c.is_ascii_digit())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/**
+ * Lines repeated at the top or at the bottom of at least half of the pages (3 or more)
+ */
+fn running_lines(pages: &[Vec<Vec<&str>>]) -> Vec<String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for paragraphs in pages {
+        let first = paragraphs.first().and_then(|lines| lines.first());
+        let last = paragraphs.last().and_then(|lines| lines.last());
+        let mut keys: Vec<String> = [first, last]
+            .into_iter()
+            .flatten()
+            .map(|line| running_key(line))
+            .filter(|key| !key.is_empty())
+            .collect();
+        keys.dedup();
+        for key in keys {
+            match counts.iter_mut().find(|(saved, _)| *saved == key) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((key, 1)),
+            }
+        }
+    }
+    let minimum = (pages.len() / 2).max(3);
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= minimum)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/**
+ * Blocks of the pages of a PDF from their text: page numbers (following each other
+ * from page to page) and running headers are left out, a paragraph cut by the end
+ * of a page is kept whole on the page it starts on
+ */
+pub fn page_blocks(pages: &[String]) -> Vec<Vec<Block>> {
+    let mut paragraphs: Vec<Vec<Vec<&str>>> = pages
+        .iter()
+        .map(|page| page_paragraphs(page.as_str()))
+        .collect();
+    let running = running_lines(&paragraphs);
+    let numbers: Vec<Vec<(usize, bool)>> =
+        paragraphs.iter().map(|page| margin_numbers(page)).collect();
+    let is_margin = |index: usize, line: &str| {
+        page_number(line).is_some_and(|number| in_page_sequence(&numbers, index, number))
+            || running.contains(&running_key(line))
+    };
+    for (index, page) in paragraphs.iter_mut().enumerate() {
+        // The lines at the top of the page, then the ones at the bottom
+        while let Some(lines) = page.first_mut() {
+            match lines.first() {
+                Some(line) if is_margin(index, line) => {
+                    lines.remove(0);
+                }
+                Some(_) => break,
+                None => {
+                    page.remove(0);
+                }
+            }
+        }
+        while let Some(lines) = page.last_mut() {
+            match lines.last() {
+                Some(line) if is_margin(index, line) => {
+                    lines.pop();
+                }
+                Some(_) => break,
+                None => {
+                    page.pop();
+                }
+            }
+        }
+    }
+
+    let mut blocks: Vec<Vec<Block>> = Vec::new();
+    for page in paragraphs {
+        let mut page_blocks = Vec::new();
+        for lines in page {
+            let text = join_lines(&lines);
+            match heading_level(text.as_str()) {
+                Some(level) => page_blocks.push(Block::Heading(level, text)),
+                None => page_blocks.push(Block::Paragraph(text)),
+            }
+        }
+        // Continuation of the last paragraph of the previous page
+        let continued = match (
+            blocks.last_mut().and_then(|page| page.last_mut()),
+            page_blocks.first(),
+        ) {
+            (Some(Block::Paragraph(previous)), Some(Block::Paragraph(next)))
+                if !previous.ends_with(['.', '!', '?', ':', '"', '”', ')'])
+                    && next.chars().next().is_some_and(|c| c.is_lowercase()) =>
+            {
+                let joined = join_lines(&[previous.as_str(), next.as_str()]);
+                *previous = joined;
+                true
+            }
+            _ => false,
+        };
+        if continued {
+            page_blocks.remove(0);
+        }
+        blocks.push(page_blocks);
+    }
+    blocks
+}
+
+/**
+ * Text of a PDF string of the document information: UTF-16 with its byte order mark,
+ * otherwise PDFDocEncoding (read as Latin-1)
+ */
+pub fn pdf_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|byte| *byte as char).collect(),
+    }
+}
+
+/**
+ * Title and author of the document information of a PDF
+ */
+fn pdf_info(document: &Document) -> (Option<String>, Option<String>) {
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .and_then(|id| document.get_dictionary(id));
+    let value = |key: &[u8]| {
+        let info = info.as_ref().ok()?;
+        let text = pdf_text_string(info.get(key).and_then(Object::as_str).ok()?);
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    };
+    (value(b"Title"), value(b"Author"))
+}
+
+/**
+ * Text of a page of a PDF. pdf-extract panics on the fonts and encodings it does not
+ * support, the panic is returned as the error of the page.
+ */
+fn page_text(document: &Document, number: u32) -> Result<String, String> {
+    let extracted = catch_unwind(AssertUnwindSafe(|| {
+        let mut text = String::new();
+        let mut output = PlainTextOutput::new(&mut text);
+        output_doc_page(document, &mut output, number).map(|_| text)
+    }));
+    match extracted {
+        Ok(Ok(text)) => Ok(text),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(panic) => Err(panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unsupported content".to_string())),
+    }
+}
+
+/**
+ * Book of the text of the pages of a PDF, one section per page.
+ * A page whose text could not be extracted (its error) keeps its section, with a notice.
+ */
+pub fn pdf_book(
+    title: &str,
+    author: &str,
+    pages: &[Result<String, String>],
+) -> Result<MemorySource, Box<dyn Error>> {
+    let texts: Vec<String> = pages
+        .iter()
+        .map(|page| page.clone().unwrap_or_default())
+        .collect();
+    let blocks = page_blocks(&texts);
+    let failed: Vec<&String> = pages
+        .iter()
+        .filter_map(|page| page.as_ref().err())
+        .collect();
+    if !failed.is_empty() && failed.len() == pages.len() {
+        return Err(format!("Unable to extract the text of the PDF: {}", failed[0]).into());
+    }
+    if failed.is_empty() && blocks.iter().all(|page| page.is_empty()) {
+        return Err("No text in the PDF (the pages may be scanned images)".into());
+    }
+
+    let mut source = MemorySource::new(BookFormat::Pdf, title, author, "", Vec::new());
+    for (index, page) in blocks.into_iter().enumerate() {
+        let number = index + 1;
+        let path = PathBuf::from(format!("page-{:03}.xhtml", number));
+        let mut body = String::new();
+        if let Err(e) = &pages[index] {
+            eprintln!("Error extracting the text of page {}: {}", number, e);
+            body.push_str(
+                format!(
+                    "<p class=\"unreadable\">The text of this page could not be read ({}).</p>\n",
+                    escape_xml(e)
+                )
+                .as_str(),
+            );
+        }
+        for (position, block) in page.iter().enumerate() {
+            match block {
+                Block::Heading(level, text) => {
+                    let id = format!("heading-{}", position + 1);
+                    let tag = format!("h{}", (level + 1).min(4));
+                    body.push_str(
+                        format!("<{} id=\"{}\">{}</{}>\n", tag, id, escape_xml(text), tag).as_str(),
+                    );
+                    let mut target = path.clone().into_os_string();
+                    target.push(format!("#{}", id));
+                    source.toc.push((text.clone(), PathBuf::from(target)));
+                }
+                Block::Paragraph(text) => {
+                    body.push_str(format!("<p>{}</p>\n", escape_xml(text)).as_str());
+                }
+            }
+        }
+        let page_title = format!("Page {}", number);
+        source.sections.push(Section {
+            path,
+            html: xhtml_page(page_title.as_str(), "", body.trim_end()),
+            title: page_title,
+        });
+    }
+    Ok(source)
+}
+
+/**
+ * Read a PDF book
+ */
+pub fn open_pdf(path: &Path) -> Result<MemorySource, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let mut document = Document::load_mem(&bytes)?;
+    if document.is_encrypted() {
+        document
+            .decrypt("")
+            .map_err(|_| "The PDF is protected by a password")?;
+    }
+    let pages: Vec<Result<String, String>> = document
+        .get_pages()
+        .keys()
+        .map(|number| page_text(&document, *number))
+        .collect();
+    let (title, author) = pdf_info(&document);
+    let title = title.unwrap_or_else(|| title_from_path(path));
+    pdf_book(title.as_str(), author.unwrap_or_default().as_str(), &pages)
+}
@@ -0,0 +1,254 @@
+use std::{error::Error, fs, path::Path};
+
+use pulldown_cmark::{html::push_html, Options, Parser};
+
+use super::{escape_xml, html::split_sections, title_from_path, BookFormat, MemorySource};
+
+/*
+ * Plain text and Markdown books. A plain text book has no markup, its chapters
+ * are found by their headings: short lines alone in their paragraph such as
+ * "Chapter 12", "PART TWO", "XII." or a line in capitals.
+ */
+
+// First words of the headings followed by a number ("Chapter 3", "Part II")
+const NUMBERED_HEADINGS: [&str; 4] = ["chapter", "part", "book", "volume"];
+// Headings on their own ("Prologue")
+const HEADINGS: [&str; 7] = [
+    "prologue",
+    "epilogue",
+    "preface",
+    "introduction",
+    "foreword",
+    "afterword",
+    "appendix",
+];
+const NUMBER_WORDS: [&str; 20] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+    "twelve", "first", "second", "third", "fourth", "fifth", "sixth", "last", "the",
+];
+const MAX_HEADING_LENGTH: usize = 60;
+
+fn is_number(word: &str) -> bool {
+    let word = word.trim_end_matches(['.', ':']);
+    !word.is_empty()
+        && (word.chars().all(|c| c.is_ascii_digit())
+            || (word.len() <= 7 && word.chars().all(|c| "IVXLCDM".contains(c))))
+}
+
+/**
+ * Heading giving the number of a chapter: "Chapter 3", "PART II.", "12"
+ */
+fn is_numbered_heading(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    let first = words.next().unwrap_or_default();
+    // "12", "XII", "I." but not the word "I"
+    if is_number(first) && first != "I" && words.clone().next().is_none() {
+        return true;
+    }
+    let first = first.trim_end_matches(['.', ':']).to_lowercase();
+    let second = words.next().unwrap_or_default();
+    NUMBERED_HEADINGS.contains(&first.as_str())
+        && (is_number(second.to_uppercase().as_str())
+            || NUMBER_WORDS.contains(&second.trim_end_matches(['.', ':']).to_lowercase().as_str()))
+}
+
+/**
+ * Whether a paragraph of a plain text book is the heading of a chapter
+ */
+pub fn is_heading(paragraph: &str) -> bool {
+    let line = paragraph.trim();
+    if line.is_empty() || line.contains('\n') || line.chars().count() > MAX_HEADING_LENGTH {
+        return false;
+    }
+    if is_numbered_heading(line) {
+        return true;
+    }
+    let first = line
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if HEADINGS.contains(&first.as_str()) && line.split_whitespace().count() <= 5 {
+        return true;
+    }
+    // A line in capitals: "THE RETURN"
+    let letters: Vec<char> = line.chars().filter(|c| c.is_alphabetic()).collect();
+    letters.len() >= 3
+        && letters.iter().all(|c| c.is_uppercase())
+        && !line.ends_with([',', ';', '!', '?'])
+}
+
+/**
+ * Paragraphs of a text: separated by empty lines (the lines of a paragraph are joined),
+ * or, if the text has no empty line, one paragraph per line
+ */
+fn paragraphs(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+    if !lines.iter().any(|line| line.trim().is_empty()) {
+        return lines
+            .iter()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+    }
+    lines
+        .split(|line| line.trim().is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            paragraph
+                .iter()
+                .map(|line| line.trim())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/**
+ * Text of a Project Gutenberg book without its licence, with its title and author
+ */
+fn gutenberg_text(text: &str) -> (&str, Option<String>, Option<String>) {
+    let header = |name: &str| {
+        text.lines()
+            .take(100)
+            .find_map(|line| line.strip_prefix(name))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let (title, author) = (header("Title:"), header("Author:"));
+    let start = text
+        .find("*** START OF")
+        .and_then(|start| text[start..].find('\n').map(|end| start + end + 1))
+        .unwrap_or(0);
+    let end = text[start..]
+        .find("*** END OF")
+        .map_or(text.len(), |end| start + end);
+    (&text[start..end], title, author)
+}
+
+/**
+ * Chapters (title, html) of a plain text book, found by their headings.
+ * A heading numbering a chapter followed by a heading naming it ("CHAPTER I", "THE
+ * BEGINNING") is one heading, headings with no text (a table of contents) are left out.
+ */
+pub fn text_chapters(text: &str, book_title: &str) -> Vec<(String, String)> {
+    let mut chapters: Vec<(String, Vec<String>)> = Vec::new();
+    for paragraph in paragraphs(text) {
+        if is_heading(paragraph.as_str()) {
+            match chapters.last_mut() {
+                Some((title, body))
+                    if body.is_empty()
+                        && is_numbered_heading(title)
+                        && !is_numbered_heading(paragraph.as_str()) =>
+                {
+                    title.push_str(format!(" — {}", paragraph).as_str());
+                }
+                _ => chapters.push((paragraph, Vec::new())),
+            }
+        } else {
+            match chapters.last_mut() {
+                Some((_, body)) => body.push(paragraph),
+                None => chapters.push((book_title.to_string(), vec![paragraph])),
+            }
+        }
+    }
+    chapters
+        .into_iter()
+        .filter(|(_, body)| !body.is_empty())
+        .map(|(title, body)| {
+            let mut html = format!("<h2>{}</h2>", escape_xml(title.as_str()));
+            for paragraph in body {
+                html.push_str(format!("\n<p>{}</p>", escape_xml(paragraph.as_str())).as_str());
+            }
+            (title, html)
+        })
+        .collect()
+}
+
+/**
+ * Read a plain text book (UTF-8, invalid bytes are replaced)
+ */
+pub fn open_text(path: &Path) -> Result<MemorySource, Box<dyn Error>> {
+    let text = String::from_utf8_lossy(&fs::read(path)?).replace("\r\n", "\n");
+    let text = text.trim_start_matches('\u{feff}');
+    let (text, title, author) = gutenberg_text(text);
+    let title = title.unwrap_or_else(|| title_from_path(path));
+    let chapters = text_chapters(text, title.as_str());
+    Ok(MemorySource::new(
+        BookFormat::Text,
+        title.as_str(),
+        author.unwrap_or_default().as_str(),
+        "",
+        chapters,
+    ))
+}
+
+/**
+ * Front matter of a Markdown file ("---" lines around "key: value" lines),
+ * returns the values and the text after it
+ */
+fn front_matter(text: &str) -> (Vec<(String, String)>, &str) {
+    let rest = match text.strip_prefix("---\n") {
+        Some(rest) => rest,
+        None => return (Vec::new(), text),
+    };
+    let end = match rest.find("\n---") {
+        Some(end) => end,
+        None => return (Vec::new(), text),
+    };
+    let values = rest[..end]
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches(['"', '\'']);
+            (key.trim().to_lowercase(), value.to_string())
+        })
+        .collect();
+    let body = rest[end + 4..].trim_start_matches(|c| c != '\n');
+    (values, body)
+}
+
+/**
+ * Html of a Markdown text
+ */
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_SMART_PUNCTUATION;
+    let mut html = String::new();
+    push_html(&mut html, Parser::new_ext(markdown, options));
+    html
+}
+
+/**
+ * Read a Markdown book, cut into chapters at its headings.
+ * The title is the one of the front matter, or the only first level heading.
+ */
+pub fn open_markdown(path: &Path) -> Result<MemorySource, Box<dyn Error>> {
+    let text = String::from_utf8_lossy(&fs::read(path)?).replace("\r\n", "\n");
+    let (values, markdown) = front_matter(text.trim_start_matches('\u{feff}'));
+    let value = |key: &str| {
+        values
+            .iter()
+            .find(|(name, value)| name == key && !value.is_empty())
+            .map(|(_, value)| value.clone())
+    };
+
+    let html = markdown_to_html(markdown);
+    let fallback = value("title").unwrap_or_else(|| title_from_path(path));
+    let (heading, chapters) = split_sections(html.as_str(), fallback.as_str());
+    let title = value("title").or(heading).unwrap_or(fallback);
+    let mut source = MemorySource::new(
+        BookFormat::Markdown,
+        title.as_str(),
+        value("author").unwrap_or_default().as_str(),
+        "",
+        chapters,
+    );
+    if let Some(language) = value("lang").or_else(|| value("language")) {
+        source.set_metadata("language", language.as_str());
+    }
+    source.folder = path.parent().map(|folder| folder.to_path_buf());
+    Ok(source)
+}
@@ -0,0 +1,584 @@
+use std::{
+    error::Error,
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use html2text::{from_read_with_decorator, render::text_renderer::TrivialDecorator};
+
+/*
+ * Read aloud: the text of a chapter is split into sentences, which are spoken one after
+ * the other by a speech engine in a background thread. The reader is told when each
+ * sentence starts (to highlight it) and when the chapter ends (to go on with the next one).
+ */
+
+// Words after which a period does not end the sentence
+const ABBREVIATIONS: [&str; 20] = [
+    "mr", "mrs", "ms", "dr", "st", "jr", "sr", "prof", "rev", "gen", "capt", "lt", "col", "vs",
+    "etc", "e.g", "i.e", "no", "a.m", "p.m",
+];
+// Characters that can follow the end of a sentence (closing quotes and brackets)
+const CLOSING: [char; 10] = ['"', '\'', '”', '’', '»', ')', ']', '」', '』', '）'];
+// Silence between two sentences in the audio files (in seconds)
+const SENTENCE_PAUSE: f64 = 0.4;
+// Speed of the speech
+pub const WORDS_PER_MINUTE: u32 = 160;
+// How often a running speech command is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static SESSIONS: AtomicU64 = AtomicU64::new(1);
+
+/**
+ * Identifier of a new reading, the events of the previous ones are ignored
+ */
+pub fn next_session() -> u64 {
+    SESSIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+/**
+ * Text of a chapter, without the decorations of html2text (e.g. "*" around emphasis)
+ */
+pub fn chapter_text(html: &str) -> String {
+    from_read_with_decorator(html.as_bytes(), usize::MAX, TrivialDecorator::new())
+}
+
+fn ends_with_abbreviation(sentence: &str) -> bool {
+    let word = sentence
+        .trim_end_matches('.')
+        .rsplit(|c: char| c.is_whitespace() || c == '(' || c == '"')
+        .next()
+        .unwrap_or_default();
+    let lowercase = word.to_lowercase();
+    // Initials: "J. R. R. Tolkien"
+    let initial = word.chars().count() == 1 && word.chars().all(char::is_uppercase);
+    initial || ABBREVIATIONS.contains(&lowercase.as_str())
+}
+
+/**
+ * Sentences of a text, a sentence never goes over two paragraphs (separated by an empty line)
+ * Example: "Mr. Smith came. He sat down!" -> ["Mr. Smith came.", "He sat down!"]
+ */
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let chars: Vec<char> = paragraph.chars().collect();
+        let mut sentence = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            sentence.push(c);
+            i += 1;
+            let wide = matches!(c, '。' | '！' | '？');
+            if !(wide || matches!(c, '.' | '!' | '?' | '…')) {
+                continue;
+            }
+            // "?!", "...", and the closing quotes belong to the sentence
+            while i < chars.len()
+                && (matches!(chars[i], '.' | '!' | '?' | '…' | '。' | '！' | '？')
+                    || CLOSING.contains(&chars[i]))
+            {
+                sentence.push(chars[i]);
+                i += 1;
+            }
+            // The sentence ends before a space (or anywhere after a full-width stop),
+            // unless the next word starts in lowercase ("he left... and came back")
+            let at_space = i == chars.len() || chars[i] == ' ';
+            let lowercase_next = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if (wide || (at_space && !lowercase_next))
+                && !(c == '.' && ends_with_abbreviation(&sentence))
+            {
+                sentences.push(sentence.trim().to_string());
+                sentence.clear();
+            }
+        }
+        if !sentence.trim().is_empty() {
+            sentences.push(sentence.trim().to_string());
+        }
+    }
+    sentences
+}
+
+/**
+ * Speaks sentences, they are spoken in a background thread
+ */
+pub trait SpeechEngine: Debug + Send + Sync {
+    /**
+     * Speak a sentence, returns when it has been spoken (or when the engine is stopped)
+     */
+    fn speak(&self, text: &str) -> Result<(), Box<dyn Error>>;
+
+    /**
+     * Stop the sentence being spoken
+     */
+    fn stop(&self);
+
+    /**
+     * Called once the last sentence has been spoken (or the reading stopped)
+     */
+    fn finish(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /**
+     * Speak the next sentences in a language (e.g. "en", "fr"), None for the default one
+     */
+    fn set_language(&self, _language: Option<&str>) {}
+}
+
+/**
+ * Engine running a speech program for each sentence: espeak-ng or speech-dispatcher (spd-say)
+ */
+#[derive(Debug)]
+pub struct CommandEngine {
+    program: String,
+    args: Vec<String>,
+    // Argument of the program followed by the language of the voice
+    language_arg: String,
+    language: Mutex<Option<String>>,
+    // The text is written on the input of the program instead of being its last argument
+    text_on_stdin: bool,
+    // Arguments of the program to silence it (the speech does not stop with the program)
+    cancel_args: Option<Vec<String>>,
+    child: Mutex<Option<Child>>,
+}
+
+impl CommandEngine {
+    /**
+     * espeak-ng, voice is a language (e.g. "en", "fr") or None for the default one
+     */
+    pub fn espeak_ng(voice: Option<&str>, words_per_minute: u32) -> Self {
+        Self {
+            program: String::from("espeak-ng"),
+            args: vec!["-s".to_string(), words_per_minute.to_string()],
+            language_arg: String::from("-v"),
+            language: Mutex::new(voice.map(|voice| voice.to_string())),
+            text_on_stdin: true,
+            cancel_args: None,
+            child: Mutex::new(None),
+        }
+    }
+
+    /**
+     * speech-dispatcher, through its client spd-say
+     */
+    pub fn speech_dispatcher(language: Option<&str>) -> Self {
+        Self {
+            program: String::from("spd-say"),
+            args: vec!["--wait".to_string()],
+            language_arg: String::from("-l"),
+            language: Mutex::new(language.map(|language| language.to_string())),
+            text_on_stdin: false,
+            cancel_args: Some(vec!["--cancel".to_string()]),
+            child: Mutex::new(None),
+        }
+    }
+
+    /**
+     * Whether the program is installed
+     */
+    pub fn is_available(&self) -> bool {
+        Command::new(self.program.as_str())
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+}
+
+impl SpeechEngine for CommandEngine {
+    fn speak(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        let mut command = Command::new(self.program.as_str());
+        command
+            .args(self.args.iter())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let language = self
+            .language
+            .lock()
+            .expect("Error while getting lock")
+            .clone();
+        if let Some(language) = language {
+            command.args([self.language_arg.as_str(), language.as_str()]);
+        }
+        if self.text_on_stdin {
+            command.stdin(Stdio::piped());
+        } else {
+            command.args(["--", text]).stdin(Stdio::null());
+        }
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        *self.child.lock().expect("Error while getting lock") = Some(child);
+
+        // The child is polled so that stop can kill it in the meantime
+        loop {
+            let mut child = self.child.lock().expect("Error while getting lock");
+            let status = match child.as_mut() {
+                Some(running) => running.try_wait()?,
+                None => return Ok(()),
+            };
+            if let Some(status) = status {
+                *child = None;
+                if !status.success() {
+                    return Err(format!("{} exited with {}", self.program, status).into());
+                }
+                return Ok(());
+            }
+            drop(child);
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(mut child) = self.child.lock().expect("Error while getting lock").take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(cancel_args) = &self.cancel_args {
+            let _ = Command::new(self.program.as_str())
+                .args(cancel_args.iter())
+                .status();
+        }
+    }
+
+    fn set_language(&self, language: Option<&str>) {
+        *self.language.lock().expect("Error while getting lock") =
+            language.map(|language| language.to_string());
+    }
+}
+
+/**
+ * The speech engine installed on the system: espeak-ng, otherwise speech-dispatcher.
+ * The programs are run to find it, it is looked for once and then kept
+ */
+pub fn system_engine(language: Option<&str>) -> Option<Arc<dyn SpeechEngine>> {
+    let espeak = CommandEngine::espeak_ng(language, WORDS_PER_MINUTE);
+    if espeak.is_available() {
+        return Some(Arc::new(espeak));
+    }
+    let dispatcher = CommandEngine::speech_dispatcher(language);
+    if dispatcher.is_available() {
+        return Some(Arc::new(dispatcher));
+    }
+    None
+}
+
+/**
+ * Speech of a sentence: mono 16 bit samples at the given rate
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+/**
+ * Turns a sentence into audio, for the WAV files
+ */
+pub trait Synthesizer: Debug + Send + Sync {
+    fn synthesize(&self, text: &str) -> Result<Audio, Box<dyn Error>>;
+}
+
+/**
+ * Synthesizer running espeak-ng offline, the audio goes through a temporary WAV file
+ */
+#[derive(Debug)]
+pub struct EspeakSynthesizer {
+    voice: Option<String>,
+    words_per_minute: u32,
+}
+
+impl EspeakSynthesizer {
+    pub fn new(voice: Option<&str>, words_per_minute: u32) -> Self {
+        Self {
+            voice: voice.map(|voice| voice.to_string()),
+            words_per_minute,
+        }
+    }
+}
+
+impl Synthesizer for EspeakSynthesizer {
+    fn synthesize(&self, text: &str) -> Result<Audio, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!(
+            "ebook-reader-speech-{}-{}.wav",
+            std::process::id(),
+            next_session()
+        ));
+        let mut command = Command::new("espeak-ng");
+        command
+            .arg("-s")
+            .arg(self.words_per_minute.to_string())
+            .arg("-w")
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(voice) = &self.voice {
+            command.arg("-v").arg(voice);
+        }
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(format!("espeak-ng exited with {}", status).into());
+        }
+
+        let audio = read_wav(&path);
+        let _ = fs::remove_file(&path);
+        audio
+    }
+}
+
+fn read_wav(path: &Path) -> Result<Audio, Box<dyn Error>> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.channels != 1 || spec.bits_per_sample != 16 || spec.sample_format != SampleFormat::Int {
+        return Err(format!("Unsupported audio format in {}", path.display()).into());
+    }
+    let samples = reader.samples::<i16>().collect::<Result<Vec<i16>, _>>()?;
+    Ok(Audio {
+        sample_rate: spec.sample_rate,
+        samples,
+    })
+}
+
+/**
+ * Engine writing the sentences into a WAV file instead of speaking them,
+ * with a short silence between two sentences.
+ * The file is created with the first sentence, it is complete once the engine is finished.
+ */
+pub struct WavEngine {
+    path: PathBuf,
+    synthesizer: Box<dyn Synthesizer>,
+    writer: Mutex<Option<WavWriter<BufWriter<File>>>>,
+}
+
+impl WavEngine {
+    pub fn new(path: &Path, synthesizer: Box<dyn Synthesizer>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            synthesizer,
+            writer: Mutex::new(None),
+        }
+    }
+}
+
+impl Debug for WavEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WavEngine")
+            .field("path", &self.path)
+            .field("synthesizer", &self.synthesizer)
+            .finish()
+    }
+}
+
+impl SpeechEngine for WavEngine {
+    fn speak(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        let audio = self.synthesizer.synthesize(text)?;
+        let mut writer = self.writer.lock().expect("Error while getting lock");
+        if writer.is_none() {
+            if let Some(folder) = self.path.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate: audio.sample_rate,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            };
+            *writer = Some(WavWriter::create(&self.path, spec)?);
+        }
+        let writer = writer.as_mut().expect("The writer was just created");
+        if writer.spec().sample_rate != audio.sample_rate {
+            return Err("The sentences have different sample rates".into());
+        }
+        for sample in audio.samples {
+            writer.write_sample(sample)?;
+        }
+        let pause = (audio.sample_rate as f64 * SENTENCE_PAUSE) as usize;
+        for _ in 0..pause {
+            writer.write_sample(0_i16)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&self) {}
+
+    fn finish(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(writer) = self.writer.lock().expect("Error while getting lock").take() {
+            writer.finalize()?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * A chapter being read aloud: its sentences and the one being spoken
+ * book, page -> book and chapter read, output -> audio file the chapter is read into
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadAloud {
+    pub session: u64,
+    pub book: usize,
+    pub page: usize,
+    pub sentences: Arc<Vec<String>>,
+    pub current: Option<usize>,
+    pub output: Option<PathBuf>,
+}
+
+/**
+ * What happens while reading aloud
+ * Sentence -> the sentence at this index starts to be spoken
+ * End -> all the sentences have been spoken, or an error stopped the reading
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpeechEvent {
+    Sentence(usize),
+    End(Result<(), String>),
+}
+
+/**
+ * Receives the events of the readings (with their session), from the background thread
+ */
+#[derive(Clone)]
+pub struct SpeechListener(Arc<dyn Fn(u64, SpeechEvent) + Send + Sync>);
+
+impl SpeechListener {
+    pub fn new(listener: impl Fn(u64, SpeechEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(listener))
+    }
+
+    pub fn notify(&self, session: u64, event: SpeechEvent) {
+        (self.0)(session, event)
+    }
+}
+
+impl Debug for SpeechListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SpeechListener")
+    }
+}
+
+/**
+ * A reading in progress, it can be stopped at any time
+ */
+#[derive(Debug)]
+pub struct SpeechHandle {
+    engine: Arc<dyn SpeechEngine>,
+    stopped: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SpeechHandle {
+    /**
+     * Stop the reading, no more events are sent
+     */
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.engine.stop();
+    }
+
+    /**
+     * Wait for the end of the reading
+     */
+    pub fn wait(&self) {
+        let thread = self.thread.lock().expect("Error while getting lock").take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+}
+
+/**
+ * Speak the sentences from the one at index start, in a background thread
+ */
+pub fn speak(
+    engine: Arc<dyn SpeechEngine>,
+    sentences: Arc<Vec<String>>,
+    start: usize,
+    session: u64,
+    listener: SpeechListener,
+) -> SpeechHandle {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let engine = engine.clone();
+        let stopped = stopped.clone();
+        thread::spawn(move || {
+            let mut result = Ok(());
+            for (index, sentence) in sentences.iter().enumerate().skip(start) {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                listener.notify(session, SpeechEvent::Sentence(index));
+                if let Err(e) = engine.speak(sentence) {
+                    result = Err(e.to_string());
+                    break;
+                }
+            }
+            if let Err(e) = engine.finish() {
+                result = result.and(Err(e.to_string()));
+            }
+            if !stopped.load(Ordering::SeqCst) {
+                listener.notify(session, SpeechEvent::End(result));
+            }
+        })
+    };
+    SpeechHandle {
+        engine,
+        stopped,
+        thread: Mutex::new(Some(thread)),
+    }
+}
+
+/**
+ * Byte range of a sentence in a text, the spaces of both may differ
+ * Example: ("He said:\n  Hello there.", "said: Hello") -> Some(3..16)
+ */
+pub fn find_sentence(text: &str, sentence: &str) -> Option<std::ops::Range<usize>> {
+    let words: Vec<&str> = sentence.split_whitespace().collect();
+    let first = words.first()?;
+    for (start, _) in text.match_indices(first) {
+        let mut end = start;
+        let found = words.iter().enumerate().all(|(i, word)| {
+            let rest = &text[end..];
+            let trimmed = if i == 0 {
+                rest
+            } else {
+                let trimmed = rest.trim_start();
+                // Words are separated by spaces in the text too
+                if trimmed.len() == rest.len() {
+                    return false;
+                }
+                trimmed
+            };
+            if !trimmed.starts_with(word) {
+                return false;
+            }
+            end += rest.len() - trimmed.len() + word.len();
+            true
+        });
+        if found {
+            return Some(start..end);
+        }
+    }
+    None
+}
@@ -0,0 +1,237 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use super::{
+    artefacts::stats_path,
+    config::{IDLE_PAGE_SECONDS, MIN_PAGE_SECONDS, SAVED_PROGRESS_PATH},
+};
+
+/**
+ * A reading session, from the moment a book is opened to the moment it is closed.
+ * Times are unix timestamps in seconds.
+ * seconds -> time spent reading (idle time on a page is not counted)
+ * words, word_seconds -> words of the pages read and the time spent on them (for the speed)
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReadingSession {
+    pub start: i64,
+    pub end: i64,
+    pub seconds: i64,
+    pub words: i64,
+    pub word_seconds: i64,
+    pub start_page: usize,
+    pub end_page: usize,
+}
+
+/**
+ * Sessions of a book, saved next to its progress
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookStats {
+    pub title: String,
+    pub sessions: Vec<ReadingSession>,
+}
+
+/**
+ * Follows the pages read during a session
+ */
+#[derive(Clone, Debug)]
+pub struct ReadingTracker {
+    session: ReadingSession,
+    page: usize,
+    page_start: i64,
+}
+
+impl ReadingTracker {
+    pub fn new(page: usize, now: i64) -> Self {
+        Self {
+            session: ReadingSession {
+                start: now,
+                end: now,
+                start_page: page,
+                end_page: page,
+                ..Default::default()
+            },
+            page,
+            page_start: now,
+        }
+    }
+
+    pub fn get_page(&self) -> usize {
+        self.page
+    }
+
+    pub fn get_session(&self) -> &ReadingSession {
+        &self.session
+    }
+
+    /**
+     * The reader left the current page (with page_words words) for page.
+     * Pages only skimmed through do not count, and the time on a page is capped
+     * so that a book left open is not counted as read.
+     */
+    pub fn visit(&mut self, page: usize, page_words: i64, now: i64) {
+        let seconds = (now - self.page_start).clamp(0, IDLE_PAGE_SECONDS);
+        if seconds >= MIN_PAGE_SECONDS {
+            self.session.seconds += seconds;
+            // Only the pages that were finished tell the reading speed
+            if page > self.page {
+                self.session.words += page_words;
+                self.session.word_seconds += seconds;
+            }
+        }
+        self.session.end = now;
+        self.session.end_page = page;
+        self.page = page;
+        self.page_start = now;
+    }
+}
+
+pub fn now() -> i64 {
+    Local::now().timestamp()
+}
+
+/**
+ * Load the sessions of a book (empty if it was never read)
+ */
+pub fn load_stats(title: &str) -> BookStats {
+    let stats = fs::read_to_string(stats_path(title))
+        .ok()
+        .and_then(|json| serde_json::from_str::<BookStats>(json.as_str()).ok());
+    stats.unwrap_or_else(|| BookStats {
+        title: title.to_string(),
+        sessions: Vec::new(),
+    })
+}
+
+/**
+ * Save a session of a book, replacing the one with the same start
+ * (a session is saved again every time the page changes)
+ */
+pub fn save_session(title: &str, session: &ReadingSession) -> Result<(), Box<dyn Error>> {
+    let mut stats = load_stats(title);
+    stats.title = title.to_string();
+    match stats.sessions.iter_mut().find(|s| s.start == session.start) {
+        Some(saved) => *saved = session.clone(),
+        None => stats.sessions.push(session.clone()),
+    }
+    fs::create_dir_all(SAVED_PROGRESS_PATH)?;
+    fs::write(stats_path(title), serde_json::to_string(&stats)?)?;
+    Ok(())
+}
+
+/**
+ * Load the sessions of all the books
+ */
+pub fn load_all_stats() -> Vec<BookStats> {
+    let dir = match fs::read_dir(SAVED_PROGRESS_PATH) {
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    dir.flatten()
+        .map(|file| file.path())
+        .filter(|path| path.to_string_lossy().ends_with(".stats.json"))
+        .filter_map(|path| read_stats_file(&path))
+        .collect()
+}
+
+fn read_stats_file(path: &Path) -> Option<BookStats> {
+    let json = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(json.as_str()) {
+        Ok(stats) => Some(stats),
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/**
+ * Reading speed in words per minute, None until a minute of reading is recorded
+ */
+pub fn reading_speed<'a>(sessions: impl IntoIterator<Item = &'a ReadingSession>) -> Option<f64> {
+    let (words, seconds) = sessions.into_iter().fold((0, 0), |(words, seconds), s| {
+        (words + s.words, seconds + s.word_seconds)
+    });
+    if seconds < 60 || words == 0 {
+        return None;
+    }
+    Some(words as f64 / (seconds as f64 / 60.0))
+}
+
+/**
+ * Minutes needed to read the words at the given speed
+ */
+pub fn time_left(words: i64, speed: f64) -> i64 {
+    if speed <= 0.0 {
+        return 0;
+    }
+    (words.max(0) as f64 / speed).ceil() as i64
+}
+
+/**
+ * Format minutes as "1 h 20 min"
+ */
+pub fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{} min", minutes),
+        (hours, 0) => format!("{} h", hours),
+        (hours, minutes) => format!("{} h {} min", hours, minutes),
+    }
+}
+
+fn local_date(timestamp: i64) -> Option<NaiveDate> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.date_naive())
+}
+
+/**
+ * Seconds read in each day (local time, by start of the session)
+ */
+pub fn daily_totals<'a>(
+    sessions: impl IntoIterator<Item = &'a ReadingSession>,
+) -> BTreeMap<NaiveDate, i64> {
+    let mut totals = BTreeMap::new();
+    for session in sessions {
+        if let Some(day) = local_date(session.start) {
+            *totals.entry(day).or_insert(0) += session.seconds;
+        }
+    }
+    totals
+}
+
+/**
+ * Seconds read in each week, by the monday of the week
+ */
+pub fn weekly_totals(daily: &BTreeMap<NaiveDate, i64>) -> BTreeMap<NaiveDate, i64> {
+    let mut totals = BTreeMap::new();
+    for (day, seconds) in daily {
+        let monday = *day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        *totals.entry(monday).or_insert(0) += seconds;
+    }
+    totals
+}
+
+/**
+ * Current and longest streak of consecutive reading days.
+ * The current streak is still running if the last reading day is today or yesterday.
+ */
+pub fn streaks(daily: &BTreeMap<NaiveDate, i64>, today: NaiveDate) -> (usize, usize) {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut last_day: Option<NaiveDate> = None;
+    for (day, _) in daily.iter().filter(|(_, seconds)| **seconds > 0) {
+        current = match last_day {
+            Some(last_day) if *day - last_day == Duration::days(1) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        last_day = Some(*day);
+    }
+
+    let running = last_day.is_some_and(|day| today - day <= Duration::days(1));
+    (if running { current } else { 0 }, longest)
+}
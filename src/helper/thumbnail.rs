@@ -0,0 +1,144 @@
+use druid::{piet::ImageFormat, Data, ImageBuf};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::config::THUMBNAILS_PATH;
+
+/**
+ * Sizes of the cached thumbnails (the cover is scaled to fit in the box)
+ * Small -> rows of the library
+ * Large -> cover preview in the metadata editor
+ */
+#[derive(Clone, Copy, Data, Debug, Hash, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Small,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            ThumbnailSize::Small => (120, 180),
+            ThumbnailSize::Large => (240, 360),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Large => "large",
+        }
+    }
+}
+
+thread_local! {
+    // Thumbnails already decoded, by (book key, size)
+    static MEMORY_CACHE: RefCell<HashMap<(String, ThumbnailSize), Arc<ImageBuf>>> =
+        RefCell::new(HashMap::new());
+}
+
+/**
+ * Key of a book in the cache, a stable (FNV-1a) hash of its identity
+ */
+pub fn cache_key(identity: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in identity.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn thumbnail_path(key: &str, size: ThumbnailSize) -> PathBuf {
+    Path::new(THUMBNAILS_PATH).join(format!("{}-{}.png", key, size.name()))
+}
+
+/**
+ * Check if the thumbnail on disk is older than the cover it was made from
+ */
+fn is_stale(thumbnail: &Path, cover: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(thumbnail), modified(cover)) {
+        (Some(thumbnail), Some(cover)) => thumbnail < cover,
+        _ => true,
+    }
+}
+
+/**
+ * Get the thumbnail of a cover.
+ * Lookup order: memory, then the thumbnails folder, then the cover is downscaled
+ * and the result saved in the thumbnails folder for the next startups.
+ */
+pub fn get_thumbnail(key: &str, cover_path: &str, size: ThumbnailSize) -> Option<Arc<ImageBuf>> {
+    let memory_key = (key.to_string(), size);
+    if let Some(thumbnail) = MEMORY_CACHE.with(|cache| cache.borrow().get(&memory_key).cloned()) {
+        return Some(thumbnail);
+    }
+
+    let path = thumbnail_path(key, size);
+    let thumbnail = if !is_stale(&path, Path::new(cover_path)) {
+        match fs::read(&path).map(|bytes| ImageBuf::from_data(bytes.as_slice())) {
+            Ok(Ok(thumbnail)) => Some(thumbnail),
+            _ => create_thumbnail(cover_path, &path, size),
+        }
+    } else {
+        create_thumbnail(cover_path, &path, size)
+    };
+
+    let thumbnail = Arc::new(thumbnail?);
+    MEMORY_CACHE.with(|cache| {
+        cache.borrow_mut().insert(memory_key, thumbnail.clone());
+    });
+    Some(thumbnail)
+}
+
+/**
+ * Downscale the cover and save it as png
+ */
+fn create_thumbnail(cover_path: &str, path: &Path, size: ThumbnailSize) -> Option<ImageBuf> {
+    let cover = match image::open(cover_path) {
+        Ok(cover) => cover,
+        Err(e) => {
+            eprintln!("Error loading cover {}: {}", cover_path, e);
+            return None;
+        }
+    };
+    let (width, height) = size.dimensions();
+    let thumbnail = cover.thumbnail(width, height).to_rgba8();
+
+    if let Err(e) = fs::create_dir_all(THUMBNAILS_PATH) {
+        eprintln!("Unable to create thumbnails folder: {}", e);
+    } else if let Err(e) = thumbnail.save_with_format(path, image::ImageFormat::Png) {
+        eprintln!("Unable to save thumbnail: {}", e);
+    }
+
+    let (width, height) = thumbnail.dimensions();
+    Some(ImageBuf::from_raw(
+        thumbnail.into_raw(),
+        ImageFormat::RgbaSeparate,
+        width as usize,
+        height as usize,
+    ))
+}
+
+/**
+ * Remove the thumbnails of a book (to be called when its cover changes)
+ */
+pub fn invalidate_thumbnails(key: &str) {
+    for size in [ThumbnailSize::Small, ThumbnailSize::Large] {
+        MEMORY_CACHE.with(|cache| {
+            cache.borrow_mut().remove(&(key.to_string(), size));
+        });
+        let path = thumbnail_path(key, size);
+        if path.exists() {
+            if let Err(e) = fs::remove_file(path) {
+                eprintln!("Unable to remove thumbnail: {}", e);
+            }
+        }
+    }
+}
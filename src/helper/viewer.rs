@@ -0,0 +1,141 @@
+use druid::{Data, Point, Rect, Size, Vec2};
+
+use super::config::{MAX_ZOOM, MIN_ZOOM};
+
+/*
+ * Layout of the pages of a comic in the reader.
+ * The pages can be shown one at a time or two by two (spreads: the cover 0 alone, then
+ * 1-2, 3-4..., the first one on the right in the books read from right to left),
+ * fitted to the width of the reader, to the whole page, or zoomed from the whole page.
+ */
+
+/**
+ * How the images of the pages fit in the reader
+ */
+#[derive(Clone, Copy, Data, Debug, Default, PartialEq)]
+pub enum ImageFit {
+    // The whole page is shown
+    #[default]
+    Page,
+    // The page is as wide as the reader, scroll to see the bottom
+    Width,
+    // The whole page times the zoom
+    Zoom,
+}
+
+/**
+ * Scale of the images to show a content (one page or a spread) in a viewport
+ */
+pub fn image_scale(content: Size, viewport: Size, fit: ImageFit, zoom: f64) -> f64 {
+    if content.width <= 0.0 || content.height <= 0.0 {
+        return 1.0;
+    }
+    let page = (viewport.width / content.width).min(viewport.height / content.height);
+    match fit {
+        ImageFit::Page => page,
+        ImageFit::Width => viewport.width / content.width,
+        ImageFit::Zoom => page * zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+    }
+}
+
+/**
+ * First page of the spread of a page: 0 for the cover, 1 for 1 and 2, 3 for 3 and 4...
+ */
+pub fn spread_start(page: usize) -> usize {
+    if page > 1 && page.is_multiple_of(2) {
+        page - 1
+    } else {
+        page
+    }
+}
+
+/**
+ * Pages shown with a page (the page alone, or its spread) in a book of length pages
+ */
+pub fn spread_pages(page: usize, length: usize, two_pages: bool) -> Vec<usize> {
+    if !two_pages {
+        return vec![page];
+    }
+    let start = spread_start(page);
+    if start > 0 && start < length {
+        vec![start, start + 1]
+    } else {
+        vec![start]
+    }
+}
+
+/**
+ * Page shown after the ones shown with a page, if any
+ */
+pub fn next_spread(page: usize, length: usize, two_pages: bool) -> Option<usize> {
+    let next = if two_pages && page > 0 {
+        spread_start(page) + 2
+    } else {
+        page + 1
+    };
+    (next <= length).then_some(next)
+}
+
+/**
+ * Page shown before the ones shown with a page, if any
+ */
+pub fn prev_spread(page: usize, two_pages: bool) -> Option<usize> {
+    let (start, step) = if two_pages {
+        (spread_start(page), 2)
+    } else {
+        (page, 1)
+    };
+    // The cover is alone before the first spread
+    start.checked_sub(step).or((start > 0).then_some(0))
+}
+
+/**
+ * Size of the pages put side by side at the same height, and the place of each one
+ * (in the order of the sizes, the first one on the right if rtl)
+ */
+pub fn spread_rects(sizes: &[Size], rtl: bool) -> (Size, Vec<Rect>) {
+    let height = sizes.iter().map(|size| size.height).fold(0.0, f64::max);
+    let widths: Vec<f64> = sizes
+        .iter()
+        .map(|size| {
+            if size.height > 0.0 {
+                size.width * height / size.height
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let width: f64 = widths.iter().sum();
+
+    let mut rects = Vec::new();
+    let mut x = if rtl { width } else { 0.0 };
+    for page_width in widths {
+        if rtl {
+            x -= page_width;
+        }
+        let rect = Rect::from_origin_size(Point::new(x, 0.0), Size::new(page_width, height));
+        rects.push(rect);
+        if !rtl {
+            x += page_width;
+        }
+    }
+    (Size::new(width, height), rects)
+}
+
+/**
+ * Offset of the content scrolled in the viewport, kept inside the content
+ * (a content smaller than the viewport is centered, its offset is negative)
+ */
+pub fn clamp_offset(offset: Vec2, content: Size, viewport: Size) -> Vec2 {
+    let clamp = |offset: f64, content: f64, viewport: f64| {
+        if content <= viewport {
+            (content - viewport) / 2.0
+        } else {
+            offset.clamp(0.0, content - viewport)
+        }
+    };
+    Vec2::new(
+        clamp(offset.x, content.width, viewport.width),
+        clamp(offset.y, content.height, viewport.height),
+    )
+}
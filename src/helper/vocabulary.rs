@@ -0,0 +1,191 @@
+use std::{error::Error, fs};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    annotations::escape_html,
+    artefacts::vocabulary_path,
+    config::SAVED_PROGRESS_PATH,
+    speech::{chapter_text, find_sentence},
+};
+
+/**
+ * A word looked up in a book, with the sentence it was in and where the sentence is
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VocabularyWord {
+    pub word: String,
+    // Definition found when the word was looked up, empty if there was none
+    pub definition: String,
+    pub context: String,
+    // Page (in the spine) and position in the page of the sentence
+    pub page: usize,
+    pub chapter_position: f64,
+    // Title of the chapter, empty in the vocabularies saved before it was kept
+    #[serde(default)]
+    pub chapter: String,
+    // Time of the lookup (seconds since the epoch)
+    pub time: i64,
+}
+
+/**
+ * Words looked up in a book, in the order they were first looked up
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vocabulary {
+    pub title: String,
+    pub words: Vec<VocabularyWord>,
+}
+
+impl Vocabulary {
+    /**
+     * Add a word, a word already in the vocabulary takes the new context and position
+     */
+    pub fn add(&mut self, word: VocabularyWord) {
+        let key = word.word.to_lowercase();
+        match self
+            .words
+            .iter_mut()
+            .find(|saved| saved.word.to_lowercase() == key)
+        {
+            Some(saved) => *saved = word,
+            None => self.words.push(word),
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.words.len() {
+            self.words.remove(index);
+        }
+    }
+}
+
+pub fn load_vocabulary(title: &str) -> Vocabulary {
+    let vocabulary = fs::read_to_string(vocabulary_path(title))
+        .ok()
+        .and_then(|json| serde_json::from_str::<Vocabulary>(json.as_str()).ok());
+    // The book may have been renamed since the vocabulary was saved
+    Vocabulary {
+        title: title.to_string(),
+        words: vocabulary
+            .map(|vocabulary| vocabulary.words)
+            .unwrap_or_default(),
+    }
+}
+
+pub fn save_vocabulary(vocabulary: &Vocabulary) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(SAVED_PROGRESS_PATH)?;
+    fs::write(
+        vocabulary_path(vocabulary.title.as_str()),
+        serde_json::to_string(vocabulary)?,
+    )?;
+    Ok(())
+}
+
+/**
+ * Position of a sentence in a page (0.0 top, 1.0 bottom), from where it is in the text
+ */
+pub fn context_position(html: &str, context: &str) -> Option<f64> {
+    let text = chapter_text(html);
+    let range = find_sentence(text.as_str(), context)?;
+    Some(range.start as f64 / text.len().max(1) as f64)
+}
+
+/**
+ * Separator of the fields of an exported deck
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeckFormat {
+    Csv,
+    Tsv,
+}
+
+impl DeckFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DeckFormat::Csv => "csv",
+            DeckFormat::Tsv => "tsv",
+        }
+    }
+
+    fn separator(&self) -> char {
+        match self {
+            DeckFormat::Csv => ',',
+            DeckFormat::Tsv => '\t',
+        }
+    }
+}
+
+/**
+ * Field of a card, already escaped: the line breaks become <br> (the cards are html)
+ * so that every card is on one line, fields with the separator or quotes are quoted
+ */
+fn deck_field(text: &str, format: DeckFormat) -> String {
+    let text = text
+        .trim()
+        .replace("\r\n", "\n")
+        .replace('\n', "<br>")
+        .replace('\t', " ");
+    if text.contains(format.separator()) || text.contains('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/**
+ * The words of the vocabulary as a deck of flash cards, one card per line:
+ * word, definition, sentence (with the word in bold) and book and chapter it comes from.
+ * The header lines give Anki the separator and the names of the fields; the cards are html
+ * so the text is escaped.
+ */
+pub fn export_deck(vocabulary: &Vocabulary, format: DeckFormat) -> String {
+    let separator = format.separator().to_string();
+    let mut deck = format!(
+        "#separator:{}\n#html:true\n#columns:{}\n",
+        match format {
+            DeckFormat::Csv => "comma",
+            DeckFormat::Tsv => "tab",
+        },
+        ["Word", "Definition", "Context", "Source"].join(separator.as_str())
+    );
+    for word in vocabulary.words.iter() {
+        let context = match find_word(word.context.as_str(), word.word.as_str()) {
+            Some((start, end)) => format!(
+                "{}<b>{}</b>{}",
+                escape_html(&word.context[..start]),
+                escape_html(&word.context[start..end]),
+                escape_html(&word.context[end..])
+            ),
+            None => escape_html(word.context.as_str()),
+        };
+        let chapter = if word.chapter.is_empty() {
+            format!("chapter {}", word.page)
+        } else {
+            word.chapter.clone()
+        };
+        let source = escape_html(format!("{}, {}", vocabulary.title, chapter).as_str());
+        let fields = [
+            escape_html(word.word.as_str()),
+            escape_html(word.definition.as_str()),
+            context,
+            source,
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| deck_field(f, format)).collect();
+        deck.push_str(fields.join(separator.as_str()).as_str());
+        deck.push('\n');
+    }
+    deck
+}
+
+/**
+ * Byte range of the word in the sentence, the case is ignored
+ */
+fn find_word(sentence: &str, word: &str) -> Option<(usize, usize)> {
+    let lowercase = word.to_lowercase();
+    sentence.char_indices().find_map(|(start, _)| {
+        let end = start + word.len();
+        let candidate = sentence.get(start..end)?;
+        (candidate.to_lowercase() == lowercase).then_some((start, end))
+    })
+}
@@ -0,0 +1,310 @@
+use quick_xml::{
+    events::{BytesEnd, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use std::error::Error;
+
+// Named entities of html that are not defined in xml
+const HTML_ENTITIES: [(&str, &str); 10] = [
+    ("&nbsp;", "&#160;"),
+    ("&ndash;", "&#8211;"),
+    ("&mdash;", "&#8212;"),
+    ("&hellip;", "&#8230;"),
+    ("&lsquo;", "&#8216;"),
+    ("&rsquo;", "&#8217;"),
+    ("&ldquo;", "&#8220;"),
+    ("&rdquo;", "&#8221;"),
+    ("&copy;", "&#169;"),
+    ("&dagger;", "&#8224;"),
+];
+
+/**
+ * Minimal XML tree used to edit package documents (OPF, container.xml...).
+ * Unlike html2text or the epub crate, this keeps qualified names
+ * (e.g. "dc:title", "opf:role") exactly as they are written in the file,
+ * so that a document can be parsed, edited and written back without losing anything.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+    // Comments, CDATA and processing instructions are kept as they are
+    Other(Event<'static>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlDocument {
+    // Declaration, doctype and comments before the root element
+    pub prolog: Vec<Event<'static>>,
+    pub root: XmlElement,
+}
+
+impl XmlDocument {
+    /**
+     * Parse a string into an XmlDocument
+     */
+    pub fn parse(xml: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::from_str(xml);
+        let mut prolog = Vec::new();
+        // Stack of the elements that are still open
+        let mut stack: Vec<XmlElement> = Vec::new();
+        let mut root: Option<XmlElement> = None;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(start) => {
+                    stack.push(XmlElement::from_start(&start)?);
+                }
+                Event::Empty(start) => {
+                    let element = XmlElement::from_start(&start)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(XmlNode::Element(element)),
+                        None => root = Some(element),
+                    }
+                }
+                Event::End(_) => {
+                    let element = match stack.pop() {
+                        Some(element) => element,
+                        None => return Err("Unexpected closing tag".into()),
+                    };
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(XmlNode::Element(element)),
+                        None => root = Some(element),
+                    }
+                }
+                Event::Text(text) => match stack.last_mut() {
+                    Some(parent) => parent
+                        .children
+                        .push(XmlNode::Text(text.unescape()?.into_owned())),
+                    None => {
+                        // Whitespace between the declaration and the root element
+                        if root.is_none() {
+                            prolog.push(Event::Text(text.into_owned()))
+                        }
+                    }
+                },
+                Event::Eof => break,
+                event => match stack.last_mut() {
+                    Some(parent) => parent.children.push(XmlNode::Other(event.into_owned())),
+                    None => {
+                        if root.is_none() {
+                            prolog.push(event.into_owned())
+                        }
+                    }
+                },
+            }
+        }
+
+        match root {
+            Some(root) => Ok(Self { prolog, root }),
+            None => Err("No root element found".into()),
+        }
+    }
+
+    /**
+     * Parse an XHTML page, the named entities of html (e.g. "&nbsp;") are accepted
+     */
+    pub fn parse_xhtml(html: &str) -> Result<Self, Box<dyn Error>> {
+        let mut html = html.to_string();
+        for (entity, code) in HTML_ENTITIES {
+            html = html.replace(entity, code);
+        }
+        Self::parse(html.as_str())
+    }
+
+    /**
+     * Serialize the document back into a string
+     */
+    pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
+        let mut writer = Writer::new(Vec::new());
+        for event in self.prolog.iter() {
+            writer.write_event(event)?;
+        }
+        self.root.write(&mut writer)?;
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+}
+
+impl XmlElement {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn from_start(start: &BytesStart) -> Result<Self, Box<dyn Error>> {
+        let mut element = Self::new(std::str::from_utf8(start.name().as_ref())?);
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            element.attributes.push((
+                std::str::from_utf8(attribute.key.as_ref())?.to_string(),
+                attribute.unescape_value()?.into_owned(),
+            ));
+        }
+        Ok(element)
+    }
+
+    fn write(&self, writer: &mut Writer<Vec<u8>>) -> Result<(), Box<dyn Error>> {
+        let start = BytesStart::new(self.name.as_str()).with_attributes(
+            self.attributes
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        if self.children.is_empty() {
+            writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+        writer.write_event(Event::Start(start))?;
+        for child in self.children.iter() {
+            match child {
+                XmlNode::Element(element) => element.write(writer)?,
+                XmlNode::Text(text) => writer.write_event(Event::Text(BytesText::new(text)))?,
+                XmlNode::Other(event) => writer.write_event(event)?,
+            }
+        }
+        writer.write_event(Event::End(BytesEnd::new(self.name.as_str())))?;
+        Ok(())
+    }
+
+    /**
+     * Name without the namespace prefix ("dc:title" -> "title")
+     */
+    pub fn local_name(&self) -> &str {
+        match self.name.split_once(':') {
+            Some((_, local)) => local,
+            None => self.name.as_str(),
+        }
+    }
+
+    /** Attributes */
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_attr(&mut self, key: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.attributes.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn with_attr(mut self, key: &str, value: &str) -> Self {
+        self.set_attr(key, value);
+        self
+    }
+
+    /** Text */
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for child in self.children.iter() {
+            match child {
+                XmlNode::Text(s) => text.push_str(s),
+                XmlNode::Element(element) => text.push_str(element.text().as_str()),
+                XmlNode::Other(_) => (),
+            }
+        }
+        text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.children = vec![XmlNode::Text(text.to_string())];
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    /** Children */
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut XmlElement> {
+        self.children.iter_mut().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    pub fn child(&self, local_name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.local_name() == local_name)
+    }
+
+    pub fn child_mut(&mut self, local_name: &str) -> Option<&mut XmlElement> {
+        self.elements_mut().find(|e| e.local_name() == local_name)
+    }
+
+    /**
+     * Append a child element, reusing the indentation of the existing children
+     */
+    pub fn push(&mut self, element: XmlElement) {
+        let indent = self.children.iter().find_map(|child| match child {
+            XmlNode::Text(text) if text.trim().is_empty() && text.contains('\n') => {
+                Some(text.clone())
+            }
+            _ => None,
+        });
+        match indent {
+            Some(indent) => {
+                // Keep the whitespace before the closing tag at the end
+                let closing = match self.children.last() {
+                    Some(XmlNode::Text(text)) if text.trim().is_empty() => self.children.pop(),
+                    _ => None,
+                };
+                self.children.push(XmlNode::Text(indent));
+                self.children.push(XmlNode::Element(element));
+                if let Some(closing) = closing {
+                    self.children.push(closing);
+                }
+            }
+            None => self.children.push(XmlNode::Element(element)),
+        }
+    }
+
+    /**
+     * Keep only the child elements matching the predicate
+     * (along with the whitespace in front of the removed ones)
+     */
+    pub fn retain_elements<F: Fn(&XmlElement) -> bool>(&mut self, keep: F) {
+        let mut children = Vec::with_capacity(self.children.len());
+        for child in self.children.drain(..) {
+            match child {
+                XmlNode::Element(element) if !keep(&element) => {
+                    if let Some(XmlNode::Text(text)) = children.last() {
+                        if text.trim().is_empty() {
+                            children.pop();
+                        }
+                    }
+                }
+                child => children.push(child),
+            }
+        }
+        self.children = children;
+    }
+
+    /**
+     * Depth-first search of the first element with the given local name
+     */
+    pub fn find(&self, local_name: &str) -> Option<&XmlElement> {
+        if self.local_name() == local_name {
+            return Some(self);
+        }
+        self.elements().find_map(|e| e.find(local_name))
+    }
+}
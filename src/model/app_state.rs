@@ -1,14 +1,53 @@
 use crate::helper::{
-    config::{DEFAULT_FONT_SIZE, LIBRARY_PATH},
-    functions::{epub_to_book, open_native_dialog, open_native_dialog_images},
+    annotations::{
+        export_annotations, load_annotations, save_annotations, Annotation, AnnotationKind,
+        Annotations, ExportFormat,
+    },
+    artefacts::{
+        artefact_paths, book_slug, chapter_audio_path, move_to_folder, remove_artefacts,
+        rename_artefacts,
+    },
+    config::{
+        DEFAULT_FONT_SIZE, DICTIONARIES_PATH, LIBRARY_PATH, LOOKUPS_PATH, MAX_ZOOM, MIN_ZOOM,
+        RECENT_LOOKUPS, SETTINGS_PATH, ZOOM_STEP,
+    },
+    convert::{convert_book, converted_path, ConvertFormat},
+    cover::generate_cover,
+    dictionary::{
+        default_dictionary, load_dictionaries, Dictionary, Lookup, LookupEntry, LookupHistory,
+    },
+    functions::{
+        confirm_dialog, file_to_book, open_native_dialog, open_native_dialog_annotations,
+        open_native_dialog_folder, open_native_dialog_images, save_native_dialog,
+    },
+    history::Position,
+    importers::{import_file, match_book},
+    links::{open_external, resolve_link, LinkOpener, LinkTarget, SystemOpener},
+    notes::Note,
+    opf::{save_cover, save_metadata, BookMetadata, SaveMode},
+    source::comic::copy_comic_folder,
+    speech::{
+        chapter_text, next_session, speak, split_sentences, system_engine, EspeakSynthesizer,
+        ReadAloud, SpeechEngine, SpeechEvent, SpeechHandle, SpeechListener, WavEngine,
+        WORDS_PER_MINUTE,
+    },
+    stats::{load_stats, now, reading_speed, save_session, ReadingTracker},
+    thumbnail::invalidate_thumbnails,
+    viewer::{next_spread, prev_spread, spread_pages, ImageFit},
+    vocabulary::{
+        context_position, export_deck, load_vocabulary, save_vocabulary, DeckFormat, Vocabulary,
+        VocabularyWord,
+    },
 };
 
 use super::{book::Book, ui_view::UiView};
 use druid::{Data, Lens};
+use druid_widget_nursery::navigator::ViewController;
 
 use std::{
+    error::Error,
     fs::{self, ReadDir},
-    path::PathBuf,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
 };
@@ -22,6 +61,61 @@ pub struct AppState {
     pub library: Arc<Vec<Book>>,
     selected: Option<usize>,
     font_size: f64,
+    pub search_query: String,
+    show_help: bool,
+    // Note shown over the page
+    #[data(same_fn = "PartialEq::eq")]
+    note: Option<Note>,
+    // Reading session of the open book (index of the book, tracker)
+    #[data(ignore)]
+    reading: Option<(usize, ReadingTracker)>,
+    // Opens the external links of the books
+    #[data(ignore)]
+    link_opener: Arc<dyn LinkOpener>,
+    // Chapter read aloud, with the sentence being spoken
+    #[data(same_fn = "PartialEq::eq")]
+    read_aloud: Option<ReadAloud>,
+    // Reading in progress in the background, and where its events go
+    #[data(ignore)]
+    speech: Option<Arc<SpeechHandle>>,
+    #[data(ignore)]
+    speech_listener: Option<SpeechListener>,
+    // Speaks the chapters, None -> the engine of the system (looked for at the first reading)
+    #[data(ignore)]
+    speech_engine: Option<Arc<dyn SpeechEngine>>,
+    // Result of the last reading aloud (audio file written, or why it stopped), shown in the reader
+    #[data(same_fn = "PartialEq::eq")]
+    speech_result: Option<Result<String, String>>,
+    // Word looked up, shown under the page, and the word typed in the lookup panel
+    #[data(same_fn = "PartialEq::eq")]
+    lookup: Option<Lookup>,
+    pub lookup_query: String,
+    // Dictionaries of the dictionaries folder, loaded at the first lookup
+    #[data(ignore)]
+    dictionaries: Option<Arc<Vec<Arc<dyn Dictionary>>>>,
+    // Words looked up in the selected book, shown in the vocabulary page
+    #[data(same_fn = "PartialEq::eq")]
+    vocabulary: Option<Vocabulary>,
+    // Bookmarks and highlights of the book read (index of the book, annotations)
+    #[data(same_fn = "PartialEq::eq")]
+    annotations: Option<(usize, Annotations)>,
+    // Highlight shown under the page, and the note written about it
+    annotation: Option<usize>,
+    pub annotation_note: String,
+    // How the pages of the comics are shown
+    image_fit: ImageFit,
+    zoom: f64,
+    two_pages: bool,
+    // Why the last save of the book editor failed, shown in the editor
+    edit_error: Option<String>,
+    // Why the last save of the metadata editor failed, shown in the editor
+    metadata_error: Option<String>,
+    // Result of the last conversion (the converted file, or why it failed), shown in the library
+    #[data(same_fn = "PartialEq::eq")]
+    conversion: Option<Result<String, String>>,
+    // Result of the last import of notes (counts, or why it failed), shown in the library
+    #[data(same_fn = "PartialEq::eq")]
+    notes_import: Option<Result<String, String>>,
 }
 
 impl AppState {
@@ -32,9 +126,82 @@ impl AppState {
             nav_state: Arc::new(vec![UiView::Library]),
             selected: None,
             font_size: DEFAULT_FONT_SIZE,
+            search_query: String::new(),
+            show_help: false,
+            note: None,
+            reading: None,
+            link_opener: Arc::new(SystemOpener),
+            read_aloud: None,
+            speech: None,
+            speech_listener: None,
+            speech_engine: None,
+            speech_result: None,
+            lookup: None,
+            lookup_query: String::new(),
+            dictionaries: None,
+            vocabulary: None,
+            annotations: None,
+            annotation: None,
+            annotation_note: String::new(),
+            image_fit: ImageFit::Page,
+            zoom: MIN_ZOOM,
+            two_pages: false,
+            edit_error: None,
+            metadata_error: None,
+            conversion: None,
+            notes_import: None,
         }
     }
 
+    /**
+     * Replace the opener of the external links (e.g. with a stub in the tests)
+     */
+    pub fn set_link_opener(&mut self, link_opener: Arc<dyn LinkOpener>) {
+        self.link_opener = link_opener;
+    }
+
+    pub fn get_edit_error(&self) -> Option<String> {
+        self.edit_error.clone()
+    }
+
+    pub fn set_edit_error(&mut self, edit_error: Option<String>) {
+        self.edit_error = edit_error;
+    }
+
+    pub fn get_metadata_error(&self) -> Option<String> {
+        self.metadata_error.clone()
+    }
+
+    pub fn set_metadata_error(&mut self, metadata_error: Option<String>) {
+        self.metadata_error = metadata_error;
+    }
+
+    pub fn get_conversion(&self) -> Option<Result<String, String>> {
+        self.conversion.clone()
+    }
+
+    pub fn get_notes_import(&self) -> Option<Result<String, String>> {
+        self.notes_import.clone()
+    }
+
+    /**
+     * Replace the speech engine of the system (e.g. with a WavEngine in the tests)
+     */
+    pub fn set_speech_engine(&mut self, speech_engine: Arc<dyn SpeechEngine>) {
+        self.speech_engine = Some(speech_engine);
+    }
+
+    pub fn get_speech_result(&self) -> Option<Result<String, String>> {
+        self.speech_result.clone()
+    }
+
+    /**
+     * Replace the dictionaries of the dictionaries folder (e.g. with test dictionaries)
+     */
+    pub fn set_dictionaries(&mut self, dictionaries: Vec<Arc<dyn Dictionary>>) {
+        self.dictionaries = Some(Arc::new(dictionaries));
+    }
+
     /**
      * Initialize library, scan the "library" folder and add all the books
      */
@@ -53,17 +220,32 @@ impl AppState {
         let mut book_list: Vec<Book> = Vec::new();
         let covers_path = "./src/library/covers";
         let ocr_path = "./src/library/ocr";
+        let audio_path = "./src/library/audio";
         let progress_path = "./src/library/progress";
+        let cache_path = "./src/library/cache";
+        let dictionaries_path = "./src/library/dictionaries";
+        let settings_path = SETTINGS_PATH;
+        let lookups_path = LOOKUPS_PATH;
         if dir.is_some() {
             // Unwrap is safe because we checked if dir is None
             for file in dir.unwrap() {
                 match file {
                     Ok(file) => {
+                        // Hidden files and folders (like the temporary folders of the
+                        // edited books) are not books
+                        if file.file_name().to_string_lossy().starts_with('.') {
+                            continue;
+                        }
                         // Check if file is a directory
                         if (!file.path().to_str().unwrap().contains(covers_path))
                             && (!file.path().to_str().unwrap().contains(ocr_path))
-                            && (!file.path().to_str().unwrap().contains(progress_path)){
-                            let book = epub_to_book(file.path());
+                            && (!file.path().to_str().unwrap().contains(audio_path))
+                            && (!file.path().to_str().unwrap().contains(progress_path))
+                            && (!file.path().to_str().unwrap().contains(cache_path))
+                            && (!file.path().to_str().unwrap().contains(dictionaries_path))
+                            && (!file.path().to_str().unwrap().contains(settings_path))
+                            && (!file.path().to_str().unwrap().contains(lookups_path)){
+                            let book = file_to_book(file.path());
                             match book {
                                 None => {
                                     eprintln!("Unable to add book {}", file.path().display());
@@ -100,7 +282,7 @@ impl AppState {
             Some(path) => path,
         };
 
-        let book = epub_to_book(path.clone());
+        let book = file_to_book(path.clone());
         let filename = path
             .file_name()
             .expect("Unable to read file name")
@@ -128,14 +310,53 @@ impl AppState {
         }
     }
 
+    /**
+     * Adds a folder of images (the pages of a comic) to the library
+     */
+    pub fn add_comic_folder(&mut self) {
+        let folder = match open_native_dialog_folder() {
+            Some(folder) => folder,
+            None => {
+                println!("No folder selected");
+                return;
+            }
+        };
+        let name = match folder.file_name() {
+            Some(name) => name.to_owned(),
+            None => {
+                eprintln!("Invalid folder {}", folder.display());
+                return;
+            }
+        };
+        let to = Path::new(LIBRARY_PATH).join(name);
+        match copy_comic_folder(folder.as_path(), to.as_path()) {
+            Ok(_) => self.add_book(Some(to)),
+            Err(e) => eprintln!("Error adding the comic: {}", e),
+        }
+    }
+
     pub fn add_book_from_path(&mut self, file: PathBuf) {
         self.add_book(Some(file))
     }
 
+    /**
+     * Add the book of a file of the library folder, or read it again if the library
+     * already has the book of that file (a copy written again over the previous one)
+     */
+    pub fn add_or_reload_book(&mut self, file: PathBuf) {
+        let index = self.library.iter().position(|book| {
+            Path::new(book.get_file_path().as_str()).file_name() == file.file_name()
+        });
+        match index {
+            Some(index) => self.reload_book(index, file),
+            None => self.add_book_from_path(file),
+        }
+    }
+
     fn add_book(&mut self, path: Option<PathBuf>) {
         match path {
             Some(path) => {
-                let book = epub_to_book(path);
+                let book = file_to_book(path);
                 match book {
                     Some(book) => {
                         let library = Arc::make_mut(&mut self.library);
@@ -196,19 +417,188 @@ impl AppState {
         self.font_size -= 2.0;
     }
 
+    /**
+     * Comic viewer methods
+     */
+    pub fn is_comic_book(&self) -> bool {
+        self.selected
+            .and_then(|index| self.library.get(index))
+            .is_some_and(|book| book.is_comic())
+    }
+
+    pub fn get_image_fit(&self) -> ImageFit {
+        self.image_fit
+    }
+
+    pub fn set_image_fit(&mut self, image_fit: ImageFit) {
+        self.image_fit = image_fit;
+    }
+
+    pub fn get_zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /**
+     * Zoom in the page (from the whole page), or out until the whole page is shown
+     */
+    pub fn zoom_in(&mut self) {
+        if self.image_fit != ImageFit::Zoom {
+            self.zoom = MIN_ZOOM;
+        }
+        self.image_fit = ImageFit::Zoom;
+        self.zoom = (self.zoom + ZOOM_STEP).min(MAX_ZOOM);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom - ZOOM_STEP).max(MIN_ZOOM);
+        if self.zoom <= MIN_ZOOM {
+            self.image_fit = ImageFit::Page;
+        }
+    }
+
+    pub fn is_two_pages(&self) -> bool {
+        self.two_pages
+    }
+
+    pub fn toggle_two_pages(&mut self) {
+        self.two_pages = !self.two_pages;
+    }
+
+    /**
+     * Whether two pages of the selected book are shown at a time
+     */
+    fn is_spread_view(&self) -> bool {
+        self.two_pages && self.is_comic_book()
+    }
+
+    /**
+     * Pages of the selected book shown in the reader (two in a spread)
+     */
+    pub fn get_shown_pages(&self) -> Vec<usize> {
+        match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => spread_pages(
+                book.get_current_page(),
+                book.get_book_length(),
+                self.is_spread_view(),
+            ),
+            None => Vec::new(),
+        }
+    }
+
+    /**
+     * Read the selected book from right to left (manga) or from left to right
+     */
+    pub fn toggle_rtl_book(&mut self) {
+        let rtl = self.is_rtl_book();
+        if let Some(book) = self
+            .selected
+            .and_then(|index| Arc::make_mut(&mut self.library).get_mut(index))
+        {
+            book.set_rtl(!rtl);
+        }
+    }
+
     /**
      * Book navigation methods
      */
+    /**
+     * Follow a link of the current page of the selected book.
+     * Links to the book are resolved from the current document,
+     * external links are opened in the browser after a confirmation.
+     */
     pub fn navigate_to_page(&mut self, link: Rc<String>) {
-        if self.selected.is_none() {
-            return;
+        let (index, target) = match self.resolve_book_link(link.as_str()) {
+            Some(resolved) => resolved,
+            None => return,
+        };
+
+        match target {
+            LinkTarget::External(url) => {
+                let confirm = |url: &str| {
+                    confirm_dialog(
+                        "Open link",
+                        format!("Open {} in the browser?", url).as_str(),
+                    )
+                };
+                if let Err(e) = open_external(url.as_str(), self.link_opener.as_ref(), confirm) {
+                    eprintln!("Error opening link {}: {}", url, e);
+                }
+            }
+            LinkTarget::Internal { path, fragment } => {
+                // Call the navigate_to method of the selected book
+                let library = Arc::make_mut(&mut self.library);
+                let book = library.get_mut(index).expect("Unable to get mut library");
+                book.navigate_to(&path, fragment.as_deref());
+            }
         }
-        // Call the navigate_to method of the selected book
-        let library = Arc::make_mut(&mut self.library);
-        let book = library
-            .get_mut(self.selected.unwrap_or(1))
-            .expect("Unable to get mut library");
-        book.navigate_to(link);
+    }
+
+    /**
+     * Index of the selected book and target of a link of its current page
+     */
+    fn resolve_book_link(&self, link: &str) -> Option<(usize, LinkTarget)> {
+        let index = self.selected?;
+        let book = self.library.get(index)?;
+        let current_doc = book
+            .get_page_path(book.get_current_page())
+            .unwrap_or_default();
+        Some((index, resolve_link(&current_doc, link)))
+    }
+
+    /**
+     * Show the note of a note reference over the page, the reading position does not change.
+     * If the note cannot be found the link is followed instead.
+     */
+    pub fn show_note(&mut self, link: Rc<String>) {
+        let text = match self.resolve_book_link(link.as_str()) {
+            Some((
+                index,
+                LinkTarget::Internal {
+                    path,
+                    fragment: Some(fragment),
+                },
+            )) => self.library[index].get_note(&path, fragment.as_str()),
+            _ => None,
+        };
+        match text {
+            Some(text) => {
+                self.note = Some(Note {
+                    href: link.to_string(),
+                    text,
+                })
+            }
+            None => self.navigate_to_page(link),
+        }
+    }
+
+    pub fn get_note(&self) -> Option<Note> {
+        self.note.clone()
+    }
+
+    pub fn is_note_visible(&self) -> bool {
+        self.note.is_some()
+    }
+
+    pub fn close_note(&mut self) {
+        self.note = None;
+    }
+
+    /**
+     * Follow the link of the note shown, to read it where it is in the book
+     */
+    pub fn go_to_note(&mut self) {
+        if let Some(note) = self.note.take() {
+            self.navigate_to_page(Rc::new(note.href));
+        }
+    }
+
+    /**
+     * Whether the pages of the selected book are turned from right to left
+     */
+    pub fn is_rtl_book(&self) -> bool {
+        self.selected
+            .and_then(|index| self.library.get(index))
+            .is_some_and(|book| book.is_rtl())
     }
 
     pub fn has_next_page(&self) -> bool {
@@ -220,6 +610,9 @@ impl AppState {
             .library
             .get(self.selected.unwrap_or(1))
             .expect("Unable to get book");
+        if self.is_spread_view() {
+            return next_spread(book.get_current_page(), book.get_book_length(), true).is_some();
+        }
         book.has_next_page()
     }
 
@@ -232,6 +625,9 @@ impl AppState {
             .library
             .get(self.selected.unwrap_or(1))
             .expect("Unable to get library");
+        if self.is_spread_view() {
+            return prev_spread(book.get_current_page(), true).is_some();
+        }
         book.has_prev_page()
     }
 
@@ -240,11 +636,18 @@ impl AppState {
             return;
         }
         // Call the navigate_to_next_page method of the selected book
+        let two_pages = self.is_spread_view();
         let library = Arc::make_mut(&mut self.library);
         let book = library
             .get_mut(self.selected.unwrap_or(1))
             .expect("Unable to get mut book");
-        book.next_page();
+        if !two_pages {
+            book.next_page();
+        } else if let Some(page) =
+            next_spread(book.get_current_page(), book.get_book_length(), true)
+        {
+            book.set_page(page);
+        }
     }
 
     pub fn navigate_to_prev_page(&mut self) {
@@ -252,11 +655,16 @@ impl AppState {
             return;
         }
         // Call the navigate_to_prev_page method of the selected book
+        let two_pages = self.is_spread_view();
         let library = Arc::make_mut(&mut self.library);
         let book = library
             .get_mut(self.selected.unwrap_or(1))
             .expect("Unable to get mut book");
-        book.prev_page();
+        if !two_pages {
+            book.prev_page();
+        } else if let Some(page) = prev_spread(book.get_current_page(), true) {
+            book.set_page(page);
+        }
     }
 
     pub fn navigate_to_page_index(&mut self, page: usize) {
@@ -267,58 +675,1232 @@ impl AppState {
         book.set_page(page);
     }
 
-    pub fn navigate_to_first_page(&mut self) {
-        self.navigate_to_page_index(1);
-    }
-
-    pub fn navigate_to_last_page(&mut self) {
+    /**
+     * Jump to a page of the selected book, keeping the position left in its history
+     */
+    pub fn jump_to_page_index(&mut self, page: usize) {
         let library = Arc::make_mut(&mut self.library);
         let book = library
             .get_mut(self.selected.unwrap_or(1))
             .expect("Unable to get mut book");
-        book.set_page(book.get_book_length());
+        book.jump_to_page(page);
     }
 
-    /** EDIT */
+    pub fn can_go_back(&self) -> bool {
+        match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book.can_go_back(),
+            None => false,
+        }
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book.can_go_forward(),
+            None => false,
+        }
+    }
 
     /**
-     * Sets the current page as the editing page, in order to be
-     * displayed in the editor
+     * Back and forward in the history of the selected book
      */
-    pub fn set_editing_page(&mut self) {
-        if self.selected.is_none() {
+    pub fn go_back(&mut self) {
+        if !self.can_go_back() {
             return;
         }
-        // Call the set_editing_page method of the selected book
         let library = Arc::make_mut(&mut self.library);
         let book = library
             .get_mut(self.selected.unwrap_or(1))
             .expect("Unable to get mut book");
-        let page_string = book.get_page_str(book.get_current_page());
-        match page_string {
-            None => {
-                println!("Error getting page string");
-                return;
-            }
-            Some(page) => {
-                book.set_page_str(page);
-            }
-        }
+        book.go_back();
     }
 
-    /** Save book progress */
-    pub fn save_book_progress(&mut self) {
-        if self.selected.is_none() {
+    pub fn go_forward(&mut self) {
+        if !self.can_go_forward() {
             return;
         }
         let library = Arc::make_mut(&mut self.library);
         let book = library
             .get_mut(self.selected.unwrap_or(1))
             .expect("Unable to get mut book");
-        match book.save_progress() {
-            Ok(_) => println!("Saved"),
-            Err(err) => eprintln!("Not saved, {}", err),
+        book.go_forward();
+    }
+
+    pub fn navigate_to_first_page(&mut self) {
+        let first_page = self
+            .selected
+            .and_then(|index| self.library.get(index))
+            .map_or(1, |book| book.get_first_page());
+        self.navigate_to_page_index(first_page);
+    }
+
+    pub fn navigate_to_last_page(&mut self) {
+        let library = Arc::make_mut(&mut self.library);
+        let book = library
+            .get_mut(self.selected.unwrap_or(1))
+            .expect("Unable to get mut book");
+        book.set_page(book.get_book_length());
+    }
+
+    /**
+     * Find the next page of the selected book containing the search query
+     */
+    pub fn search_book(&mut self) {
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        if self.search_query.trim().is_empty() {
+            return;
+        }
+        match self.library[index].find_text(self.search_query.trim()) {
+            Some(page) => {
+                let library = Arc::make_mut(&mut self.library);
+                library[index].jump_to_page(page);
+            }
+            None => println!("No results for \"{}\"", self.search_query),
+        }
+    }
+
+    /**
+     * Leave the reader (and close the reading session)
+     */
+    pub fn close_book(&mut self) {
+        self.stop_reading_aloud();
+        self.speech_result = None;
+        self.end_reading_session();
+        self.show_help = false;
+        self.note = None;
+        self.lookup = None;
+        self.annotation = None;
+        self.annotations = None;
+        self.pop_view();
+    }
+
+    /**
+     * Read the current chapter aloud from the sentence at the position in the chapter,
+     * or into an audio file (output) from its start.
+     * The listener receives the events of the reading, they must be given to speech_event.
+     */
+    pub fn read_aloud(&mut self, output: Option<PathBuf>, listener: SpeechListener) {
+        self.stop_reading_aloud();
+        self.speech_result = None;
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        let book = &self.library[index];
+        let page = book.get_current_page();
+        let html = match book.get_page_str(page) {
+            Some(html) => html,
+            None => return,
+        };
+        let sentences = Arc::new(split_sentences(chapter_text(html.as_str()).as_str()));
+        let language = book.get_language().map(|language| language.to_lowercase());
+
+        let (engine, start): (Arc<dyn SpeechEngine>, usize) = match &output {
+            Some(path) => {
+                let synthesizer = EspeakSynthesizer::new(language.as_deref(), WORDS_PER_MINUTE);
+                (Arc::new(WavEngine::new(path, Box::new(synthesizer))), 0)
+            }
+            None => {
+                // Looking for the engine runs the speech programs, it is done once
+                if self.speech_engine.is_none() {
+                    self.speech_engine = system_engine(None);
+                }
+                let engine = match self.speech_engine.clone() {
+                    Some(engine) => engine,
+                    None => {
+                        let error = String::from(
+                            "No speech engine found, install espeak-ng or speech-dispatcher",
+                        );
+                        eprintln!("{}", error);
+                        self.speech_result = Some(Err(error));
+                        return;
+                    }
+                };
+                engine.set_language(language.as_deref());
+                let start = (book.get_chapter_position() * sentences.len() as f64) as usize;
+                (engine, start.min(sentences.len().saturating_sub(1)))
+            }
+        };
+
+        let session = next_session();
+        let handle = speak(engine, sentences.clone(), start, session, listener.clone());
+        self.speech = Some(Arc::new(handle));
+        self.speech_listener = Some(listener);
+        self.read_aloud = Some(ReadAloud {
+            session,
+            book: index,
+            page,
+            sentences,
+            current: None,
+            output,
+        });
+    }
+
+    /**
+     * Read the current chapter into an audio file, in the audio folder of the book
+     */
+    pub fn read_aloud_to_file(&mut self, listener: SpeechListener) {
+        let book = match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book,
+            None => return,
+        };
+        let path = chapter_audio_path(book.get_title().as_str(), book.get_current_page());
+        self.read_aloud(Some(path), listener);
+    }
+
+    pub fn stop_reading_aloud(&mut self) {
+        if let Some(speech) = self.speech.take() {
+            speech.stop();
+        }
+        self.read_aloud = None;
+    }
+
+    pub fn is_reading_aloud(&self) -> bool {
+        self.read_aloud.is_some()
+    }
+
+    /**
+     * Sentence being spoken, if it is in the chapter shown
+     */
+    pub fn get_spoken_sentence(&self) -> Option<String> {
+        let reading = self.read_aloud.as_ref()?;
+        let book = self.library.get(reading.book)?;
+        if self.selected != Some(reading.book) || book.get_current_page() != reading.page {
+            return None;
+        }
+        reading
+            .current
+            .and_then(|index| reading.sentences.get(index).cloned())
+    }
+
+    /**
+     * Handle an event of the reading in progress (the events of the previous readings are ignored):
+     * highlight the sentence spoken, then go on with the next chapter
+     */
+    pub fn speech_event(&mut self, session: u64, event: &SpeechEvent) {
+        let reading = match self.read_aloud.as_mut() {
+            Some(reading) if reading.session == session => reading,
+            _ => return,
+        };
+        match event {
+            SpeechEvent::Sentence(index) => {
+                reading.current = Some(*index);
+                // The chapter scrolls along with the sentences spoken
+                let position = *index as f64 / reading.sentences.len().max(1) as f64;
+                if reading.output.is_none() && self.get_spoken_sentence().is_some() {
+                    self.set_chapter_position(position);
+                }
+            }
+            SpeechEvent::End(Err(e)) => {
+                eprintln!("Error reading aloud: {}", e);
+                self.stop_reading_aloud();
+                self.speech_result = Some(Err(e.clone()));
+            }
+            SpeechEvent::End(Ok(())) => {
+                let output = reading.output.clone();
+                let page = reading.page;
+                let same_page = self.selected == Some(reading.book)
+                    && self.library[reading.book].get_current_page() == page;
+                self.stop_reading_aloud();
+                if let Some(output) = output {
+                    self.speech_result = Some(Ok(output.display().to_string()));
+                } else if same_page && self.has_next_page() {
+                    self.navigate_to_next_page();
+                    if let Some(listener) = self.speech_listener.clone() {
+                        self.read_aloud(None, listener);
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * Dictionaries to look the words up in, loaded the first time
+     */
+    fn get_dictionaries(&mut self) -> Arc<Vec<Arc<dyn Dictionary>>> {
+        self.dictionaries
+            .get_or_insert_with(|| {
+                let dictionaries = load_dictionaries(Path::new(DICTIONARIES_PATH))
+                    .into_iter()
+                    .map(|dictionary| Arc::new(dictionary) as Arc<dyn Dictionary>)
+                    .collect();
+                Arc::new(dictionaries)
+            })
+            .clone()
+    }
+
+    /**
+     * Look a word of the book up (context -> sentence it is in) and show its definitions.
+     * The dictionary chosen in the lookup panel is kept while it is open,
+     * otherwise the language of the book picks it.
+     * The word is added to the lookup history.
+     */
+    pub fn look_up_word(&mut self, word: &str, context: &str) {
+        let dictionaries = self.get_dictionaries();
+        let book = self.selected.and_then(|index| self.library.get(index));
+        let dictionary = match &self.lookup {
+            Some(lookup) => lookup.dictionary,
+            None => default_dictionary(
+                &dictionaries,
+                book.and_then(|book| book.get_language()).as_deref(),
+            ),
+        };
+        let definitions = match dictionary.and_then(|index| dictionaries.get(index)) {
+            Some(dictionary) if !word.is_empty() => dictionary.lookup(word),
+            _ => Vec::new(),
+        };
+
+        // The words looked up in the page are kept in the vocabulary of the book
+        if !word.is_empty() && !context.is_empty() {
+            if let Some(book) = book {
+                let page = book.get_current_page();
+                let chapter_position = book
+                    .get_page_str(page)
+                    .and_then(|html| context_position(html.as_str(), context))
+                    .unwrap_or_else(|| book.get_chapter_position());
+                let mut vocabulary = load_vocabulary(book.get_title().as_str());
+                vocabulary.add(VocabularyWord {
+                    word: word.to_string(),
+                    definition: definitions
+                        .first()
+                        .map(|definition| definition.text.clone())
+                        .unwrap_or_default(),
+                    context: context.to_string(),
+                    page,
+                    chapter_position,
+                    chapter: book.get_chapter_title(page).unwrap_or_default(),
+                    time: now(),
+                });
+                if let Err(e) = save_vocabulary(&vocabulary) {
+                    eprintln!("Error saving the vocabulary: {}", e);
+                }
+            }
+        }
+
+        let path = Path::new(LOOKUPS_PATH);
+        let mut history = LookupHistory::load(path);
+        if !word.is_empty() {
+            history.add(LookupEntry {
+                word: word.to_string(),
+                book: book.map(|book| book.get_title()).unwrap_or_default(),
+                context: context.to_string(),
+                time: now(),
+            });
+            if let Err(e) = history.save(path) {
+                eprintln!("Error saving the lookup history: {}", e);
+            }
+        }
+
+        self.lookup = Some(Lookup {
+            word: word.to_string(),
+            context: context.to_string(),
+            dictionaries: dictionaries
+                .iter()
+                .map(|dictionary| dictionary.name().to_string())
+                .collect(),
+            dictionary,
+            definitions,
+            history: history.recent(RECENT_LOOKUPS),
+        });
+    }
+
+    /**
+     * Look the word typed in the lookup panel up
+     */
+    pub fn look_up_query(&mut self) {
+        let word = self.lookup_query.trim().to_string();
+        if !word.is_empty() {
+            self.look_up_word(word.as_str(), "");
+        }
+    }
+
+    /**
+     * Open the lookup panel without a word (it shows the lookup history)
+     */
+    pub fn open_dictionary(&mut self) {
+        if self.lookup.is_none() {
+            self.look_up_word("", "");
+        }
+    }
+
+    /**
+     * Look the word shown up in another dictionary
+     */
+    pub fn set_dictionary(&mut self, dictionary: usize) {
+        let lookup = match self.lookup.as_mut() {
+            Some(lookup) => lookup,
+            None => return,
+        };
+        lookup.dictionary = Some(dictionary);
+        lookup.definitions = match self.dictionaries.as_ref().and_then(|d| d.get(dictionary)) {
+            Some(dictionary) if !lookup.word.is_empty() => dictionary.lookup(lookup.word.as_str()),
+            _ => Vec::new(),
+        };
+    }
+
+    pub fn get_lookup(&self) -> Option<Lookup> {
+        self.lookup.clone()
+    }
+
+    pub fn is_lookup_visible(&self) -> bool {
+        self.lookup.is_some()
+    }
+
+    pub fn close_lookup(&mut self) {
+        self.lookup = None;
+    }
+
+    /**
+     * Open the vocabulary page of the selected book
+     */
+    pub fn open_vocabulary(&mut self) {
+        let book = match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book,
+            None => return,
+        };
+        self.vocabulary = Some(load_vocabulary(book.get_title().as_str()));
+        self.add_view(UiView::Vocabulary);
+    }
+
+    pub fn get_vocabulary(&self) -> Option<Vocabulary> {
+        self.vocabulary.clone()
+    }
+
+    pub fn close_vocabulary(&mut self) {
+        self.vocabulary = None;
+        self.pop_view();
+    }
+
+    pub fn remove_vocabulary_word(&mut self, index: usize) {
+        if let Some(vocabulary) = self.vocabulary.as_mut() {
+            vocabulary.remove(index);
+            if let Err(e) = save_vocabulary(vocabulary) {
+                eprintln!("Error saving the vocabulary: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Go back to the reader, at the sentence a word of the vocabulary was looked up in
+     * (the position left is kept in the history)
+     */
+    pub fn go_to_vocabulary_word(&mut self, index: usize) {
+        let word = match self.vocabulary.as_ref().and_then(|v| v.words.get(index)) {
+            Some(word) => word.clone(),
+            None => return,
+        };
+        self.close_vocabulary();
+        let library = Arc::make_mut(&mut self.library);
+        if let Some(book) = self.selected.and_then(|index| library.get_mut(index)) {
+            book.jump_to(Position {
+                page: word.page,
+                chapter_position: word.chapter_position,
+            });
+        }
+    }
+
+    /**
+     * Export the vocabulary as a deck of flash cards (e.g. for Anki), where the user chooses
+     */
+    pub fn export_vocabulary(&self, format: DeckFormat) {
+        let vocabulary = match &self.vocabulary {
+            Some(vocabulary) => vocabulary,
+            None => return,
+        };
+        let name = format!(
+            "{}-vocabulary.{}",
+            vocabulary.title.replace(' ', "-"),
+            format.extension()
+        );
+        let path = match save_native_dialog(name.as_str(), &[("Flash cards", format.extension())]) {
+            Some(path) => path,
+            None => return,
+        };
+        match fs::write(&path, export_deck(vocabulary, format)) {
+            Ok(_) => println!("Vocabulary exported to {}", path.display()),
+            Err(e) => eprintln!("Error exporting the vocabulary: {}", e),
+        }
+    }
+
+    /**
+     * Load the annotations of the book read, if they are not loaded yet
+     */
+    pub fn load_book_annotations(&mut self) {
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        if !matches!(&self.annotations, Some((book, _)) if *book == index) {
+            let book = &self.library[index];
+            let annotations =
+                load_annotations(book.get_title().as_str(), book.get_author().as_str());
+            self.annotations = Some((index, annotations));
+            self.annotation = None;
+        }
+    }
+
+    /**
+     * Save the annotations of the book read after a change
+     */
+    fn save_book_annotations(&self) {
+        if let Some((_, annotations)) = &self.annotations {
+            if let Err(e) = save_annotations(annotations) {
+                eprintln!("Error saving the annotations: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Add an annotation at a position of the book read, returns its index
+     */
+    fn add_annotation(
+        &mut self,
+        kind: AnnotationKind,
+        position: Position,
+        text: &str,
+    ) -> Option<usize> {
+        self.load_book_annotations();
+        let book = self.library.get(self.selected?)?;
+        let annotation = Annotation {
+            kind,
+            page: position.page,
+            chapter_position: position.chapter_position,
+            chapter: book.get_chapter_title(position.page).unwrap_or_default(),
+            text: text.to_string(),
+            note: String::new(),
+            time: now(),
+        };
+        let index = self.annotations.as_mut()?.1.add(annotation);
+        self.save_book_annotations();
+        Some(index)
+    }
+
+    /**
+     * Bookmark the position of the book read
+     */
+    pub fn add_bookmark(&mut self) {
+        if let Some(position) = self.get_position() {
+            self.add_annotation(AnnotationKind::Bookmark, position, "");
+        }
+    }
+
+    /**
+     * Highlight a sentence of the page shown (if it is not highlighted yet)
+     * and show the highlight, to write a note about it
+     */
+    pub fn highlight_sentence(&mut self, sentence: &str) {
+        self.load_book_annotations();
+        let book = match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book,
+            None => return,
+        };
+        let page = book.get_current_page();
+        let existing = self
+            .annotations
+            .as_ref()
+            .and_then(|(_, annotations)| annotations.find_highlight(page, sentence));
+        let index = match existing {
+            Some(index) => Some(index),
+            None => {
+                let chapter_position = book
+                    .get_page_str(page)
+                    .and_then(|html| context_position(html.as_str(), sentence))
+                    .unwrap_or_else(|| book.get_chapter_position());
+                let position = Position {
+                    page,
+                    chapter_position,
+                };
+                self.add_annotation(AnnotationKind::Highlight, position, sentence)
+            }
+        };
+        self.annotation = index;
+        self.annotation_note = self.get_annotation().map(|a| a.note).unwrap_or_default();
+    }
+
+    /**
+     * Sentences highlighted in the page shown
+     */
+    pub fn get_page_highlights(&self) -> Vec<String> {
+        match (&self.annotations, self.selected) {
+            (Some((book, annotations)), Some(index)) if *book == index => {
+                annotations.page_highlights(self.library[index].get_current_page())
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_annotation(&self) -> Option<Annotation> {
+        let (_, annotations) = self.annotations.as_ref()?;
+        annotations.annotations.get(self.annotation?).cloned()
+    }
+
+    pub fn is_annotation_visible(&self) -> bool {
+        self.get_annotation().is_some()
+    }
+
+    pub fn close_annotation(&mut self) {
+        self.annotation = None;
+    }
+
+    /**
+     * Save the note written about the highlight shown
+     */
+    pub fn save_annotation_note(&mut self) {
+        let index = match self.annotation {
+            Some(index) => index,
+            None => return,
+        };
+        if let Some((_, annotations)) = self.annotations.as_mut() {
+            if let Some(annotation) = annotations.annotations.get_mut(index) {
+                annotation.note = self.annotation_note.trim().to_string();
+            }
+        }
+        self.save_book_annotations();
+        self.annotation = None;
+    }
+
+    /**
+     * Remove the highlight shown (and its note)
+     */
+    pub fn remove_annotation(&mut self) {
+        let index = match self.annotation.take() {
+            Some(index) => index,
+            None => return,
+        };
+        if let Some((_, annotations)) = self.annotations.as_mut() {
+            annotations.remove(index);
+        }
+        self.save_book_annotations();
+    }
+
+    /**
+     * Ask where to save the export, its extension gives the format (Markdown by default)
+     */
+    fn write_export(name: &str, books: &[Annotations]) {
+        let filters: Vec<(&str, &str)> = ExportFormat::ALL
+            .iter()
+            .map(|format| (format.name(), format.extension()))
+            .collect();
+        let path = match save_native_dialog(name, &filters) {
+            Some(path) => path,
+            None => return,
+        };
+        let exported = export_annotations(books, ExportFormat::from_path(&path))
+            .and_then(|export| fs::write(&path, export).map_err(|e| e.into()));
+        match exported {
+            Ok(_) => println!("Notes exported to {}", path.display()),
+            Err(e) => eprintln!("Error exporting the notes: {}", e),
+        }
+    }
+
+    /**
+     * Export the bookmarks, highlights and notes of the book read
+     */
+    pub fn export_book_annotations(&mut self) {
+        self.load_book_annotations();
+        if let Some((_, annotations)) = &self.annotations {
+            let name = format!("{}-notes.md", annotations.title.replace(' ', "-"));
+            Self::write_export(name.as_str(), std::slice::from_ref(annotations));
+        }
+    }
+
+    /**
+     * Export the bookmarks, highlights and notes of all the books of the library
+     */
+    pub fn export_library_annotations(&self) {
+        let books: Vec<Annotations> = self
+            .library
+            .iter()
+            .map(|book| load_annotations(book.get_title().as_str(), book.get_author().as_str()))
+            .filter(|annotations| !annotations.annotations.is_empty())
+            .collect();
+        if books.is_empty() {
+            println!("No notes in the library");
+            return;
+        }
+        Self::write_export("Library-notes.md", &books);
+    }
+
+    /**
+     * Import the notes made on another reader (Kindle clippings or KOReader notes)
+     */
+    pub fn import_annotations_from_file(&mut self) {
+        if let Some(path) = open_native_dialog_annotations() {
+            self.import_annotations(&path);
+        }
+    }
+
+    /**
+     * Import the notes of a file into the books of the library they were made in,
+     * each one is placed where its text is found in the book (or in the page
+     * the other reader gives, for a bookmark). Notes already imported are skipped.
+     */
+    pub fn import_annotations(&mut self, path: &Path) {
+        let imported = match import_file(path) {
+            Ok(imported) => imported,
+            Err(e) => {
+                eprintln!("Error reading the notes of {}: {}", path.display(), e);
+                self.notes_import = Some(Err(format!("{}: {}", path.display(), e)));
+                return;
+            }
+        };
+        let keys: Vec<(String, String, String)> = self
+            .library
+            .iter()
+            .map(|book| (book.get_title(), book.get_author(), book.get_identifier()))
+            .collect();
+        let keys: Vec<(&str, &str, &str)> = keys
+            .iter()
+            .map(|(title, author, identifier)| {
+                (title.as_str(), author.as_str(), identifier.as_str())
+            })
+            .collect();
+
+        let (mut added, mut unmatched, mut not_found) = (0, 0, 0);
+        let mut books: Vec<(usize, Annotations)> = Vec::new();
+        for annotation in imported {
+            let index = match match_book(&keys, &annotation) {
+                Some(index) => index,
+                None => {
+                    unmatched += 1;
+                    continue;
+                }
+            };
+            let book = &self.library[index];
+            let position = match annotation.kind {
+                AnnotationKind::Highlight => {
+                    book.find_passage(annotation.text.as_str(), annotation.page_hint)
+                }
+                AnnotationKind::Bookmark => annotation
+                    .page_hint
+                    .filter(|page| {
+                        *page >= book.get_first_page() && *page <= book.get_book_length()
+                    })
+                    .map(|page| Position {
+                        page,
+                        chapter_position: 0.0,
+                    }),
+            };
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    not_found += 1;
+                    continue;
+                }
+            };
+
+            if !books.iter().any(|(book, _)| *book == index) {
+                let annotations =
+                    load_annotations(book.get_title().as_str(), book.get_author().as_str());
+                books.push((index, annotations));
+            }
+            let annotations = match books.iter_mut().find(|(book, _)| *book == index) {
+                Some((_, annotations)) => annotations,
+                None => continue,
+            };
+            let duplicate = annotations.annotations.iter().any(|saved| {
+                saved.kind == annotation.kind
+                    && saved.page == position.page
+                    && saved.text == annotation.text
+            });
+            if duplicate {
+                continue;
+            }
+            let chapter = if annotation.chapter.is_empty() {
+                book.get_chapter_title(position.page).unwrap_or_default()
+            } else {
+                annotation.chapter
+            };
+            annotations.add(Annotation {
+                kind: annotation.kind,
+                page: position.page,
+                chapter_position: position.chapter_position,
+                chapter,
+                text: annotation.text,
+                note: annotation.note,
+                time: if annotation.time > 0 {
+                    annotation.time
+                } else {
+                    now()
+                },
+            });
+            added += 1;
+        }
+
+        let mut error = None;
+        for (index, annotations) in books.iter() {
+            if let Err(e) = save_annotations(annotations) {
+                eprintln!("Error saving the annotations: {}", e);
+                error = Some(e.to_string());
+            }
+            // The annotations of the book read are loaded again
+            if matches!(&self.annotations, Some((book, _)) if book == index) {
+                self.annotations = None;
+                self.annotation = None;
+                self.load_book_annotations();
+            }
+        }
+        self.notes_import = Some(match error {
+            Some(error) => Err(error),
+            None => Ok(format!(
+                "Notes imported: {}, not in the library: {}, not found in their book: {}",
+                added, unmatched, not_found
+            )),
+        });
+    }
+
+    /**
+     * Help overlay of the reader (list of the keyboard shortcuts)
+     */
+    pub fn is_help_visible(&self) -> bool {
+        self.show_help
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    /**
+     * Progress methods
+     */
+    pub fn get_progress(&self) -> f64 {
+        match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book.get_progress(),
+            None => 0.0,
+        }
+    }
+
+    /**
+     * Go to a fraction of the selected book
+     */
+    pub fn seek(&mut self, progress: f64) {
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        let library = Arc::make_mut(&mut self.library);
+        library[index].seek(progress);
+    }
+
+    pub fn get_position(&self) -> Option<Position> {
+        self.selected
+            .and_then(|index| self.library.get(index))
+            .map(|book| book.get_position())
+    }
+
+    pub fn get_chapter_position(&self) -> f64 {
+        match self.selected.and_then(|index| self.library.get(index)) {
+            Some(book) => book.get_chapter_position(),
+            None => 0.0,
+        }
+    }
+
+    /**
+     * Save the position in the chapter (the library is only changed if the position moved)
+     */
+    pub fn set_chapter_position(&mut self, position: f64) {
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        if (self.library[index].get_chapter_position() - position).abs() < 0.001 {
+            return;
+        }
+        let library = Arc::make_mut(&mut self.library);
+        library[index].set_chapter_position(position);
+    }
+
+    /** EDIT */
+
+    /**
+     * Sets the current page as the editing page, in order to be
+     * displayed in the editor
+     */
+    pub fn set_editing_page(&mut self) {
+        if self.selected.is_none() {
+            return;
+        }
+        // Call the set_editing_page method of the selected book
+        let library = Arc::make_mut(&mut self.library);
+        let book = library
+            .get_mut(self.selected.unwrap_or(1))
+            .expect("Unable to get mut book");
+        let page_string = book.get_page_str(book.get_current_page());
+        match page_string {
+            None => {
+                println!("Error getting page string");
+                return;
+            }
+            Some(page) => {
+                book.set_page_str(page);
+            }
+        }
+    }
+
+    /** METADATA */
+
+    /**
+     * Write the edited metadata into the epub file of the book at index,
+     * then reload the book (or add the copy to the library).
+     * Progress, notes and cover are saved by title: a book cannot take the title
+     * of another one, and a copy with the title of a book is marked " (copy)"
+     */
+    pub fn save_metadata(
+        &mut self,
+        index: usize,
+        mut metadata: BookMetadata,
+        cover: Option<PathBuf>,
+        mode: SaveMode,
+    ) -> Result<(), Box<dyn Error>> {
+        let book = self
+            .library
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("No book at index {}", index))?;
+        let old_slug = book_slug(book.get_title().as_str());
+        let new_slug = book_slug(metadata.title.as_str());
+        let shared = |slug: &str, except: Option<usize>| {
+            self.library
+                .iter()
+                .enumerate()
+                .any(|(i, other)| Some(i) != except && book_slug(&other.get_title()) == slug)
+        };
+
+        match mode {
+            SaveMode::InPlace if new_slug != old_slug => {
+                if shared(new_slug.as_str(), Some(index))
+                    || !artefact_paths(metadata.title.as_str()).is_empty()
+                {
+                    return Err(
+                        format!("Another book is already called \"{}\"", metadata.title).into(),
+                    );
+                }
+            }
+            SaveMode::InPlace => (),
+            SaveMode::Copy => {
+                let title = metadata.title.clone();
+                let mut number = 1;
+                while shared(book_slug(metadata.title.as_str()).as_str(), None)
+                    || !artefact_paths(metadata.title.as_str()).is_empty()
+                {
+                    metadata.title = match number {
+                        1 => format!("{} (copy)", title),
+                        number => format!("{} (copy {})", title, number),
+                    };
+                    number += 1;
+                }
+            }
+        }
+        // The artefacts of the old title stay with the other books that have it
+        let old_title_shared = shared(old_slug.as_str(), Some(index));
+
+        let path = save_metadata(
+            Path::new(book.get_file_path().as_str()),
+            &metadata,
+            cover,
+            mode,
+        )?;
+
+        match mode {
+            SaveMode::InPlace => {
+                if !old_title_shared {
+                    if let Err(e) =
+                        rename_artefacts(book.get_title().as_str(), metadata.title.as_str())
+                    {
+                        eprintln!("Unable to move the files of the book: {}", e);
+                    }
+                }
+                self.reload_book(index, path);
+            }
+            SaveMode::Copy => self.add_book_from_path(path),
+        }
+        Ok(())
+    }
+
+    /**
+     * Replace the book at index with the one read again from path
+     */
+    fn reload_book(&mut self, index: usize, path: PathBuf) {
+        match file_to_book(path) {
+            Some(book) => {
+                let library = Arc::make_mut(&mut self.library);
+                // The cover may have changed, the thumbnail widgets read the cache again
+                // when the book updates or when they are scrolled into view
+                invalidate_thumbnails(&library[index].get_cache_key());
+                library[index] = book;
+            }
+            None => eprintln!("Unable to reload the book"),
+        }
+    }
+
+    /** LIBRARY MANAGEMENT */
+
+    /**
+     * Remove the book at index from the library, keeping its file:
+     * the epub is moved to a folder chosen by the user, outside of the library folder
+     */
+    pub fn remove_book(&mut self, index: usize) {
+        let book = match self.library.get(index) {
+            Some(book) => book.clone(),
+            None => return,
+        };
+        let folder = match open_native_dialog_folder() {
+            Some(folder) => folder,
+            None => {
+                eprintln!("No folder selected");
+                return;
+            }
+        };
+        let library_folder = fs::canonicalize(LIBRARY_PATH).ok();
+        if library_folder.is_some() && fs::canonicalize(&folder).ok() == library_folder {
+            eprintln!("The book must be moved outside of the library folder");
+            return;
+        }
+
+        match move_to_folder(Path::new(book.get_file_path().as_str()), folder.as_path()) {
+            Ok(path) => {
+                println!("Book moved to {}", path.display());
+                self.forget_book(index);
+            }
+            Err(e) => eprintln!("Unable to move the book: {}", e),
+        }
+    }
+
+    /**
+     * Delete the book at index, after confirmation its file is moved to the trash
+     */
+    pub fn delete_book(&mut self, index: usize) {
+        let book = match self.library.get(index) {
+            Some(book) => book.clone(),
+            None => return,
+        };
+        let text = format!("Move \"{}\" to the trash?", book.get_title());
+        if !confirm_dialog("Delete book", text.as_str()) {
+            return;
+        }
+
+        match trash::delete(book.get_file_path()) {
+            Ok(_) => self.forget_book(index),
+            Err(e) => eprintln!("Unable to move the book to the trash: {}", e),
+        }
+    }
+
+    /**
+     * Open the folder containing the book at index in the file manager
+     */
+    pub fn open_book_folder(&self, index: usize) {
+        let book = match self.library.get(index) {
+            Some(book) => book,
+            None => return,
+        };
+        let folder = fs::canonicalize(book.get_file_path())
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        match folder {
+            Some(folder) => {
+                if let Err(e) = opener::open(folder) {
+                    eprintln!("Unable to open the folder: {}", e);
+                }
+            }
+            None => eprintln!("Unable to find the folder of the book"),
+        }
+    }
+
+    /**
+     * Convert the book at index into another file, where the user chooses:
+     * its extension gives the format (EPUB if it is not known)
+     */
+    pub fn convert_book(&mut self, index: usize) {
+        let book = match self.library.get(index) {
+            Some(book) => book,
+            None => return,
+        };
+        let source = match book.get_source() {
+            Some(source) => source,
+            None => return,
+        };
+        let filters: Vec<(&str, &str)> = ConvertFormat::ALL
+            .iter()
+            .map(|format| (format.name(), format.extension()))
+            .collect();
+        let name = format!("{}.epub", book.get_title().replace(' ', "-"));
+        let path = match save_native_dialog(name.as_str(), &filters) {
+            Some(path) => path,
+            None => return,
+        };
+        let (path, format) = converted_path(path);
+        let converted = {
+            let mut source = source.lock().expect("Error while getting lock");
+            convert_book(source.as_mut(), format)
+        };
+        self.conversion =
+            match converted.and_then(|content| fs::write(&path, content).map_err(|e| e.into())) {
+                Ok(_) => Some(Ok(path.display().to_string())),
+                Err(e) => {
+                    eprintln!("Error converting the book: {}", e);
+                    Some(Err(e.to_string()))
+                }
+            };
+    }
+
+    /**
+     * Drop the book at index from the library and clean up its artefacts,
+     * unless another book of the library has the same title (they share them)
+     */
+    fn forget_book(&mut self, index: usize) {
+        let library = Arc::make_mut(&mut self.library);
+        let book = library.remove(index);
+        let slug = book_slug(book.get_title().as_str());
+        if library
+            .iter()
+            .any(|other| book_slug(other.get_title().as_str()) == slug)
+        {
+            invalidate_thumbnails(book.get_cache_key().as_str());
+        } else {
+            remove_artefacts(book.get_title().as_str(), book.get_cache_key().as_str());
+        }
+        self.reading = None;
+
+        self.selected = match self.selected {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+    }
+
+    /** COVER */
+
+    /**
+     * Open a native dialog to select an image, then use it as the cover of the book at index
+     */
+    pub fn set_cover_from_file(&mut self, index: usize) {
+        match open_native_dialog_images() {
+            Some(path) => self.write_cover(index, path.as_path()),
+            None => eprintln!("No file selected"),
+        }
+    }
+
+    /**
+     * Generate a cover (title and author on a coloured background)
+     * and use it as the cover of the book at index
+     */
+    pub fn generate_cover(&mut self, index: usize) {
+        let book = match self.library.get(index) {
+            Some(book) => book.clone(),
+            None => return,
+        };
+        let bytes = match generate_cover(book.get_title().as_str(), book.get_author().as_str()) {
+            Some(bytes) => bytes,
+            None => {
+                eprintln!("Unable to generate cover");
+                return;
+            }
+        };
+        let path =
+            std::env::temp_dir().join(format!("{}-cover.png", book.get_title().replace(' ', "-")));
+        match fs::write(&path, bytes) {
+            Ok(_) => self.write_cover(index, path.as_path()),
+            Err(e) => eprintln!("Unable to save generated cover: {}", e),
+        }
+        let _ = fs::remove_file(path);
+    }
+
+    /**
+     * Write the image into the epub file of the book at index as its cover
+     */
+    fn write_cover(&mut self, index: usize, cover: &Path) {
+        let book = match self.library.get(index) {
+            Some(book) => book.clone(),
+            None => return,
+        };
+        match save_cover(Path::new(book.get_file_path().as_str()), cover) {
+            Ok(path) => self.reload_book(index, path),
+            Err(e) => eprintln!("Error saving cover: {}", e),
+        }
+    }
+
+    /** Save book progress */
+    pub fn save_book_progress(&mut self) {
+        if self.selected.is_none() {
+            return;
+        }
+        let library = Arc::make_mut(&mut self.library);
+        let book = library
+            .get_mut(self.selected.unwrap_or(1))
+            .expect("Unable to get mut book");
+        match book.save_progress() {
+            Ok(_) => println!("Saved"),
+            Err(err) => eprintln!("Not saved, {}", err),
+        }
+    }
+
+    /** READING STATISTICS */
+
+    /**
+     * Follow the reading of the selected book: starts a session when the book is opened,
+     * then records the page left every time the page changes
+     */
+    pub fn track_reading(&mut self) {
+        let index = match self.selected {
+            Some(index) if index < self.library.len() => index,
+            _ => return,
+        };
+        let page = self.library[index].get_current_page();
+
+        match &mut self.reading {
+            Some((book, tracker)) if *book == index => {
+                if tracker.get_page() == page {
+                    return;
+                }
+                let words = self.library[index].get_page_words(tracker.get_page());
+                tracker.visit(page, words, now());
+            }
+            _ => {
+                self.end_reading_session();
+                self.reading = Some((index, ReadingTracker::new(page, now())));
+                return;
+            }
+        }
+        self.save_reading_session();
+    }
+
+    /**
+     * Record the time on the last page and close the session (the book is closed)
+     */
+    pub fn end_reading_session(&mut self) {
+        if let Some((_, tracker)) = &mut self.reading {
+            let page = tracker.get_page();
+            tracker.visit(page, 0, now());
+            self.save_reading_session();
+        }
+        self.reading = None;
+    }
+
+    /**
+     * Save the current session and update the reading speed of the book
+     */
+    fn save_reading_session(&mut self) {
+        let (index, tracker) = match &self.reading {
+            Some((index, tracker)) => (*index, tracker.clone()),
+            None => return,
+        };
+        // Nothing read yet (e.g. the book was only opened)
+        if tracker.get_session().seconds == 0 {
+            return;
+        }
+        let library = Arc::make_mut(&mut self.library);
+        let book = match library.get_mut(index) {
+            Some(book) => book,
+            None => return,
+        };
+        let title = book.get_title();
+        if let Err(e) = save_session(title.as_str(), tracker.get_session()) {
+            eprintln!("Unable to save reading session: {}", e);
+            return;
         }
+        book.set_reading_speed(reading_speed(&load_stats(title.as_str()).sessions));
     }
 
     /** OCR */
@@ -341,7 +1923,7 @@ impl AppState {
                         println!("Page: {:?}", page);
                         match page {
                             Some(page) => {
-                                self.navigate_to_page_index(page);
+                                self.jump_to_page_index(page);
                             }
                             None => {
                                 eprintln!("No page found");
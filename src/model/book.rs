@@ -1,114 +1,192 @@
 use druid::{Data, ImageBuf, Lens};
-use epub::doc::EpubDoc;
 use std::error::Error;
 use std::fmt::Debug;
-use std::fs::File;
-use std::io::{BufReader, Write};
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
-use crate::helper::config::SAVED_PROGRESS_PATH;
-use crate::helper::functions::path_to_bytes;
+use crate::helper::annotations::chapter_title;
+use crate::helper::config::{DEFAULT_READING_SPEED, SAVED_PROGRESS_PATH};
+use crate::helper::functions::{path_to_bytes, resolve_relative_path};
+use crate::helper::history::{History, Position};
+use crate::helper::layout::stylesheet_hrefs;
+use crate::helper::links::fragment_position;
+use crate::helper::location::{location, parse_saved_position, progress, seek, words_read};
+use crate::helper::notes::note_text;
+use crate::helper::opf::PageProgression;
+use crate::helper::search::find_passage;
+use crate::helper::source::{BookFormat, BookSource, SharedSource};
+use crate::helper::speech::chapter_text;
+use crate::helper::stats::{load_stats, reading_speed, time_left};
+use crate::helper::thumbnail::{cache_key, get_thumbnail, ThumbnailSize};
 
 #[derive(Data, Clone, Lens)]
 pub struct Book {
     title: String,
     author: String,
-    cover: Option<Arc<ImageBuf>>,
-    doc: Option<Arc<Mutex<EpubDoc<BufReader<File>>>>>,
+    identifier: String,
+    // Covers are not decoded at startup, the library shows cached thumbnails
+    cover_path: String,
+    // The book file, read through the source of its format
+    source: Option<SharedSource>,
     current_page_index: usize,
     current_page_str: String,
     file_path: String,
     has_progress: bool,
     word_count_chapters: Arc<Vec<i32>>,
     physical_page_range: Option<(i32, i32)>,
+    // Position in the current chapter (0.0 top, 1.0 bottom)
+    chapter_position: f64,
+    // Positions left by following links or search results
+    history: Arc<History>,
+    // Words per minute, from the reading sessions of this book
+    reading_speed: Option<f64>,
+    // Direction the pages are turned, from the spine of the book
+    page_progression: PageProgression,
 }
 
 impl Book {
     pub fn new(
-        doc: EpubDoc<BufReader<File>>,
+        mut source: Box<dyn BookSource>,
         title: String,
         author: String,
         cover_path: String,
         file_path: String,
         word_count_chapters: Arc<Vec<i32>>,
     ) -> Self {
-        // Extract cover image from cover_path
-        let cover = if cover_path.is_empty() {
-            None
+        let identifier = source.metadata("identifier").unwrap_or_default();
+        let page_progression = source.page_progression();
+        // Comics start at their first image, the other books after their title page
+        let first_page = if source.format() == BookFormat::Comic {
+            0
         } else {
-            let cover_path = PathBuf::from(cover_path);
-            let bytes = path_to_bytes(cover_path).unwrap(); //Unwrap is safe because we already checked if the path is empty
-            match ImageBuf::from_data(bytes.as_slice()) {
-                Ok(cover) => Some(Arc::new(cover)),
-                Err(e) => {
-                    eprintln!("Error loading cover: {}", e);
-                    None
-                }
-            }
+            1
         };
+        let reading_speed = reading_speed(&load_stats(title.as_str()).sessions);
 
         // Check if the book has a title.json file in progress folder
         let progress = Path::new(SAVED_PROGRESS_PATH)
             .join(title.clone().replace(" ", "-") + ".json")
             .exists();
 
-        let mut current_page_index = 1;
+        let mut current_page_index = first_page;
+        let mut chapter_position = 0.0;
         if progress {
             let saved_progress_path =
                 SAVED_PROGRESS_PATH.to_owned() + &title.as_str().replace(" ", "-") + ".json";
-            let file = File::open(saved_progress_path);
-            current_page_index = match file {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-                    let json: usize = serde_json::from_reader(reader).unwrap(); //Unwrap is safe because we already checked if the file exists
-                    json
-                }
-                Err(e) => {
-                    eprintln!("Error getting progress: {}", e);
-                    1
-                }
+            match fs::read_to_string(saved_progress_path) {
+                Ok(json) => match parse_saved_position(json.as_str()) {
+                    Some(position) => {
+                        current_page_index = position.page;
+                        chapter_position = position.chapter_position.clamp(0.0, 1.0);
+                    }
+                    None => eprintln!("Error getting progress: invalid progress file"),
+                },
+                Err(e) => eprintln!("Error getting progress: {}", e),
             }
         }
 
         // Create book
         Self {
-            doc: Some(Arc::new(Mutex::new(doc))),
+            source: Some(Arc::new(Mutex::new(source))),
             title,
             author,
-            cover,
+            identifier,
+            cover_path,
             current_page_index,
             current_page_str: String::new(),
             file_path,
             has_progress: progress,
             word_count_chapters,
             physical_page_range: None,
+            chapter_position,
+            history: Arc::new(History::default()),
+            reading_speed,
+            page_progression,
         }
     }
 
     pub fn new_empty() -> Self {
         Self {
-            doc: None,
+            source: None,
             title: String::new(),
             author: String::new(),
-            cover: None,
+            identifier: String::new(),
+            cover_path: String::new(),
             current_page_index: 0,
             current_page_str: String::new(),
             file_path: String::new(),
             has_progress: false,
             word_count_chapters: Arc::new(Vec::new()),
             physical_page_range: None,
+            chapter_position: 0.0,
+            history: Arc::new(History::default()),
+            reading_speed: None,
+            page_progression: PageProgression::Ltr,
         }
     }
 
     /** Getters */
+
+    /**
+     * Decode the full size cover (the library uses get_thumbnail instead)
+     */
     pub fn get_image_buf(&self) -> Option<Arc<ImageBuf>> {
-        self.cover.as_ref().cloned()
+        if self.cover_path.is_empty() {
+            return None;
+        }
+        let bytes = path_to_bytes(PathBuf::from(self.cover_path.as_str()))?;
+        match ImageBuf::from_data(bytes.as_slice()) {
+            Ok(cover) => Some(Arc::new(cover)),
+            Err(e) => {
+                eprintln!("Error loading cover: {}", e);
+                None
+            }
+        }
+    }
+
+    /**
+     * Get the downscaled cover, from the thumbnail cache
+     */
+    pub fn get_thumbnail(&self, size: ThumbnailSize) -> Option<Arc<ImageBuf>> {
+        if self.cover_path.is_empty() {
+            return None;
+        }
+        get_thumbnail(
+            self.get_cache_key().as_str(),
+            self.cover_path.as_str(),
+            size,
+        )
+    }
+
+    pub fn get_cover_path(&self) -> String {
+        self.cover_path.clone()
     }
 
-    pub fn get_doc(&self) -> Option<Arc<Mutex<EpubDoc<BufReader<File>>>>> {
-        self.doc.as_ref().cloned()
+    /**
+     * Key of the book in the caches, from its identifier and its file
+     */
+    pub fn get_cache_key(&self) -> String {
+        cache_key(format!("{}|{}", self.identifier, self.file_path).as_str())
+    }
+
+    pub fn get_identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    pub fn get_source(&self) -> Option<SharedSource> {
+        self.source.as_ref().cloned()
+    }
+
+    /**
+     * Format of the book file (EPUB if the book has no file)
+     */
+    pub fn get_format(&self) -> BookFormat {
+        match &self.source {
+            Some(source) => source.lock().expect("Error while getting lock").format(),
+            None => BookFormat::Epub,
+        }
     }
 
     pub fn get_title(&self) -> String {
@@ -119,9 +197,22 @@ impl Book {
         self.author.clone()
     }
 
+    /**
+     * Language of the book (dc:language), e.g. "en" or "fr-CA"
+     */
+    pub fn get_language(&self) -> Option<String> {
+        let source = self.get_source()?;
+        let source = source.lock().expect("Error while getting lock");
+        source.metadata("language")
+    }
+
     pub fn get_book_length(&self) -> usize {
-        match &self.doc {
-            Some(doc) => doc.lock().expect("Error while getting lock").spine.len() - 1,
+        match &self.source {
+            Some(source) => source
+                .lock()
+                .expect("Error while getting lock")
+                .len()
+                .saturating_sub(1),
             None => 0,
         }
     }
@@ -134,6 +225,145 @@ impl Book {
         self.word_count_chapters.clone()
     }
 
+    /** Reading time */
+
+    /**
+     * Words per minute of the reader (a default speed until it is known)
+     */
+    pub fn get_reading_speed(&self) -> f64 {
+        self.reading_speed.unwrap_or(DEFAULT_READING_SPEED)
+    }
+
+    pub fn set_reading_speed(&mut self, reading_speed: Option<f64>) {
+        self.reading_speed = reading_speed;
+    }
+
+    /**
+     * Words of the page at page_index (0 if unknown)
+     */
+    pub fn get_page_words(&self, page_index: usize) -> i64 {
+        self.word_count_chapters
+            .get(page_index)
+            .map_or(0, |words| *words as i64)
+    }
+
+    /**
+     * Estimated minutes to finish the current chapter
+     */
+    pub fn get_chapter_time_left(&self) -> i64 {
+        let words = self.get_page_words(self.current_page_index);
+        let words_left = (words as f64 * (1.0 - self.chapter_position)).round() as i64;
+        time_left(words_left, self.get_reading_speed())
+    }
+
+    /**
+     * Estimated minutes to finish the book, from the current position
+     */
+    pub fn get_book_time_left(&self) -> i64 {
+        let words = self
+            .word_count_chapters
+            .iter()
+            .map(|words| *words as i64)
+            .sum::<i64>();
+        let words_left = words - self.get_words_read();
+        time_left(words_left, self.get_reading_speed())
+    }
+
+    /** Progress */
+
+    pub fn get_chapter_position(&self) -> f64 {
+        self.chapter_position
+    }
+
+    pub fn set_chapter_position(&mut self, position: f64) {
+        self.chapter_position = position.clamp(0.0, 1.0);
+    }
+
+    pub fn get_words_read(&self) -> i64 {
+        words_read(
+            &self.word_count_chapters,
+            self.current_page_index,
+            self.chapter_position,
+        )
+    }
+
+    /**
+     * Fraction of the book read, from the words of the chapters
+     */
+    pub fn get_progress(&self) -> f64 {
+        progress(
+            &self.word_count_chapters,
+            self.current_page_index,
+            self.chapter_position,
+        )
+    }
+
+    /**
+     * Current location and number of locations of the book
+     */
+    pub fn get_location(&self) -> (i64, i64) {
+        location(
+            &self.word_count_chapters,
+            self.current_page_index,
+            self.chapter_position,
+        )
+    }
+
+    /**
+     * Go to a fraction of the book (chapter and position in the chapter)
+     */
+    pub fn seek(&mut self, progress: f64) {
+        let (page, position) = seek(&self.word_count_chapters, progress);
+        let page = page.clamp(self.get_first_page(), self.get_book_length().max(1));
+        self.set_page(page);
+        self.set_chapter_position(position);
+    }
+
+    /**
+     * Whether the pages are turned from right to left (the next page is on the left)
+     */
+    pub fn is_rtl(&self) -> bool {
+        self.page_progression == PageProgression::Rtl
+    }
+
+    /**
+     * Turn the pages the other way (a manga without its direction in the book)
+     */
+    pub fn set_rtl(&mut self, rtl: bool) {
+        self.page_progression = if rtl {
+            PageProgression::Rtl
+        } else {
+            PageProgression::Ltr
+        };
+    }
+
+    /**
+     * Whether the pages of the book are images, shown by the image viewer
+     */
+    pub fn is_comic(&self) -> bool {
+        self.get_format() == BookFormat::Comic
+    }
+
+    /**
+     * First page shown in the reader: the first image of a comic, otherwise the page
+     * after the title page
+     */
+    pub fn get_first_page(&self) -> usize {
+        if self.is_comic() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /**
+     * Whether the sections of the book are the pages of the original (a PDF),
+     * the section is then shown as the page number
+     */
+    pub fn has_original_pages(&self) -> bool {
+        self.get_format() == BookFormat::Pdf
+    }
+
     pub fn get_current_page_str(&self) -> String {
         self.current_page_str.clone()
     }
@@ -153,13 +383,14 @@ impl Book {
     }
 
     pub fn has_prev_page(&self) -> bool {
-        self.current_page_index > 1
+        self.current_page_index > self.get_first_page()
     }
 
     pub fn next_page(&mut self) {
         self.reset_page_range();
         if self.has_next_page() {
             self.current_page_index += 1;
+            self.chapter_position = 0.0;
         }
     }
 
@@ -167,68 +398,188 @@ impl Book {
         self.reset_page_range();
         if self.has_prev_page() {
             self.current_page_index -= 1;
+            self.chapter_position = 0.0;
         }
     }
 
     pub fn set_page(&mut self, page_index: usize) {
         self.reset_page_range();
         self.current_page_index = page_index;
+        self.chapter_position = 0.0;
+    }
+
+    /** History */
+
+    pub fn get_position(&self) -> Position {
+        Position {
+            page: self.current_page_index,
+            chapter_position: self.chapter_position,
+        }
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.set_page(position.page);
+        self.set_chapter_position(position.chapter_position);
     }
 
     /**
-     * doc.resources
-     * It’s a HashMap<a: String, (b: PathBuf, c: String)> where ‘a’ is the resource id, ‘b’ is the resource full path and ‘c’ is the resource mimetype
-     * Get the resource id of the resource which the resource full path ends with the link
+     * Jump to a page (link or search result), the position left is saved in the history
      */
-    pub fn navigate_to(&mut self, link: Rc<String>) {
-        self.reset_page_range();
-        let binding = self.get_doc().expect("Error while getting arc");
-        let doc = binding.lock().expect("Error while getting lock");
+    pub fn jump_to_page(&mut self, page_index: usize) {
+        if page_index != self.current_page_index {
+            let position = self.get_position();
+            Arc::make_mut(&mut self.history).record(position);
+        }
+        self.set_page(page_index);
+    }
 
-        // From "chapter_001.xhtml" to resource_id in the spine
-        let resource_id = doc
-            .resources
-            .iter()
-            .find(|(_, (path, _))| path.ends_with(link.as_str()))
-            .map(|(id, _)| id);
-
-        match resource_id {
-            Some(resource_id) => {
-                let page_index = doc.resource_id_to_chapter(resource_id);
-                println!(
-                    "Navigating to link: {} (page_index: {:?})",
-                    link, page_index
-                );
-                self.set_page(page_index.unwrap_or(1))
-            }
+    /**
+     * Jump to a position (link to an anchor), the position left is saved in the history
+     */
+    pub fn jump_to(&mut self, position: Position) {
+        let current = self.get_position();
+        if position != current {
+            Arc::make_mut(&mut self.history).record(current);
+        }
+        self.set_position(position);
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.history.can_go_back()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.history.can_go_forward()
+    }
+
+    /**
+     * Return to the position before the last jump
+     */
+    pub fn go_back(&mut self) {
+        let current = self.get_position();
+        if let Some(position) = Arc::make_mut(&mut self.history).back(current) {
+            self.set_position(position);
+        }
+    }
+
+    /**
+     * Go again to the position left by going back
+     */
+    pub fn go_forward(&mut self) {
+        let current = self.get_position();
+        if let Some(position) = Arc::make_mut(&mut self.history).forward(current) {
+            self.set_position(position);
+        }
+    }
+
+    /**
+     * Go to the page at the path of the link (e.g. "OEBPS/chapter_001.xhtml")
+     * and scroll to the element of the fragment if there is one
+     */
+    pub fn navigate_to(&mut self, path: &Path, fragment: Option<&str>) {
+        let page_index = {
+            let source = self.get_source().expect("Error while getting arc");
+            let source = source.lock().expect("Error while getting lock");
+            source.section_index(path)
+        };
+
+        let page_index = match page_index {
+            Some(page_index) => page_index,
             None => {
-                eprintln!("Error navigating to link: {}", link);
+                eprintln!("Error navigating to link: {}", path.display());
+                return;
+            }
+        };
+        let chapter_position = fragment
+            .and_then(|fragment| {
+                let position = self
+                    .get_page_str(page_index)
+                    .and_then(|html| fragment_position(html.as_str(), fragment));
+                if position.is_none() {
+                    eprintln!("Anchor not found: {}#{}", path.display(), fragment);
+                }
+                position
+            })
+            .unwrap_or(0.0);
+        self.jump_to(Position {
+            page: page_index,
+            chapter_position,
+        });
+    }
+
+    /**
+     * Text of the note with the given id in the document at path (e.g. OEBPS/notes.xhtml)
+     */
+    pub fn get_note(&self, path: &Path, fragment: &str) -> Option<String> {
+        let source = self.get_source()?;
+        let mut source = source.lock().expect("Error while getting lock");
+        match source.resource_str(path) {
+            Ok(html) => note_text(html.as_str(), fragment),
+            Err(e) => {
+                eprintln!("Error reading note {}: {}", path.display(), e);
+                None
             }
         }
     }
 
+    /**
+     * Content of the stylesheets linked by a page (html is the page itself)
+     */
+    pub fn get_page_stylesheets(&self, page_index: usize, html: &str) -> Vec<String> {
+        let page_path = match self.get_page_path(page_index) {
+            Some(page_path) => page_path,
+            None => return Vec::new(),
+        };
+        let source = match self.get_source() {
+            Some(source) => source,
+            None => return Vec::new(),
+        };
+        let mut source = source.lock().expect("Error while getting lock");
+        stylesheet_hrefs(html)
+            .iter()
+            .filter_map(|href| {
+                let path = resolve_relative_path(&page_path, href.as_str());
+                match source.resource_str(&path) {
+                    Ok(css) => Some(css),
+                    Err(e) => {
+                        eprintln!("Error reading stylesheet {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Full path in the book of the document of a page
+     * Example: OEBPS/chapter_001.xhtml
+     */
+    pub fn get_page_path(&self, page_index: usize) -> Option<PathBuf> {
+        let source = self.get_source()?;
+        let source = source.lock().expect("Error while getting lock");
+        source.section_path(page_index)
+    }
+
     /**
      * Get the current doc path
      * Example: OEBPS/chapter_001.xhtml (relative path to the epub file)
      */
     pub fn get_current_doc_path(&self) -> Option<PathBuf> {
-        let doc = self.get_doc().expect("Error while getting arc");
-        let doc = doc.lock().expect("Error while getting lock");
-        match doc.get_current_path() {
-            Ok(path) => Some(path),
-            Err(e) => {
-                eprintln!("Error getting current doc path: {}", e);
-                None
-            }
+        let path = self.get_page_path(self.current_page_index);
+        if path.is_none() {
+            eprintln!(
+                "Error getting current doc path: {}",
+                self.current_page_index
+            );
         }
+        path
     }
 
     pub fn get_page_str(&self, page_index: usize) -> Option<String> {
         if page_index > 0 && page_index <= self.get_book_length() {
-            let doc = self.get_doc().expect("Error while getting arc");
-            let mut doc_mut = doc.lock().expect("Error while getting lock");
-            doc_mut.set_current_page(page_index).unwrap();
-            match doc_mut.get_current_str() {
+            let source = self.get_source().expect("Error while getting arc");
+            let mut source = source.lock().expect("Error while getting lock");
+            match source.section(page_index) {
                 Ok(current_str) => Some(current_str),
                 Err(err) => {
                     println!("{:?}", err);
@@ -240,17 +591,51 @@ impl Book {
         }
     }
 
+    /**
+     * Decode the image of a page of a comic
+     */
+    pub fn get_page_image(&self, page_index: usize) -> Option<Arc<ImageBuf>> {
+        let bytes = {
+            let source = self.get_source()?;
+            let mut source = source.lock().expect("Error while getting lock");
+            source.page_image(page_index)?
+        };
+        match ImageBuf::from_data(bytes.as_slice()) {
+            Ok(image) => Some(Arc::new(image)),
+            Err(e) => {
+                eprintln!("Error loading page {}: {}", page_index, e);
+                None
+            }
+        }
+    }
+
+    /**
+     * Title of the chapter of a page, from the table of contents or from the page itself
+     */
+    pub fn get_chapter_title(&self, page_index: usize) -> Option<String> {
+        let toc = {
+            let source = self.get_source()?;
+            let source = source.lock().expect("Error while getting lock");
+            source.toc()
+        };
+        let html = self.get_page_str(page_index)?;
+        chapter_title(
+            &toc,
+            self.get_page_path(page_index).as_deref(),
+            html.as_str(),
+        )
+    }
+
     /**
      * Get the page index from ocr text.
      */
     pub fn get_page_from_ocr_text(&self, text: String) -> Option<usize> {
-        // Iterate through all the text files in the epub doc
-        let doc = self.get_doc().expect("Error while getting arc");
-        let mut doc_mut = doc.lock().expect("Error while getting lock");
+        // Iterate through all the sections of the book
+        let source = self.get_source().expect("Error while getting arc");
+        let mut source_mut = source.lock().expect("Error while getting lock");
         let mut page_index = 1;
-        while page_index <= doc_mut.spine.len() - 1 {
-            doc_mut.set_current_page(page_index).unwrap();
-            match doc_mut.get_current_str() {
+        while page_index < source_mut.len() {
+            match source_mut.section(page_index) {
                 Ok(current_str) => {
                     if current_str.contains(&text) {
                         println!("Found text in page: {}", page_index);
@@ -264,7 +649,52 @@ impl Book {
             }
             page_index += 1;
         }
-        return None;
+        drop(source_mut);
+        // The OCR may have misread some words
+        self.find_passage(text.as_str(), None)
+            .map(|position| position.page)
+    }
+
+    /**
+     * Text of the pages of the book (page index, text), to search passages in
+     */
+    pub fn get_pages_text(&self) -> Vec<(usize, String)> {
+        (1..=self.get_book_length())
+            .filter_map(|page| Some((page, chapter_text(self.get_page_str(page)?.as_str()))))
+            .collect()
+    }
+
+    /**
+     * Position of a passage in the book, even if some of its words differ,
+     * looking first at the page of the hint
+     */
+    pub fn find_passage(&self, text: &str, hint: Option<usize>) -> Option<Position> {
+        find_passage(&self.get_pages_text(), text, hint).map(|found| Position {
+            page: found.page,
+            chapter_position: found.position,
+        })
+    }
+
+    /**
+     * Find the next page containing the text (case insensitive, whatever its spaces),
+     * starting after the current page and continuing from the start of the book.
+     * The text of the pages is searched, not their markup
+     */
+    pub fn find_text(&self, text: &str) -> Option<usize> {
+        let words = |text: &str| text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let text = words(text.to_lowercase().as_str());
+        let length = self.get_book_length();
+        if length == 0 || text.is_empty() {
+            return None;
+        }
+        (1..=length)
+            .map(|offset| (self.current_page_index + offset - 1) % length + 1)
+            .find(|page| {
+                self.get_page_str(*page).is_some_and(|page_str| {
+                    let page_text = chapter_text(page_str.as_str()).to_lowercase();
+                    words(page_text.as_str()).contains(text.as_str())
+                })
+            })
     }
 
     /**
@@ -296,10 +726,11 @@ impl Book {
     }
 
     /**
-     * Save the current page to a json file (for keeping track of the reading page)
+     * Save the current page and the position in it to a json file
+     * (for keeping track of the reading page)
      */
     pub fn save_progress(&mut self) -> Result<(), Box<dyn Error>> {
-        let json = serde_json::to_string(&self.current_page_index)?;
+        let json = serde_json::to_string(&self.get_position())?;
         let saved_progress_path =
             SAVED_PROGRESS_PATH.to_owned() + &self.title.as_str().replace(" ", "-") + ".json";
         let mut file = match File::create(saved_progress_path){
@@ -328,7 +759,7 @@ impl Debug for Book {
         f.debug_struct("Book")
             .field("title", &self.title)
             .field("author", &self.author)
-            .field("cover", &self.cover)
+            .field("cover_path", &self.cover_path)
             .field("current_page_index", &self.current_page_index)
             .field("current_page_str", &self.current_page_str)
             .field("word_count", &self.word_count_chapters)
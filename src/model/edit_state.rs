@@ -1,11 +1,21 @@
 use super::{app_state::AppState, book::Book};
+use crate::helper::{
+    epub_writer::write_epub,
+    opf::{apply_metadata, find_opf_path, read_entry_string, read_metadata, CONTAINER_PATH},
+    source::BookFormat,
+    xml::XmlDocument,
+};
 use druid::{widget::ScopeTransfer, Data, Lens};
-use std::{ffi::OsString, path::PathBuf, sync::Arc};
-
+use druid_widget_nursery::navigator::ViewController;
 use std::{
+    error::Error,
+    fmt,
     fs::{self, File},
-    io::Write,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
+
 use zip::ZipArchive;
 
 // this holds state that will be used when on the edit page
@@ -61,164 +71,125 @@ impl ScopeTransfer for EditTransfer {
 
             println!("Library: {:?}", inner.library);
 
-            // Update the selected book html file with the changes made in the edit page
-            let page_to_update = state.book.get_current_page_str();
-            let file_to_update = state.book.get_current_doc_path();
-            match file_to_update {
-                None => {
-                    eprintln!("Unable to update file");
+            // Write the edited page into a copy of the book, added to the library.
+            // The editor is left only once the copy is saved, otherwise it shows the error.
+            match save_edited_book(&state.book) {
+                Ok(new_epub_path) => {
+                    inner.set_edit_error(None);
+                    inner.add_or_reload_book(new_epub_path);
+                    inner.pop_view();
                 }
-                Some(file) => {
-                    let zip_path = "src/library/copy.zip";
-                    let new_zip_path = "src/library/new.zip";
-                    let dir_path = "src/library/new";
-                    let edited_html = page_to_update.as_str();
-
-                    let path_buf = file.to_path_buf();
-                    let file_path = match path_buf.as_os_str().to_str(){
-                        Some(path) => path,
-                        None => {
-                            eprintln!("Error encountered while converting file path to string!");
-                            return;
-                        }
-                    };
-                    let dir_file_path = [dir_path, file_path].join("/");
-
-                    let epub_path = state.book.get_file_path();
-                    let new_epub_path = epub_path.replace(".epub", "-edit.epub");
-
-                    //Converting epub into zip
-                    match fs::copy(epub_path, zip_path){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while copying file!");
-                            return;
-                        }
-                    };
-
-                    let zip_file = match File::options()
-                        .write(true)
-                        .read(true)
-                        .open(zip_path){
-                            Ok(file) => file,
-                            Err(_) => {
-                                eprintln!("Error encountered while opening zip file!");
-                                return;
-                            }
-                        };
-
-                    //Extract the zip
-                    let mut archive = match ZipArchive::new(zip_file){
-                        Ok(archive) => archive,
-                        Err(_) => {
-                            eprintln!("Error encountered while opening zip file!");
-                            return;
-                        }
-                    };
-                    match archive.extract(dir_path){
-                            Ok(_) => (),
-                            Err(_) => {
-                                eprintln!("Error encountered while extracting zip file!");
-                                return;
-                            }
-                    };
-                        
-
-                    //Replace old file with the edit one
-                    let mut edited_file = match File::create(dir_file_path.as_str()){
-                        Ok(file) => file,
-                        Err(_) => {
-                            eprintln!("Error encountered while editing old file!");
-                            return;
-                        }
-                    };
-                    match edited_file.write_all(edited_html.as_bytes()){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while editing old file!");
-                            return;
-                        }
-                    };
-
-                    //Replace the title
-                    let dir_metadata_path = [dir_path, "OEBPS/package.opf"].join("/");
-                    let edited_metadata_string = match fs::read_to_string(dir_metadata_path.as_str()){
-                        Ok(string) => string,
-                        Err(_) => {
-                            eprintln!("Error encountered while editing metadata file!");
-                            return;
-                        }
-                    };
-                    let mut metadata_file = match File::create(dir_metadata_path.as_str()){
-                        Ok(file) => file,
-                        Err(_) => {
-                            eprintln!("Error encountered while editing metadata file!");
-                            return;
-                        }
-                    };
-                        
-                    match metadata_file.write_all(
-                            edited_metadata_string
-                                .replace("</dc:title>", " (edit)</dc:title>")
-                                .as_bytes(),
-                    ){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while editing metadata file!");
-                            return;
-                        }
-                    };
-                        
-
-                    //Converting directory into zip
-                    let new_zip_path_buf = PathBuf::from(OsString::from(new_zip_path));
-                    let dir_path_buf = PathBuf::from(OsString::from(dir_path));
-                    match zip_extensions::write::zip_create_from_directory(
-                        &new_zip_path_buf,
-                        &dir_path_buf,
-                    ){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error while zipping the directory");
-                            return;
-                        }
-                    };
-
-                    //Converting zip into epub
-                    match fs::copy(new_zip_path, new_epub_path.clone()){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while copying file!");
-                            return;
-                        }
-                    };
-
-                    //Delete unnecesesary file
-                    match fs::remove_file(zip_path){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while deleting zip file!");
-                            return;
-                        }
-                    };
-                    match fs::remove_file(new_zip_path){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while deleting zip file!");
-                            return;
-                        }
-                    };
-                    match fs::remove_dir_all(dir_path){
-                        Ok(_) => (),
-                        Err(_) => {
-                            eprintln!("Error encountered while deleting directory!");
-                            return;
-                        }
-                    };
-
-                    inner.add_book_from_path(PathBuf::from(new_epub_path))
+                Err(e) => {
+                    eprintln!("Error while saving the edited book: {}", e);
+                    inner.set_edit_error(Some(e.to_string()));
                 }
             }
         }
     }
 }
+
+/**
+ * Why an edited book could not be saved
+ */
+#[derive(Debug)]
+pub enum SaveError {
+    // Only the pages of an EPUB file can be written back
+    NotEpub,
+    // The edited page is not a page of the book
+    NoPage,
+    // The book file or its package document could not be read
+    Read(Box<dyn Error>),
+    // The copy of the book could not be written
+    Write(Box<dyn Error>),
+    // The copy could not be moved into the library
+    Rename(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::NotEpub => write!(f, "only EPUB books can be edited"),
+            SaveError::NoPage => write!(f, "the edited page is not in the book"),
+            SaveError::Read(e) => write!(f, "unable to read the book ({})", e),
+            SaveError::Write(e) => write!(f, "unable to write the edited book ({})", e),
+            SaveError::Rename(e) => {
+                write!(f, "unable to move the edited book into the library ({})", e)
+            }
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+/**
+ * Save the edited page of the book into a copy of its file ("-edit.epub").
+ * Returns the path of the copy.
+ */
+pub fn save_edited_book(book: &Book) -> Result<PathBuf, SaveError> {
+    if book.get_format() != BookFormat::Epub {
+        return Err(SaveError::NotEpub);
+    }
+    let page_path = book.get_current_doc_path().ok_or(SaveError::NoPage)?;
+    save_edited_page(
+        Path::new(book.get_file_path().as_str()),
+        &page_path,
+        book.get_current_page_str().as_str(),
+    )
+}
+
+/**
+ * Write a copy of the EPUB file ("-edit.epub") with the page at page_path replaced by html
+ * and " (edit)" after its title. Returns the path of the copy.
+ * The copy is written in a temporary folder next to it, removed whatever happens, then
+ * renamed into place (over the copy of a previous save): the library never holds a half
+ * written book, and two saves never share their files. The temporary folder is hidden,
+ * the library scan skips it.
+ */
+pub fn save_edited_page(
+    epub_path: &Path,
+    page_path: &Path,
+    html: &str,
+) -> Result<PathBuf, SaveError> {
+    let page_name = page_path.to_string_lossy().replace('\\', "/");
+    let mut archive = File::open(epub_path)
+        .map_err(|e| e.into())
+        .and_then(|file| ZipArchive::new(file).map_err(|e| e.into()))
+        .map_err(SaveError::Read)?;
+    if archive.by_name(page_name.as_str()).is_err() {
+        return Err(SaveError::NoPage);
+    }
+    let (opf_path, opf) = edited_package(&mut archive).map_err(SaveError::Read)?;
+    let replaced = vec![(page_name, html.as_bytes().to_vec()), (opf_path, opf)];
+
+    let stem = epub_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let new_epub_path = epub_path.with_file_name(format!("{}-edit.epub", stem));
+    let folder = match new_epub_path.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    // In the folder of the library, so that the rename stays on the same file system
+    let tmp_dir = tempfile::Builder::new()
+        .prefix(".edit-")
+        .tempdir_in(&folder)
+        .map_err(|e| SaveError::Write(e.into()))?;
+    let tmp_path = tmp_dir.path().join("book.epub");
+    write_epub(epub_path, &tmp_path, &replaced).map_err(SaveError::Write)?;
+    fs::rename(&tmp_path, &new_epub_path).map_err(SaveError::Rename)?;
+    Ok(new_epub_path)
+}
+
+/**
+ * Package document of the book with " (edit)" after its title, and its path in the archive
+ */
+fn edited_package(archive: &mut ZipArchive<File>) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+    let container = read_entry_string(archive, CONTAINER_PATH)?;
+    let opf_path = find_opf_path(container.as_str()).ok_or("No package document found")?;
+    let mut opf = XmlDocument::parse(read_entry_string(archive, opf_path.as_str())?.as_str())?;
+    let mut metadata = read_metadata(&opf);
+    metadata.title = format!("{} (edit)", metadata.title);
+    apply_metadata(&mut opf, &metadata);
+    Ok((opf_path, opf.to_string()?.into_bytes()))
+}
@@ -0,0 +1,114 @@
+use super::app_state::AppState;
+use crate::helper::opf::{load_metadata, BookMetadata, SaveMode};
+use druid::{widget::ScopeTransfer, Data, Lens};
+use druid_widget_nursery::navigator::ViewController;
+use std::path::{Path, PathBuf};
+
+/**
+ * State of the metadata editor.
+ * Lists are edited as plain strings (authors separated by "&", tags by ",")
+ * and converted back into a BookMetadata when saving.
+ */
+#[derive(Clone, Data, Lens, Debug)]
+pub struct MetadataState {
+    pub index: usize,
+    pub title: String,
+    pub authors: String,
+    pub series: String,
+    pub series_index: String,
+    pub tags: String,
+    pub description: String,
+    pub language: String,
+    // New cover image chosen by the user (if any)
+    pub cover_path: Option<String>,
+    // Set when one of the save buttons is clicked, until the save is written back
+    pub save_mode: Option<SaveMode>,
+}
+
+impl MetadataState {
+    pub fn new(data: AppState) -> Self {
+        let index = data.get_selected().unwrap_or(0);
+        let book = &data.get_library()[index];
+        let metadata = match load_metadata(Path::new(book.get_file_path().as_str())) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("Error loading metadata: {}", e);
+                BookMetadata {
+                    title: book.get_title(),
+                    authors: vec![book.get_author()],
+                    ..Default::default()
+                }
+            }
+        };
+        Self::from_metadata(index, metadata)
+    }
+
+    pub fn from_metadata(index: usize, metadata: BookMetadata) -> Self {
+        Self {
+            index,
+            title: metadata.title,
+            authors: metadata.authors.join(" & "),
+            series: metadata.series.unwrap_or_default(),
+            series_index: metadata.series_index.unwrap_or_default(),
+            tags: metadata.tags.join(", "),
+            description: metadata.description,
+            language: metadata.language,
+            cover_path: None,
+            save_mode: None,
+        }
+    }
+
+    pub fn to_metadata(&self) -> BookMetadata {
+        let split = |s: &str, separator: char| -> Vec<String> {
+            s.split(separator)
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        let optional = |s: &str| -> Option<String> {
+            match s.trim() {
+                "" => None,
+                s => Some(s.to_string()),
+            }
+        };
+        BookMetadata {
+            title: self.title.trim().to_string(),
+            authors: split(self.authors.as_str(), '&'),
+            series: optional(self.series.as_str()),
+            series_index: optional(self.series_index.as_str()),
+            tags: split(self.tags.as_str(), ','),
+            description: self.description.trim().to_string(),
+            language: self.language.trim().to_string(),
+        }
+    }
+}
+
+pub struct MetadataTransfer;
+
+impl ScopeTransfer for MetadataTransfer {
+    type In = AppState;
+
+    type State = MetadataState;
+
+    fn read_input(&self, state: &mut Self::State, _inner: &Self::In) {
+        // The form keeps its own values until it is saved, a save is written back once
+        state.save_mode = None;
+    }
+
+    fn write_back_input(&self, state: &Self::State, inner: &mut Self::In) {
+        if let Some(mode) = state.save_mode {
+            // The editor is left only once the book is saved, otherwise it shows the error
+            let cover = state.cover_path.as_ref().map(PathBuf::from);
+            match inner.save_metadata(state.index, state.to_metadata(), cover, mode) {
+                Ok(()) => {
+                    inner.set_metadata_error(None);
+                    inner.pop_view();
+                }
+                Err(e) => {
+                    eprintln!("Error saving metadata: {}", e);
+                    inner.set_metadata_error(Some(e.to_string()));
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod app_state;
 pub mod book;
 pub mod edit_state;
+pub mod metadata_state;
 pub mod ui_view;
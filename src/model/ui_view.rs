@@ -5,4 +5,7 @@ pub enum UiView {
     Library,
     BookRead,
     BookEdit,
+    MetadataEdit,
+    Stats,
+    Vocabulary,
 }
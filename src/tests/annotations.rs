@@ -0,0 +1,217 @@
+/* ANNOTATIONS TESTS */
+
+/**
+ * Annotations of a test book: a bookmark and two highlights, one with a note
+ */
+#[cfg(test)]
+fn test_annotations() -> crate::helper::annotations::Annotations {
+    use crate::helper::annotations::{Annotation, AnnotationKind, Annotations};
+
+    let mut annotations = Annotations {
+        title: "Annotations Test Book".to_string(),
+        author: "Jane <Doe>".to_string(),
+        annotations: Vec::new(),
+    };
+    annotations.add(Annotation {
+        kind: AnnotationKind::Highlight,
+        page: 5,
+        chapter_position: 0.5,
+        chapter: "Chapter Two".to_string(),
+        text: "It was a dark & stormy night.".to_string(),
+        note: "A classic opening.".to_string(),
+        time: 0,
+    });
+    annotations.add(Annotation {
+        kind: AnnotationKind::Bookmark,
+        page: 2,
+        chapter_position: 0.25,
+        chapter: "Chapter One".to_string(),
+        text: String::new(),
+        note: String::new(),
+        time: 0,
+    });
+    annotations.add(Annotation {
+        kind: AnnotationKind::Highlight,
+        page: 5,
+        chapter_position: 0.1,
+        chapter: "Chapter Two".to_string(),
+        text: "The rain fell.".to_string(),
+        note: String::new(),
+        time: 0,
+    });
+    annotations
+}
+
+/**
+ * Tests that the annotations are kept in the order of the book and saved with the book
+ */
+#[test]
+fn annotations_saved_in_book_order() {
+    use crate::helper::{
+        annotations::{load_annotations, save_annotations},
+        artefacts::{annotations_path, artefact_paths},
+    };
+
+    let mut annotations = test_annotations();
+    let texts: Vec<&str> = annotations
+        .annotations
+        .iter()
+        .map(|annotation| annotation.text.as_str())
+        .collect();
+    assert_eq!(
+        texts,
+        vec!["", "The rain fell.", "It was a dark & stormy night."]
+    );
+    assert_eq!(annotations.find_highlight(5, "The rain fell."), Some(1));
+    assert_eq!(annotations.find_highlight(4, "The rain fell."), None);
+    assert_eq!(
+        annotations.page_highlights(5),
+        vec!["The rain fell.", "It was a dark & stormy night."]
+    );
+    assert!(annotations.page_highlights(2).is_empty());
+
+    save_annotations(&annotations).expect("Unable to save annotations");
+    let title = annotations.title.clone();
+    assert_eq!(load_annotations(title.as_str(), "Jane <Doe>"), annotations);
+    assert!(artefact_paths(title.as_str()).contains(&annotations_path(title.as_str())));
+
+    annotations.remove(1);
+    annotations.remove(9);
+    assert_eq!(annotations.annotations.len(), 2);
+    std::fs::remove_file(annotations_path(title.as_str())).expect("Unable to remove annotations");
+}
+
+/**
+ * Tests the Markdown, JSON and HTML exports of a book and of the library
+ */
+#[test]
+fn annotations_exported() {
+    use crate::helper::annotations::{export_annotations, Annotations, ExportFormat};
+
+    let annotations = test_annotations();
+    let markdown =
+        export_annotations(std::slice::from_ref(&annotations), ExportFormat::Markdown).unwrap();
+    assert_eq!(
+        markdown,
+        "# Annotations Test Book
+
+*Jane <Doe>*
+
+## Chapter One
+
+### Bookmark
+
+*chapter 2, 25%*
+
+## Chapter Two
+
+### Highlight
+
+> The rain fell.
+
+*chapter 5, 10%*
+
+### Highlight
+
+> It was a dark & stormy night.
+
+A classic opening.
+
+*chapter 5, 50%*
+
+"
+    );
+
+    let json = export_annotations(std::slice::from_ref(&annotations), ExportFormat::Json).unwrap();
+    let parsed: Annotations = serde_json::from_str(json.as_str()).unwrap();
+    assert_eq!(parsed, annotations);
+    assert!(json.contains("\"kind\": \"bookmark\""));
+
+    let html = export_annotations(std::slice::from_ref(&annotations), ExportFormat::Html).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Annotations Test Book</title>"));
+    assert!(html.contains("<p class=\"author\">Jane &lt;Doe&gt;</p>"));
+    assert!(html.contains("<blockquote>It was a dark &amp; stormy night.</blockquote>"));
+    assert!(html.contains("<p class=\"note\">A classic opening.</p>"));
+    assert_eq!(html.matches("<h2>").count(), 2);
+
+    // The whole library
+    let mut other = test_annotations();
+    other.title = "Other Book".to_string();
+    let books = [annotations, other];
+    let json = export_annotations(&books, ExportFormat::Json).unwrap();
+    let parsed: Vec<Annotations> = serde_json::from_str(json.as_str()).unwrap();
+    assert_eq!(parsed.len(), 2);
+    let html = export_annotations(&books, ExportFormat::Html).unwrap();
+    assert!(html.contains("<title>Library notes</title>"));
+    assert_eq!(html.matches("<section class=\"book\">").count(), 2);
+    let markdown = export_annotations(&books, ExportFormat::Markdown).unwrap();
+    assert!(markdown.contains("\n# Other Book\n"));
+}
+
+/**
+ * Tests the format of an export chosen by the extension of the file
+ */
+#[test]
+fn export_format_from_extension() {
+    use crate::helper::annotations::ExportFormat;
+    use std::path::Path;
+
+    assert_eq!(
+        ExportFormat::from_path(Path::new("notes.json")),
+        ExportFormat::Json
+    );
+    assert_eq!(
+        ExportFormat::from_path(Path::new("notes.HTM")),
+        ExportFormat::Html
+    );
+    assert_eq!(
+        ExportFormat::from_path(Path::new("notes.md")),
+        ExportFormat::Markdown
+    );
+    assert_eq!(
+        ExportFormat::from_path(Path::new("notes")),
+        ExportFormat::Markdown
+    );
+}
+
+/**
+ * Tests the title of the chapter of a page, from the table of contents or the page
+ */
+#[test]
+fn chapter_titles() {
+    use crate::helper::annotations::chapter_title;
+    use std::path::{Path, PathBuf};
+
+    let toc = vec![
+        ("Chapter  One".to_string(), PathBuf::from("OEBPS/one.xhtml")),
+        ("Section".to_string(), PathBuf::from("OEBPS/two.xhtml#s1")),
+    ];
+    let html =
+        "<html><head><title>Book</title></head><body><h2>The <i>Second</i> Part</h2></body></html>";
+    assert_eq!(
+        chapter_title(&toc, Some(Path::new("OEBPS/one.xhtml")), html).as_deref(),
+        Some("Chapter One")
+    );
+    assert_eq!(
+        chapter_title(&toc, Some(Path::new("OEBPS/two.xhtml")), html).as_deref(),
+        Some("Section")
+    );
+    assert_eq!(
+        chapter_title(&toc, Some(Path::new("OEBPS/three.xhtml")), html).as_deref(),
+        Some("The Second Part")
+    );
+    assert_eq!(
+        chapter_title(
+            &[],
+            None,
+            "<html><head><title>Book</title></head><body><p>Text</p></body></html>"
+        )
+        .as_deref(),
+        Some("Book")
+    );
+    assert_eq!(
+        chapter_title(&[], None, "<html><body><p>Text</p></body></html>"),
+        None
+    );
+}
@@ -0,0 +1,67 @@
+#[allow(unused)]
+const TEST_COVER_PATH: &str = "./src/library/covers/The-Prisoner-of-Zenda.jpg";
+
+/* ARTEFACTS TESTS */
+
+/**
+ * Tests that progress and cover follow the book when its title changes,
+ * and that they are removed together with the book
+ */
+#[test]
+fn artefacts_renamed_and_removed() {
+    use crate::helper::artefacts::{
+        artefact_paths, progress_path, remove_artefacts, rename_artefacts,
+    };
+
+    let old_title = "Artefacts Test Book";
+    let new_title = "Artefacts Test Book Renamed";
+    std::fs::write(progress_path(old_title), "{}").expect("Unable to write progress");
+    let cover =
+        std::path::Path::new(crate::helper::config::COVERS_PATH).join("Artefacts-Test-Book.jpg");
+    std::fs::copy(TEST_COVER_PATH, &cover).expect("Unable to write cover");
+    assert_eq!(artefact_paths(old_title).len(), 2);
+
+    rename_artefacts(old_title, new_title).expect("Artefacts not renamed");
+    assert!(artefact_paths(old_title).is_empty());
+    let renamed = artefact_paths(new_title);
+    assert_eq!(renamed.len(), 2);
+    assert!(renamed
+        .iter()
+        .any(|path| path.ends_with("Artefacts-Test-Book-Renamed.jpg")));
+
+    // The files of another book are never overwritten
+    std::fs::write(progress_path(old_title), "{}").expect("Unable to write progress");
+    assert!(rename_artefacts(old_title, new_title).is_err());
+    assert_eq!(artefact_paths(old_title).len(), 1);
+    assert_eq!(artefact_paths(new_title).len(), 2);
+
+    remove_artefacts(old_title, "artefacts-test");
+    remove_artefacts(new_title, "artefacts-test");
+    assert!(artefact_paths(new_title).is_empty());
+}
+
+/**
+ * Tests that a file is moved into another folder and never overwrites a file there
+ */
+#[test]
+fn file_moved_to_folder() {
+    let from_folder = std::env::temp_dir().join("ebook-reader-move-from");
+    let to_folder = std::env::temp_dir().join("ebook-reader-move-to");
+    let _ = std::fs::remove_dir_all(&from_folder);
+    let _ = std::fs::remove_dir_all(&to_folder);
+    std::fs::create_dir_all(&from_folder).expect("Unable to create folder");
+    std::fs::create_dir_all(&to_folder).expect("Unable to create folder");
+
+    let file = from_folder.join("book.epub");
+    std::fs::write(&file, "book").expect("Unable to write file");
+    let moved = crate::helper::artefacts::move_to_folder(&file, &to_folder).expect("Not moved");
+    assert_eq!(moved, to_folder.join("book.epub"));
+    assert!(!file.exists());
+
+    std::fs::write(&file, "other").expect("Unable to write file");
+    assert!(crate::helper::artefacts::move_to_folder(&file, &to_folder).is_err());
+    assert!(file.exists());
+
+    let _ = std::fs::remove_dir_all(&from_folder);
+    let _ = std::fs::remove_dir_all(&to_folder);
+}
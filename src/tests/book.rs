@@ -7,7 +7,7 @@ const TEST_FILE_PATH: &str = "./src/library/hope-prisoner-of-zenda.epub";
  */
 #[test]
 fn book_created_from_epub_file() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(book) => {
             assert_eq!(book.get_title(), "The Prisoner of Zenda");
@@ -25,7 +25,7 @@ fn book_created_from_epub_file() {
  */
 #[test]
 fn book_created_from_epub_file_with_cover() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(book) => {
             assert_eq!(book.get_title(), "The Prisoner of Zenda");
@@ -45,10 +45,39 @@ fn book_created_from_epub_file_with_cover() {
 #[test]
 fn book_created_from_epub_file_not_existing() {
     let file_path = "./src/library/unknown.epub";
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(file_path));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(file_path));
     assert_eq!(book.is_none(), true);
 }
 
+/**
+ * Tests that the words of every section of a book are counted, the last one included
+ */
+#[test]
+fn book_words_counted_to_last_chapter() {
+    use crate::helper::artefacts::remove_artefacts;
+    use crate::helper::source::open_source;
+
+    // The book starts without saved progress, and leaves no cover in the library
+    remove_artefacts("The Short Story", "");
+
+    let dir = crate::tests::test_folder();
+    let path = dir.path().join("the_short_story.txt");
+    std::fs::write(
+        &path,
+        "The Short Story\n\nCHAPTER I\n\nThe only chapter of the book, and it is not long.\n",
+    )
+    .expect("Unable to write test book");
+
+    let sections = open_source(&path).expect("Unable to open book").len();
+    let book = crate::helper::functions::file_to_book(path).expect("Book not created");
+    assert_eq!(book.get_word_count().len(), sections);
+    // The start of the only chapter is not the end of the book
+    assert_eq!(book.get_current_page(), 1);
+    assert!(book.get_progress() < 1.0);
+    assert!(book.get_book_time_left() > 0);
+    remove_artefacts("The Short Story", book.get_cache_key().as_str());
+}
+
 /** BOOK NAVIGATION TESTS */
 
 /**
@@ -56,7 +85,7 @@ fn book_created_from_epub_file_not_existing() {
  */
 #[test]
 fn first_page_of_book_loaded() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(book) => {
             assert_eq!(book.get_current_page(), 1);
@@ -72,7 +101,7 @@ fn first_page_of_book_loaded() {
  */
 #[test]
 fn next_page_of_book_loaded() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(mut book) => {
             book.next_page();
@@ -89,7 +118,7 @@ fn next_page_of_book_loaded() {
  */
 #[test]
 fn previous_page_of_book_loaded() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(mut book) => {
             book.next_page();
@@ -107,7 +136,7 @@ fn previous_page_of_book_loaded() {
  */
 #[test]
 fn last_page_of_book_loaded() {
-    let book = crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH));
     match book {
         Some(mut book) => {
             book.set_page(book.get_book_length());
@@ -118,3 +147,17 @@ fn last_page_of_book_loaded() {
         }
     }
 }
+
+/**
+ * Tests that the text of the pages is searched, not their markup
+ */
+#[test]
+fn text_found_without_markup() {
+    let book = crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_FILE_PATH))
+        .expect("Book not created");
+    assert!(book.find_text("Rassendyll").is_some());
+    assert!(book.find_text("rudolf \n rassendyll").is_some());
+    // Every page of the book is an XHTML document
+    assert_eq!(book.find_text("xmlns"), None);
+    assert_eq!(book.find_text("   "), None);
+}
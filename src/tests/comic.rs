@@ -0,0 +1,257 @@
+/* COMIC BOOKS TESTS */
+
+/**
+ * Tests that the pages are sorted with their numbers as numbers
+ */
+#[test]
+fn comic_pages_natural_order() {
+    use crate::helper::source::comic::natural_cmp;
+
+    let mut names = vec![
+        "page10.jpg",
+        "Page2.jpg",
+        "page1.jpg",
+        "page01b.jpg",
+        "cover.jpg",
+        "page002.jpg",
+    ];
+    names.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(
+        names,
+        vec![
+            "cover.jpg",
+            "page1.jpg",
+            "page01b.jpg",
+            "Page2.jpg",
+            "page002.jpg",
+            "page10.jpg"
+        ]
+    );
+}
+
+/**
+ * Tests the metadata and the reading direction read from ComicInfo.xml
+ */
+#[test]
+fn comic_info_parsed() {
+    use crate::helper::opf::PageProgression;
+    use crate::helper::source::comic::parse_comic_info;
+
+    let xml = r#"<?xml version="1.0"?>
+<ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Title>The Storm</Title>
+  <Series>Sea Tales</Series>
+  <Number>3</Number>
+  <Writer>Jane Doe</Writer>
+  <LanguageISO>ja</LanguageISO>
+  <Manga>YesAndRightToLeft</Manga>
+</ComicInfo>"#;
+    let comic_info = parse_comic_info(xml).expect("Unable to parse ComicInfo");
+    let metadata = comic_info.metadata;
+    assert!(metadata.contains(&("title".to_string(), "Sea Tales #3: The Storm".to_string())));
+    assert!(metadata.contains(&("creator".to_string(), "Jane Doe".to_string())));
+    assert!(metadata.contains(&("language".to_string(), "ja".to_string())));
+    assert_eq!(comic_info.page_progression, PageProgression::Rtl);
+
+    let comic_info =
+        parse_comic_info("<ComicInfo><Series>Sea Tales</Series><Manga>Yes</Manga></ComicInfo>")
+            .expect("Unable to parse ComicInfo");
+    assert_eq!(
+        comic_info.metadata,
+        vec![("title".to_string(), "Sea Tales".to_string())]
+    );
+    assert_eq!(comic_info.page_progression, PageProgression::Ltr);
+}
+
+/**
+ * Tests that the images of a CBZ archive are its pages, the first one being the cover
+ */
+#[test]
+fn cbz_comic_opened() {
+    use crate::helper::opf::PageProgression;
+    use crate::helper::source::{open_source, BookFormat};
+    use std::io::Write;
+    use std::path::Path;
+
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    let path = folder.join("sea_tales.cbz");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let options = zip::write::FileOptions::default();
+    for (name, bytes) in [
+        ("pages/10.png", vec![10]),
+        ("pages/2.png", vec![2]),
+        ("pages/1.png", vec![1]),
+        ("__MACOSX/pages/._1.png", vec![0]),
+        ("pages/notes.txt", vec![0]),
+    ] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(&bytes).unwrap();
+    }
+    zip.start_file("ComicInfo.xml", options).unwrap();
+    zip.write_all(b"<ComicInfo><Manga>YesAndRightToLeft</Manga></ComicInfo>")
+        .unwrap();
+    zip.finish().expect("Unable to write test comic");
+
+    let mut source = open_source(&path).expect("Unable to open comic");
+    assert_eq!(source.format(), BookFormat::Comic);
+    assert_eq!(source.metadata("title").as_deref(), Some("sea tales"));
+    assert_eq!(source.len(), 3);
+    assert_eq!(source.cover(), Some(vec![1]));
+    assert_eq!(source.page_image(1), Some(vec![2]));
+    assert_eq!(source.page_image(2), Some(vec![10]));
+    assert_eq!(source.page_image(3), None);
+    assert!(source
+        .section(1)
+        .unwrap()
+        .contains("<img src=\"pages/2.png\" alt=\"\"/>"));
+    assert!(source.resource(Path::new("pages/notes.txt")).is_err());
+    assert_eq!(source.page_progression(), PageProgression::Rtl);
+}
+
+/**
+ * Tests that a folder of images is a comic, and that only its images are copied
+ */
+#[test]
+fn image_folder_comic_opened() {
+    use crate::helper::opf::PageProgression;
+    use crate::helper::source::{comic::copy_comic_folder, open_source, BookFormat};
+
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    let images = folder.join("scans");
+    std::fs::create_dir_all(&images).unwrap();
+    std::fs::write(images.join("b.JPG"), [2]).unwrap();
+    std::fs::write(images.join("a.jpg"), [1]).unwrap();
+    std::fs::write(images.join("Thumbs.db"), [0]).unwrap();
+
+    let copy = folder.join("Old Scans");
+    copy_comic_folder(&images, &copy).expect("Unable to copy comic");
+    assert!(copy.join("b.JPG").exists());
+    assert!(!copy.join("Thumbs.db").exists());
+    assert!(copy_comic_folder(&images, &copy).is_err());
+
+    let mut source = open_source(&copy).expect("Unable to open comic");
+    assert_eq!(source.format(), BookFormat::Comic);
+    assert_eq!(source.metadata("title").as_deref(), Some("Old Scans"));
+    assert_eq!(source.len(), 2);
+    assert_eq!(source.cover(), Some(vec![1]));
+    assert_eq!(source.page_image(1), Some(vec![2]));
+    assert_eq!(source.page_progression(), PageProgression::Ltr);
+
+    // No images: not a comic
+    let empty = folder.join("empty");
+    std::fs::create_dir_all(&empty).unwrap();
+    assert!(open_source(&empty).is_err());
+    assert!(copy_comic_folder(&empty, &folder.join("copy")).is_err());
+}
+
+/**
+ * Tests the pages shown two by two and the pages turned in spreads
+ */
+#[test]
+fn comic_spreads() {
+    use crate::helper::viewer::{next_spread, prev_spread, spread_pages};
+
+    // One page at a time, from the cover
+    assert_eq!(spread_pages(4, 9, false), vec![4]);
+    assert_eq!(next_spread(4, 9, false), Some(5));
+    assert_eq!(next_spread(9, 9, false), None);
+    assert_eq!(prev_spread(2, false), Some(1));
+    assert_eq!(prev_spread(1, false), Some(0));
+    assert_eq!(prev_spread(0, false), None);
+
+    // The cover alone, then spreads 1-2, 3-4... the last page alone in a book of odd length
+    assert_eq!(spread_pages(0, 9, true), vec![0]);
+    assert_eq!(next_spread(0, 9, true), Some(1));
+    assert_eq!(spread_pages(1, 9, true), vec![1, 2]);
+    assert_eq!(spread_pages(4, 9, true), vec![3, 4]);
+    assert_eq!(spread_pages(9, 9, true), vec![9]);
+    assert_eq!(next_spread(4, 9, true), Some(5));
+    assert_eq!(next_spread(8, 9, true), Some(9));
+    assert_eq!(next_spread(7, 8, true), None);
+    assert_eq!(prev_spread(6, true), Some(3));
+    assert_eq!(prev_spread(2, true), Some(0));
+    assert_eq!(prev_spread(0, true), None);
+}
+
+/**
+ * Tests that a comic opens on its first image and that the reader goes back to it
+ */
+#[test]
+fn comic_first_image_shown() {
+    use crate::helper::artefacts::remove_artefacts;
+    use crate::helper::functions::file_to_book;
+
+    // The comics start without saved progress, and leave no cover in the library
+    let titles = ["Single Page", "Three Pages"];
+    for title in titles {
+        remove_artefacts(title, "");
+    }
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    let single = folder.join("Single Page");
+    let pages = folder.join("Three Pages");
+    std::fs::create_dir_all(&single).unwrap();
+    std::fs::create_dir_all(&pages).unwrap();
+    let image = image::RgbImage::new(2, 3);
+    image.save(single.join("only.png")).unwrap();
+    for name in ["1.png", "2.png", "3.png"] {
+        image.save(pages.join(name)).unwrap();
+    }
+
+    // A comic of one image shows it
+    let book = file_to_book(single).expect("Comic not created");
+    assert_eq!(book.get_current_page(), 0);
+    assert!(book.get_page_image(0).is_some());
+    assert!(!book.has_prev_page());
+    assert!(!book.has_next_page());
+
+    let mut book = file_to_book(pages).expect("Comic not created");
+    assert_eq!(book.get_current_page(), 0);
+    book.next_page();
+    book.next_page();
+    assert_eq!(book.get_current_page(), 2);
+    assert!(!book.has_next_page());
+    book.prev_page();
+    book.prev_page();
+    assert_eq!(book.get_current_page(), 0);
+    assert!(book.get_page_image(0).is_some());
+    assert!(!book.has_prev_page());
+    for title in titles {
+        remove_artefacts(title, "");
+    }
+}
+
+/**
+ * Tests the size of the pages fitted to the reader and their place in a spread
+ */
+#[test]
+fn comic_pages_fitted() {
+    use crate::helper::viewer::{clamp_offset, image_scale, spread_rects, ImageFit};
+    use druid::{Rect, Size, Vec2};
+
+    let page = Size::new(400.0, 600.0);
+    let viewport = Size::new(800.0, 600.0);
+    assert_eq!(image_scale(page, viewport, ImageFit::Page, 1.0), 1.0);
+    assert_eq!(image_scale(page, viewport, ImageFit::Width, 1.0), 2.0);
+    assert_eq!(image_scale(page, viewport, ImageFit::Zoom, 1.5), 1.5);
+    // The zoom is never smaller than the whole page
+    assert_eq!(image_scale(page, viewport, ImageFit::Zoom, 0.5), 1.0);
+
+    // Pages of different heights side by side, the first one on the right from right to left
+    let sizes = [Size::new(400.0, 600.0), Size::new(100.0, 300.0)];
+    let (size, rects) = spread_rects(&sizes, false);
+    assert_eq!(size, Size::new(600.0, 600.0));
+    assert_eq!(rects[0], Rect::new(0.0, 0.0, 400.0, 600.0));
+    assert_eq!(rects[1], Rect::new(400.0, 0.0, 600.0, 600.0));
+    let (_, rects) = spread_rects(&sizes, true);
+    assert_eq!(rects[0], Rect::new(200.0, 0.0, 600.0, 600.0));
+    assert_eq!(rects[1], Rect::new(0.0, 0.0, 200.0, 600.0));
+
+    // Scrolled inside a page wider than the reader, centered in a smaller one
+    let offset = clamp_offset(Vec2::new(-50.0, 900.0), Size::new(1000.0, 400.0), viewport);
+    assert_eq!(offset, Vec2::new(0.0, -100.0));
+    let offset = clamp_offset(Vec2::new(500.0, 0.0), Size::new(1000.0, 400.0), viewport);
+    assert_eq!(offset, Vec2::new(200.0, -100.0));
+}
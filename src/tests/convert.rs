@@ -0,0 +1,167 @@
+/* CONVERSION TESTS */
+
+/**
+ * Book of two chapters, with an image and a stylesheet, to convert
+ */
+#[cfg(test)]
+fn convert_test_book() -> crate::helper::source::MemorySource {
+    use crate::helper::source::{BookFormat, MemorySource};
+    use std::path::PathBuf;
+
+    let mut source = MemorySource::new(
+        BookFormat::Html,
+        "The Old Sea",
+        "Jane Doe",
+        "<link rel=\"stylesheet\" href=\"style/book.css\"/>\n",
+        vec![
+            (
+                "The Storm".to_string(),
+                "<h1>The Storm</h1>\n<p>The <em>wind</em> rose,\n  the <strong>sea</strong> too.</p>\n<p><img src=\"images/wave.png\" alt=\"A wave\"/></p>".to_string(),
+            ),
+            (
+                "After".to_string(),
+                "<h2>After</h2>\n<ul><li>A boat</li><li>Two oars</li></ul>\n<blockquote><p>Calm.</p></blockquote>".to_string(),
+            ),
+        ],
+    );
+    source.resources = vec![
+        (PathBuf::from("images/wave.png"), vec![1, 2, 3]),
+        (
+            PathBuf::from("style/book.css"),
+            b"body { background: url('../images/sky.png') }".to_vec(),
+        ),
+        (PathBuf::from("images/sky.png"), vec![4, 5]),
+        (PathBuf::from("images/unused.png"), vec![6]),
+    ];
+    source.cover = Some(vec![1, 2, 3]);
+    source
+}
+
+/**
+ * Tests the format of the converted file from its extension
+ */
+#[test]
+fn convert_format_from_extension() {
+    use crate::helper::convert::ConvertFormat;
+    use std::path::Path;
+
+    assert_eq!(
+        ConvertFormat::from_path(Path::new("a.TXT")),
+        Some(ConvertFormat::Text)
+    );
+    assert_eq!(
+        ConvertFormat::from_path(Path::new("a.markdown")),
+        Some(ConvertFormat::Markdown)
+    );
+    assert_eq!(
+        ConvertFormat::from_path(Path::new("a.epub")),
+        Some(ConvertFormat::Epub)
+    );
+    assert_eq!(ConvertFormat::from_path(Path::new("a")), None);
+    assert_eq!(ConvertFormat::from_path(Path::new("a.pdf")), None);
+}
+
+/**
+ * Tests that a file without a known extension is saved as EPUB with the EPUB extension
+ */
+#[test]
+fn converted_path_gets_epub_extension() {
+    use crate::helper::convert::{converted_path, ConvertFormat};
+    use std::path::PathBuf;
+
+    assert_eq!(
+        converted_path(PathBuf::from("book.md")),
+        (PathBuf::from("book.md"), ConvertFormat::Markdown)
+    );
+    assert_eq!(
+        converted_path(PathBuf::from("book.pdf")),
+        (PathBuf::from("book.pdf.epub"), ConvertFormat::Epub)
+    );
+    assert_eq!(
+        converted_path(PathBuf::from("book")),
+        (PathBuf::from("book.epub"), ConvertFormat::Epub)
+    );
+}
+
+/**
+ * Tests that the converted EPUB starts with the uncompressed mimetype, and has the
+ * sections, the files they use, the cover and a new navigation document
+ */
+#[test]
+fn book_converted_to_epub() {
+    use crate::helper::convert::{convert_book, ConvertFormat};
+    use std::io::{Cursor, Read};
+    use zip::{CompressionMethod, ZipArchive};
+
+    let mut source = convert_test_book();
+    let bytes = convert_book(&mut source, ConvertFormat::Epub).expect("Unable to convert book");
+    // The mimetype can be read at a fixed offset of the file
+    assert_eq!(&bytes[30..38], b"mimetype");
+    assert_eq!(&bytes[38..58], b"application/epub+zip");
+
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).expect("Invalid archive");
+    let mimetype = archive.by_index(0).unwrap();
+    assert_eq!(mimetype.name(), "mimetype");
+    assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+    drop(mimetype);
+    let names: Vec<&str> = archive.file_names().collect();
+    for name in [
+        "META-INF/container.xml",
+        "content.opf",
+        "nav.xhtml",
+        "title.xhtml",
+        "chapter-002.xhtml",
+        "images/wave.png",
+        "style/book.css",
+        "images/sky.png",
+    ] {
+        assert!(names.contains(&name), "{} is missing", name);
+    }
+    assert!(!names.contains(&"images/unused.png"));
+
+    let mut read = |name: &str| {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    };
+    let opf = read("content.opf");
+    assert!(opf.contains("version=\"3.0\""));
+    assert!(opf.contains("<dc:title>The Old Sea</dc:title>"));
+    assert!(opf.contains("<dc:creator>Jane Doe</dc:creator>"));
+    assert!(opf.contains("property=\"dcterms:modified\""));
+    // The cover is the image of the first chapter
+    assert!(opf.contains(
+        "<item id=\"cover-image\" href=\"images/wave.png\" media-type=\"image/png\" properties=\"cover-image\"/>"
+    ));
+    assert!(
+        opf.contains("href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"")
+    );
+    assert!(opf.contains("<itemref idref=\"section-0\"/>\n    <itemref idref=\"section-1\"/>"));
+    let nav = read("nav.xhtml");
+    assert!(nav.contains("<li><a href=\"chapter-001.xhtml\">The Storm</a></li>"));
+    assert!(nav.contains("<li><a href=\"chapter-002.xhtml\">After</a></li>"));
+}
+
+/**
+ * Tests the book converted to Markdown and to plain text, with its headings
+ */
+#[test]
+fn book_converted_to_text() {
+    use crate::helper::convert::{convert_book, ConvertFormat};
+
+    let mut source = convert_test_book();
+    let markdown = convert_book(&mut source, ConvertFormat::Markdown).unwrap();
+    assert_eq!(
+        String::from_utf8(markdown).unwrap(),
+        "# The Old Sea\n\nJane Doe\n\n## The Storm\n\nThe *wind* rose, the **sea** too.\n\n### After\n\n- A boat\n- Two oars\n\n> Calm.\n"
+    );
+    let text = convert_book(&mut source, ConvertFormat::Text).unwrap();
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "THE OLD SEA\n\nJane Doe\n\nThe Storm\n\nThe wind rose, the sea too.\n\nAfter\n\n- A boat\n- Two oars\n\n    Calm.\n"
+    );
+}
@@ -0,0 +1,65 @@
+#[allow(unused)]
+const TEST_COVER_PATH: &str = "./src/library/covers/The-Prisoner-of-Zenda.jpg";
+#[allow(unused)]
+const TEST_PNG_PATH: &str = "./src/library/ocr/the-grand-hall.png";
+
+/* COVER TESTS */
+
+/**
+ * Tests that the first image of a cover page is found (html and svg covers)
+ */
+#[test]
+fn first_image_found_in_page() {
+    let html = "<html><body><div><img alt=\"Cover\" src=\"images/cover.jpg\"/></div></body></html>";
+    let src = crate::helper::cover::first_image_src(html);
+    assert_eq!(src, Some("images/cover.jpg".to_string()));
+
+    let svg = "<svg><image width=\"600\" xlink:href='../Images/cover.png'/></svg>";
+    let src = crate::helper::cover::first_image_src(svg);
+    assert_eq!(src, Some("../Images/cover.png".to_string()));
+
+    let text = "<html><body><p>No images here</p></body></html>";
+    assert_eq!(crate::helper::cover::first_image_src(text), None);
+}
+
+/**
+ * Tests that image paths are resolved relative to the page they appear in
+ */
+#[test]
+fn relative_path_resolved() {
+    let base = std::path::Path::new("OEBPS/Text/cover.xhtml");
+    let path = crate::helper::functions::resolve_relative_path(base, "../Images/cover.png");
+    assert_eq!(path, std::path::PathBuf::from("OEBPS/Images/cover.png"));
+}
+
+/**
+ * Tests that the cover extension follows the real image format
+ */
+#[test]
+fn cover_extension_from_format() {
+    let png = std::fs::read(TEST_PNG_PATH).expect("Unable to read image");
+    assert_eq!(crate::helper::cover::image_extension(&png), Some("png"));
+
+    let jpeg = std::fs::read(TEST_COVER_PATH).expect("Unable to read cover");
+    assert_eq!(crate::helper::cover::image_extension(&jpeg), Some("jpg"));
+
+    assert_eq!(crate::helper::cover::image_extension(b"not an image"), None);
+}
+
+/**
+ * Tests that a generated cover is a valid png image
+ */
+#[test]
+fn cover_generated() {
+    let cover = crate::helper::cover::generate_cover("The Prisoner of Zenda", "Anthony Hope");
+    match cover {
+        Some(cover) => {
+            let image = image::load_from_memory(&cover).expect("Invalid cover");
+            assert_eq!(image.width() as usize, crate::helper::config::COVER_WIDTH);
+            assert_eq!(image.height() as usize, crate::helper::config::COVER_HEIGHT);
+        }
+        None => {
+            panic!("Cover not generated")
+        }
+    }
+}
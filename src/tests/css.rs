@@ -0,0 +1,54 @@
+/* CSS TESTS */
+
+/**
+ * Tests the order of the declarations: specificity, then order in the stylesheet, then style attribute
+ */
+#[test]
+fn cascade_declarations() {
+    use crate::helper::css::Stylesheet;
+    use crate::helper::xml::XmlDocument;
+
+    let css = "
+        /* Comments and at-rules are skipped */
+        @charset \"utf-8\";
+        @media print { p { display: none } }
+        p.note { text-align: right }
+        p { text-align: center; font-style: italic !important }
+        div > p { margin: 0 }
+        section p { text-indent: 1em }
+        a:hover, p + p { color: red }
+    ";
+    let stylesheet = Stylesheet::parse(css);
+
+    let document = XmlDocument::parse(
+        "<html><body><div><p class=\"note first\" style=\"text-align: left\">Text</p></div></body></html>",
+    )
+    .unwrap();
+    let html = &document.root;
+    let body = html.find("body").unwrap();
+    let div = body.find("div").unwrap();
+    let p = div.find("p").unwrap();
+
+    let declarations: Vec<(String, String)> = stylesheet
+        .declarations(p, &[html, body, div])
+        .into_iter()
+        .map(|d| (d.property, d.value))
+        .collect();
+    let expected = [
+        ("text-align", "center"),
+        ("font-style", "italic"),
+        ("margin", "0"),
+        ("text-align", "right"),
+        ("text-align", "left"),
+    ];
+    assert_eq!(
+        declarations,
+        expected
+            .iter()
+            .map(|(p, v)| (p.to_string(), v.to_string()))
+            .collect::<Vec<(String, String)>>()
+    );
+
+    // "div > p" does not match a p that is not a child of a div
+    assert_eq!(stylesheet.declarations(p, &[html, body]).len(), 4);
+}
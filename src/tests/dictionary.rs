@@ -0,0 +1,340 @@
+/* DICTIONARY TESTS */
+
+/**
+ * Write a StarDict dictionary (name.ifo, name.idx, name.dict or name.dict.dz) in the folder,
+ * the entries are given as (word, data of the entry)
+ */
+#[cfg(test)]
+fn write_stardict(
+    folder: &std::path::Path,
+    name: &str,
+    options: &str,
+    entries: &[(&str, &[u8])],
+    compressed: bool,
+) -> std::path::PathBuf {
+    use std::io::Write;
+
+    std::fs::create_dir_all(folder).unwrap();
+    let mut idx = Vec::new();
+    let mut dict = Vec::new();
+    for (word, data) in entries {
+        idx.extend_from_slice(word.as_bytes());
+        idx.push(0);
+        idx.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+        idx.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        dict.extend_from_slice(data);
+    }
+    let ifo = folder.join(format!("{}.ifo", name));
+    std::fs::write(
+        &ifo,
+        format!(
+            "StarDict's dict ifo file\nversion=2.4.2\nbookname={}\nwordcount={}\nidxfilesize={}\n{}",
+            name,
+            entries.len(),
+            idx.len(),
+            options
+        ),
+    )
+    .unwrap();
+    std::fs::write(folder.join(format!("{}.idx", name)), idx).unwrap();
+    if compressed {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&dict).unwrap();
+        std::fs::write(
+            folder.join(format!("{}.dict.dz", name)),
+            encoder.finish().unwrap(),
+        )
+        .unwrap();
+    } else {
+        std::fs::write(folder.join(format!("{}.dict", name)), dict).unwrap();
+    }
+    ifo
+}
+
+/**
+ * Tests looking words up in StarDict dictionaries, with and without the types
+ * of the fields in the entries, compressed or not
+ */
+#[test]
+fn stardict_lookup() {
+    use crate::helper::dictionary::{Dictionary, StarDict};
+
+    let folder = std::env::temp_dir().join("ebook-reader-dictionary-lookup");
+    let _ = std::fs::remove_dir_all(&folder);
+
+    let ifo = write_stardict(
+        &folder,
+        "plain",
+        "sametypesequence=m\nlang=en-en\n",
+        &[
+            ("Apple", b"A round fruit."),
+            ("book", b"Pages bound together."),
+        ],
+        false,
+    );
+    let plain = StarDict::open(&ifo).unwrap();
+    assert_eq!(plain.name(), "plain");
+    assert_eq!(plain.language(), Some("en"));
+    let definitions = plain.lookup("apple");
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].word, "Apple");
+    assert_eq!(definitions[0].text, "A round fruit.");
+    assert_eq!(definitions[0].dictionary, "plain");
+    assert_eq!(plain.lookup("BOOK")[0].text, "Pages bound together.");
+    assert!(plain.lookup("pear").is_empty());
+
+    // Typed fields: phonetics, html and a picture (skipped)
+    let mut entry = Vec::new();
+    entry.extend_from_slice(b"t/\xcb\x88k\xc3\xa6t/\0");
+    entry.extend_from_slice(b"h<b>cat</b><br>A small <i>animal</i> &amp; pet.\0");
+    entry.extend_from_slice(b"P");
+    entry.extend_from_slice(&3u32.to_be_bytes());
+    entry.extend_from_slice(b"\x01\x02\x03");
+    let ifo = write_stardict(
+        &folder.join("en"),
+        "typed",
+        "",
+        &[("cat", entry.as_slice())],
+        true,
+    );
+    let typed = StarDict::open(&ifo).unwrap();
+    // The language comes from the folder of the dictionary
+    assert_eq!(typed.language(), Some("en"));
+    assert_eq!(
+        typed.lookup("Cat")[0].text,
+        "/ˈkæt/\ncat\nA small animal & pet."
+    );
+
+    assert!(StarDict::open(&folder.join("missing.ifo")).is_err());
+    let _ = std::fs::remove_dir_all(&folder);
+}
+
+/**
+ * Dictzip file of the data: a gzip file with the RA field in its header, the chunks of
+ * chunk_length bytes compressed on their own (ended by a full flush)
+ */
+#[cfg(test)]
+fn dictzip(data: &[u8], chunk_length: usize) -> Vec<u8> {
+    use flate2::{Compress, Compression, Crc, FlushCompress};
+
+    let mut chunks = Vec::new();
+    let mut compress = Compress::new(Compression::default(), false);
+    let count = data.len().div_ceil(chunk_length);
+    for (index, chunk) in data.chunks(chunk_length).enumerate() {
+        let flush = if index + 1 == count {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Full
+        };
+        let mut compressed = Vec::with_capacity(chunk_length * 2 + 64);
+        compress
+            .compress_vec(chunk, &mut compressed, flush)
+            .unwrap();
+        chunks.push(compressed);
+    }
+
+    let mut ra = Vec::new();
+    for number in [1, chunk_length, chunks.len()] {
+        ra.extend_from_slice(&(number as u16).to_le_bytes());
+    }
+    for chunk in chunks.iter() {
+        ra.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+    }
+    let mut file = vec![0x1f, 0x8b, 8, 4 | 8, 0, 0, 0, 0, 2, 3];
+    file.extend_from_slice(&(ra.len() as u16 + 4).to_le_bytes());
+    file.extend_from_slice(b"RA");
+    file.extend_from_slice(&(ra.len() as u16).to_le_bytes());
+    file.extend_from_slice(&ra);
+    file.extend_from_slice(b"dictionary.dict\0");
+    for chunk in chunks {
+        file.extend_from_slice(&chunk);
+    }
+    let mut crc = Crc::new();
+    crc.update(data);
+    file.extend_from_slice(&crc.sum().to_le_bytes());
+    file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    file
+}
+
+/**
+ * Tests that the entries of a dictzip dictionary are read from their chunks,
+ * and that the index can be compressed
+ */
+#[test]
+fn stardict_dictzip_lookup() {
+    use crate::helper::dictionary::{dictzip_chunks, Dictionary, StarDict};
+    use std::io::{Read, Write};
+
+    let folder = std::env::temp_dir().join("ebook-reader-dictionary-dictzip");
+    let _ = std::fs::remove_dir_all(&folder);
+    let ifo = write_stardict(
+        &folder,
+        "chunked",
+        "sametypesequence=m\n",
+        &[
+            ("ant", b"A small insect living in large groups."),
+            ("bee", b"An insect making honey."),
+            ("cow", b"A farm animal giving milk."),
+        ],
+        false,
+    );
+
+    // The .dict compressed in chunks of 16 bytes, the .idx with gzip
+    let dict = std::fs::read(ifo.with_extension("dict")).unwrap();
+    let dz = dictzip(&dict, 16);
+    let chunks = dictzip_chunks(&dz).expect("No dictzip chunks");
+    assert_eq!(chunks.chunk_length, 16);
+    assert_eq!(chunks.chunks.len(), dict.len().div_ceil(16));
+    let mut whole = Vec::new();
+    flate2::read::GzDecoder::new(dz.as_slice())
+        .read_to_end(&mut whole)
+        .unwrap();
+    assert_eq!(whole, dict);
+    std::fs::write(ifo.with_extension("dict.dz"), dz).unwrap();
+    std::fs::remove_file(ifo.with_extension("dict")).unwrap();
+    let idx = std::fs::read(ifo.with_extension("idx")).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&idx).unwrap();
+    std::fs::write(ifo.with_extension("idx.gz"), encoder.finish().unwrap()).unwrap();
+    std::fs::remove_file(ifo.with_extension("idx")).unwrap();
+
+    let dictionary = StarDict::open(&ifo).unwrap();
+    // Entries across several chunks, the last one ending with the file
+    assert_eq!(
+        dictionary.lookup("ant")[0].text,
+        "A small insect living in large groups."
+    );
+    assert_eq!(dictionary.lookup("bee")[0].text, "An insect making honey.");
+    assert_eq!(
+        dictionary.lookup("Cow")[0].text,
+        "A farm animal giving milk."
+    );
+
+    // A gzip file without the RA field has no chunks
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&dict).unwrap();
+    assert_eq!(dictzip_chunks(&encoder.finish().unwrap()), None);
+    let _ = std::fs::remove_dir_all(&folder);
+}
+
+/**
+ * Tests that the dictionaries of the folders are loaded
+ * and that the language of the book picks the dictionary
+ */
+#[test]
+fn dictionary_chosen_by_language() {
+    use crate::helper::dictionary::{default_dictionary, load_dictionaries, Dictionary};
+    use std::sync::Arc;
+
+    let folder = std::env::temp_dir().join("ebook-reader-dictionary-language");
+    let _ = std::fs::remove_dir_all(&folder);
+    write_stardict(
+        &folder.join("it"),
+        "Italiano",
+        "sametypesequence=m\n",
+        &[("casa", b"Abitazione.")],
+        false,
+    );
+    write_stardict(
+        &folder.join("fr"),
+        "Français",
+        "sametypesequence=m\n",
+        &[("maison", b"Habitation.")],
+        false,
+    );
+    write_stardict(
+        &folder,
+        "English",
+        "sametypesequence=m\nlang=en\n",
+        &[("house", b"A building.")],
+        false,
+    );
+
+    let dictionaries: Vec<Arc<dyn Dictionary>> = load_dictionaries(&folder)
+        .into_iter()
+        .map(|dictionary| Arc::new(dictionary) as Arc<dyn Dictionary>)
+        .collect();
+    let names: Vec<&str> = dictionaries.iter().map(|d| d.name()).collect();
+    assert_eq!(names, vec!["English", "Français", "Italiano"]);
+
+    assert_eq!(default_dictionary(&dictionaries, Some("it-IT")), Some(2));
+    assert_eq!(default_dictionary(&dictionaries, Some("FR")), Some(1));
+    // No dictionary in the language of the book, the first one is used
+    assert_eq!(default_dictionary(&dictionaries, Some("de")), Some(0));
+    assert_eq!(default_dictionary(&dictionaries, None), Some(0));
+    assert_eq!(default_dictionary(&[], Some("en")), None);
+    let _ = std::fs::remove_dir_all(&folder);
+}
+
+/**
+ * Tests finding the word clicked in a paragraph and the sentence it is in
+ */
+#[test]
+fn word_and_sentence_at_position() {
+    use crate::helper::dictionary::{sentence_at, word_at};
+
+    let text = "He said “well-known” things in a café. Don't go; it's the end.";
+    assert_eq!(word_at(text, 0).as_deref(), Some("He"));
+    assert_eq!(word_at(text, 1).as_deref(), Some("He"));
+    let well = text.find("known").unwrap();
+    assert_eq!(word_at(text, well).as_deref(), Some("well-known"));
+    assert_eq!(
+        word_at(text, text.find("Don").unwrap() + 4).as_deref(),
+        Some("Don't")
+    );
+    // Between two words, on a space or a quote
+    assert_eq!(word_at(text, 2).as_deref(), Some("He"));
+    assert_eq!(word_at(text, text.find(';').unwrap() + 1), None);
+    // In the middle of a multibyte character
+    assert_eq!(
+        word_at(text, text.find('é').unwrap() + 1).as_deref(),
+        Some("café")
+    );
+
+    assert_eq!(
+        sentence_at(text, well),
+        "He said “well-known” things in a café."
+    );
+    assert_eq!(
+        sentence_at(text, text.find("end").unwrap()),
+        "Don't go; it's the end."
+    );
+}
+
+/**
+ * Tests that the history keeps the last words looked up, once each, and is saved
+ */
+#[test]
+fn lookup_history_saved() {
+    use crate::helper::dictionary::{LookupEntry, LookupHistory, LOOKUP_HISTORY_LIMIT};
+
+    let path = std::env::temp_dir()
+        .join("ebook-reader-lookup-history")
+        .join("lookups.json");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(LookupHistory::load(&path), LookupHistory::default());
+
+    let mut history = LookupHistory::default();
+    for word in ["apple", "book", "Apple", "cat"] {
+        history.add(LookupEntry {
+            word: word.to_string(),
+            book: "Test book".to_string(),
+            context: format!("A sentence with {}.", word),
+            time: 0,
+        });
+    }
+    assert_eq!(history.recent(10), vec!["cat", "Apple", "book"]);
+    assert_eq!(history.recent(2), vec!["cat", "Apple"]);
+
+    history.save(&path).unwrap();
+    assert_eq!(LookupHistory::load(&path), history);
+
+    for i in 0..LOOKUP_HISTORY_LIMIT + 10 {
+        history.add(LookupEntry {
+            word: format!("word{}", i),
+            ..Default::default()
+        });
+    }
+    assert_eq!(history.entries.len(), LOOKUP_HISTORY_LIMIT);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,81 @@
+/* BOOK EDITOR TESTS */
+
+#[allow(unused)]
+const TEST_FILE_PATH: &str = "./src/library/hope-prisoner-of-zenda.epub";
+
+/**
+ * Copy of the test book in a folder of its own, removed with the folder
+ */
+#[cfg(test)]
+fn edit_test_book() -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = crate::tests::test_folder();
+    let path = dir.path().join("zenda.epub");
+    std::fs::copy(TEST_FILE_PATH, &path).expect("Unable to copy test book");
+    (dir, path)
+}
+
+/**
+ * Tests that the edited page is saved into a copy of the book, with nothing left behind
+ */
+#[test]
+fn edited_page_saved() {
+    use crate::helper::opf::load_metadata;
+    use crate::model::edit_state::save_edited_page;
+    use std::io::Read;
+    use std::path::Path;
+
+    let (_dir, path) = edit_test_book();
+    let folder = path.parent().unwrap().to_path_buf();
+    let title = load_metadata(&path).unwrap().title;
+    let html = "<html><body><p>Edited</p></body></html>";
+    let saved = save_edited_page(&path, Path::new("OEBPS/ch01.xhtml"), html)
+        .expect("Unable to save edited page");
+    assert_eq!(saved, folder.join("zenda-edit.epub"));
+    assert_eq!(
+        load_metadata(&saved).unwrap().title,
+        format!("{} (edit)", title)
+    );
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&saved).unwrap()).unwrap();
+    let mut page = String::new();
+    archive
+        .by_name("OEBPS/ch01.xhtml")
+        .unwrap()
+        .read_to_string(&mut page)
+        .unwrap();
+    assert_eq!(page, html);
+
+    // Saved again over the previous copy, without temporary files left in the folder
+    save_edited_page(&path, Path::new("OEBPS/ch01.xhtml"), html).expect("Unable to save again");
+    let mut files: Vec<String> = std::fs::read_dir(&folder)
+        .unwrap()
+        .flatten()
+        .map(|file| file.file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["zenda-edit.epub", "zenda.epub"]);
+}
+
+/**
+ * Tests the errors of a failed save, and that it leaves the library as it was
+ */
+#[test]
+fn edited_page_not_saved() {
+    use crate::model::edit_state::{save_edited_page, SaveError};
+    use std::path::Path;
+
+    let (_dir, path) = edit_test_book();
+    let folder = path.parent().unwrap().to_path_buf();
+    let result = save_edited_page(&path, Path::new("OEBPS/missing.xhtml"), "<p/>");
+    assert!(matches!(result, Err(SaveError::NoPage)));
+
+    std::fs::write(&path, "Not a book").unwrap();
+    let result = save_edited_page(&path, Path::new("OEBPS/ch01.xhtml"), "<p/>");
+    assert!(matches!(result, Err(SaveError::Read(_))));
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("unable to read the book"));
+
+    let files: Vec<_> = std::fs::read_dir(&folder).unwrap().flatten().collect();
+    assert_eq!(files.len(), 1);
+}
@@ -0,0 +1,121 @@
+/* EPUB WRITER TESTS */
+
+/**
+ * Archive of an EPUB badly packed: the mimetype compressed and after another entry
+ */
+#[cfg(test)]
+fn badly_packed_epub() -> Vec<u8> {
+    use std::io::{Cursor, Write};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, content, options) in [
+        ("META-INF/container.xml", "<container/>", deflated),
+        ("mimetype", "application/epub+zip", deflated),
+        ("OEBPS/content.opf", "<package/>", deflated),
+        ("OEBPS/chapter1.xhtml", "<p>Old page</p>", stored),
+        ("OEBPS/chapter2.xhtml", "<p>Other page</p>", deflated),
+    ] {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/**
+ * Tests that the rewritten archive has the mimetype first and uncompressed, the other
+ * entries in their order and with their compression, and only the changed ones replaced
+ */
+#[test]
+fn epub_rewritten_entry_by_entry() {
+    use crate::helper::epub_writer::rewrite_epub;
+    use std::io::{Cursor, Read};
+    use zip::{CompressionMethod, ZipArchive};
+
+    let mut archive = ZipArchive::new(Cursor::new(badly_packed_epub())).unwrap();
+    let replaced = vec![
+        (
+            "OEBPS/chapter1.xhtml".to_string(),
+            b"<p>New page</p>".to_vec(),
+        ),
+        ("OEBPS/notes.xhtml".to_string(), b"<p>Notes</p>".to_vec()),
+    ];
+    let bytes = rewrite_epub(&mut archive, Cursor::new(Vec::new()), &replaced)
+        .expect("Unable to write book")
+        .into_inner();
+    assert_eq!(&bytes[30..58], b"mimetypeapplication/epub+zip");
+
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+    let names: Vec<&str> = archive.file_names().collect();
+    assert_eq!(names.len(), 6);
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).unwrap();
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        entries.push((entry.name().to_string(), entry.compression(), content));
+    }
+    let expected = [
+        (
+            "mimetype",
+            CompressionMethod::Stored,
+            "application/epub+zip",
+        ),
+        (
+            "META-INF/container.xml",
+            CompressionMethod::Deflated,
+            "<container/>",
+        ),
+        (
+            "OEBPS/content.opf",
+            CompressionMethod::Deflated,
+            "<package/>",
+        ),
+        (
+            "OEBPS/chapter1.xhtml",
+            CompressionMethod::Stored,
+            "<p>New page</p>",
+        ),
+        (
+            "OEBPS/chapter2.xhtml",
+            CompressionMethod::Deflated,
+            "<p>Other page</p>",
+        ),
+        (
+            "OEBPS/notes.xhtml",
+            CompressionMethod::Deflated,
+            "<p>Notes</p>",
+        ),
+    ];
+    for ((name, compression, content), expected) in entries.iter().zip(expected) {
+        assert_eq!((name.as_str(), *compression, content.as_str()), expected);
+    }
+}
+
+/**
+ * Tests that a failed copy leaves nothing behind
+ */
+#[test]
+fn epub_copy_failed_cleaned() {
+    use crate::helper::epub_writer::write_epub;
+
+    let folder = std::env::temp_dir().join("epub-writer-test");
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    let epub_path = folder.join("book.epub");
+    std::fs::write(&epub_path, badly_packed_epub()).unwrap();
+
+    let target = folder.join("book-edit.epub");
+    write_epub(&epub_path, &target, &[]).expect("Unable to copy book");
+    assert!(target.exists());
+
+    // Not an archive
+    let text_path = folder.join("book.txt");
+    std::fs::write(&text_path, "Not a book").unwrap();
+    let target = folder.join("text-edit.epub");
+    assert!(write_epub(&text_path, &target, &[]).is_err());
+    assert!(!target.exists());
+    std::fs::remove_dir_all(folder).expect("Unable to remove test folder");
+}
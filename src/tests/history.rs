@@ -0,0 +1,60 @@
+/* HISTORY TESTS */
+
+/**
+ * Tests going back and forward between the positions left by jumps
+ */
+#[test]
+fn history_back_and_forward() {
+    use crate::helper::history::{History, Position};
+
+    let position = |page| Position {
+        page,
+        chapter_position: 0.5,
+    };
+    let mut history = History::default();
+    assert!(!history.can_go_back());
+
+    // Link from page 3 to page 10, then search result from page 10 to page 20
+    history.record(position(3));
+    history.record(position(10));
+    assert_eq!(history.back(position(20)), Some(position(10)));
+    assert_eq!(history.back(position(10)), Some(position(3)));
+    assert_eq!(history.back(position(3)), None);
+
+    assert!(history.can_go_forward());
+    assert_eq!(history.forward(position(3)), Some(position(10)));
+
+    // A new jump drops the forward positions
+    history.record(position(10));
+    assert!(!history.can_go_forward());
+    assert_eq!(history.back(position(15)), Some(position(10)));
+    assert_eq!(history.back(position(10)), Some(position(3)));
+}
+
+/**
+ * Tests that the history keeps a limited number of positions
+ */
+#[test]
+fn history_limited() {
+    use crate::helper::config::HISTORY_LIMIT;
+    use crate::helper::history::{History, Position};
+
+    let mut history = History::default();
+    for page in 0..HISTORY_LIMIT + 10 {
+        history.record(Position {
+            page,
+            chapter_position: 0.0,
+        });
+    }
+    let mut current = Position {
+        page: 0,
+        chapter_position: 0.0,
+    };
+    let mut count = 0;
+    while let Some(position) = history.back(current) {
+        current = position;
+        count += 1;
+    }
+    assert_eq!(count, HISTORY_LIMIT);
+    assert_eq!(current.page, 10);
+}
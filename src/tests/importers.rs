@@ -0,0 +1,216 @@
+/* IMPORTERS TESTS */
+
+/**
+ * Tests reading the highlights, notes and bookmarks of a Kindle "My Clippings.txt"
+ */
+#[test]
+fn kindle_clippings_parsed() {
+    use crate::helper::{annotations::AnnotationKind, importers::parse_kindle_clippings};
+
+    let clippings = "\u{feff}The Test Book: A Story (Doe, Jane)\r
+- Your Highlight on page 12 | Location 170-172 | Added on Monday, March 6, 2023 9:15:01 PM\r
+\r
+It was a dark and stormy night.\r
+==========\r
+The Test Book: A Story (Doe, Jane)\r
+- Your Note on page 12 | Location 172 | Added on Monday, March 6, 2023 9:16:00 PM\r
+\r
+A classic opening.\r
+==========\r
+Another Book (John Smith)\r
+- Your Bookmark on Location 40 | Added on Tuesday, March 7, 2023 8:00:00 AM\r
+\r
+\r
+==========\r
+";
+    let annotations = parse_kindle_clippings(clippings);
+    assert_eq!(annotations.len(), 2);
+
+    let highlight = &annotations[0];
+    assert_eq!(highlight.title, "The Test Book: A Story");
+    assert_eq!(highlight.author, "Doe, Jane");
+    assert_eq!(highlight.kind, AnnotationKind::Highlight);
+    assert_eq!(highlight.text, "It was a dark and stormy night.");
+    assert_eq!(highlight.note, "A classic opening.");
+    assert_eq!(highlight.time, 1678137301);
+
+    let bookmark = &annotations[1];
+    assert_eq!(bookmark.title, "Another Book");
+    assert_eq!(bookmark.author, "John Smith");
+    assert_eq!(bookmark.kind, AnnotationKind::Bookmark);
+    assert!(bookmark.text.is_empty());
+}
+
+/**
+ * Tests reading the notes KOReader keeps for a book, in the recent and the older format
+ */
+#[test]
+fn koreader_metadata_parsed() {
+    use crate::helper::{annotations::AnnotationKind, importers::parse_koreader_metadata};
+
+    let metadata = r#"-- we can read Lua syntax here!
+return {
+    ["annotations"] = {
+        [1] = {
+            ["chapter"] = "Chapter One",
+            ["datetime"] = "2024-01-02 10:11:12",
+            ["drawer"] = "lighten",
+            ["note"] = "Line one\
+Line two",
+            ["page"] = "/body/DocFragment[3]/body/p[4]/text().0",
+            ["pos0"] = "/body/DocFragment[3]/body/p[4]/text().0",
+            ["pos1"] = "/body/DocFragment[3]/body/p[4]/text().57",
+            ["text"] = "He said \"hello\" to me.",
+        },
+        [2] = {
+            ["chapter"] = "Chapter Two",
+            ["datetime"] = "2024-01-03 08:00:00",
+            ["page"] = "/body/DocFragment[5]/body/p[1]/text().0",
+            ["text"] = "in Chapter Two",
+        },
+    },
+    ["doc_props"] = {
+        ["authors"] = "Jane Doe",
+        ["identifiers"] = "calibre:12\nurn:uuid:AB-12",
+        ["title"] = "The Test Book",
+    },
+    ["percent_finished"] = 0.25,
+    ["summary"] = { status = "reading", modified = "2024-01-03" },
+    ["readermenu"] = { true, false, nil },
+}
+"#;
+    let annotations = parse_koreader_metadata(metadata).expect("Unable to parse metadata");
+    assert_eq!(annotations.len(), 2);
+
+    let highlight = &annotations[0];
+    assert_eq!(highlight.title, "The Test Book");
+    assert_eq!(highlight.author, "Jane Doe");
+    assert_eq!(highlight.identifiers, vec!["calibre:12", "urn:uuid:AB-12"]);
+    assert_eq!(highlight.kind, AnnotationKind::Highlight);
+    assert_eq!(highlight.text, "He said \"hello\" to me.");
+    assert_eq!(highlight.note, "Line one\nLine two");
+    assert_eq!(highlight.chapter, "Chapter One");
+    assert_eq!(highlight.page_hint, Some(2));
+    assert_eq!(highlight.time, 1704190272);
+
+    let bookmark = &annotations[1];
+    assert_eq!(bookmark.kind, AnnotationKind::Bookmark);
+    assert!(bookmark.text.is_empty());
+    assert_eq!(bookmark.page_hint, Some(4));
+
+    let older = r#"return {
+    ["highlight"] = {
+        [7] = {
+            [1] = { ["text"] = "Older highlight.", ["pos0"] = "/body/DocFragment[7]/p[1]" },
+        },
+    },
+    ["stats"] = { ["title"] = "Old Book", ["authors"] = "Someone" },
+}"#;
+    let annotations = parse_koreader_metadata(older).expect("Unable to parse metadata");
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].title, "Old Book");
+    assert_eq!(annotations[0].text, "Older highlight.");
+    assert_eq!(annotations[0].page_hint, Some(6));
+
+    assert!(parse_koreader_metadata("return { [\"title\"] = ").is_err());
+}
+
+/**
+ * Tests matching the imported annotations to the books of the library
+ */
+#[test]
+fn imported_annotations_matched_to_books() {
+    use crate::helper::{
+        annotations::AnnotationKind,
+        importers::{match_book, ImportedAnnotation},
+    };
+
+    let books = [
+        ("The Test Book", "Jane Doe", "urn:uuid:ab-12"),
+        ("Another Book", "John Smith", "9780000000002"),
+        ("Another Book", "Someone Else", ""),
+    ];
+    let imported = |title: &str, author: &str, identifiers: &[&str]| ImportedAnnotation {
+        title: title.to_string(),
+        author: author.to_string(),
+        identifiers: identifiers.iter().map(|id| id.to_string()).collect(),
+        kind: AnnotationKind::Highlight,
+        text: "Text".to_string(),
+        note: String::new(),
+        chapter: String::new(),
+        time: 0,
+        page_hint: None,
+    };
+
+    // Identifier, whatever the title
+    assert_eq!(
+        match_book(&books, &imported("Other title", "", &["uuid:AB12"])),
+        Some(0)
+    );
+    assert_eq!(
+        match_book(&books, &imported("", "", &["ISBN 978-0-00-000000-2"])),
+        Some(1)
+    );
+    // Title with a subtitle, author written the other way round
+    assert_eq!(
+        match_book(
+            &books,
+            &imported("The Test Book: A Story", "Doe, Jane", &[])
+        ),
+        Some(0)
+    );
+    // Same title, the author tells the books apart
+    assert_eq!(
+        match_book(&books, &imported("Another Book", "Smith, John", &[])),
+        Some(1)
+    );
+    assert_eq!(match_book(&books, &imported("Another Book", "", &[])), None);
+    assert_eq!(
+        match_book(&books, &imported("The Test Book", "Nobody", &[])),
+        None
+    );
+    assert_eq!(match_book(&books, &imported("Unknown", "", &[])), None);
+}
+
+/**
+ * Tests finding a passage in the pages of a book, as it is or with some words changed
+ */
+#[test]
+fn passage_found_in_pages() {
+    use crate::helper::search::find_passage;
+
+    let pages = vec![
+        (
+            1,
+            "Chapter one. The morning was quiet and grey.".to_string(),
+        ),
+        (
+            2,
+            "Chapter two. Nobody knew where the old captain had hidden the map, \
+             but everyone in the village had a theory about it."
+                .to_string(),
+        ),
+        (3, "Chapter three. The map was found.".to_string()),
+    ];
+
+    let found = find_passage(&pages, "the OLD captain had hidden—the map", None).unwrap();
+    assert_eq!(found.page, 2);
+    assert_eq!(found.score, 1.0);
+    assert!(found.position > 0.1 && found.position < 0.5);
+
+    // Words misread by the OCR or changed in another edition
+    let found = find_passage(&pages, "everyone in the vilage had a theory about it", None).unwrap();
+    assert_eq!(found.page, 2);
+    assert!(found.score < 1.0);
+    assert!(found.position > 0.5);
+
+    // The hint is looked at first
+    assert_eq!(find_passage(&pages, "the map", Some(3)).unwrap().page, 3);
+    assert_eq!(find_passage(&pages, "the map", None).unwrap().page, 2);
+
+    assert_eq!(
+        find_passage(&pages, "A sentence of another book.", None),
+        None
+    );
+    assert_eq!(find_passage(&pages, " ... ", None), None);
+}
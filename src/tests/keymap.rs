@@ -0,0 +1,140 @@
+/* KEYMAP TESTS */
+
+/**
+ * Tests that key bindings are parsed with their modifiers
+ */
+#[test]
+fn key_binding_parsed() {
+    use crate::helper::keymap::KeyBinding;
+
+    let binding = KeyBinding::parse("Ctrl+F").expect("Invalid binding");
+    assert_eq!(binding, KeyBinding::new(true, false, false, "f"));
+    assert_eq!(binding.to_string(), "Ctrl+F");
+
+    assert_eq!(
+        KeyBinding::parse("Ctrl++"),
+        Some(KeyBinding::new(true, false, false, "+"))
+    );
+    assert_eq!(
+        KeyBinding::parse("Shift+Space"),
+        Some(KeyBinding::new(false, false, true, " "))
+    );
+    // Shift is needed to type "?" on some layouts, it does not change the key
+    assert_eq!(
+        KeyBinding::parse("?"),
+        Some(KeyBinding::new(false, false, true, "?"))
+    );
+    assert_eq!(KeyBinding::parse("Hyper+X"), None);
+    assert_eq!(KeyBinding::parse(""), None);
+}
+
+/**
+ * Tests the default bindings of the reader
+ */
+#[test]
+fn default_keymap() {
+    use crate::helper::keymap::{KeyBinding, Keymap, ReaderAction};
+
+    let keymap = Keymap::default();
+    let action = |ctrl, shift, key| keymap.action(&KeyBinding::new(ctrl, false, shift, key));
+    assert_eq!(
+        action(false, false, "ArrowRight"),
+        Some(ReaderAction::NextPage)
+    );
+    assert_eq!(action(false, false, " "), Some(ReaderAction::NextPage));
+    assert_eq!(action(false, true, " "), Some(ReaderAction::PrevPage));
+    assert_eq!(action(false, false, "PageUp"), Some(ReaderAction::PrevPage));
+    assert_eq!(
+        action(false, false, "ArrowDown"),
+        Some(ReaderAction::NextPage)
+    );
+    assert_eq!(
+        action(false, false, "ArrowUp"),
+        Some(ReaderAction::PrevPage)
+    );
+    assert_eq!(action(false, false, "Home"), Some(ReaderAction::FirstPage));
+    assert_eq!(action(true, false, "F"), Some(ReaderAction::Search));
+    assert_eq!(action(true, false, "-"), Some(ReaderAction::DecreaseFont));
+    assert_eq!(action(false, false, "Escape"), Some(ReaderAction::Back));
+    assert_eq!(action(false, false, "f"), None);
+}
+
+/**
+ * Tests that the settings file replaces the keys of an action
+ */
+#[test]
+fn keymap_overridden_by_settings() {
+    use crate::helper::keymap::{KeyBinding, Keymap, ReaderAction};
+
+    let settings = crate::helper::settings::Settings::from_json(
+        r#"{ "keymap": { "next_page": ["L", "ArrowDown"], "unknown": ["X"] } }"#,
+    )
+    .expect("Invalid settings");
+    let keymap = Keymap::with_overrides(&settings.keymap);
+
+    let key = |key| KeyBinding::new(false, false, false, key);
+    assert_eq!(keymap.action(&key("l")), Some(ReaderAction::NextPage));
+    assert_eq!(
+        keymap.action(&key("ArrowDown")),
+        Some(ReaderAction::NextPage)
+    );
+    assert_eq!(keymap.action(&key("ArrowRight")), None);
+    assert_eq!(
+        keymap.action(&key("ArrowLeft")),
+        Some(ReaderAction::PrevPage)
+    );
+
+    let help = keymap.help();
+    assert!(help.contains(&("Next page".to_string(), "L, ArrowDown".to_string())));
+
+    // Settings without a keymap keep the defaults
+    let settings = crate::helper::settings::Settings::from_json("{}").expect("Invalid settings");
+    assert_eq!(Keymap::with_overrides(&settings.keymap), Keymap::default());
+}
+
+/**
+ * Tests that a key given to two actions in the settings file stays with the first action
+ */
+#[test]
+fn keymap_conflict_kept_for_first_action() {
+    use crate::helper::keymap::{KeyBinding, Keymap, ReaderAction};
+
+    let settings = crate::helper::settings::Settings::from_json(
+        r#"{ "keymap": { "search": ["L"], "prev_page": ["L", "K"], "next_page": ["L"] } }"#,
+    )
+    .expect("Invalid settings");
+    let keymap = Keymap::with_overrides(&settings.keymap);
+
+    let key = |key| KeyBinding::new(false, false, false, key);
+    assert_eq!(keymap.action(&key("l")), Some(ReaderAction::NextPage));
+    assert_eq!(keymap.action(&key("k")), Some(ReaderAction::PrevPage));
+    assert!(keymap.keys(ReaderAction::Search).is_empty());
+}
+
+/**
+ * Tests that the arrows turn the pages the other way in the books read from right to left
+ */
+#[test]
+fn arrows_mirrored_in_rtl_books() {
+    use crate::helper::keymap::{KeyBinding, Keymap, ReaderAction};
+
+    let keymap = Keymap::default();
+    let key = |key| KeyBinding::new(false, false, false, key);
+    assert_eq!(
+        keymap.book_action(&key("ArrowLeft"), true),
+        Some(ReaderAction::NextPage)
+    );
+    assert_eq!(
+        keymap.book_action(&key("ArrowRight"), true),
+        Some(ReaderAction::PrevPage)
+    );
+    assert_eq!(
+        keymap.book_action(&key("ArrowLeft"), false),
+        Some(ReaderAction::PrevPage)
+    );
+    // Keys without a side keep their action
+    assert_eq!(
+        keymap.book_action(&key("PageDown"), true),
+        Some(ReaderAction::NextPage)
+    );
+}
@@ -0,0 +1,363 @@
+/* LAYOUT TESTS */
+
+/**
+ * Text of the blocks of a page
+ */
+#[cfg(test)]
+fn block_texts(blocks: &[crate::helper::layout::Block]) -> Vec<String> {
+    use crate::helper::layout::Block;
+
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Text(text) => text.spans.iter().map(|s| s.text.as_str()).collect(),
+            Block::Table(_) => String::from("<table>"),
+            Block::Rule(_) => String::from("<hr>"),
+        })
+        .collect()
+}
+
+/**
+ * Tests laying out a page in blocks with the styles of the elements and of the book
+ */
+#[test]
+fn layout_blocks() {
+    use crate::helper::layout::{layout_page, page_stylesheet, Block, TextAlign};
+
+    let page = r#"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head>
+  <title>Chapter I</title>
+  <link rel="stylesheet" type="text/css" href="../css/book.css"/>
+  <style>p.first { text-indent: 0 }</style>
+</head>
+<body>
+  <h1 class="chapter">Chapter   I</h1>
+  <p class="first">It was a <em>dark</em> and <b>stormy</b>
+     night;<br/>the rain fell.</p>
+  <p>Except at <a href="notes.xhtml#n1">occasional</a> intervals&nbsp;...</p>
+  <div class="hidden">Not shown</div>
+</body>
+</html>"#;
+    let book_css = "
+        .chapter { text-align: center; margin-top: 3em }
+        p { text-indent: 1.5em; margin: 0 }
+        .hidden { display: none }
+    "
+    .to_string();
+
+    let stylesheet = page_stylesheet(page, &[book_css]);
+    let blocks = layout_page(page, &stylesheet);
+    assert_eq!(
+        block_texts(&blocks),
+        vec![
+            "Chapter I",
+            "It was a dark and stormy night;\nthe rain fell.",
+            "Except at occasional intervals\u{a0}...",
+        ]
+    );
+
+    let text = |i: usize| match &blocks[i] {
+        Block::Text(text) => text.clone(),
+        block => panic!("Expected text, found {:?}", block),
+    };
+    let heading = text(0);
+    assert_eq!(heading.text_align, TextAlign::Center);
+    // Lengths in em are relative to the font size of the element
+    assert_eq!(heading.space_before, 3.0 * 2.0);
+    assert!(heading.spans[0].bold);
+    assert_eq!(heading.spans[0].size, 2.0);
+
+    // The <style> of the page comes after the stylesheet of the book
+    let first = text(1);
+    assert_eq!(first.text_indent, 0.0);
+    // The bottom margin of the heading and the top margin of the paragraph collapse
+    assert_eq!(first.space_before, 0.67 * 2.0);
+    let styles: Vec<(bool, bool)> = first.spans.iter().map(|s| (s.italic, s.bold)).collect();
+    assert_eq!(
+        styles,
+        vec![
+            (false, false),
+            (true, false),
+            (false, false),
+            (false, true),
+            (false, false)
+        ]
+    );
+
+    let second = text(2);
+    assert_eq!(second.text_indent, 1.5);
+    assert_eq!(second.spans[1].link, Some("notes.xhtml#n1".to_string()));
+}
+
+/**
+ * Tests the stylesheets linked by a page
+ */
+#[test]
+fn linked_stylesheets() {
+    use crate::helper::layout::stylesheet_hrefs;
+
+    let page = r#"<html><head>
+        <link rel="stylesheet" href="../styles/main.css"/>
+        <link rel="icon" href="icon.png"/>
+        <link href="extra.css" rel="Stylesheet"/>
+    </head><body/></html>"#;
+    assert_eq!(
+        stylesheet_hrefs(page),
+        vec!["../styles/main.css".to_string(), "extra.css".to_string()]
+    );
+}
+
+/**
+ * Tests the widths of the columns of a table
+ */
+#[test]
+fn table_column_widths() {
+    use crate::helper::layout::column_widths;
+
+    let min = [2.0, 4.0, 6.0];
+    let max = [4.0, 10.0, 20.0];
+    // Everything fits
+    assert_eq!(column_widths(&min, &max, 40.0), vec![4.0, 10.0, 20.0]);
+    // Not even the narrowest widths fit, the table scrolls
+    assert_eq!(column_widths(&min, &max, 10.0), vec![2.0, 4.0, 6.0]);
+    // In between: half of the space each column can grow
+    assert_eq!(column_widths(&min, &max, 23.0), vec![3.0, 7.0, 13.0]);
+}
+
+/**
+ * Tests laying out a table with a caption, a header and cells over several columns
+ */
+#[test]
+fn layout_table() {
+    use crate::helper::layout::{layout_page, page_stylesheet, Block};
+
+    let page = r#"<html><body>
+  <p>Before</p>
+  <table>
+    <caption>Table 1</caption>
+    <thead><tr><th>Name</th><th>Value</th></tr></thead>
+    <tbody>
+      <tr><td>Length</td><td><p>12 <em>m</em></p></td></tr>
+      <tr><td colspan="2">Total</td></tr>
+    </tbody>
+  </table>
+</body></html>"#;
+    let blocks = layout_page(page, &page_stylesheet(page, &[]));
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(block_texts(&blocks[..2]), vec!["Before", "Table 1"]);
+
+    let table = match &blocks[2] {
+        Block::Table(table) => table,
+        block => panic!("Expected a table, found {:?}", block),
+    };
+    let rows: Vec<Vec<(String, bool, usize)>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    (
+                        block_texts(&cell.blocks).join("|"),
+                        cell.header,
+                        cell.colspan,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    let expected = vec![
+        vec![("Name", true, 1), ("Value", true, 1)],
+        vec![("Length", false, 1), ("12 m", false, 1)],
+        vec![("Total", false, 2)],
+    ];
+    assert_eq!(
+        rows,
+        expected
+            .into_iter()
+            .map(|row| row
+                .into_iter()
+                .map(|(text, header, colspan)| (text.to_string(), header, colspan))
+                .collect::<Vec<(String, bool, usize)>>())
+            .collect::<Vec<Vec<(String, bool, usize)>>>()
+    );
+
+    // The paragraph in a cell has no space above it
+    match &table.rows[1][1].blocks[0] {
+        Block::Text(text) => assert_eq!(text.space_before, 0.0),
+        block => panic!("Expected text, found {:?}", block),
+    }
+    // Columns as wide as their longest text, padding included
+    assert_eq!(table.column_widths.len(), 2);
+    assert_eq!(table.column_widths[0], 6.0 * 0.5 + 0.8);
+}
+
+/**
+ * Tests the markers of the list items for each list-style-type
+ */
+#[test]
+fn list_markers() {
+    use crate::helper::layout::list_marker;
+
+    let markers = [
+        ("disc", 1, "•"),
+        ("circle", 2, "◦"),
+        ("square", 3, "▪"),
+        ("decimal", 12, "12."),
+        ("decimal-leading-zero", 7, "07."),
+        ("lower-alpha", 28, "ab."),
+        ("upper-latin", 3, "C."),
+        ("lower-roman", 14, "xiv."),
+        ("upper-roman", 1994, "MCMXCIV."),
+        ("unknown", 1, "•"),
+    ];
+    for (list_style_type, number, marker) in markers {
+        assert_eq!(
+            list_marker(list_style_type, number),
+            Some(marker.to_string())
+        );
+    }
+    assert_eq!(list_marker("none", 1), None);
+}
+
+/**
+ * Tests laying out nested lists, block quotes and rules
+ */
+#[test]
+fn layout_lists_and_quotes() {
+    use crate::helper::layout::{layout_page, page_stylesheet, Block};
+
+    let page = r#"<html><body>
+  <ol start="3">
+    <li>Third
+      <ul><li>Nested</li></ul>
+    </li>
+    <li value="10">Tenth</li>
+  </ol>
+  <ol reversed="reversed" style="list-style-type: upper-roman"><li>Two</li><li>One</li></ol>
+  <hr/>
+  <blockquote><p>Quoted</p><blockquote><p>Twice</p></blockquote></blockquote>
+</body></html>"#;
+    let blocks = layout_page(page, &page_stylesheet(page, &[]));
+
+    let paragraphs: Vec<(String, Option<String>, Vec<f64>)> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Text(text) => Some((
+                text.spans.iter().map(|s| s.text.as_str()).collect(),
+                text.marker.clone(),
+                text.quote_bars.clone(),
+            )),
+            _ => None,
+        })
+        .collect();
+    let marker = |marker: &str| Some(marker.to_string());
+    assert_eq!(
+        paragraphs,
+        vec![
+            ("Third".to_string(), marker("3."), vec![]),
+            ("Nested".to_string(), marker("◦"), vec![]),
+            ("Tenth".to_string(), marker("10."), vec![]),
+            ("Two".to_string(), marker("II."), vec![]),
+            ("One".to_string(), marker("I."), vec![]),
+            ("Quoted".to_string(), None, vec![1.5]),
+            ("Twice".to_string(), None, vec![1.5, 4.0]),
+        ]
+    );
+
+    // The nested list is indented in its item
+    let margins: Vec<f64> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Text(text) => Some(text.margin_left),
+            _ => None,
+        })
+        .take(2)
+        .collect();
+    assert_eq!(margins, vec![2.5, 5.0]);
+    assert!(matches!(blocks[5], Block::Rule(_)));
+}
+
+/**
+ * Tests the direction of the first strong character of a text
+ */
+#[test]
+fn text_base_direction() {
+    use crate::helper::layout::{base_direction, Direction};
+
+    assert_eq!(base_direction("1. שלום עולם"), Some(Direction::Rtl));
+    assert_eq!(base_direction("« مرحبا »"), Some(Direction::Rtl));
+    assert_eq!(base_direction("(Hello) שלום"), Some(Direction::Ltr));
+    assert_eq!(base_direction("こんにちは"), Some(Direction::Ltr));
+    assert_eq!(base_direction("42 - 7"), None);
+}
+
+/**
+ * Tests the direction of the paragraphs: dir attribute, direction property and text
+ */
+#[test]
+fn layout_directions() {
+    use crate::helper::layout::{layout_page, page_stylesheet, Block, Direction, TextAlign};
+
+    let page = r#"<html><body>
+  <p>English paragraph</p>
+  <p>פסקה בעברית</p>
+  <div dir="rtl">
+    <p>Latin text in a right-to-left block</p>
+    <p dir="auto">Automatic</p>
+    <p style="direction: ltr; text-align: left">Left to right again</p>
+  </div>
+  <ul dir="rtl"><li>פריט</li></ul>
+  <blockquote><p>اقتباس</p></blockquote>
+</body></html>"#;
+    let blocks = layout_page(page, &page_stylesheet(page, &[]));
+    let texts: Vec<_> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Text(text) => Some(text.clone()),
+            _ => None,
+        })
+        .collect();
+    let directions: Vec<Direction> = texts.iter().map(|text| text.direction).collect();
+    assert_eq!(
+        directions,
+        vec![
+            Direction::Ltr,
+            Direction::Rtl,
+            Direction::Rtl,
+            Direction::Ltr,
+            Direction::Ltr,
+            Direction::Rtl,
+            Direction::Rtl,
+        ]
+    );
+    assert_eq!(texts[4].text_align, TextAlign::Left);
+
+    // Lists are indented, and quotes have their bar, on the right in a right-to-left block
+    let item = &texts[5];
+    assert_eq!((item.margin_left, item.margin_right), (0.0, 2.5));
+    assert!(item.marker.is_some());
+    assert_eq!(texts[6].quote_bars, vec![1.5]);
+    assert_eq!((texts[6].margin_left, texts[6].margin_right), (2.5, 2.5));
+}
+
+/**
+ * Tests the writing mode of a page, given by the styles of its html or body element
+ */
+#[test]
+fn page_writing_mode() {
+    use crate::helper::layout::{page_stylesheet, writing_mode, WritingMode};
+
+    let vertical = r#"<html><head><style>html { -epub-writing-mode: vertical-rl }</style></head>
+<body><p>縦書きの文章</p></body></html>"#;
+    assert_eq!(
+        writing_mode(vertical, &page_stylesheet(vertical, &[])),
+        WritingMode::VerticalRl
+    );
+
+    let horizontal = r#"<html><body style="writing-mode: horizontal-tb"><p>Text</p></body></html>"#;
+    assert_eq!(
+        writing_mode(horizontal, &page_stylesheet(horizontal, &[])),
+        WritingMode::HorizontalTb
+    );
+}
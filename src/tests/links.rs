@@ -0,0 +1,109 @@
+/* LINKS TESTS */
+
+/**
+ * Tests resolving the links relative to the current document
+ */
+#[test]
+fn resolve_internal_links() {
+    use crate::helper::links::{resolve_link, LinkTarget};
+    use std::path::{Path, PathBuf};
+
+    let current = Path::new("OEBPS/text/chapter_001.xhtml");
+    let internal = |path: &str, fragment: Option<&str>| LinkTarget::Internal {
+        path: PathBuf::from(path),
+        fragment: fragment.map(|f| f.to_string()),
+    };
+
+    assert_eq!(
+        resolve_link(current, "chapter_002.xhtml"),
+        internal("OEBPS/text/chapter_002.xhtml", None)
+    );
+    assert_eq!(
+        resolve_link(current, "../notes.xhtml#fn1"),
+        internal("OEBPS/notes.xhtml", Some("fn1"))
+    );
+    // Fragment only: same document
+    assert_eq!(
+        resolve_link(current, "#section-2"),
+        internal("OEBPS/text/chapter_001.xhtml", Some("section-2"))
+    );
+    // Escaped names and empty fragments
+    assert_eq!(
+        resolve_link(current, "chapter%20two.xhtml#"),
+        internal("OEBPS/text/chapter two.xhtml", None)
+    );
+}
+
+/**
+ * Tests that web and mail links are not looked for in the book
+ */
+#[test]
+fn resolve_external_links() {
+    use crate::helper::links::{resolve_link, LinkTarget};
+    use std::path::Path;
+
+    let current = Path::new("OEBPS/chapter_001.xhtml");
+    for url in [
+        "https://www.gutenberg.org/",
+        "HTTP://example.com/page#top",
+        "mailto:someone@example.com",
+    ] {
+        assert_eq!(
+            resolve_link(current, url),
+            LinkTarget::External(url.to_string())
+        );
+    }
+}
+
+/**
+ * Tests the position of an anchor in the page
+ */
+#[test]
+fn anchor_position() {
+    use crate::helper::links::fragment_position;
+
+    let html = "<html><head><title>Chapter title</title></head><body>\
+        <p>aaaaaaaaaa</p><p id=\"middle\">bbbbbbbbbb</p>\
+        <p><a name='end'></a>cccccccccc</p></body></html>";
+
+    assert_eq!(fragment_position(html, "middle"), Some(1.0 / 3.0));
+    assert_eq!(fragment_position(html, "end"), Some(2.0 / 3.0));
+    assert_eq!(fragment_position(html, "missing"), None);
+    // "data-id" is not an id
+    assert_eq!(
+        fragment_position("<body><p data-id=\"x\">text</p></body>", "x"),
+        None
+    );
+}
+
+/**
+ * Opener recording the links instead of opening them
+ */
+#[derive(Debug, Default)]
+struct StubOpener {
+    opened: std::cell::RefCell<Vec<String>>,
+}
+
+impl crate::helper::links::LinkOpener for StubOpener {
+    fn open(&self, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.opened.borrow_mut().push(url.to_string());
+        Ok(())
+    }
+}
+
+/**
+ * Tests that external links are opened only after the confirmation
+ */
+#[test]
+fn open_external_links() {
+    use crate::helper::links::open_external;
+
+    let opener = StubOpener::default();
+    let url = "https://www.gutenberg.org/";
+
+    assert!(!open_external(url, &opener, |_| false).unwrap());
+    assert!(opener.opened.borrow().is_empty());
+
+    assert!(open_external(url, &opener, |asked| asked == url).unwrap());
+    assert_eq!(*opener.opened.borrow(), vec![url.to_string()]);
+}
@@ -0,0 +1,108 @@
+/* LOCATION TESTS */
+
+#[allow(unused)]
+const WORD_COUNTS: [i32; 5] = [0, 100, 300, 0, 600];
+
+/**
+ * Tests that progress grows with the words read, not with the chapters
+ */
+#[test]
+fn progress_from_word_counts() {
+    use crate::helper::location::{progress, words_read};
+
+    assert_eq!(words_read(&WORD_COUNTS, 1, 0.0), 0);
+    assert_eq!(words_read(&WORD_COUNTS, 2, 0.5), 250);
+    assert_eq!(progress(&WORD_COUNTS, 2, 0.5), 0.25);
+    assert_eq!(progress(&WORD_COUNTS, 4, 1.0), 1.0);
+    // A short chapter moves the progress less than a long one
+    assert!(progress(&WORD_COUNTS, 2, 0.0) < 0.2);
+    assert_eq!(progress(&[], 1, 0.5), 0.0);
+}
+
+/**
+ * Tests that locations are counted in words
+ */
+#[test]
+fn locations_from_word_counts() {
+    use crate::helper::config::WORDS_PER_LOCATION;
+    use crate::helper::location::location;
+
+    let locations = (1000 + WORDS_PER_LOCATION - 1) / WORDS_PER_LOCATION;
+    assert_eq!(location(&WORD_COUNTS, 1, 0.0), (1, locations));
+    assert_eq!(location(&WORD_COUNTS, 4, 1.0), (locations, locations));
+}
+
+/**
+ * Tests that seeking finds the chapter and the position in it
+ */
+#[test]
+fn seek_to_fraction() {
+    use crate::helper::location::{progress, seek};
+
+    assert_eq!(seek(&WORD_COUNTS, 0.0), (1, 0.0));
+    assert_eq!(seek(&WORD_COUNTS, 0.25), (2, 0.5));
+    // Empty chapters are skipped
+    assert_eq!(seek(&WORD_COUNTS, 0.4), (4, 0.0));
+    assert_eq!(seek(&WORD_COUNTS, 1.0), (4, 1.0));
+
+    let (page, position) = seek(&WORD_COUNTS, 0.7);
+    assert!((progress(&WORD_COUNTS, page, position) - 0.7).abs() < 0.001);
+}
+
+/**
+ * Tests the progress, the locations and the seeking with the words of the spine of a
+ * real book, up to the end of its last chapter
+ */
+#[test]
+fn location_in_book_spine() {
+    use crate::helper::functions::count_words;
+    use crate::helper::location::{location, progress, seek};
+    use crate::helper::source::open_source;
+    use std::path::Path;
+
+    let mut source = open_source(Path::new("./src/library/hope-prisoner-of-zenda.epub"))
+        .expect("Unable to open book");
+    let counts = count_words(source.as_mut());
+    assert_eq!(counts.len(), source.len());
+    let last = counts.len() - 1;
+    assert!(counts[last] > 0);
+
+    // Entering the last chapter is not the end of the book, its end is
+    assert!(progress(&counts, last, 0.0) < 1.0);
+    assert_eq!(progress(&counts, last, 1.0), 1.0);
+    let (current, total) = location(&counts, last, 0.0);
+    assert!(current < total);
+    assert_eq!(location(&counts, last, 1.0), (total, total));
+
+    // The middle of the last chapter can be reached
+    let total_words: i64 = counts.iter().map(|words| *words as i64).sum();
+    let middle = 1.0 - counts[last] as f64 / total_words as f64 / 2.0;
+    let (page, position) = seek(&counts, middle);
+    assert_eq!(page, last);
+    assert!((position - 0.5).abs() < 0.01);
+}
+
+/**
+ * Tests the position read from the saved progress, and from the page alone saved
+ * by older versions
+ */
+#[test]
+fn saved_position_parsed() {
+    use crate::helper::history::Position;
+    use crate::helper::location::parse_saved_position;
+
+    let position = Position {
+        page: 4,
+        chapter_position: 0.25,
+    };
+    let json = serde_json::to_string(&position).unwrap();
+    assert_eq!(parse_saved_position(json.as_str()), Some(position));
+    assert_eq!(
+        parse_saved_position("7"),
+        Some(Position {
+            page: 7,
+            chapter_position: 0.0
+        })
+    );
+    assert_eq!(parse_saved_position("{}"), None);
+}
@@ -0,0 +1,167 @@
+#[allow(unused)]
+const TEST_FILE_PATH: &str = "./src/library/hope-prisoner-of-zenda.epub";
+#[allow(unused)]
+const TEST_CONTAINER: &str = "<?xml version=\"1.0\"?>
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">
+  <rootfiles>
+    <rootfile full-path=\"content/book.opf\" media-type=\"application/oebps-package+xml\"/>
+  </rootfiles>
+</container>";
+#[allow(unused)]
+const TEST_OPF: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"2.0\" unique-identifier=\"id\">
+  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:opf=\"http://www.idpf.org/2007/opf\">
+    <dc:identifier id=\"id\">urn:uuid:1234</dc:identifier>
+    <dc:title>Old Title</dc:title>
+    <dc:creator opf:role=\"aut\" opf:file-as=\"Author, First\">First Author</dc:creator>
+    <dc:creator opf:role=\"aut\">Second Author</dc:creator>
+    <dc:subject>Fiction</dc:subject>
+    <dc:language>en</dc:language>
+    <meta name=\"calibre:series\" content=\"Old Series\"/>
+  </metadata>
+  <manifest>
+    <item id=\"chapter\" href=\"chapter.xhtml\" media-type=\"application/xhtml+xml\"/>
+  </manifest>
+</package>";
+
+/* METADATA TESTS */
+
+/**
+ * Tests that the package document path is read from container.xml
+ */
+#[test]
+fn opf_path_found_in_container() {
+    let path = crate::helper::opf::find_opf_path(TEST_CONTAINER);
+    assert_eq!(path, Some("content/book.opf".to_string()));
+}
+
+/**
+ * Tests that the metadata is read from the package document
+ */
+#[test]
+fn metadata_read_from_opf() {
+    let opf = crate::helper::xml::XmlDocument::parse(TEST_OPF).expect("Unable to parse opf");
+    let metadata = crate::helper::opf::read_metadata(&opf);
+    assert_eq!(metadata.title, "Old Title");
+    assert_eq!(metadata.authors, vec!["First Author", "Second Author"]);
+    assert_eq!(metadata.tags, vec!["Fiction"]);
+    assert_eq!(metadata.language, "en");
+    assert_eq!(metadata.series, Some("Old Series".to_string()));
+}
+
+/**
+ * Tests the page progression direction of the spine
+ */
+#[test]
+fn page_progression_read_from_spine() {
+    use crate::helper::opf::{read_page_progression, PageProgression};
+    use crate::helper::xml::XmlDocument;
+
+    let opf = XmlDocument::parse(TEST_OPF).expect("Unable to parse opf");
+    assert_eq!(read_page_progression(&opf), PageProgression::Ltr);
+
+    let rtl = TEST_OPF.replace(
+        "</manifest>",
+        "</manifest>\n  <spine page-progression-direction=\"rtl\"><itemref idref=\"chapter\"/></spine>",
+    );
+    let opf = XmlDocument::parse(rtl.as_str()).expect("Unable to parse opf");
+    assert_eq!(read_page_progression(&opf), PageProgression::Rtl);
+}
+
+/**
+ * Tests that edited metadata is written in the package document,
+ * keeping the elements and attributes that were not edited
+ */
+#[test]
+fn metadata_written_into_opf() {
+    let mut opf = crate::helper::xml::XmlDocument::parse(TEST_OPF).expect("Unable to parse opf");
+    let mut metadata = crate::helper::opf::read_metadata(&opf);
+    metadata.title = "New Title".to_string();
+    metadata.authors = vec!["First Author".to_string()];
+    metadata.tags = vec!["Adventure".to_string(), "Classic".to_string()];
+    metadata.series = None;
+    crate::helper::opf::apply_metadata(&mut opf, &metadata);
+
+    let xml = opf.to_string().expect("Unable to write opf");
+    assert!(xml.contains("<dc:identifier id=\"id\">urn:uuid:1234</dc:identifier>"));
+    assert!(xml.contains("opf:file-as=\"Author, First\""));
+    assert!(!xml.contains("Second Author"));
+    assert!(!xml.contains("removed:creator"));
+    assert!(!xml.contains("calibre:series"));
+
+    let opf = crate::helper::xml::XmlDocument::parse(xml.as_str()).expect("Unable to parse opf");
+    assert_eq!(crate::helper::opf::read_metadata(&opf), metadata);
+}
+
+/**
+ * Tests that the metadata is read and written with another prefix for the Dublin Core namespace
+ */
+#[test]
+fn metadata_with_other_dc_prefix() {
+    use crate::helper::opf::{apply_metadata, read_metadata};
+    use crate::helper::xml::XmlDocument;
+
+    let other = TEST_OPF
+        .replace("xmlns:dc=", "xmlns:DC=")
+        .replace("dc:", "DC:");
+    let mut opf = XmlDocument::parse(other.as_str()).expect("Unable to parse opf");
+    let mut metadata = read_metadata(&opf);
+    assert_eq!(metadata.title, "Old Title");
+    assert_eq!(metadata.authors, vec!["First Author", "Second Author"]);
+
+    metadata.title = "New Title".to_string();
+    metadata.description = "A description".to_string();
+    apply_metadata(&mut opf, &metadata);
+
+    let xml = opf.to_string().expect("Unable to write opf");
+    assert!(xml.contains("<DC:title>New Title</DC:title>"));
+    assert!(xml.contains("<DC:description>A description</DC:description>"));
+    assert!(!xml.contains("<dc:"));
+
+    let opf = XmlDocument::parse(xml.as_str()).expect("Unable to parse opf");
+    assert_eq!(read_metadata(&opf), metadata);
+}
+
+/**
+ * Tests that the metadata of an epub file is saved as a copy and read back,
+ * a second copy not replacing the first one
+ */
+#[test]
+fn metadata_saved_as_copy() {
+    let path = std::env::temp_dir().join("metadata-test.epub");
+    std::fs::copy(TEST_FILE_PATH, &path).expect("Unable to copy test file");
+
+    let mut metadata = crate::helper::opf::load_metadata(&path).expect("Unable to load metadata");
+    assert_eq!(metadata.title, "The Prisoner of Zenda");
+    metadata.title = "The Prisoner of Zenda (revised)".to_string();
+    metadata.series = Some("Ruritania".to_string());
+    metadata.series_index = Some("1".to_string());
+
+    let saved = crate::helper::opf::save_metadata(
+        &path,
+        &metadata,
+        None,
+        crate::helper::opf::SaveMode::Copy,
+    )
+    .expect("Unable to save metadata");
+    assert_ne!(saved, path);
+    assert_eq!(
+        crate::helper::opf::load_metadata(&saved).expect("Unable to load metadata"),
+        metadata
+    );
+
+    let second = crate::helper::opf::save_metadata(
+        &path,
+        &metadata,
+        None,
+        crate::helper::opf::SaveMode::Copy,
+    )
+    .expect("Unable to save metadata");
+    assert_ne!(second, saved);
+    assert!(second.to_string_lossy().ends_with("-copy-2.epub"));
+    assert!(saved.exists());
+
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(saved);
+    let _ = std::fs::remove_file(second);
+}
@@ -1,2 +1,33 @@
 pub mod book;
 pub mod ocr;
+pub mod metadata;
+pub mod cover;
+pub mod thumbnail;
+pub mod artefacts;
+pub mod stats;
+pub mod location;
+pub mod keymap;
+pub mod history;
+pub mod links;
+pub mod notes;
+pub mod css;
+pub mod layout;
+pub mod speech;
+pub mod dictionary;
+pub mod vocabulary;
+pub mod annotations;
+pub mod importers;
+pub mod source;
+pub mod comic;
+pub mod pdf;
+pub mod convert;
+pub mod epub_writer;
+pub mod edit;
+
+/**
+ * Folder of its own for the files of a test, removed with everything in it when dropped
+ */
+#[cfg(test)]
+pub fn test_folder() -> tempfile::TempDir {
+    tempfile::tempdir().expect("Unable to create test folder")
+}
@@ -0,0 +1,79 @@
+/* NOTES TESTS */
+
+/**
+ * Tests the ids recognised as notes in books without epub:type
+ */
+#[test]
+fn note_ids() {
+    use crate::helper::notes::is_note_id;
+
+    for id in [
+        "fn1",
+        "footnote-3",
+        "ch02_note12",
+        "Footnote_1_1",
+        "en4",
+        "ftn.7",
+    ] {
+        assert!(is_note_id(id), "{} should be a note", id);
+    }
+    for id in [
+        "chapter1",
+        "fnref1",
+        "FNanchor_1_1",
+        "note",
+        "section-2",
+        "notes",
+    ] {
+        assert!(!is_note_id(id), "{} should not be a note", id);
+    }
+}
+
+/**
+ * Tests finding the note references of a page
+ */
+#[test]
+fn find_note_references() {
+    use crate::helper::notes::note_refs;
+
+    let html = r##"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+  <p>Semantic<a epub:type="noteref" href="notes.xhtml#c1">1</a>&nbsp;and
+  plain<a href="#fn2">2</a> notes, superscript<sup><a href="#p9">*</a></sup> ones.</p>
+  <p>A <a href="chapter_002.xhtml">chapter</a> and an <a href="#section-2">anchor</a>.</p>
+</body>
+</html>"##;
+
+    let refs = note_refs(html);
+    assert_eq!(refs.len(), 3);
+    for href in ["notes.xhtml#c1", "#fn2", "#p9"] {
+        assert!(refs.contains(href), "{} should be a note reference", href);
+    }
+}
+
+/**
+ * Tests reading the text of a note from its id
+ */
+#[test]
+fn read_note_text() {
+    use crate::helper::notes::note_text;
+
+    let html = r##"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+  <aside epub:type="footnote" id="c1"><p>The <em>first</em>
+     note.</p></aside>
+  <p class="note"><a id="fn2" href="#ref2">2.</a> The second note.</p>
+  <p><a id="empty"></a></p>
+</body>
+</html>"##;
+
+    assert_eq!(note_text(html, "c1"), Some("The first note.".to_string()));
+    // The id of the number linking back gives the whole paragraph
+    assert_eq!(
+        note_text(html, "fn2"),
+        Some("2. The second note.".to_string())
+    );
+    assert_eq!(note_text(html, "empty"), None);
+    assert_eq!(note_text(html, "missing"), None);
+}
@@ -21,7 +21,7 @@ fn ocr_from_file() {
 #[test]
 fn ocr_from_file_with_book() {
     let book =
-        crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_NOTRE_DAME_PATH));
+        crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_NOTRE_DAME_PATH));
     match book {
         Some(book) => {
             let text = tesseract::ocr(OCR_TEST_FILE_PATH, "eng");
@@ -42,7 +42,7 @@ fn ocr_from_file_with_book() {
 #[test]
 fn reverse_ocr() {
     let book =
-        crate::helper::functions::epub_to_book(std::path::PathBuf::from(TEST_NOTRE_DAME_PATH));
+        crate::helper::functions::file_to_book(std::path::PathBuf::from(TEST_NOTRE_DAME_PATH));
     match book {
         Some(mut book) => {
             book.set_page(1);
@@ -0,0 +1,195 @@
+/* PDF BOOKS TESTS */
+
+/**
+ * Tests the paragraphs and headings found in the text of the pages, without the page
+ * numbers and the running headers, and the paragraphs cut by the end of a page
+ */
+#[test]
+fn pdf_page_blocks() {
+    use crate::helper::source::pdf::{page_blocks, Block};
+
+    let pages: Vec<String> = [
+        "Journal of Tests 1\n\n1 Introduction\n\nThe first line of the para-\ngraph goes on here.\n\nA second paragraph starts\nand does not end\n\n1",
+        "Journal of Tests 2\n\non this page at all.\n\n2.1 Data\n\nSome data.\n\n- 2 -",
+        "Journal of Tests 3\n\nReferences\n\nA. Author, 2 Apples were on the table, 1999.\n\nPage 3 of 3",
+    ]
+    .iter()
+    .map(|page| page.to_string())
+    .collect();
+    let blocks = page_blocks(&pages);
+    assert_eq!(
+        blocks[0],
+        vec![
+            Block::Heading(1, "1 Introduction".to_string()),
+            Block::Paragraph("The first line of the paragraph goes on here.".to_string()),
+            Block::Paragraph(
+                "A second paragraph starts and does not end on this page at all.".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        blocks[1],
+        vec![
+            Block::Heading(2, "2.1 Data".to_string()),
+            Block::Paragraph("Some data.".to_string()),
+        ]
+    );
+    assert_eq!(
+        blocks[2],
+        vec![
+            Block::Heading(1, "References".to_string()),
+            Block::Paragraph("A. Author, 2 Apples were on the table, 1999.".to_string()),
+        ]
+    );
+}
+
+/**
+ * Tests that only well-formed roman numerals and numbers following the pages
+ * are left out as page numbers
+ */
+#[test]
+fn pdf_page_numbers() {
+    use crate::helper::source::pdf::{page_blocks, Block};
+
+    let pages: Vec<String> = [
+        "Some words of the preface.\n\ni",
+        "More words of the preface.\n\nii",
+        "A square with the hall and the\ncivic",
+        "Text of the book.\n\n1",
+        "It was a mild and\nill\n\n2",
+        "The last page, from\n1999\n\nv",
+    ]
+    .iter()
+    .map(|page| page.to_string())
+    .collect();
+    let blocks = page_blocks(&pages);
+    let paragraph = |text: &str| vec![Block::Paragraph(text.to_string())];
+    assert_eq!(blocks[0], paragraph("Some words of the preface."));
+    assert_eq!(blocks[1], paragraph("More words of the preface."));
+    assert_eq!(blocks[2], paragraph("A square with the hall and the civic"));
+    assert_eq!(blocks[3], paragraph("Text of the book."));
+    assert_eq!(blocks[4], paragraph("It was a mild and ill"));
+    assert_eq!(
+        blocks[5],
+        vec![
+            Block::Paragraph("The last page, from 1999".to_string()),
+            Block::Paragraph("v".to_string()),
+        ]
+    );
+}
+
+/**
+ * Tests the strings of the document information, in UTF-16 or in PDFDocEncoding
+ */
+#[test]
+fn pdf_text_strings() {
+    use crate::helper::source::pdf::pdf_text_string;
+
+    assert_eq!(pdf_text_string(b"A Paper"), "A Paper");
+    assert_eq!(pdf_text_string(&[0x43, 0x61, 0x66, 0xe9]), "Café");
+    assert_eq!(
+        pdf_text_string(&[0xfe, 0xff, 0x00, 0x4a, 0x00, 0xf6, 0x00, 0x72, 0x00, 0x67]),
+        "Jörg"
+    );
+}
+
+/**
+ * Tests that the pages of a PDF are the sections of the book, with their original numbers,
+ * and that a page that cannot be read is reported on its section
+ */
+#[test]
+fn pdf_book_opened() {
+    use crate::helper::source::{open_source, BookFormat};
+    use pdf_extract::{
+        content::{Content, Operation},
+        dictionary, Document, Object, Stream,
+    };
+    use std::path::PathBuf;
+
+    // Three pages of lines (font size, height, text), the last one without its size,
+    // which makes pdf-extract panic
+    let pages: Vec<Vec<(f32, i64, &str)>> = vec![
+        vec![
+            (20.0, 760, "1 Introduction"),
+            (11.0, 730, "The first line of the para-"),
+            (11.0, 716, "graph goes on here."),
+            (11.0, 690, "A second paragraph starts"),
+            (11.0, 676, "and does not end"),
+            (9.0, 40, "1"),
+        ],
+        vec![(11.0, 760, "on this page at all."), (9.0, 40, "2")],
+        vec![(11.0, 760, "Never read")],
+    ];
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let font_id = document.add_object(
+        dictionary! {"Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica"},
+    );
+    let resources_id = document.add_object(dictionary! {"Font" => dictionary! {"F1" => font_id}});
+    let mut kids = Vec::new();
+    for (index, lines) in pages.into_iter().enumerate() {
+        let mut operations = Vec::new();
+        for (size, y, text) in lines {
+            operations.push(Operation::new("BT", vec![]));
+            operations.push(Operation::new("Tf", vec!["F1".into(), size.into()]));
+            operations.push(Operation::new("Td", vec![72.into(), y.into()]));
+            operations.push(Operation::new("Tj", vec![Object::string_literal(text)]));
+            operations.push(Operation::new("ET", vec![]));
+        }
+        let content = Content { operations }.encode().unwrap();
+        let content_id = document.add_object(Stream::new(dictionary! {}, content));
+        let mut page =
+            dictionary! {"Type" => "Page", "Parent" => pages_id, "Contents" => content_id};
+        if index < 2 {
+            page.set("MediaBox", vec![0.into(), 0.into(), 595.into(), 842.into()]);
+        }
+        let page_id = document.add_object(page);
+        kids.push(page_id.into());
+    }
+    let count = kids.len() as i64;
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+        }),
+    );
+    let info_id = document.add_object(dictionary! {
+        "Title" => Object::string_literal("A Paper"),
+        "Author" => Object::string_literal("Jane Doe"),
+    });
+    let catalog_id = document.add_object(dictionary! {"Type" => "Catalog", "Pages" => pages_id});
+    document.trailer.set("Root", catalog_id);
+    document.trailer.set("Info", info_id);
+
+    let path = std::env::temp_dir().join("pdf-test-paper.pdf");
+    document.save(&path).expect("Unable to write test PDF");
+    let mut source = open_source(&path).expect("Unable to open PDF");
+    assert_eq!(source.format(), BookFormat::Pdf);
+    assert_eq!(source.metadata("title").as_deref(), Some("A Paper"));
+    assert_eq!(source.metadata("creator").as_deref(), Some("Jane Doe"));
+    // The title page, then the pages 1, 2 and 3
+    assert_eq!(source.len(), 4);
+    assert_eq!(
+        source.section_path(2),
+        Some(PathBuf::from("page-002.xhtml"))
+    );
+    let page = source.section(1).unwrap();
+    assert!(page.contains("<h2 id=\"heading-1\">1 Introduction</h2>"));
+    assert!(page.contains("<p>The first line of the paragraph goes on here.</p>"));
+    assert!(page.contains("and does not end on this page at all.</p>"));
+    assert!(!source.section(2).unwrap().contains("<p>"));
+    let unreadable = source.section(3).unwrap();
+    assert!(unreadable.contains("The text of this page could not be read (MediaBox)."));
+    assert!(!unreadable.contains("Never read"));
+    assert_eq!(
+        source.toc(),
+        vec![(
+            "1 Introduction".to_string(),
+            PathBuf::from("page-001.xhtml#heading-1")
+        )]
+    );
+    std::fs::remove_file(path).expect("Unable to remove test PDF");
+}
@@ -0,0 +1,274 @@
+/* BOOK SOURCES TESTS */
+
+/**
+ * Tests the format of a book file from its extension
+ */
+#[test]
+fn book_format_from_extension() {
+    use crate::helper::source::BookFormat;
+    use std::path::Path;
+
+    assert_eq!(
+        BookFormat::from_path(Path::new("book.EPUB")),
+        Some(BookFormat::Epub)
+    );
+    assert_eq!(
+        BookFormat::from_path(Path::new("notes.markdown")),
+        Some(BookFormat::Markdown)
+    );
+    assert_eq!(
+        BookFormat::from_path(Path::new("page.htm")),
+        Some(BookFormat::Html)
+    );
+    assert_eq!(BookFormat::from_path(Path::new("cover.png")), None);
+    assert_eq!(BookFormat::from_path(Path::new("README")), None);
+    assert!(BookFormat::all_extensions().contains(&"fb2"));
+}
+
+/**
+ * Tests the headings of chapters recognized in a plain text book
+ */
+#[test]
+fn plain_text_headings() {
+    use crate::helper::source::text::is_heading;
+
+    for heading in [
+        "Chapter 1",
+        "CHAPTER XII.",
+        "Part Two",
+        "Book the First",
+        "IV.",
+        "17",
+        "Prologue",
+        "THE RETURN OF THE KING",
+    ] {
+        assert!(is_heading(heading), "{} is a heading", heading);
+    }
+    for paragraph in [
+        "Part of the problem was the rain.",
+        "He said nothing.",
+        "WHAT?",
+        "I",
+        "Chapter 1 began with a long line that is certainly not a heading at all, just text.",
+        "CHAPTER 1\nIt was a dark night.",
+    ] {
+        assert!(!is_heading(paragraph), "{} is not a heading", paragraph);
+    }
+}
+
+/**
+ * Tests that a plain text book is cut into chapters at its headings,
+ * without the licence of Project Gutenberg and its table of contents
+ */
+#[test]
+fn plain_text_book_opened() {
+    use crate::helper::source::open_source;
+    use std::path::Path;
+
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    let path = folder.join("the_old_house.txt");
+    std::fs::write(
+        &path,
+        "The Project Gutenberg eBook\r\n\r\nTitle: The Old House\r\nAuthor: Jane Doe\r\n\r\n\
+*** START OF THE PROJECT GUTENBERG EBOOK THE OLD HOUSE ***\r\n\r\n\
+CONTENTS\r\n\r\nCHAPTER I\r\n\r\nCHAPTER II\r\n\r\n\
+CHAPTER I\r\n\r\nTHE ARRIVAL\r\n\r\nThe house stood at the end\r\nof the road.\r\n\r\n\
+Nobody lived there.\r\n\r\n\
+CHAPTER II\r\n\r\nThe door was open & the lights were on.\r\n\r\n\
+*** END OF THE PROJECT GUTENBERG EBOOK THE OLD HOUSE ***\r\nLicence.\r\n",
+    )
+    .expect("Unable to write test book");
+
+    let mut source = open_source(&path).expect("Unable to open text book");
+    assert_eq!(source.metadata("title").as_deref(), Some("The Old House"));
+    assert_eq!(source.metadata("creator").as_deref(), Some("Jane Doe"));
+    // Title page and two chapters
+    assert_eq!(source.len(), 3);
+    let toc: Vec<String> = source.toc().into_iter().map(|(label, _)| label).collect();
+    assert_eq!(toc, vec!["CHAPTER I — THE ARRIVAL", "CHAPTER II"]);
+
+    let chapter = source.section(1).expect("Unable to read chapter");
+    assert!(chapter.contains("<p>The house stood at the end of the road.</p>"));
+    assert!(chapter.contains("<p>Nobody lived there.</p>"));
+    let chapter = source.section(2).expect("Unable to read chapter");
+    assert!(chapter.contains("open &amp; the lights"));
+    assert!(!chapter.contains("Licence"));
+    assert_eq!(
+        source.section_index(Path::new("chapter-002.xhtml")),
+        Some(2)
+    );
+    assert!(source.cover().is_none());
+
+    // No heading at all: one chapter named after the file
+    let path = folder.join("short-story.txt");
+    std::fs::write(&path, "One line.\nAnother line.\n").expect("Unable to write test book");
+    let mut source = open_source(&path).expect("Unable to open text book");
+    assert_eq!(source.metadata("title").as_deref(), Some("short story"));
+    assert_eq!(source.len(), 2);
+    assert!(source
+        .section(1)
+        .unwrap()
+        .contains("<p>One line.</p>\n<p>Another line.</p>"));
+}
+
+/**
+ * Tests that a Markdown book is converted to html and cut at its headings
+ */
+#[test]
+fn markdown_book_opened() {
+    use crate::helper::source::open_source;
+
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    let path = folder.join("notes.md");
+    std::fs::write(
+        &path,
+        "---\ntitle: \"Field Notes\"\nauthor: Jane Doe\nlang: en\n---\n\n\
+Written in the *spring*.\n\n# Birds\n\nA **robin**.\n\n# Trees\n\n- oak\n- elm\n",
+    )
+    .expect("Unable to write test book");
+
+    let mut source = open_source(&path).expect("Unable to open Markdown book");
+    assert_eq!(source.metadata("title").as_deref(), Some("Field Notes"));
+    assert_eq!(source.metadata("creator").as_deref(), Some("Jane Doe"));
+    assert_eq!(source.metadata("language").as_deref(), Some("en"));
+    let toc: Vec<String> = source.toc().into_iter().map(|(label, _)| label).collect();
+    assert_eq!(toc, vec!["Field Notes", "Birds", "Trees"]);
+    assert!(source.section(1).unwrap().contains("<em>spring</em>"));
+    assert!(source
+        .section(2)
+        .unwrap()
+        .contains("<strong>robin</strong>"));
+    assert!(source.section(3).unwrap().contains("<li>elm</li>"));
+
+    // The only first level heading is the title of the book
+    let path = folder.join("guide.md");
+    std::fs::write(
+        &path,
+        "# The Guide\n\n## Start\n\nText.\n\n## End\n\nMore.\n",
+    )
+    .expect("Unable to write test book");
+    let source = open_source(&path).expect("Unable to open Markdown book");
+    assert_eq!(source.metadata("title").as_deref(), Some("The Guide"));
+    let toc: Vec<String> = source.toc().into_iter().map(|(label, _)| label).collect();
+    assert_eq!(toc, vec!["Start", "End"]);
+}
+
+/**
+ * Tests that an html book is cut at its headings, keeps its stylesheets
+ * and reads the files it links to next to it
+ */
+#[test]
+fn html_book_opened() {
+    use crate::helper::source::open_source;
+    use std::path::Path;
+
+    let dir = crate::tests::test_folder();
+    let folder = dir.path();
+    std::fs::write(folder.join("style.css"), "p { color: red; }").unwrap();
+    std::fs::write(folder.join("cover.png"), [1, 2, 3]).unwrap();
+    let path = folder.join("book.html");
+    std::fs::write(
+        &path,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+<title>An &amp; Html Book</title><meta name=\"author\" content=\"John Smith\">\
+<link rel=\"stylesheet\" href=\"style.css\"></head>\n<body>\
+<p><img src=\"cover.png\" alt=\"\"></p>\
+<h1 id=\"one\">First <i>chapter</i></h1><p>One.</p>\
+<header>Not a heading</header>\
+<H1>Second</H1><p>Two.</p></body></html>",
+    )
+    .expect("Unable to write test book");
+
+    let mut source = open_source(&path).expect("Unable to open html book");
+    assert_eq!(source.metadata("title").as_deref(), Some("An & Html Book"));
+    assert_eq!(source.metadata("creator").as_deref(), Some("John Smith"));
+    let toc: Vec<String> = source.toc().into_iter().map(|(label, _)| label).collect();
+    // The text before the first heading has only the cover
+    assert_eq!(toc, vec!["First chapter", "Second"]);
+
+    let chapter = source.section(1).unwrap();
+    assert!(chapter.contains("<link rel=\"stylesheet\" href=\"style.css\">"));
+    assert!(chapter.contains("<p>One.</p><header>Not a heading</header>"));
+    assert!(!chapter.contains("Two."));
+    assert_eq!(
+        source.resource_str(Path::new("style.css")).unwrap(),
+        "p { color: red; }"
+    );
+    assert!(source.resource(Path::new("../book.html")).is_err());
+    assert_eq!(source.cover(), Some(vec![1, 2, 3]));
+}
+
+/**
+ * Tests that an FB2 book is converted: its description, its sections,
+ * its notes (a page of their own) and its images
+ */
+#[test]
+fn fb2_book_parsed() {
+    use crate::helper::source::{fb2::parse_fb2, BookSource};
+    use std::path::Path;
+
+    let fb2 = r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <description>
+    <title-info>
+      <author><first-name>Lev</first-name><last-name>Tolstoy</last-name></author>
+      <book-title>Short Stories</book-title>
+      <lang>ru</lang>
+      <coverpage><image l:href="#cover.jpg"/></coverpage>
+    </title-info>
+    <document-info><id>fb2-id-1</id></document-info>
+  </description>
+  <body>
+    <title><p>Short Stories</p></title>
+    <section id="s1">
+      <title><p>The First</p></title>
+      <p>Some <emphasis>text</emphasis> with a note<a l:href="#n1" type="note">1</a>.</p>
+      <empty-line/>
+      <poem><stanza><v>A verse</v></stanza></poem>
+    </section>
+    <section>
+      <title><p>The Second</p></title>
+      <p>More &amp; more.</p>
+      <image l:href="#cover.jpg"/>
+    </section>
+  </body>
+  <body name="notes">
+    <section id="n1"><p>The note.</p></section>
+  </body>
+  <binary id="cover.jpg" content-type="image/jpeg">AQID
+  BA==</binary>
+</FictionBook>"##;
+
+    let mut source = parse_fb2(fb2, "fallback").expect("Unable to parse FB2 book");
+    assert_eq!(source.metadata("title").as_deref(), Some("Short Stories"));
+    assert_eq!(source.metadata("creator").as_deref(), Some("Lev Tolstoy"));
+    assert_eq!(source.metadata("language").as_deref(), Some("ru"));
+    assert_eq!(source.metadata("identifier").as_deref(), Some("fb2-id-1"));
+    let toc: Vec<String> = source.toc().into_iter().map(|(label, _)| label).collect();
+    assert_eq!(toc, vec!["The First", "The Second", "Notes"]);
+
+    let chapter = source.section(1).unwrap();
+    assert!(chapter.contains("<h2>The First</h2>"));
+    assert!(chapter.contains("<em>text</em>"));
+    assert!(chapter.contains("<a href=\"notes.xhtml#n1\">1</a>"));
+    assert!(chapter.contains("<br/>"));
+    assert!(chapter.contains("<p class=\"verse\">A verse</p>"));
+    let chapter = source.section(2).unwrap();
+    assert!(chapter.contains("More &amp; more."));
+    assert!(chapter.contains("<img src=\"images/cover.jpg\" alt=\"\"/>"));
+
+    assert_eq!(source.section_index(Path::new("notes.xhtml")), Some(3));
+    assert!(source
+        .resource_str(Path::new("notes.xhtml"))
+        .unwrap()
+        .contains("<div id=\"n1\"><p>The note.</p></div>"));
+    assert_eq!(
+        source.resource(Path::new("images/cover.jpg")).unwrap(),
+        vec![1, 2, 3, 4]
+    );
+    assert_eq!(source.cover(), Some(vec![1, 2, 3, 4]));
+
+    assert!(parse_fb2("<html><body/></html>", "fallback").is_err());
+}
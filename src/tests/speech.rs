@@ -0,0 +1,150 @@
+/* SPEECH TESTS */
+
+/**
+ * Synthesizer writing a constant sample for each character of the sentence
+ */
+#[cfg(test)]
+#[derive(Debug)]
+struct TestSynthesizer;
+
+#[cfg(test)]
+impl crate::helper::speech::Synthesizer for TestSynthesizer {
+    fn synthesize(
+        &self,
+        text: &str,
+    ) -> Result<crate::helper::speech::Audio, Box<dyn std::error::Error>> {
+        if text.contains("fail") {
+            return Err("Unable to synthesize".into());
+        }
+        Ok(crate::helper::speech::Audio {
+            sample_rate: 8000,
+            samples: vec![1000; text.chars().count()],
+        })
+    }
+}
+
+/**
+ * Tests splitting a text into sentences
+ */
+#[test]
+fn sentences_split() {
+    use crate::helper::speech::split_sentences;
+
+    let text = "Mr. Smith met J. R. Hartley at 3 p.m. today.  He said: \"Hello!\" Then he left...
+and came back?!
+
+A new paragraph without a stop
+彼は来た。彼女も来た！";
+    assert_eq!(
+        split_sentences(text),
+        vec![
+            "Mr. Smith met J. R. Hartley at 3 p.m. today.",
+            "He said: \"Hello!\"",
+            "Then he left... and came back?!",
+            "A new paragraph without a stop 彼は来た。",
+            "彼女も来た！",
+        ]
+    );
+    assert!(split_sentences("  \n\n ").is_empty());
+}
+
+/**
+ * Tests finding a sentence in the text of a paragraph, whatever its spaces
+ */
+#[test]
+fn sentence_found_in_text() {
+    use crate::helper::speech::find_sentence;
+
+    let text = "He said:\n  Hello there. Hello again.";
+    assert_eq!(find_sentence(text, "said: Hello"), Some(3..16));
+    assert_eq!(find_sentence(text, "Hello again."), Some(24..36));
+    assert_eq!(find_sentence(text, "Hello friend."), None);
+    assert_eq!(find_sentence(text, "   "), None);
+}
+
+/**
+ * Tests reading sentences into a WAV file in the background, with the events of the reading
+ */
+#[test]
+fn sentences_read_into_wav() {
+    use crate::helper::speech::{speak, SpeechEvent, SpeechListener, WavEngine};
+    use std::sync::{mpsc, Arc, Mutex};
+
+    let path = std::env::temp_dir().join("ebook-reader-speech-test.wav");
+    let _ = std::fs::remove_file(&path);
+    let engine = Arc::new(WavEngine::new(&path, Box::new(TestSynthesizer)));
+    let sentences = Arc::new(vec![
+        "Skipped.".to_string(),
+        "First one.".to_string(),
+        "Second.".to_string(),
+    ]);
+
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let listener = SpeechListener::new(move |session, event| {
+        let _ = sender.lock().unwrap().send((session, event));
+    });
+    speak(engine, sentences, 1, 7, listener).wait();
+
+    let events: Vec<(u64, SpeechEvent)> = receiver.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            (7, SpeechEvent::Sentence(1)),
+            (7, SpeechEvent::Sentence(2)),
+            (7, SpeechEvent::End(Ok(()))),
+        ]
+    );
+
+    // Each sentence is followed by a pause of 0.4 seconds
+    let reader = hound::WavReader::open(&path).expect("Unable to read audio file");
+    assert_eq!(reader.spec().sample_rate, 8000);
+    assert_eq!(reader.spec().channels, 1);
+    assert_eq!(reader.duration(), 10 + 3200 + 7 + 3200);
+    std::fs::remove_file(&path).expect("Unable to remove audio file");
+}
+
+/**
+ * Tests that an error of the engine ends the reading
+ */
+#[test]
+fn reading_ended_by_error() {
+    use crate::helper::speech::{speak, SpeechEvent, SpeechListener, WavEngine};
+    use std::sync::{mpsc, Arc, Mutex};
+
+    let path = std::env::temp_dir().join("ebook-reader-speech-error-test.wav");
+    let engine = Arc::new(WavEngine::new(&path, Box::new(TestSynthesizer)));
+    let sentences = Arc::new(vec![
+        "This will fail.".to_string(),
+        "Never read.".to_string(),
+    ]);
+
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let listener = SpeechListener::new(move |_, event| {
+        let _ = sender.lock().unwrap().send(event);
+    });
+    speak(engine, sentences, 0, 1, listener).wait();
+
+    let events: Vec<SpeechEvent> = receiver.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            SpeechEvent::Sentence(0),
+            SpeechEvent::End(Err("Unable to synthesize".to_string())),
+        ]
+    );
+    assert!(!path.exists());
+}
+
+/**
+ * Tests the audio files of the chapters, kept with the other artefacts of the book
+ */
+#[test]
+fn chapter_audio_paths() {
+    use crate::helper::artefacts::{audio_path, chapter_audio_path};
+
+    let path = chapter_audio_path("The Prisoner of Zenda", 3);
+    assert!(path.ends_with("The-Prisoner-of-Zenda/chapter-003.wav"));
+    assert!(path.starts_with(audio_path("The Prisoner of Zenda")));
+}
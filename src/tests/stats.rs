@@ -0,0 +1,81 @@
+/* STATS TESTS */
+
+/**
+ * Tests that skimmed pages and idle time are not counted, and that only
+ * the pages that were finished count for the reading speed
+ */
+#[test]
+fn reading_session_tracked() {
+    use crate::helper::config::{IDLE_PAGE_SECONDS, MIN_PAGE_SECONDS};
+
+    let mut tracker = crate::helper::stats::ReadingTracker::new(1, 0);
+    // Page 1 read in two minutes
+    tracker.visit(2, 500, 120);
+    // Page 2 skimmed
+    tracker.visit(3, 800, 120 + MIN_PAGE_SECONDS - 1);
+    // Page 3 left open, then back to page 2
+    tracker.visit(2, 300, 10_000);
+
+    let session = tracker.get_session();
+    assert_eq!(session.seconds, 120 + IDLE_PAGE_SECONDS);
+    assert_eq!(session.words, 500);
+    assert_eq!(session.word_seconds, 120);
+    assert_eq!(session.start_page, 1);
+    assert_eq!(session.end_page, 2);
+    assert_eq!(tracker.get_page(), 2);
+}
+
+/**
+ * Tests reading speed and estimated time left
+ */
+#[test]
+fn reading_speed_computed() {
+    use crate::helper::stats::{format_minutes, reading_speed, time_left, ReadingSession};
+
+    let sessions = vec![
+        ReadingSession {
+            words: 1000,
+            word_seconds: 240,
+            ..Default::default()
+        },
+        ReadingSession {
+            words: 500,
+            word_seconds: 120,
+            ..Default::default()
+        },
+    ];
+    assert_eq!(reading_speed(&sessions), Some(250.0));
+    assert_eq!(reading_speed(&sessions[..0]), None);
+
+    assert_eq!(time_left(1000, 250.0), 4);
+    assert_eq!(time_left(1001, 250.0), 5);
+    assert_eq!(format_minutes(45), "45 min");
+    assert_eq!(format_minutes(120), "2 h");
+    assert_eq!(format_minutes(125), "2 h 5 min");
+}
+
+/**
+ * Tests weekly totals and reading streaks
+ */
+#[test]
+fn streaks_and_weekly_totals() {
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+
+    let day = |d: u32| NaiveDate::from_ymd_opt(2023, 3, d).expect("Invalid date");
+    // Wednesday 1 to Friday 3, then Monday 6 and Tuesday 7
+    let daily: BTreeMap<NaiveDate, i64> = [(1, 600), (2, 300), (3, 60), (6, 120), (7, 900)]
+        .into_iter()
+        .map(|(d, seconds)| (day(d), seconds))
+        .collect();
+
+    let weekly = crate::helper::stats::weekly_totals(&daily);
+    assert_eq!(
+        weekly.get(&NaiveDate::from_ymd_opt(2023, 2, 27).unwrap()),
+        Some(&960)
+    );
+    assert_eq!(weekly.get(&day(6)), Some(&1020));
+
+    assert_eq!(crate::helper::stats::streaks(&daily, day(8)), (2, 3));
+    assert_eq!(crate::helper::stats::streaks(&daily, day(10)), (0, 3));
+}
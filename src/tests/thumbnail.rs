@@ -0,0 +1,43 @@
+#[allow(unused)]
+const TEST_COVER_PATH: &str = "./src/library/covers/The-Prisoner-of-Zenda.jpg";
+
+/* THUMBNAIL TESTS */
+
+/**
+ * Tests that the cache key of a book is stable and differs between books
+ */
+#[test]
+fn cache_key_stable() {
+    let key = crate::helper::thumbnail::cache_key("id|./src/library/book.epub");
+    assert_eq!(
+        key,
+        crate::helper::thumbnail::cache_key("id|./src/library/book.epub")
+    );
+    assert_ne!(
+        key,
+        crate::helper::thumbnail::cache_key("id|./src/library/other.epub")
+    );
+    assert_eq!(key.len(), 16);
+}
+
+/**
+ * Tests that a thumbnail fits in its box, is saved on disk and removed when invalidated
+ */
+#[test]
+fn thumbnail_created_and_invalidated() {
+    use crate::helper::thumbnail::{get_thumbnail, invalidate_thumbnails, ThumbnailSize};
+
+    let key = crate::helper::thumbnail::cache_key("thumbnail-test");
+    let thumbnail =
+        get_thumbnail(&key, TEST_COVER_PATH, ThumbnailSize::Small).expect("Thumbnail not created");
+    let (width, height) = ThumbnailSize::Small.dimensions();
+    assert!(thumbnail.width() <= width as usize);
+    assert!(thumbnail.height() <= height as usize);
+
+    let path = std::path::Path::new(crate::helper::config::THUMBNAILS_PATH)
+        .join(format!("{}-small.png", key));
+    assert!(path.exists());
+
+    invalidate_thumbnails(&key);
+    assert!(!path.exists());
+}
@@ -0,0 +1,106 @@
+/* VOCABULARY TESTS */
+
+/**
+ * Word looked up in a test book
+ */
+#[cfg(test)]
+fn vocabulary_word(
+    word: &str,
+    context: &str,
+    page: usize,
+) -> crate::helper::vocabulary::VocabularyWord {
+    crate::helper::vocabulary::VocabularyWord {
+        word: word.to_string(),
+        definition: format!("Definition of {}", word),
+        context: context.to_string(),
+        page,
+        chapter_position: 0.5,
+        chapter: String::new(),
+        time: 0,
+    }
+}
+
+/**
+ * Tests that a word looked up again is kept once, with its last context,
+ * and that the vocabulary of a book is saved with its artefacts
+ */
+#[test]
+fn vocabulary_saved_per_book() {
+    use crate::helper::{
+        artefacts::{artefact_paths, vocabulary_path},
+        vocabulary::{load_vocabulary, save_vocabulary},
+    };
+
+    let title = "Vocabulary Test Book";
+    let _ = std::fs::remove_file(vocabulary_path(title));
+    let mut vocabulary = load_vocabulary(title);
+    assert_eq!(vocabulary.title, title);
+    assert!(vocabulary.words.is_empty());
+
+    vocabulary.add(vocabulary_word("ephemeral", "An ephemeral joy.", 2));
+    vocabulary.add(vocabulary_word("laconic", "A laconic reply.", 3));
+    vocabulary.add(vocabulary_word("Ephemeral", "Ephemeral fame.", 5));
+    let words: Vec<(&str, usize)> = vocabulary
+        .words
+        .iter()
+        .map(|word| (word.word.as_str(), word.page))
+        .collect();
+    assert_eq!(words, vec![("Ephemeral", 5), ("laconic", 3)]);
+
+    save_vocabulary(&vocabulary).expect("Unable to save vocabulary");
+    assert_eq!(load_vocabulary(title), vocabulary);
+    assert!(artefact_paths(title).contains(&vocabulary_path(title)));
+
+    vocabulary.remove(0);
+    vocabulary.remove(5);
+    assert_eq!(vocabulary.words.len(), 1);
+    assert_eq!(vocabulary.words[0].word, "laconic");
+    std::fs::remove_file(vocabulary_path(title)).expect("Unable to remove vocabulary");
+}
+
+/**
+ * Tests exporting the vocabulary as CSV and TSV decks
+ */
+#[test]
+fn vocabulary_exported_as_deck() {
+    use crate::helper::vocabulary::{export_deck, DeckFormat, Vocabulary};
+
+    let mut vocabulary = Vocabulary {
+        title: "Test Book".to_string(),
+        words: Vec::new(),
+    };
+    vocabulary.add(vocabulary_word("quaint", "A quaint, \"old\" house.", 4));
+    let mut word = vocabulary_word("Sly", "He was sly\tand quick <& clever>.", 7);
+    word.definition = "Cunning.\nDeceitful.".to_string();
+    word.chapter = "The Fox & the Hound".to_string();
+    vocabulary.add(word);
+
+    assert_eq!(
+        export_deck(&vocabulary, DeckFormat::Csv),
+        "#separator:comma\n#html:true\n#columns:Word,Definition,Context,Source\n\
+quaint,Definition of quaint,\"A <b>quaint</b>, &quot;old&quot; house.\",\"Test Book, chapter 4\"\n\
+Sly,Cunning.<br>Deceitful.,He was <b>sly</b> and quick &lt;&amp; clever&gt;.,\"Test Book, The Fox &amp; the Hound\"\n"
+    );
+    assert_eq!(
+        export_deck(&vocabulary, DeckFormat::Tsv),
+        "#separator:tab\n#html:true\n#columns:Word\tDefinition\tContext\tSource\n\
+quaint\tDefinition of quaint\tA <b>quaint</b>, &quot;old&quot; house.\tTest Book, chapter 4\n\
+Sly\tCunning.<br>Deceitful.\tHe was <b>sly</b> and quick &lt;&amp; clever&gt;.\tTest Book, The Fox &amp; the Hound\n"
+    );
+    assert_eq!(DeckFormat::Tsv.extension(), "tsv");
+}
+
+/**
+ * Tests that the position of a word is the one of its sentence in the page
+ */
+#[test]
+fn vocabulary_context_position() {
+    use crate::helper::vocabulary::context_position;
+
+    let html = "<html><body><p>The first sentence is here.</p>\
+<p>The word is in this second sentence.</p><p>And the end.</p></body></html>";
+    let position =
+        context_position(html, "The word is in this second sentence.").expect("Sentence not found");
+    assert!(position > 0.2 && position < 0.8);
+    assert_eq!(context_position(html, "Not in the page."), None);
+}
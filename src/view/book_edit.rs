@@ -1,5 +1,4 @@
 use crate::{
-    controller::view::POP_VIEW,
     model::{
         app_state::AppState,
         book::Book,
@@ -11,7 +10,7 @@ use druid::{
         Button, Container, CrossAxisAlignment, Flex, Label, MainAxisAlignment, Scope, Scroll,
         TextBox, Padding,
     },
-    Widget, WidgetExt,
+    Color, Widget, WidgetExt,
 };
 use druid_widget_nursery::navigator::ViewController;
 
@@ -24,6 +23,7 @@ fn header() -> impl Widget<AppState> {
         .must_fill_main_axis(true);
         
     let back_button = Button::new("Back").on_click(|_event, data: &mut AppState, _env| {
+        data.set_edit_error(None);
         data.pop_view();
     });
 
@@ -33,6 +33,17 @@ fn header() -> impl Widget<AppState> {
     
 }
 
+/**
+ * Why the last save failed, empty if it did not
+ */
+fn save_error() -> impl Widget<AppState> {
+    Label::dynamic(|data: &AppState, _env| match data.get_edit_error() {
+        Some(error) => format!("The book was not saved: {}", error),
+        None => String::new(),
+    })
+    .with_text_color(Color::rgb8(0xc0, 0x39, 0x2b))
+}
+
 pub fn book_edit() -> Box<dyn Widget<AppState>> {
 
     let textbox = Flex::column()
@@ -55,10 +66,9 @@ pub fn book_edit() -> Box<dyn Widget<AppState>> {
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .lens(EditState::book);
 
-    let save_button = Button::new("Save").on_click(|event, data: &mut EditState, _env| {
+    // The editor is closed by EditTransfer once the book is saved
+    let save_button = Button::new("Save").on_click(|_event, data: &mut EditState, _env| {
         data.was_saved = true;
-        // use a command here because EditState does not have access to the navigation state
-        event.submit_command(POP_VIEW);
     });
 
     let layout = Flex::column()
@@ -73,6 +83,7 @@ pub fn book_edit() -> Box<dyn Widget<AppState>> {
     let layout = Scope::from_function(EditState::new, EditTransfer, layout);
     let layout = Flex::column()
         .with_child(header())
+        .with_child(save_error())
         .with_flex_child(layout, 1.0)
         .main_axis_alignment(MainAxisAlignment::SpaceAround);
 
@@ -1,22 +1,37 @@
 use std::sync::Arc;
 
 use druid::{
+    im::Vector,
+    lens,
     widget::{
-        Button, Container, CrossAxisAlignment, Flex, Label, MainAxisAlignment, Padding, Scroll,
-        SizedBox, ViewSwitcher,
+        Button, Container, CrossAxisAlignment, Either, Flex, Label, LineBreaking,
+        MainAxisAlignment, Padding, Scroll, SizedBox, TextBox, ViewSwitcher,
     },
-    Command, Env, Insets, Target, Widget, WidgetExt,
+    Color, Command, Env, Insets, Target, Widget, WidgetExt,
 };
-use druid_widget_nursery::navigator::ViewController;
 
 use crate::{
-    controller::{parser::parse, view::BOOK_EDIT},
-    helper::config::PADDING_LG,
+    controller::{
+        keys::{ReaderKeyController, SearchController},
+        parser::{parse, LinkController},
+        reading::{ChapterScrollController, ReadingController},
+        speech::{speech_listener, SpeechController},
+        view::BOOK_EDIT,
+    },
+    helper::{
+        config::{PADDING_LG, PADDING_SM, SUBTITLE},
+        keymap::Keymap,
+        settings::load_settings,
+        stats::format_minutes,
+        viewer::ImageFit,
+    },
     model::{app_state::AppState, ui_view::UiView},
+    view::{comic_page::ComicPage, seek_bar::SeekBar},
 };
 
 // details views - this is the second view after clicking on a contact
 pub fn book_view() -> Box<dyn Widget<AppState>> {
+    let keymap = Keymap::with_overrides(&load_settings().keymap);
     let book_menu = book_menu();
     let top_right_buttons = top_right();
     let book_controls = book_controls();
@@ -30,32 +45,116 @@ pub fn book_view() -> Box<dyn Widget<AppState>> {
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .must_fill_main_axis(true);
 
-    let book_text = Scroll::new(book_page).vertical().fix_height(600.0);
+    let book_text = Scroll::new(book_page.controller(LinkController))
+        .vertical()
+        .controller(ChapterScrollController::new())
+        .fix_height(600.0);
+
+    // The pages of a comic are images, shown by the image viewer
+    let book_text = Either::new(
+        |data: &AppState, _env| data.is_comic_book(),
+        ComicPage::default().expand_width().fix_height(600.0),
+        book_text,
+    );
+
+    // The help overlay takes the place of the text while it is shown
+    let book_text = Either::new(
+        |data: &AppState, _env| data.is_help_visible(),
+        help_overlay(&keymap).fix_height(600.0),
+        book_text,
+    );
+
+    // Note of a note reference, shown under the text so that the position does not change
+    let note = Either::new(
+        |data: &AppState, _env| data.is_note_visible(),
+        note_popover(),
+        SizedBox::empty(),
+    );
+
+    // Highlight made in the page, to write a note about it
+    let annotation = Either::new(
+        |data: &AppState, _env| data.is_annotation_visible(),
+        annotation_panel(),
+        SizedBox::empty(),
+    );
+
+    // Audio file written by the last reading aloud, or why it stopped
+    let speech_result = Either::new(
+        |data: &AppState, _env| data.get_speech_result().is_some(),
+        speech_result(),
+        SizedBox::empty(),
+    );
+
+    // Definitions of the word looked up, under the text too
+    let lookup = Either::new(
+        |data: &AppState, _env| data.is_lookup_visible(),
+        lookup_panel(),
+        SizedBox::empty(),
+    );
+
+    // Progress in the book, click or drag to seek
+    // (it fills from the right in the books read from right to left)
+    let seek_bar = SeekBar::new().lens(lens::Map::new(
+        |data: &AppState| {
+            let progress = data.get_progress();
+            if data.is_rtl_book() {
+                1.0 - progress
+            } else {
+                progress
+            }
+        },
+        |data: &mut AppState, fraction: f64| {
+            let progress = if data.is_rtl_book() {
+                1.0 - fraction
+            } else {
+                fraction
+            };
+            if (progress - data.get_progress()).abs() > f64::EPSILON {
+                data.seek(progress);
+            }
+        },
+    ));
     let bottom_bar = Flex::row()
         .with_child(book_controls)
         .main_axis_alignment(MainAxisAlignment::End);
 
+    // The comics have no text to find in a physical book, but the viewer settings
+    let second_row = Either::new(
+        |data: &AppState, _env| data.is_comic_book(),
+        comic_controls(),
+        reverse_ocr_row,
+    );
+
     let top_bar_layout = Flex::column()
         .with_child(top_bar)
-        .with_child(reverse_ocr_row)
+        .with_child(second_row)
+        .with_child(speech_result)
         .cross_axis_alignment(CrossAxisAlignment::Start);
 
     let layout = Flex::column()
         .with_child(top_bar_layout)
         .with_spacer(20_f64)
         .with_child(book_text)
+        .with_child(note)
+        .with_child(annotation)
+        .with_child(lookup)
+        .with_spacer(20_f64)
+        .with_child(seek_bar)
         .with_spacer(20_f64)
         .with_child(bottom_bar)
         .cross_axis_alignment(CrossAxisAlignment::Center);
 
-    let container = Padding::new(PADDING_LG, Container::new(layout.center()));
+    let container = Padding::new(PADDING_LG, Container::new(layout.center()))
+        .controller(ReaderKeyController::new(keymap))
+        .controller(SpeechController)
+        .controller(ReadingController);
 
     Box::new(container)
 }
 
 fn book_menu() -> impl Widget<AppState> {
     let back_button = Button::new("Back").on_click(|_event, data: &mut AppState, _env| {
-        data.pop_view();
+        data.close_book();
     });
 
     let edit_button = Button::new("Edit").on_click(|event, data: &mut AppState, _env| {
@@ -80,9 +179,78 @@ fn book_menu() -> impl Widget<AppState> {
         data.decrease_font_size();
     });
 
+    // Back and forward in the positions left by links and search results
+    let history_buttons = ViewSwitcher::new(
+        |data: &AppState, _env| (data.can_go_back(), data.can_go_forward()),
+        |(can_go_back, can_go_forward), _data, _env| {
+            let mut buttons = Flex::row();
+            if *can_go_back {
+                buttons.add_child(Button::new("◀ Go back").on_click(
+                    |_ctx, data: &mut AppState, _env| {
+                        data.go_back();
+                    },
+                ));
+            }
+            if *can_go_forward {
+                buttons.add_child(Button::new("Go forward ▶").on_click(
+                    |_ctx, data: &mut AppState, _env| {
+                        data.go_forward();
+                    },
+                ));
+            }
+            Box::new(buttons)
+        },
+    );
+
+    // Read the chapter aloud (the next chapters follow), or into an audio file
+    let read_aloud_buttons = ViewSwitcher::new(
+        |data: &AppState, _env| data.is_reading_aloud(),
+        |reading_aloud, _data, _env| {
+            if *reading_aloud {
+                Box::new(
+                    Button::new("Stop reading").on_click(|_ctx, data: &mut AppState, _env| {
+                        data.stop_reading_aloud();
+                    }),
+                )
+            } else {
+                let read_button =
+                    Button::new("Read aloud").on_click(|ctx, data: &mut AppState, _env| {
+                        let listener = speech_listener(ctx);
+                        data.read_aloud(None, listener);
+                    });
+                let save_button = Button::new("Save chapter as audio").on_click(
+                    |ctx, data: &mut AppState, _env| {
+                        let listener = speech_listener(ctx);
+                        data.read_aloud_to_file(listener);
+                    },
+                );
+                Box::new(Flex::row().with_child(read_button).with_child(save_button))
+            }
+        },
+    );
+
+    let dictionary_button =
+        Button::new("Dictionary").on_click(|_ctx, data: &mut AppState, _env| {
+            data.open_dictionary();
+        });
+
+    let vocabulary_button =
+        Button::new("Vocabulary").on_click(|_ctx, data: &mut AppState, _env| {
+            data.open_vocabulary();
+        });
+
+    let help_button = Button::new("Shortcuts").on_click(|_ctx, data: &mut AppState, _env| {
+        data.toggle_help();
+    });
+
     let save_book_progress = Button::new("Bookmark").on_click(|_ctx, data: &mut AppState, _env| {
         println!("Saving book progress");
         data.save_book_progress();
+        data.add_bookmark();
+    });
+
+    let export_button = Button::new("Export notes").on_click(|_ctx, data: &mut AppState, _env| {
+        data.export_book_annotations();
     });
 
     let flex = Flex::row()
@@ -91,6 +259,12 @@ fn book_menu() -> impl Widget<AppState> {
         .with_child(increase_font_button)
         .with_child(decrease_font_button)
         .with_child(save_book_progress)
+        .with_child(read_aloud_buttons)
+        .with_child(dictionary_button)
+        .with_child(vocabulary_button)
+        .with_child(export_button)
+        .with_child(help_button)
+        .with_child(history_buttons)
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .must_fill_main_axis(true)
         .padding(Insets::new(0.0, 0.0, 0.0, PADDING_LG));
@@ -98,6 +272,28 @@ fn book_menu() -> impl Widget<AppState> {
     flex
 }
 
+/**
+ * Where the chapter was read into, or why the reading aloud stopped
+ */
+fn speech_result() -> impl Widget<AppState> {
+    let written = Label::dynamic(|data: &AppState, _env| match data.get_speech_result() {
+        Some(Ok(path)) => format!("Chapter read into {}", path),
+        _ => String::new(),
+    });
+    let failed = Label::dynamic(|data: &AppState, _env| match data.get_speech_result() {
+        Some(Err(error)) => format!("The chapter was not read: {}", error),
+        _ => String::new(),
+    })
+    .with_line_break_mode(LineBreaking::WordWrap)
+    .with_text_color(Color::rgb8(0xc0, 0x39, 0x2b));
+
+    Either::new(
+        |data: &AppState, _env| matches!(data.get_speech_result(), Some(Err(_))),
+        failed,
+        written,
+    )
+}
+
 fn reverse_ocr_row() -> impl Widget<AppState> {
     let reverse_ocr_button = Button::new("Get physical page of this chapter").on_click(
         |_ctx, data: &mut AppState, _env| {
@@ -128,34 +324,176 @@ fn reverse_ocr_row() -> impl Widget<AppState> {
         .main_axis_alignment(MainAxisAlignment::Start)
 }
 
+/**
+ * How the pages of a comic are shown: fitted, zoomed, two by two, from right to left
+ */
+fn comic_controls() -> impl Widget<AppState> {
+    ViewSwitcher::new(
+        |data: &AppState, _env| {
+            (
+                data.get_image_fit(),
+                data.is_two_pages(),
+                data.is_rtl_book(),
+            )
+        },
+        |(image_fit, two_pages, rtl), _data, _env| {
+            let mark = |selected: bool, label: &str| {
+                if selected {
+                    format!("● {}", label)
+                } else {
+                    label.to_string()
+                }
+            };
+            let fit_width_button = Button::new(mark(*image_fit == ImageFit::Width, "Fit width"))
+                .on_click(|_ctx, data: &mut AppState, _env| {
+                    data.set_image_fit(ImageFit::Width);
+                });
+            let fit_page_button = Button::new(mark(*image_fit == ImageFit::Page, "Fit page"))
+                .on_click(|_ctx, data: &mut AppState, _env| {
+                    data.set_image_fit(ImageFit::Page);
+                });
+            let zoom_in_button =
+                Button::new("Zoom +").on_click(|_ctx, data: &mut AppState, _env| {
+                    data.zoom_in();
+                });
+            let zoom_out_button =
+                Button::new("Zoom -").on_click(|_ctx, data: &mut AppState, _env| {
+                    data.zoom_out();
+                });
+            let zoom_label = Label::dynamic(|data: &AppState, _env| match data.get_image_fit() {
+                ImageFit::Zoom => format!("{:.0}%", data.get_zoom() * 100.0),
+                _ => String::new(),
+            });
+            let two_pages_button = Button::new(mark(*two_pages, "Two pages")).on_click(
+                |_ctx, data: &mut AppState, _env| {
+                    data.toggle_two_pages();
+                },
+            );
+            let rtl_button = Button::new(mark(*rtl, "Right to left")).on_click(
+                |_ctx, data: &mut AppState, _env| {
+                    data.toggle_rtl_book();
+                },
+            );
+
+            Box::new(
+                Flex::row()
+                    .with_child(fit_width_button)
+                    .with_child(fit_page_button)
+                    .with_default_spacer()
+                    .with_child(zoom_out_button)
+                    .with_child(zoom_in_button)
+                    .with_child(zoom_label)
+                    .with_default_spacer()
+                    .with_child(two_pages_button)
+                    .with_child(rtl_button)
+                    .main_axis_alignment(MainAxisAlignment::Start),
+            )
+        },
+    )
+}
+
 fn top_right() -> impl Widget<AppState> {
     let page_counter = Label::dynamic(|data: &AppState, _env: &Env| {
         if let Some(idx) = data.get_selected() {
+            let book = &data.get_library()[idx];
+            if book.is_comic() {
+                // The images are counted from 1, the first one is the section 0
+                return format!(
+                    "Page {}/{} · {:.0}%",
+                    book.get_current_page() + 1,
+                    book.get_book_length() + 1,
+                    book.get_progress() * 100.0
+                );
+            }
+            let (location, locations) = book.get_location();
+            let section = if book.has_original_pages() {
+                "Page"
+            } else {
+                "Chapter"
+            };
             format!(
-                "Page {}/{}",
-                data.get_library()[idx].get_current_page(),
-                data.get_library()[idx].get_book_length()
+                "{} {}/{} · Location {} of {} · {:.0}%",
+                section,
+                book.get_current_page(),
+                book.get_book_length(),
+                location,
+                locations,
+                book.get_progress() * 100.0
             )
         } else {
             "".to_string()
         }
     });
 
+    // Estimated reading time, from the reading speed of the book
+    let time_left = Label::dynamic(|data: &AppState, _env: &Env| {
+        if let Some(idx) = data.get_selected() {
+            let book = &data.get_library()[idx];
+            format!(
+                "{} left in chapter · {} left in book",
+                format_minutes(book.get_chapter_time_left()),
+                format_minutes(book.get_book_time_left())
+            )
+        } else {
+            "".to_string()
+        }
+    });
+
+    // Search in the book, Enter goes to the next page containing the text
+    let search_box = TextBox::new()
+        .with_placeholder("Search")
+        .lens(AppState::search_query)
+        .controller(SearchController)
+        .fix_width(160.0);
+    let search_button = Button::new("Find").on_click(|_ctx, data: &mut AppState, _env| {
+        data.search_book();
+    });
+
     let ocr_button =
         Button::new("Search from photo").on_click(|_ctx, data: &mut AppState, _env| {
             data.ocr_from_file();
         });
 
     Flex::row()
+        .with_child(search_box)
+        .with_child(search_button)
+        .with_default_spacer()
         .with_child(ocr_button)
         .with_default_spacer()
+        .with_child(time_left)
+        .with_default_spacer()
         .with_child(page_counter)
 }
 
+/**
+ * Buttons to turn the pages, the next page is on the left in the books read from right to left
+ */
 fn book_controls() -> impl Widget<AppState> {
-    let control_next = ViewSwitcher::new(
+    ViewSwitcher::new(
+        |data: &AppState, _env| data.is_rtl_book(),
+        |rtl, _data, _env| {
+            let controls = if *rtl {
+                Flex::row()
+                    .with_child(control_next(true))
+                    .with_child(control_prev(true))
+            } else {
+                Flex::row()
+                    .with_child(control_prev(false))
+                    .with_child(control_next(false))
+            };
+            Box::new(
+                controls
+                    .must_fill_main_axis(true)
+                    .main_axis_alignment(MainAxisAlignment::End),
+            )
+        },
+    )
+}
+
+fn control_next(rtl: bool) -> impl Widget<AppState> {
+    ViewSwitcher::new(
         |data: &AppState, _env| data.has_next_page(),
-        |f, _data, _env| {
+        move |f, _data, _env| {
             if *f {
                 let last_page_button =
                     Button::new("Last page").on_click(|_event, data: &mut AppState, _env| {
@@ -167,20 +505,30 @@ fn book_controls() -> impl Widget<AppState> {
                         data.navigate_to_next_page();
                     });
 
-                Box::new(
-                    Flex::row()
-                        .with_child(next_button)
-                        .with_child(last_page_button),
-                )
+                if rtl {
+                    Box::new(
+                        Flex::row()
+                            .with_child(last_page_button)
+                            .with_child(next_button),
+                    )
+                } else {
+                    Box::new(
+                        Flex::row()
+                            .with_child(next_button)
+                            .with_child(last_page_button),
+                    )
+                }
             } else {
                 Box::new(SizedBox::empty())
             }
         },
-    );
+    )
+}
 
-    let control_prev = ViewSwitcher::new(
+fn control_prev(rtl: bool) -> impl Widget<AppState> {
+    ViewSwitcher::new(
         |data: &AppState, _env| data.has_prev_page(),
-        |f, _data, _env| {
+        move |f, _data, _env| {
             if *f {
                 let first_page_button =
                     Button::new("First page").on_click(|_event, data: &mut AppState, _env| {
@@ -192,33 +540,274 @@ fn book_controls() -> impl Widget<AppState> {
                         data.navigate_to_prev_page();
                     });
 
-                Box::new(
-                    Flex::row()
-                        .with_child(first_page_button)
-                        .with_child(prev_button),
-                )
+                if rtl {
+                    Box::new(
+                        Flex::row()
+                            .with_child(prev_button)
+                            .with_child(first_page_button),
+                    )
+                } else {
+                    Box::new(
+                        Flex::row()
+                            .with_child(first_page_button)
+                            .with_child(prev_button),
+                    )
+                }
             } else {
                 Box::new(SizedBox::empty())
             }
         },
-    );
-
-    Flex::row()
-        .with_child(control_prev)
-        .with_child(control_next)
-        .must_fill_main_axis(true)
-        .main_axis_alignment(MainAxisAlignment::End)
+    )
 }
 
 fn book_page() -> impl Widget<AppState> {
     let page_switcher = ViewSwitcher::new(
         |data: &AppState, _env| data.get_library()[data.get_selected().unwrap()].get_current_page(),
         |page_index, data, _env| {
-            let page = data.get_library()[data.get_selected().expect("No book selected")].get_page_str(*page_index);
-            let parsed_page = parse(page.expect("No page found"));
+            let library = data.get_library();
+            let book = &library[data.get_selected().expect("No book selected")];
+            let page = book.get_page_str(*page_index).expect("No page found");
+            let stylesheets = book.get_page_stylesheets(*page_index, page.as_str());
+            let parsed_page = parse(page, stylesheets);
             Box::new(parsed_page)
         },
     );
 
     page_switcher
 }
+
+/**
+ * List of the keyboard shortcuts of the reader
+ */
+fn help_overlay(keymap: &Keymap) -> impl Widget<AppState> {
+    let mut shortcuts = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for (description, keys) in keymap.help() {
+        shortcuts.add_child(
+            Flex::row()
+                .with_child(Label::new(description).fix_width(240.0))
+                .with_child(Label::new(keys)),
+        );
+        shortcuts.add_spacer(PADDING_SM);
+    }
+
+    let close_button = Button::new("Close").on_click(|_ctx, data: &mut AppState, _env| {
+        data.toggle_help();
+    });
+
+    Flex::column()
+        .with_child(Label::new("Keyboard shortcuts").with_font(SUBTITLE))
+        .with_spacer(PADDING_LG)
+        .with_child(shortcuts)
+        .with_spacer(PADDING_LG)
+        .with_child(close_button)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+/**
+ * Text of the note shown, with buttons to close it or to read it in its place in the book
+ */
+fn note_popover() -> impl Widget<AppState> {
+    let text = Label::dynamic(|data: &AppState, _env| {
+        data.get_note().map(|note| note.text).unwrap_or_default()
+    })
+    .with_line_break_mode(LineBreaking::WordWrap);
+
+    let go_to_button = Button::new("Go to note").on_click(|_ctx, data: &mut AppState, _env| {
+        data.go_to_note();
+    });
+    let close_button = Button::new("Close").on_click(|_ctx, data: &mut AppState, _env| {
+        data.close_note();
+    });
+    let buttons = Flex::row()
+        .with_child(go_to_button)
+        .with_spacer(PADDING_SM)
+        .with_child(close_button)
+        .main_axis_alignment(MainAxisAlignment::End)
+        .must_fill_main_axis(true);
+
+    Flex::column()
+        .with_child(
+            Scroll::new(text.padding(PADDING_SM))
+                .vertical()
+                .fix_height(120.0),
+        )
+        .with_spacer(PADDING_SM)
+        .with_child(buttons)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .padding(PADDING_SM)
+        .border(Color::grey8(0xd4), 1.0)
+        .rounded(4.0)
+        .expand_width()
+}
+
+/**
+ * Definitions of the word looked up (double click a word of the page) in the dictionary
+ * chosen, a box to look up another word and the last words looked up
+ */
+fn lookup_panel() -> impl Widget<AppState> {
+    let word = Label::dynamic(|data: &AppState, _env| {
+        data.get_lookup()
+            .map(|lookup| lookup.word)
+            .unwrap_or_default()
+    })
+    .with_font(SUBTITLE);
+
+    // The dictionary used is marked, click another one to look the word up in it
+    let dictionaries = ViewSwitcher::new(
+        |data: &AppState, _env| {
+            data.get_lookup()
+                .map(|lookup| (Vector::from(lookup.dictionaries), lookup.dictionary))
+                .unwrap_or_default()
+        },
+        |(dictionaries, selected), _data, _env| {
+            let mut buttons = Flex::row();
+            for (index, name) in dictionaries.iter().enumerate() {
+                let label = if Some(index) == *selected {
+                    format!("● {}", name)
+                } else {
+                    name.clone()
+                };
+                buttons.add_child(Button::new(label).on_click(
+                    move |_ctx, data: &mut AppState, _env| {
+                        data.set_dictionary(index);
+                    },
+                ));
+            }
+            Box::new(buttons)
+        },
+    );
+
+    let close_button = Button::new("Close").on_click(|_ctx, data: &mut AppState, _env| {
+        data.close_lookup();
+    });
+    let header = Flex::row()
+        .with_child(word)
+        .with_flex_spacer(1.0)
+        .with_child(dictionaries)
+        .with_spacer(PADDING_SM)
+        .with_child(close_button)
+        .must_fill_main_axis(true);
+
+    let definitions = Label::dynamic(|data: &AppState, _env| match data.get_lookup() {
+        None => String::new(),
+        Some(lookup) if lookup.dictionaries.is_empty() => {
+            "No dictionaries, add StarDict dictionaries to the dictionaries folder".to_string()
+        }
+        Some(lookup) if lookup.word.is_empty() => "Type a word to look it up".to_string(),
+        Some(lookup) if lookup.definitions.is_empty() => {
+            format!("\"{}\" is not in the dictionary", lookup.word)
+        }
+        Some(lookup) => lookup
+            .definitions
+            .iter()
+            .map(|definition| format!("{}\n{}", definition.word, definition.text))
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+    })
+    .with_line_break_mode(LineBreaking::WordWrap);
+
+    let query = TextBox::new()
+        .with_placeholder("Look up a word")
+        .lens(AppState::lookup_query)
+        .fix_width(200.0);
+    let look_up_button = Button::new("Look up").on_click(|_ctx, data: &mut AppState, _env| {
+        data.look_up_query();
+    });
+
+    // Last words looked up, click one to look it up again
+    let history = ViewSwitcher::new(
+        |data: &AppState, _env| {
+            data.get_lookup()
+                .map(|lookup| Vector::from(lookup.history))
+                .unwrap_or_default()
+        },
+        |history, _data, _env| {
+            let mut buttons = Flex::row();
+            if !history.is_empty() {
+                buttons.add_child(Label::new("Recent:"));
+            }
+            for word in history.iter() {
+                let word = word.clone();
+                buttons.add_child(Button::new(word.as_str()).on_click(
+                    move |_ctx, data: &mut AppState, _env| {
+                        data.look_up_word(word.as_str(), "");
+                    },
+                ));
+            }
+            Box::new(Scroll::new(buttons).horizontal())
+        },
+    );
+
+    let search_row = Flex::row()
+        .with_child(query)
+        .with_spacer(PADDING_SM)
+        .with_child(look_up_button)
+        .with_spacer(PADDING_SM)
+        .with_flex_child(history, 1.0)
+        .must_fill_main_axis(true);
+
+    Flex::column()
+        .with_child(header)
+        .with_spacer(PADDING_SM)
+        .with_child(
+            Scroll::new(definitions.padding(PADDING_SM))
+                .vertical()
+                .fix_height(150.0),
+        )
+        .with_spacer(PADDING_SM)
+        .with_child(search_row)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .padding(PADDING_SM)
+        .border(Color::grey8(0xd4), 1.0)
+        .rounded(4.0)
+        .expand_width()
+}
+
+/**
+ * Sentence highlighted (double click with Shift in the page), with the note written about it
+ */
+fn annotation_panel() -> impl Widget<AppState> {
+    let quote = Label::dynamic(|data: &AppState, _env| {
+        data.get_annotation()
+            .map(|annotation| format!("“{}” ({})", annotation.text, annotation.position_label()))
+            .unwrap_or_default()
+    })
+    .with_line_break_mode(LineBreaking::WordWrap);
+
+    let note = TextBox::multiline()
+        .with_placeholder("Write a note")
+        .lens(AppState::annotation_note)
+        .expand_width()
+        .fix_height(80.0);
+
+    let save_button = Button::new("Save note").on_click(|_ctx, data: &mut AppState, _env| {
+        data.save_annotation_note();
+    });
+    let remove_button =
+        Button::new("Remove highlight").on_click(|_ctx, data: &mut AppState, _env| {
+            data.remove_annotation();
+        });
+    let close_button = Button::new("Close").on_click(|_ctx, data: &mut AppState, _env| {
+        data.close_annotation();
+    });
+    let buttons = Flex::row()
+        .with_child(remove_button)
+        .with_spacer(PADDING_SM)
+        .with_child(save_button)
+        .with_spacer(PADDING_SM)
+        .with_child(close_button)
+        .main_axis_alignment(MainAxisAlignment::End)
+        .must_fill_main_axis(true);
+
+    Flex::column()
+        .with_child(quote.padding(PADDING_SM))
+        .with_spacer(PADDING_SM)
+        .with_child(note)
+        .with_spacer(PADDING_SM)
+        .with_child(buttons)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .padding(PADDING_SM)
+        .border(Color::grey8(0xd4), 1.0)
+        .rounded(4.0)
+        .expand_width()
+}
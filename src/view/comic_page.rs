@@ -0,0 +1,129 @@
+use druid::{
+    kurbo::Affine,
+    piet::{InterpolationMode, PietImage},
+    BoxConstraints, Color, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, MouseButton,
+    PaintCtx, Point, RenderContext, Size, UpdateCtx, Vec2, Widget,
+};
+
+use crate::{
+    helper::viewer::{clamp_offset, image_scale, spread_rects},
+    model::app_state::AppState,
+};
+
+/**
+ * Pages of a comic shown in the reader: the image of the page, or the two images
+ * of a spread, fitted to the width, to the page or zoomed.
+ * The images are decoded when their pages are shown, scroll or drag to move in them.
+ */
+#[derive(Default)]
+pub struct ComicPage {
+    // Pages shown, their images and sizes
+    pages: Vec<usize>,
+    images: Vec<(PietImage, Size)>,
+    // Size of the images as they are drawn, and the part of them shown
+    content: Size,
+    offset: Vec2,
+    drag: Option<Point>,
+}
+
+impl ComicPage {
+    fn scroll_by(&mut self, ctx: &mut EventCtx, delta: Vec2) {
+        self.offset = clamp_offset(self.offset + delta, self.content, ctx.size());
+        ctx.request_paint();
+    }
+}
+
+impl Widget<AppState> for ComicPage {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut AppState, _env: &Env) {
+        match event {
+            Event::Wheel(mouse) => {
+                self.scroll_by(ctx, mouse.wheel_delta);
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                self.drag = Some(mouse.pos);
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let Some(last) = self.drag.replace(mouse.pos) {
+                    self.scroll_by(ctx, last - mouse.pos);
+                }
+            }
+            Event::MouseUp(_) if ctx.is_active() => {
+                self.drag = None;
+                ctx.set_active(false);
+            }
+            _ => (),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &AppState,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, _env: &Env) {
+        // Other pages (or another book): the images are decoded again, from the top
+        if data.get_shown_pages() != self.pages || data.get_selected() != old_data.get_selected() {
+            self.pages.clear();
+            self.images.clear();
+            self.offset = Vec2::ZERO;
+            ctx.request_paint();
+        }
+        if data.get_image_fit() != old_data.get_image_fit()
+            || data.get_zoom() != old_data.get_zoom()
+            || data.is_rtl_book() != old_data.is_rtl_book()
+        {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AppState,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, _env: &Env) {
+        let viewport = ctx.size();
+        if self.pages.is_empty() {
+            self.pages = data.get_shown_pages();
+            if let Some(book) = data.get_selected().map(|index| &data.get_library()[index]) {
+                for page in self.pages.iter() {
+                    if let Some(image_buf) = book.get_page_image(*page) {
+                        let size = image_buf.size();
+                        self.images.push((image_buf.to_image(ctx.render_ctx), size));
+                    }
+                }
+            }
+        }
+        if self.images.is_empty() {
+            ctx.fill(viewport.to_rect(), &Color::grey8(0xd4));
+            return;
+        }
+
+        let sizes: Vec<Size> = self.images.iter().map(|(_, size)| *size).collect();
+        let (content, rects) = spread_rects(&sizes, data.is_rtl_book());
+        let scale = image_scale(content, viewport, data.get_image_fit(), data.get_zoom());
+        self.content = content * scale;
+        self.offset = clamp_offset(self.offset, self.content, viewport);
+
+        let offset = self.offset;
+        let images = &self.images;
+        ctx.clip(viewport.to_rect());
+        ctx.with_save(|ctx| {
+            ctx.transform(Affine::translate(-offset) * Affine::scale(scale));
+            for ((image, _), rect) in images.iter().zip(rects) {
+                ctx.draw_image(image, rect, InterpolationMode::Bilinear);
+            }
+        });
+    }
+}
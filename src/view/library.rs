@@ -1,14 +1,24 @@
 use crate::{
-    controller::view::BOOK_READ,
-    helper::config::{APP_NAME, COVER_PLACEHOLDER, PADDING_LG, PADDING_SM, TITLE},
+    controller::{
+        library::{
+            LibraryController, CONVERT_BOOK, DELETE_BOOK, GENERATE_COVER, OPEN_FOLDER, REMOVE_BOOK,
+            SET_COVER,
+        },
+        view::{BOOK_READ, METADATA_EDIT},
+    },
+    helper::{
+        config::{APP_NAME, COVER_PLACEHOLDER, PADDING_LG, PADDING_SM, TITLE},
+        thumbnail::ThumbnailSize,
+    },
     model::{app_state::AppState, book::Book, ui_view::UiView},
+    view::thumbnail::Thumbnail,
 };
 use druid::{
     widget::{
-        Button, Container, FillStrat, Flex, Image, Label, List, ListIter, MainAxisAlignment,
-        Padding, Scroll, SizedBox, Svg, ViewSwitcher,
+        Button, Container, Either, FillStrat, Flex, Label, List, ListIter, MainAxisAlignment,
+        Padding, ProgressBar, Scroll, SizedBox, Svg, ViewSwitcher,
     },
-    Command, Data, EventCtx, Target, Widget, WidgetExt,
+    Color, Command, Data, EventCtx, Target, Widget, WidgetExt,
 };
 use std::sync::Arc;
 
@@ -39,16 +49,16 @@ pub fn library() -> Box<dyn Widget<AppState>> {
              _env: &_| { book.get_author() },
         );
 
-        // Book cover
+        // Book cover, a cached thumbnail loaded when the row becomes visible
         let cover = Flex::row().with_child(ViewSwitcher::new(
             |(_views, book, _selected, _idx): &(Arc<Vec<UiView>>, Book, Option<usize>, usize),
-             _env| book.get_image_buf().is_some(),
-            move |f, data, _env| {
-                if *f {
-                    Box::new(
-                        Image::new(data.1.get_image_buf().as_ref().unwrap().as_ref().clone()) //Unwrap is safe because the image is checked
-                            .fix_size(100.0, 200.0),
-                    )
+             _env| book.get_cover_path(),
+            move |f, _data, _env| {
+                if !f.is_empty() {
+                    Box::new(Thumbnail::new(ThumbnailSize::Small).lens(druid::lens!(
+                        (Arc<Vec<UiView>>, Book, Option<usize>, usize),
+                        1
+                    )))
                 } else {
                     Box::new(
                         Svg::new(COVER_PLACEHOLDER.parse().unwrap()).fill_mode(FillStrat::Fill), //Unwrap is safe because the missing-cover is hardcoded
@@ -57,6 +67,32 @@ pub fn library() -> Box<dyn Widget<AppState>> {
             },
         ));
 
+        // Reading progress of the book
+        let progress_bar = Flex::row()
+            .with_child(
+                ProgressBar::new()
+                    .lens(druid::lens::Map::new(
+                        |(_views, book, _selected, _idx): &(
+                            Arc<Vec<UiView>>,
+                            Book,
+                            Option<usize>,
+                            usize,
+                        )| book.get_progress(),
+                        |_data, _progress| {},
+                    ))
+                    .fix_width(150.0),
+            )
+            .with_spacer(PADDING_SM)
+            .with_child(Label::new(
+                |(_views, book, _selected, _idx): &(
+                    Arc<Vec<UiView>>,
+                    Book,
+                    Option<usize>,
+                    usize,
+                ),
+                 _env: &_| format!("{:.0}%", book.get_progress() * 100.0),
+            ));
+
         // Book progress (if any)
         let progress_switcher = ViewSwitcher::new(
             |(_views, book, _selected, _idx): &(Arc<Vec<UiView>>, Book, Option<usize>, usize),
@@ -79,12 +115,61 @@ pub fn library() -> Box<dyn Widget<AppState>> {
             },
         );
 
+        // Edit the metadata of the book
+        let metadata_button = Button::new("Edit metadata").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                let new_views = Arc::make_mut(&mut data.0);
+                new_views.push(UiView::MetadataEdit);
+                data.0 = Arc::new(new_views.to_owned());
+                data.2 = Some(data.3);
+                event.submit_command(Command::new(METADATA_EDIT, data.3, Target::Auto));
+            },
+        );
+
+        // Replace the cover with an image file, or with a generated one
+        let set_cover_button = Button::new("Set cover").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(SET_COVER, data.3, Target::Auto));
+            },
+        );
+        let generate_cover_button = Button::new("Generate cover").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(GENERATE_COVER, data.3, Target::Auto));
+            },
+        );
+
+        // Move the book out of the library, move it to the trash, or show its file
+        let remove_button = Button::new("Remove from library").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(REMOVE_BOOK, data.3, Target::Auto));
+            },
+        );
+        let delete_button = Button::new("Delete").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(DELETE_BOOK, data.3, Target::Auto));
+            },
+        );
+        let open_folder_button = Button::new("Open folder").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(OPEN_FOLDER, data.3, Target::Auto));
+            },
+        );
+
+        // Save the book as EPUB, plain text or Markdown
+        let convert_button = Button::new("Convert").on_click(
+            |event, data: &mut (Arc<Vec<UiView>>, Book, Option<usize>, usize), _env| {
+                event.submit_command(Command::new(CONVERT_BOOK, data.3, Target::Auto));
+            },
+        );
+
         // Details and functions of the book
         let details = Flex::column()
             .with_child(book_title)
             .with_spacer(PADDING_SM)
             .with_child(author)
             .with_spacer(PADDING_SM)
+            .with_child(progress_bar)
+            .with_spacer(PADDING_SM)
             .with_child(progress_switcher);
 
         //Entire book layout
@@ -106,17 +191,40 @@ pub fn library() -> Box<dyn Widget<AppState>> {
             event.submit_command(Command::new(BOOK_READ, data.3, Target::Auto));
         });
 
-        book_layout
+        // Actions are kept outside of the clickable area, otherwise the book would be opened too
+        let actions = Flex::column()
+            .with_child(metadata_button)
+            .with_spacer(PADDING_SM)
+            .with_child(set_cover_button)
+            .with_spacer(PADDING_SM)
+            .with_child(generate_cover_button)
+            .with_spacer(PADDING_SM)
+            .with_child(convert_button);
+        let file_actions = Flex::column()
+            .with_child(open_folder_button)
+            .with_spacer(PADDING_SM)
+            .with_child(remove_button)
+            .with_spacer(PADDING_SM)
+            .with_child(delete_button);
+
+        Flex::row()
+            .with_child(book_layout)
+            .with_spacer(PADDING_SM)
+            .with_child(actions)
+            .with_spacer(PADDING_SM)
+            .with_child(file_actions)
     });
 
     // Layout of the page
     let layout = Flex::column()
         .with_child(header())
+        .with_child(conversion())
+        .with_child(notes_import())
         .with_flex_child(Scroll::new(list.with_spacing(20.0)).center(), 1.)
         .must_fill_main_axis(true)
         .expand_width();
 
-    Box::new(Padding::new(PADDING_LG, Container::new(layout)))
+    Box::new(Padding::new(PADDING_LG, Container::new(layout)).controller(LibraryController))
 }
 
 /* Header section */
@@ -128,16 +236,97 @@ fn header() -> impl Widget<AppState> {
             data.add_book_from_file();
         });
 
+    let add_comic_button =
+        Button::new("Add image folder").on_click(|_ctx: &mut EventCtx, data: &mut AppState, _| {
+            data.add_comic_folder();
+        });
+
+    let stats_button =
+        Button::new("Statistics").on_click(|_ctx: &mut EventCtx, data: &mut AppState, _| {
+            let views = Arc::make_mut(&mut data.nav_state);
+            views.push(UiView::Stats);
+            data.nav_state = Arc::new(views.to_owned());
+        });
+
+    let export_button =
+        Button::new("Export all notes").on_click(|_ctx: &mut EventCtx, data: &mut AppState, _| {
+            data.export_library_annotations();
+        });
+
+    let import_button =
+        Button::new("Import notes").on_click(|_ctx: &mut EventCtx, data: &mut AppState, _| {
+            data.import_annotations_from_file();
+        });
+
     let mut header = Flex::row()
         .with_child(header_label)
         .main_axis_alignment(MainAxisAlignment::SpaceBetween)
         .must_fill_main_axis(true);
 
-    header.add_child(add_book_button);
+    header.add_child(
+        Flex::row()
+            .with_child(stats_button)
+            .with_spacer(PADDING_SM)
+            .with_child(import_button)
+            .with_spacer(PADDING_SM)
+            .with_child(export_button)
+            .with_spacer(PADDING_SM)
+            .with_child(add_comic_button)
+            .with_spacer(PADDING_SM)
+            .with_child(add_book_button),
+    );
 
     header
 }
 
+/**
+ * Result of the last action on files (a conversion, an import of notes) read by result,
+ * the error in red, nothing if there is no result
+ */
+fn result_label(
+    result: fn(&AppState) -> Option<Result<String, String>>,
+    done: &'static str,
+    failed: &'static str,
+) -> impl Widget<AppState> {
+    let done_label = Label::dynamic(move |data: &AppState, _env| match result(data) {
+        Some(Ok(message)) => format!("{}{}", done, message),
+        _ => String::new(),
+    });
+    let failed_label = Label::dynamic(move |data: &AppState, _env| match result(data) {
+        Some(Err(error)) => format!("{}{}", failed, error),
+        _ => String::new(),
+    })
+    .with_text_color(Color::rgb8(0xc0, 0x39, 0x2b));
+
+    Either::new(
+        move |data: &AppState, _env| matches!(result(data), Some(Err(_))),
+        failed_label,
+        done_label,
+    )
+}
+
+/**
+ * Where the last converted book was saved, or why it was not
+ */
+fn conversion() -> impl Widget<AppState> {
+    result_label(
+        AppState::get_conversion,
+        "Book converted to ",
+        "The book was not converted: ",
+    )
+}
+
+/**
+ * How many notes the last import added, or why it failed
+ */
+fn notes_import() -> impl Widget<AppState> {
+    result_label(
+        AppState::get_notes_import,
+        "",
+        "The notes were not imported: ",
+    )
+}
+
 // TODO: Da capire bene
 // A little special implementation to give the list view all that it needs
 // to list the Book structs
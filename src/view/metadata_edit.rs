@@ -0,0 +1,167 @@
+use crate::{
+    helper::{
+        config::{PADDING_LG, PADDING_SM, TITLE},
+        functions::open_native_dialog_images,
+        opf::SaveMode,
+        thumbnail::ThumbnailSize,
+    },
+    model::{
+        app_state::AppState,
+        book::Book,
+        metadata_state::{MetadataState, MetadataTransfer},
+    },
+    view::thumbnail::Thumbnail,
+};
+use druid::{
+    lens,
+    widget::{
+        Button, Container, CrossAxisAlignment, Flex, Label, LineBreaking, MainAxisAlignment,
+        Padding, Scope, Scroll, TextBox,
+    },
+    Color, Lens, Widget, WidgetExt,
+};
+use druid_widget_nursery::navigator::ViewController;
+
+fn header() -> impl Widget<AppState> {
+    let header_label = Label::new("Metadata Editor").with_font(TITLE);
+
+    let back_button = Button::new("Back").on_click(|_event, data: &mut AppState, _env| {
+        data.set_metadata_error(None);
+        data.pop_view();
+    });
+
+    Flex::row()
+        .with_child(header_label)
+        .with_child(back_button)
+        .main_axis_alignment(MainAxisAlignment::SpaceBetween)
+        .must_fill_main_axis(true)
+}
+
+/**
+ * A row of the form, with the name of the field and a textbox
+ */
+fn field<L>(name: &str, lens: L) -> impl Widget<MetadataState>
+where
+    L: Lens<MetadataState, String> + 'static,
+{
+    Flex::row()
+        .with_child(Label::new(name).fix_width(160.0))
+        .with_flex_child(TextBox::new().expand_width().lens(lens), 1.0)
+        .with_spacer(PADDING_SM)
+}
+
+fn cover_row() -> impl Widget<MetadataState> {
+    let cover_label = Label::dynamic(|data: &MetadataState, _env| match &data.cover_path {
+        Some(path) => path.clone(),
+        None => String::from("Current cover"),
+    })
+    .with_line_break_mode(LineBreaking::WordWrap);
+
+    let cover_button =
+        Button::new("Choose cover").on_click(|_ctx, data: &mut MetadataState, _env| {
+            if let Some(path) = open_native_dialog_images() {
+                data.cover_path = Some(path.to_string_lossy().to_string());
+            }
+        });
+
+    Flex::row()
+        .with_child(Label::new("Cover").fix_width(160.0))
+        .with_child(cover_button)
+        .with_default_spacer()
+        .with_flex_child(cover_label, 1.0)
+}
+
+/**
+ * Cover of the book edited, as it is before saving
+ */
+fn cover_preview() -> impl Widget<AppState> {
+    Thumbnail::new(ThumbnailSize::Large).lens(lens::Map::new(
+        |data: &AppState| {
+            let library = data.get_library();
+            library[data.get_selected().unwrap_or(0)].clone()
+        },
+        // The cover is only shown, a new one is saved with the metadata
+        |_data: &mut AppState, _book: Book| {},
+    ))
+}
+
+/**
+ * Why the last save failed (an empty title, a book that could not be written),
+ * empty if it did not
+ */
+fn save_error() -> impl Widget<AppState> {
+    Label::dynamic(|data: &AppState, _env| match data.get_metadata_error() {
+        Some(error) => format!("The metadata was not saved: {}", error),
+        None => String::new(),
+    })
+    .with_line_break_mode(LineBreaking::WordWrap)
+    .with_text_color(Color::rgb8(0xc0, 0x39, 0x2b))
+}
+
+/**
+ * Button that saves the form with the given mode,
+ * the editor is closed by MetadataTransfer once the book is saved
+ */
+fn save_button(text: &str, mode: SaveMode) -> impl Widget<MetadataState> {
+    Button::new(text).on_click(move |_event, data: &mut MetadataState, _env| {
+        data.save_mode = Some(mode);
+    })
+}
+
+pub fn metadata_edit() -> Box<dyn Widget<AppState>> {
+    let description = Flex::row()
+        .with_child(Label::new("Description").fix_width(160.0))
+        .with_flex_child(
+            TextBox::multiline()
+                .expand_width()
+                .fix_height(160.0)
+                .lens(MetadataState::description),
+            1.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+
+    let form = Flex::column()
+        .with_child(field("Title", MetadataState::title))
+        .with_spacer(PADDING_SM)
+        .with_child(field("Authors (A & B)", MetadataState::authors))
+        .with_spacer(PADDING_SM)
+        .with_child(field("Series", MetadataState::series))
+        .with_spacer(PADDING_SM)
+        .with_child(field("Series index", MetadataState::series_index))
+        .with_spacer(PADDING_SM)
+        .with_child(field("Tags (a, b)", MetadataState::tags))
+        .with_spacer(PADDING_SM)
+        .with_child(field("Language", MetadataState::language))
+        .with_spacer(PADDING_SM)
+        .with_child(description)
+        .with_spacer(PADDING_SM)
+        .with_child(cover_row())
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+
+    let buttons = Flex::row()
+        .with_child(save_button("Save", SaveMode::InPlace))
+        .with_default_spacer()
+        .with_child(save_button("Save as copy", SaveMode::Copy))
+        .main_axis_alignment(MainAxisAlignment::End)
+        .must_fill_main_axis(true);
+
+    let layout = Flex::column()
+        .with_flex_child(Scroll::new(form).vertical(), 1.0)
+        .with_spacer(PADDING_SM)
+        .with_child(buttons);
+
+    // Same as the book editor, the scope only writes back into the AppState when saving
+    let layout = Scope::from_function(MetadataState::new, MetadataTransfer, layout);
+    let layout = Flex::row()
+        .with_child(cover_preview())
+        .with_spacer(PADDING_LG)
+        .with_flex_child(layout, 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+    let layout = Flex::column()
+        .with_child(header())
+        .with_spacer(PADDING_LG)
+        .with_child(save_error())
+        .with_flex_child(layout, 1.0);
+
+    Box::new(Padding::new(PADDING_LG, Container::new(layout)))
+}
@@ -1,4 +1,11 @@
 pub mod book_edit;
 pub mod book_view;
+pub mod comic_page;
 pub mod library;
+pub mod metadata_edit;
 pub mod navigator;
+pub mod seek_bar;
+pub mod stats;
+pub mod thumbnail;
+pub mod vertical_text;
+pub mod vocabulary;
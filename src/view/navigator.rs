@@ -5,7 +5,10 @@ use crate::{
 use druid::{Widget, WidgetExt};
 use druid_widget_nursery::navigator::Navigator;
 
-use super::{book_edit::book_edit, book_view::book_view, library::library};
+use super::{
+    book_edit::book_edit, book_view::book_view, library::library, metadata_edit::metadata_edit,
+    stats::stats, vocabulary::vocabulary,
+};
 
 // use super::{contact_detail::contact_details, contact_edit::contact_edit, contacts::contacts};
 
@@ -14,5 +17,8 @@ pub fn navigator() -> impl Widget<AppState> {
     Navigator::new(UiView::Library, library)
         .with_view_builder(UiView::BookRead, book_view)
         .with_view_builder(UiView::BookEdit, book_edit)
+        .with_view_builder(UiView::MetadataEdit, metadata_edit)
+        .with_view_builder(UiView::Stats, stats)
+        .with_view_builder(UiView::Vocabulary, vocabulary)
         .controller(NavigatorController)
 }
@@ -0,0 +1,111 @@
+use druid::{
+    kurbo::{Circle, RoundedRect},
+    BoxConstraints, Color, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, RenderContext, Size, UpdateCtx, Widget,
+};
+
+const BAR_HEIGHT: f64 = 8.0;
+const HEIGHT: f64 = 20.0;
+
+/**
+ * Progress bar that can be clicked or dragged to seek, the data is the fraction (0.0 - 1.0).
+ * While dragging only the bar moves, the data changes when the mouse is released.
+ */
+#[derive(Default)]
+pub struct SeekBar {
+    drag: Option<f64>,
+}
+
+impl SeekBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn fraction(ctx: &EventCtx, position: Point) -> f64 {
+        let width = ctx.size().width;
+        if width <= 0.0 {
+            return 0.0;
+        }
+        (position.x / width).clamp(0.0, 1.0)
+    }
+}
+
+impl Widget<f64> for SeekBar {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut f64, _env: &Env) {
+        match event {
+            Event::MouseDown(mouse) => {
+                ctx.set_active(true);
+                self.drag = Some(Self::fraction(ctx, mouse.pos));
+                ctx.request_paint();
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                self.drag = Some(Self::fraction(ctx, mouse.pos));
+                ctx.request_paint();
+            }
+            Event::MouseUp(mouse) if ctx.is_active() => {
+                ctx.set_active(false);
+                self.drag = None;
+                *data = Self::fraction(ctx, mouse.pos);
+                ctx.request_paint();
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &f64, _env: &Env) {
+        if let LifeCycle::HotChanged(_) = event {
+            ctx.request_paint();
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &f64, data: &f64, _env: &Env) {
+        if (old_data - data).abs() > f64::EPSILON {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &f64,
+        _env: &Env,
+    ) -> Size {
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            bc.min().width.max(200.0)
+        };
+        bc.constrain(Size::new(width, HEIGHT))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &f64, _env: &Env) {
+        let size = ctx.size();
+        let fraction = self.drag.unwrap_or(*data).clamp(0.0, 1.0);
+        let top = (size.height - BAR_HEIGHT) / 2.0;
+
+        let track = RoundedRect::new(0.0, top, size.width, top + BAR_HEIGHT, BAR_HEIGHT / 2.0);
+        ctx.fill(track, &Color::grey8(0xd4));
+
+        let done = RoundedRect::new(
+            0.0,
+            top,
+            size.width * fraction,
+            top + BAR_HEIGHT,
+            BAR_HEIGHT / 2.0,
+        );
+        ctx.fill(done, &Color::rgb8(0x3a, 0x7b, 0xd5));
+
+        // Handle, bigger when the mouse is over the bar
+        let radius = if ctx.is_hot() || ctx.is_active() {
+            HEIGHT / 2.0
+        } else {
+            BAR_HEIGHT
+        };
+        let center = Point::new(size.width * fraction, size.height / 2.0);
+        ctx.fill(
+            Circle::new(center, radius / 1.5),
+            &Color::rgb8(0x3a, 0x7b, 0xd5),
+        );
+    }
+}
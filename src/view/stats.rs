@@ -0,0 +1,138 @@
+use crate::{
+    helper::{
+        config::{PADDING_LG, PADDING_SM, SUBTITLE, TITLE},
+        stats::{
+            daily_totals, format_minutes, load_all_stats, reading_speed, streaks, weekly_totals,
+            BookStats,
+        },
+    },
+    model::app_state::AppState,
+};
+use chrono::{Datelike, Duration, Local};
+use druid::{
+    widget::{
+        Button, Container, CrossAxisAlignment, Flex, Label, MainAxisAlignment, Padding, Scroll,
+    },
+    Widget, WidgetExt,
+};
+use druid_widget_nursery::navigator::ViewController;
+
+fn header() -> impl Widget<AppState> {
+    let header_label = Label::new("Reading Statistics").with_font(TITLE);
+
+    let back_button = Button::new("Back").on_click(|_event, data: &mut AppState, _env| {
+        data.pop_view();
+    });
+
+    Flex::row()
+        .with_child(header_label)
+        .with_child(back_button)
+        .main_axis_alignment(MainAxisAlignment::SpaceBetween)
+        .must_fill_main_axis(true)
+}
+
+fn duration(seconds: i64) -> String {
+    format_minutes(seconds / 60)
+}
+
+fn speed(speed: Option<f64>) -> String {
+    match speed {
+        Some(speed) => format!("{:.0} words per minute", speed),
+        None => "not enough data".to_string(),
+    }
+}
+
+/**
+ * Statistics page, the reading sessions of all the books are read
+ * when the page is opened
+ */
+pub fn stats() -> Box<dyn Widget<AppState>> {
+    let all_stats = load_all_stats();
+    let sessions = all_stats.iter().flat_map(|stats| stats.sessions.iter());
+    let daily = daily_totals(sessions.clone());
+    let weekly = weekly_totals(&daily);
+    let today = Local::now().date_naive();
+    let (current_streak, longest_streak) = streaks(&daily, today);
+
+    // Totals
+    let summary = Flex::column()
+        .with_child(Label::new(format!(
+            "Total reading time: {}",
+            duration(daily.values().sum())
+        )))
+        .with_child(Label::new(format!(
+            "Reading speed: {}",
+            speed(reading_speed(sessions))
+        )))
+        .with_child(Label::new(format!(
+            "Current streak: {} days",
+            current_streak
+        )))
+        .with_child(Label::new(format!(
+            "Longest streak: {} days",
+            longest_streak
+        )))
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+
+    // Last 7 days
+    let mut days = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for offset in (0..7).rev() {
+        let day = today - Duration::days(offset);
+        let seconds = daily.get(&day).copied().unwrap_or(0);
+        days.add_child(Label::new(format!(
+            "{}: {}",
+            day.format("%a %d/%m"),
+            duration(seconds)
+        )));
+    }
+
+    // Last 4 weeks
+    let mut weeks = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    for offset in (0..4).rev() {
+        let week = monday - Duration::weeks(offset);
+        let seconds = weekly.get(&week).copied().unwrap_or(0);
+        weeks.add_child(Label::new(format!(
+            "Week of {}: {}",
+            week.format("%d/%m"),
+            duration(seconds)
+        )));
+    }
+
+    // Books, the most read first
+    let mut books: Vec<&BookStats> = all_stats.iter().collect();
+    let total = |stats: &BookStats| stats.sessions.iter().map(|s| s.seconds).sum::<i64>();
+    books.sort_by_key(|stats| -total(stats));
+    let mut book_list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for stats in books {
+        book_list.add_child(Label::new(format!(
+            "{}: {}, {}",
+            stats.title,
+            duration(total(stats)),
+            speed(reading_speed(&stats.sessions))
+        )));
+    }
+
+    let layout = Flex::column()
+        .with_child(header())
+        .with_spacer(PADDING_LG)
+        .with_child(summary)
+        .with_spacer(PADDING_LG)
+        .with_child(Label::new("Last 7 days").with_font(SUBTITLE))
+        .with_spacer(PADDING_SM)
+        .with_child(days)
+        .with_spacer(PADDING_LG)
+        .with_child(Label::new("Last 4 weeks").with_font(SUBTITLE))
+        .with_spacer(PADDING_SM)
+        .with_child(weeks)
+        .with_spacer(PADDING_LG)
+        .with_child(Label::new("Books").with_font(SUBTITLE))
+        .with_spacer(PADDING_SM)
+        .with_child(book_list)
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+
+    Box::new(Padding::new(
+        PADDING_LG,
+        Container::new(Scroll::new(layout.expand_width()).vertical()),
+    ))
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use druid::{
+    piet::{InterpolationMode, PietImage},
+    BoxConstraints, Color, Data, Env, Event, EventCtx, ImageBuf, LayoutCtx, LifeCycle,
+    LifeCycleCtx, PaintCtx, Rect, RenderContext, Size, UpdateCtx, Widget,
+};
+
+use crate::{helper::thumbnail::ThumbnailSize, model::book::Book};
+
+/**
+ * Cover thumbnail of a book.
+ * The image is taken from the thumbnail cache the first time the widget is inside
+ * the visible area (when its view context changes, e.g. on scroll), so the covers are
+ * loaded only when their rows are scrolled into view. Paint only draws what was loaded.
+ */
+pub struct Thumbnail {
+    size: ThumbnailSize,
+    thumbnail: Option<Arc<ImageBuf>>,
+    // The thumbnail made into an image of the render context, at the first paint
+    image: Option<PietImage>,
+    // The widget was in the visible area the last time its view context changed
+    visible: bool,
+    // The cover could not be loaded, do not try again until the book changes
+    missing: bool,
+}
+
+impl Thumbnail {
+    pub fn new(size: ThumbnailSize) -> Self {
+        Self {
+            size,
+            thumbnail: None,
+            image: None,
+            visible: false,
+            missing: false,
+        }
+    }
+
+    /**
+     * Read the thumbnail from the cache if it is visible and was not read yet
+     */
+    fn load(&mut self, data: &Book) -> bool {
+        if !self.visible || self.thumbnail.is_some() || self.missing {
+            return false;
+        }
+        match data.get_thumbnail(self.size) {
+            Some(thumbnail) => self.thumbnail = Some(thumbnail),
+            None => self.missing = true,
+        }
+        true
+    }
+}
+
+impl Widget<Book> for Thumbnail {
+    fn event(&mut self, _ctx: &mut EventCtx, _event: &Event, _data: &mut Book, _env: &Env) {}
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Book, _env: &Env) {
+        if let LifeCycle::ViewContextChanged(view) = event {
+            // The clip is the part of the window showing the widget, in its coordinates
+            let bounds = ctx.size().to_rect();
+            self.visible = view.clip.intersect(bounds).area() > 0.0;
+            if self.load(data) {
+                ctx.request_paint();
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Book, data: &Book, _env: &Env) {
+        // The cover may have changed, read it again from the cache
+        if !old_data.same(data) {
+            self.thumbnail = None;
+            self.image = None;
+            self.missing = false;
+            self.load(data);
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Book,
+        _env: &Env,
+    ) -> Size {
+        let (width, height) = self.size.dimensions();
+        bc.constrain(Size::new(width as f64, height as f64))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Book, _env: &Env) {
+        let bounds = ctx.size().to_rect();
+
+        if let (None, Some(thumbnail)) = (&self.image, &self.thumbnail) {
+            self.image = Some(thumbnail.to_image(ctx.render_ctx));
+        }
+
+        match (&self.image, &self.thumbnail) {
+            (Some(image), Some(thumbnail)) => {
+                // Center the image in the box, keeping its aspect ratio
+                let size = thumbnail.size();
+                let scale = (bounds.width() / size.width).min(bounds.height() / size.height);
+                let image_size = Size::new(size.width * scale, size.height * scale);
+                let origin = (
+                    (bounds.width() - image_size.width) / 2.0,
+                    (bounds.height() - image_size.height) / 2.0,
+                );
+                let rect = Rect::from_origin_size(origin, image_size);
+                ctx.draw_image(image, rect, InterpolationMode::Bilinear);
+            }
+            _ => {
+                ctx.fill(bounds, &Color::grey8(0xd4));
+            }
+        }
+    }
+}
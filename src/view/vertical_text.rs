@@ -0,0 +1,192 @@
+use std::ops::Range;
+
+use druid::{
+    piet::{PietTextLayout, Text, TextAlignment, TextAttribute, TextLayout, TextLayoutBuilder},
+    theme, BoxConstraints, Data, Env, Event, EventCtx, FontFamily, FontStyle, FontWeight,
+    LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, RenderContext, Size, UpdateCtx, Widget,
+};
+
+use crate::helper::layout::Span;
+
+// Width of a column in em, the space between the columns included
+const COLUMN_WIDTH_EM: f64 = 1.5;
+// Height of a character in its column, in em (the line height of the system font)
+const ROW_HEIGHT_EM: f64 = 1.2;
+// Height of a band of columns (in pixels) and space between two bands (in em)
+const BAND_HEIGHT: f64 = 520.0;
+const BAND_GAP_EM: f64 = 2.0;
+
+/**
+ * Characters of a column, one per line, and the spans they come from
+ */
+#[derive(Default)]
+struct Column<'a> {
+    text: String,
+    chars: usize,
+    // The line break ends the column, the next character starts a new one
+    full: bool,
+    runs: Vec<(Range<usize>, &'a Span)>,
+}
+
+impl<'a> Column<'a> {
+    fn push(&mut self, c: char, span: &'a Span) {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        let start = self.text.len();
+        self.text.push(c);
+        let end = self.text.len();
+        match self.runs.last_mut() {
+            Some((range, last)) if std::ptr::eq(*last, span) => range.end = end,
+            _ => self.runs.push((start..end, span)),
+        }
+        self.chars += 1;
+    }
+}
+
+/**
+ * Text written vertically (writing-mode: vertical-rl).
+ * Characters go from top to bottom in columns, the columns from right to left;
+ * when the columns fill the width a new band of columns starts below,
+ * so that the page still scrolls vertically. Every paragraph starts a new column.
+ * Each column is a single text layout with one upright character per line; the columns
+ * are laid out once and only placed again when the width changes. Links are not clickable.
+ */
+pub struct VerticalText {
+    paragraphs: Vec<Vec<Span>>,
+    font_size: f64,
+    // Columns in reading order, built at the first layout
+    columns: Option<Vec<PietTextLayout>>,
+    // Position of every column for the width of the last layout
+    positions: Vec<Point>,
+    width: f64,
+    height: f64,
+}
+
+impl VerticalText {
+    pub fn new(paragraphs: Vec<Vec<Span>>, font_size: f64) -> Self {
+        Self {
+            paragraphs,
+            font_size,
+            columns: None,
+            positions: Vec::new(),
+            width: 0.0,
+            height: 0.0,
+        }
+    }
+
+    /**
+     * Split the paragraphs in columns of at most a band of characters and lay them out
+     */
+    fn build_columns(&self, ctx: &mut LayoutCtx, env: &Env) -> Vec<PietTextLayout> {
+        let rows = ((BAND_HEIGHT / (self.font_size * ROW_HEIGHT_EM)).floor() as usize).max(1);
+        let mut columns = Vec::new();
+        for paragraph in self.paragraphs.iter() {
+            let mut column = Column::default();
+            for span in paragraph.iter() {
+                for c in span.text.chars() {
+                    // A line break goes on in the next column
+                    if c == '\n' {
+                        column.full = true;
+                        continue;
+                    }
+                    if column.full || column.chars == rows {
+                        columns.push(std::mem::take(&mut column));
+                    }
+                    column.push(c, span);
+                }
+            }
+            columns.push(column);
+        }
+
+        let color = env.get(theme::TEXT_COLOR);
+        let column_width = self.font_size * COLUMN_WIDTH_EM;
+        columns
+            .into_iter()
+            .filter_map(|column| {
+                let mut builder = ctx
+                    .text()
+                    .new_text_layout(column.text)
+                    .font(FontFamily::SYSTEM_UI, self.font_size)
+                    .alignment(TextAlignment::Center)
+                    .max_width(column_width)
+                    .text_color(color.clone());
+                for (range, span) in column.runs {
+                    if span.bold {
+                        builder = builder.range_attribute(range.clone(), FontWeight::BOLD);
+                    }
+                    if span.italic {
+                        builder = builder.range_attribute(range.clone(), FontStyle::Italic);
+                    }
+                    builder = builder.range_attribute(
+                        range,
+                        TextAttribute::FontSize(self.font_size * span.size),
+                    );
+                }
+                match builder.build() {
+                    Ok(layout) => Some(layout),
+                    Err(e) => {
+                        eprintln!("Error laying out vertical text: {}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Place the columns right to left in bands as tall as their tallest column
+     */
+    fn position_columns(&mut self, width: f64) {
+        self.positions.clear();
+        let column_width = self.font_size * COLUMN_WIDTH_EM;
+        let columns_per_band = ((width / column_width).floor() as usize).max(1);
+        let gap = BAND_GAP_EM * self.font_size;
+        let mut top = 0.0;
+        self.height = 0.0;
+        if let Some(columns) = &self.columns {
+            for band in columns.chunks(columns_per_band) {
+                let mut band_height: f64 = 0.0;
+                for (column, layout) in band.iter().enumerate() {
+                    let x = width - (column + 1) as f64 * column_width;
+                    self.positions.push(Point::new(x, top));
+                    band_height = band_height.max(layout.size().height);
+                }
+                self.height = top + band_height;
+                top += band_height + gap;
+            }
+        }
+        self.width = width;
+    }
+}
+
+impl<T: Data> Widget<T> for VerticalText {
+    fn event(&mut self, _ctx: &mut EventCtx, _event: &Event, _data: &mut T, _env: &Env) {}
+
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &T, _env: &Env) {}
+
+    fn update(&mut self, _ctx: &mut UpdateCtx, _old_data: &T, _data: &T, _env: &Env) {}
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, env: &Env) -> Size {
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            bc.min().width.max(600.0)
+        };
+        if self.columns.is_none() {
+            self.columns = Some(self.build_columns(ctx, env));
+            self.position_columns(width);
+        } else if (width - self.width).abs() > f64::EPSILON {
+            self.position_columns(width);
+        }
+        bc.constrain(Size::new(width, self.height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, _env: &Env) {
+        if let Some(columns) = &self.columns {
+            for (layout, position) in columns.iter().zip(self.positions.iter()) {
+                ctx.draw_text(layout, *position);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    helper::{
+        config::{PADDING_LG, PADDING_SM, SUBTITLE, TITLE},
+        vocabulary::DeckFormat,
+    },
+    model::app_state::AppState,
+};
+use druid::{
+    widget::{
+        Button, Container, CrossAxisAlignment, Flex, Label, LineBreaking, MainAxisAlignment,
+        Padding, Scroll, ViewSwitcher,
+    },
+    Color, Widget, WidgetExt,
+};
+
+fn header() -> impl Widget<AppState> {
+    let header_label = Label::dynamic(|data: &AppState, _env| {
+        let title = data
+            .get_vocabulary()
+            .map(|vocabulary| vocabulary.title)
+            .unwrap_or_default();
+        format!("Vocabulary of {}", title)
+    })
+    .with_font(TITLE);
+
+    let csv_button = Button::new("Export CSV").on_click(|_ctx, data: &mut AppState, _env| {
+        data.export_vocabulary(DeckFormat::Csv);
+    });
+    let tsv_button = Button::new("Export TSV").on_click(|_ctx, data: &mut AppState, _env| {
+        data.export_vocabulary(DeckFormat::Tsv);
+    });
+    let back_button = Button::new("Back").on_click(|_ctx, data: &mut AppState, _env| {
+        data.close_vocabulary();
+    });
+
+    Flex::row()
+        .with_child(header_label)
+        .with_flex_spacer(1.0)
+        .with_child(csv_button)
+        .with_spacer(PADDING_SM)
+        .with_child(tsv_button)
+        .with_spacer(PADDING_SM)
+        .with_child(back_button)
+        .main_axis_alignment(MainAxisAlignment::SpaceBetween)
+        .must_fill_main_axis(true)
+}
+
+/**
+ * Vocabulary page of the book read: the words looked up with their definition
+ * and the sentence they were in, a word can be removed or read again in its place
+ */
+pub fn vocabulary() -> Box<dyn Widget<AppState>> {
+    let words = ViewSwitcher::new(
+        |data: &AppState, _env| {
+            data.get_vocabulary()
+                .map(|vocabulary| vocabulary.words.len())
+        },
+        |_, data: &AppState, _env| {
+            let words = data
+                .get_vocabulary()
+                .map(|vocabulary| vocabulary.words)
+                .unwrap_or_default();
+            if words.is_empty() {
+                return Box::new(Label::new(
+                    "Double click a word while reading to look it up and add it here",
+                ));
+            }
+
+            let mut list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+            for (index, word) in words.into_iter().enumerate() {
+                let go_to_button = Button::new(format!("Chapter {}", word.page)).on_click(
+                    move |_ctx, data: &mut AppState, _env| {
+                        data.go_to_vocabulary_word(index);
+                    },
+                );
+                let remove_button =
+                    Button::new("Remove").on_click(move |_ctx, data: &mut AppState, _env| {
+                        data.remove_vocabulary_word(index);
+                    });
+                let title = Flex::row()
+                    .with_child(Label::new(word.word).with_font(SUBTITLE))
+                    .with_flex_spacer(1.0)
+                    .with_child(go_to_button)
+                    .with_spacer(PADDING_SM)
+                    .with_child(remove_button)
+                    .must_fill_main_axis(true);
+
+                let mut entry = Flex::column()
+                    .with_child(title)
+                    .cross_axis_alignment(CrossAxisAlignment::Start);
+                if !word.definition.is_empty() {
+                    entry.add_child(
+                        Label::new(word.definition).with_line_break_mode(LineBreaking::WordWrap),
+                    );
+                }
+                entry.add_spacer(PADDING_SM);
+                entry.add_child(
+                    Label::new(format!("“{}”", word.context))
+                        .with_text_color(Color::grey8(0x9a))
+                        .with_line_break_mode(LineBreaking::WordWrap),
+                );
+                list.add_child(entry.padding(PADDING_SM).expand_width());
+                list.add_spacer(PADDING_SM);
+            }
+            Box::new(list)
+        },
+    );
+
+    let layout = Flex::column()
+        .with_child(header())
+        .with_spacer(PADDING_LG)
+        .with_child(words)
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+
+    Box::new(Padding::new(
+        PADDING_LG,
+        Container::new(Scroll::new(layout.expand_width()).vertical()),
+    ))
+}